@@ -0,0 +1,144 @@
+//! Core benchmarks: `cargo bench -p rustris-core --features internals`
+//!
+//! - `frames`: whole-machine throughput, 60 frames per iteration.
+//! - `cpu`: CPU + memory bus only, no PPU.
+//! - `ppu`: one frame of PPU rendering over a fixed VRAM/OAM setup, no CPU.
+//! - `state`: save-state capture, the per-snapshot cost of rewind.
+//!
+//! The ROM is taken from `RUSTRIS_BENCH_ROM` (e.g. `tetris.gb`); without it a small
+//! built-in program that keeps copying memory around is used.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rustris_core::internals::{CPU, MMU, PPU};
+use rustris_core::{Cartridge, InputSource, VideoSink, GB, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use std::hint::black_box;
+
+const PITCH: usize = SCREEN_WIDTH as usize * 3;
+
+/// T-cycles in one DMG frame (154 lines × 456 dots).
+const FRAME_CYCLES: u32 = 70_224;
+
+struct Screen(Vec<u8>);
+
+impl VideoSink for Screen {
+    fn buffer(&mut self) -> (&mut [u8], usize) {
+        (&mut self.0, PITCH)
+    }
+
+    fn present(&mut self) {}
+}
+
+struct NoInput;
+
+impl InputSource for NoInput {
+    fn poll(&mut self) -> u8 {
+        0
+    }
+}
+
+fn screen() -> Screen {
+    Screen(vec![0; PITCH * SCREEN_HEIGHT as usize])
+}
+
+fn cartridge() -> Cartridge {
+    match std::env::var("RUSTRIS_BENCH_ROM") {
+        Ok(path) => Cartridge::from_file(&path).expect("cannot read RUSTRIS_BENCH_ROM"),
+        Err(_) => Cartridge::from_bytes(synthetic_rom()),
+    }
+}
+
+/// 32KB ROM whose entry point copies the first 8KB of ROM into WRAM, forever.
+fn synthetic_rom() -> Vec<u8> {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]); // NOP; JP 0150
+    rom[0x150..0x166].copy_from_slice(&[
+        0x21, 0x00, 0x00, // LD HL,0000
+        0x11, 0x00, 0xC0, // LD DE,C000
+        0x01, 0x00, 0x20, // LD BC,2000
+        0x2A,             // loop: LD A,(HL+)
+        0x12,             // LD (DE),A
+        0x13,             // INC DE
+        0x0B,             // DEC BC
+        0x78,             // LD A,B
+        0xB1,             // OR C
+        0x20, 0xF8,       // JR NZ,loop
+        0xC3, 0x50, 0x01, // JP 0150
+        0x00, 0x00,
+    ]);
+    rom
+}
+
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frames");
+    group.throughput(Throughput::Elements(60));
+    group.bench_function("60", |b| {
+        let mut gb = GB::new(cartridge()).expect("cannot emulate the benchmark ROM");
+        let mut screen = screen();
+        b.iter(|| {
+            for _ in 0..60 {
+                gb.run_frame(&mut screen, &mut NoInput);
+            }
+        });
+    });
+    group.finish();
+}
+
+fn cpu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(10_000));
+    group.bench_function("10k instructions", |b| {
+        let mut cpu = CPU::new();
+        let mut mmu = MMU::new(cartridge(), false).expect("cannot emulate the benchmark ROM");
+        b.iter(|| {
+            for _ in 0..10_000 {
+                black_box(cpu.step(&mut mmu));
+            }
+        });
+    });
+    group.finish();
+}
+
+fn ppu(c: &mut Criterion) {
+    let mut mmu = MMU::new(cartridge(), false).expect("cannot emulate the benchmark ROM");
+    // Tiles with every color, a map using all of them, 40 sprites and both palettes set
+    for addr in 0x8000..0x9800u16 {
+        mmu.write_byte(addr, (addr as u8).wrapping_mul(37));
+    }
+    for addr in 0x9800..0xA000u16 {
+        mmu.write_byte(addr, addr as u8);
+    }
+    for i in 0..40u16 {
+        let sprite = 0xFE00 + i * 4;
+        mmu.write_byte(sprite, 16 + (i as u8 * 7) % 144);
+        mmu.write_byte(sprite + 1, 8 + (i as u8 * 13) % 160);
+        mmu.write_byte(sprite + 2, i as u8);
+        mmu.write_byte(sprite + 3, (i as u8 & 3) << 5);
+    }
+    mmu.write_byte(0xFF40, 0x93); // LCD + BG + sprites on, tile data at 0x8000
+    mmu.write_byte(0xFF47, 0xE4);
+    mmu.write_byte(0xFF48, 0xE4);
+    mmu.write_byte(0xFF49, 0x1B);
+
+    let mut group = c.benchmark_group("ppu");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("frame", |b| {
+        let mut ppu = PPU::new();
+        let mut screen = screen();
+        b.iter(|| {
+            for _ in 0..FRAME_CYCLES / 4 {
+                ppu.step(&mut mmu, 4, &mut screen.0, PITCH);
+            }
+        });
+    });
+    group.finish();
+}
+
+fn state(c: &mut Criterion) {
+    let mut gb = GB::new(cartridge()).expect("cannot emulate the benchmark ROM");
+    gb.run_frame(&mut screen(), &mut NoInput);
+    c.bench_function("state/save", |b| b.iter(|| black_box(gb.save_state())));
+}
+
+criterion_group!(benches, frames, cpu, ppu, state);
+criterion_main!(benches);
@@ -0,0 +1,174 @@
+//! CPU fuzz target: `cargo +nightly fuzz run cpu` (from `rustris-core/`).
+//!
+//! Feeds arbitrary instruction streams to the CPU over a flat 64KB RAM bus (no MBC, no
+//! I/O side effects) and checks, after every instruction:
+//! - no panics (overflow checks are on in fuzz builds);
+//! - only the eleven unused opcodes lock the CPU up;
+//! - the low nibble of F stays zero;
+//! - the instruction took 4..=24 T-cycles, in whole M-cycles;
+//! - PC moved past the instruction, unless it's a jump/call/return;
+//! - SP moved by exactly what the instruction pushes or pops;
+//! - RST pushed the address after it and jumped to its vector (RST 38H included).
+//!
+//! There's no reference implementation to diff against; the invariants above are
+//! what the core alone can vouch for.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustris_core::internals::{Bus, CPU};
+use rustris_core::Registers;
+
+/// Instructions run per input; a locked-up (illegal opcode) or halted CPU ends the run early.
+const MAX_STEPS: usize = 1000;
+
+/// Where the program is loaded and execution starts.
+const ENTRY: u16 = 0x0100;
+
+struct FlatRam(Box<[u8; 0x10000]>);
+
+impl Bus for FlatRam {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.0[addr as usize]
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        self.0[addr as usize] = value;
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // First 12 bytes seed the registers, the rest is the program
+    if data.len() < 12 {
+        return;
+    }
+    let (seed, program) = data.split_at(12);
+
+    let mut ram = FlatRam(Box::new([0; 0x10000]));
+    let len = program.len().min(0x10000 - ENTRY as usize);
+    ram.0[ENTRY as usize..ENTRY as usize + len].copy_from_slice(&program[..len]);
+
+    let mut cpu = CPU::new();
+    cpu.set_registers(&Registers {
+        a: seed[0],
+        f: seed[1] & 0xF0,
+        b: seed[2],
+        c: seed[3],
+        d: seed[4],
+        e: seed[5],
+        h: seed[6],
+        l: seed[7],
+        sp: u16::from_le_bytes([seed[8], seed[9]]),
+        pc: ENTRY,
+    });
+    let _ = (seed[10], seed[11]); // Reserved
+
+    for _ in 0..MAX_STEPS {
+        // Keep interrupts out of the picture: the program may have written to IE
+        ram.0[0xFFFF] = 0;
+
+        let before = cpu.registers();
+        let opcode = ram.read_byte(before.pc);
+        let t = cpu.step(&mut ram);
+        if cpu.unimplemented_opcode().is_some() {
+            assert!(is_unused(opcode), "lockup on {:02X}, which is a real instruction", opcode);
+            assert_eq!(cpu.registers(), before, "lockup on {:02X} changed registers", opcode);
+            break;
+        }
+        if cpu.halted() {
+            break; // Nothing can wake it with interrupts disabled
+        }
+        let after = cpu.registers();
+
+        assert_eq!(after.f & 0x0F, 0, "opcode {:02X} set F low bits", opcode);
+        assert!(t % 4 == 0 && (4..=24).contains(&t), "opcode {:02X} took {} T-cycles", opcode, t);
+
+        let next = before.pc.wrapping_add(length(opcode));
+        if !is_control_flow(opcode) {
+            assert_eq!(after.pc, next, "opcode {:02X} left PC at {:04X}", opcode, after.pc);
+        }
+
+        let taken = after.pc != next;
+        let delta = after.sp.wrapping_sub(before.sp);
+        match sp_delta(opcode) {
+            Some(Stack::Always(d)) => {
+                assert_eq!(delta, d, "opcode {:02X} moved SP by {}", opcode, delta as i16);
+            }
+            Some(Stack::IfTaken(d)) => {
+                // A taken branch that lands on the fall-through address is indistinguishable
+                let ok = if taken { delta == d } else { delta == 0 || delta == d };
+                assert!(ok, "opcode {:02X} moved SP by {}", opcode, delta as i16);
+            }
+            None => {}
+        }
+
+        if is_rst(opcode) {
+            let pushed = u16::from_le_bytes([ram.read_byte(after.sp), ram.read_byte(after.sp.wrapping_add(1))]);
+            assert_eq!(after.pc, (opcode & 0x38) as u16, "RST {:02X} jumped to {:04X}", opcode, after.pc);
+            assert_eq!(pushed, next, "RST {:02X} pushed {:04X}", opcode, pushed);
+        }
+    }
+});
+
+/// The opcodes the SM83 doesn't have, which lock it up.
+fn is_unused(opcode: u8) -> bool {
+    matches!(opcode, 0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD)
+}
+
+fn is_rst(opcode: u8) -> bool {
+    opcode & 0xC7 == 0xC7
+}
+
+/// Expected SP movement, as a wrapping 16-bit delta.
+enum Stack {
+    Always(u16),
+    IfTaken(u16), // Conditional call/return
+}
+
+fn sp_delta(opcode: u8) -> Option<Stack> {
+    const PUSHED: u16 = 2u16.wrapping_neg();
+    const POPPED: u16 = 2;
+    match opcode {
+        // Instructions that set SP explicitly
+        0x31 | 0x33 | 0x3B | 0xE8 | 0xF9 => None,
+        0xC5 | 0xD5 | 0xE5 | 0xF5 => Some(Stack::Always(PUSHED)),
+        0xC1 | 0xD1 | 0xE1 | 0xF1 => Some(Stack::Always(POPPED)),
+        0xCD => Some(Stack::Always(PUSHED)),
+        0xC4 | 0xCC | 0xD4 | 0xDC => Some(Stack::IfTaken(PUSHED)),
+        0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => Some(Stack::Always(PUSHED)),
+        0xC9 | 0xD9 => Some(Stack::Always(POPPED)),
+        0xC0 | 0xC8 | 0xD0 | 0xD8 => Some(Stack::IfTaken(POPPED)),
+        _ => Some(Stack::Always(0)),
+    }
+}
+
+/// Instruction length in bytes, opcode included.
+fn length(opcode: u8) -> u16 {
+    match opcode {
+        // LD r,d8
+        0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => 2,
+        // STOP, JR, ALU A,d8, LDH, ADD SP,e8 / LD HL,SP+e8, CB prefix
+        0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => 2,
+        0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE => 2,
+        0xE0 | 0xF0 | 0xE8 | 0xF8 | 0xCB => 2,
+        // LD rr,d16, LD (a16),SP, JP, CALL, LD (a16),A / LD A,(a16)
+        0x01 | 0x11 | 0x21 | 0x31 | 0x08 => 3,
+        0xC2 | 0xC3 | 0xCA | 0xD2 | 0xDA => 3,
+        0xC4 | 0xCC | 0xCD | 0xD4 | 0xDC => 3,
+        0xEA | 0xFA => 3,
+        _ => 1,
+    }
+}
+
+/// Jumps, calls, returns, restarts and HALT/STOP: PC may land anywhere.
+fn is_control_flow(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 // STOP, JR
+            | 0x76 // HALT
+            | 0xC2 | 0xC3 | 0xCA | 0xD2 | 0xDA | 0xE9 // JP
+            | 0xC4 | 0xCC | 0xCD | 0xD4 | 0xDC // CALL
+            | 0xC0 | 0xC8 | 0xC9 | 0xD0 | 0xD8 | 0xD9 // RET, RETI
+            | 0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF // RST
+    )
+}
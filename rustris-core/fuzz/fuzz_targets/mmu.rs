@@ -0,0 +1,121 @@
+//! Memory map fuzz target: `cargo +nightly fuzz run mmu` (from `rustris-core/`).
+//!
+//! Builds a cartridge (type, size, RAM, DMG or CGB and accuracy profile picked by the
+//! input) whose every ROM byte encodes its bank, then hammers the MMU with random reads,
+//! writes and ticks, checking after each one:
+//! - no panics (overflow checks are on in fuzz builds);
+//! - ROM reads come from the image, from bank 0/1 without a mapper, and the bank seen in
+//!   each half of 0000–7FFF only changes after a write there (a bank switch);
+//! - WRAM reads back what was written, and E000–FDFF mirrors C000–DDFF both ways;
+//! - VRAM, OAM, HRAM and IE read back what was written, cartridge RAM too unless it
+//!   reads FF (disabled, or a clock register selected);
+//! - FEA0–FEFF reads FF whatever was written;
+//! - the registers with fixed or read-only bits keep them: P1 bits 6–7, STAT bit 7 and
+//!   mode/coincidence bits, DIV cleared by any write, and VBK, SVBK and KEY1 on CGB.
+//!
+//! The invariants are the memory map's own; timing and what the PPU or APU do with the
+//! registers are out of scope.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustris_core::internals::MMU;
+use rustris_core::{Cartridge, Profile};
+
+/// Cartridge types tried: no mapper, MBC1, MBC3 (with clock), MBC5 (with rumble).
+const CART_TYPES: [u8; 8] = [0x00, 0x01, 0x03, 0x0F, 0x13, 0x19, 0x1B, 0x1C];
+
+const PROFILES: [Profile; 3] = [Profile::Tetris, Profile::Compatible, Profile::Accurate];
+
+/// Header bytes the image sets (cartridge type, ROM and RAM size), which don't encode
+/// their bank.
+const HEADER: std::ops::RangeInclusive<usize> = 0x0147..=0x0149;
+
+/// Bytes read as the cartridge setup before the operations start.
+const SETUP: usize = 3;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < SETUP {
+        return;
+    }
+    let (setup, ops) = data.split_at(SETUP);
+
+    // 2 to 128 banks: enough for MBC1's upper bank bits
+    let banks = 2usize << (setup[1] % 7);
+    let mut rom: Vec<u8> = (0..banks * 0x4000).map(|i| (i >> 14) as u8 ^ i as u8).collect();
+    rom[0x0147] = CART_TYPES[setup[0] as usize % CART_TYPES.len()];
+    rom[0x0148] = (setup[1] % 7) as u8;
+    rom[0x0149] = setup[2] % 6;
+    let mapped = rom[0x0147] != 0x00;
+    let cgb = setup[1] & 0x08 != 0;
+    let profile = PROFILES[(setup[1] >> 4) as usize % PROFILES.len()];
+
+    let Ok(mut mmu) = MMU::new(Cartridge::from_bytes(rom), cgb) else { return };
+    mmu.set_profile(profile);
+
+    let mut seen_banks: [Option<u8>; 2] = [None; 2]; // Bank last read in 0000–3FFF and 4000–7FFF
+    for op in ops.chunks_exact(4) {
+        let addr = u16::from_le_bytes([op[1], op[2]]);
+        let value = op[3];
+        match op[0] % 4 {
+            0 | 1 => {
+                let before = mmu.read_byte(addr);
+                mmu.write_byte(addr, value);
+                if addr < 0x8000 {
+                    seen_banks = [None; 2];
+                }
+                check_write(&mmu, addr, value, before, cgb, profile);
+            }
+            2 => {
+                let read = mmu.read_byte(addr);
+                if addr < 0x8000 && !HEADER.contains(&(addr as usize & 0x3FFF)) {
+                    let bank = read ^ addr as u8;
+                    assert!((bank as usize) < banks, "{:04X} read {:02X}, not from a ROM bank", addr, read);
+                    if !mapped {
+                        assert_eq!(bank as u16, addr >> 14, "{:04X} read from bank {} without a mapper", addr, bank);
+                    }
+                    let half = &mut seen_banks[addr as usize >> 14];
+                    assert_eq!(*half.get_or_insert(bank), bank, "{:04X} switched banks without a write", addr);
+                }
+                if (0xFEA0..=0xFEFF).contains(&addr) {
+                    assert_eq!(read, 0xFF, "unusable {:04X} read {:02X}", addr, read);
+                }
+            }
+            _ => mmu.tick(4 * (1 + (value as u32 & 0x3F))),
+        }
+    }
+});
+
+/// Checks what reads back right after writing `value` to `addr`, which read `before`.
+fn check_write(mmu: &MMU, addr: u16, value: u8, before: u8, cgb: bool, profile: Profile) {
+    let read = mmu.read_byte(addr);
+    match addr {
+        0x8000..=0x9FFF | 0xFE00..=0xFE9F | 0xFF80..=0xFFFF => {
+            assert_eq!(read, value, "{:04X} read {:02X} after writing {:02X}", addr, read, value);
+        }
+        0xA000..=0xBFFF => {
+            assert!(read == value || read == 0xFF, "cartridge RAM {:04X} read {:02X} after writing {:02X}", addr, read, value);
+        }
+        0xC000..=0xFDFF => {
+            assert_eq!(read, value, "WRAM {:04X} read {:02X} after writing {:02X}", addr, read, value);
+            let mirror = if addr >= 0xE000 { addr - 0x2000 } else { addr + 0x2000 };
+            if mirror <= 0xFDFF {
+                let mirrored = mmu.read_byte(mirror);
+                assert_eq!(mirrored, value, "{:04X} read {:02X}, not mirroring {:04X}", mirror, mirrored, addr);
+            }
+        }
+        0xFEA0..=0xFEFF => assert_eq!(read, 0xFF, "unusable {:04X} read {:02X} after a write", addr, read),
+        0xFF00 => assert_eq!(read & 0xC0, 0xC0, "P1 read {:02X}, bits 6–7 not set", read),
+        0xFF04 if profile != Profile::Tetris => assert_eq!(read, 0, "DIV read {:02X} after a write", read),
+        0xFF41 if profile != Profile::Tetris => {
+            let expected = 0x80 | (value & 0x78) | (before & 0x07);
+            assert_eq!(read, expected, "STAT read {:02X} after writing {:02X} over {:02X}", read, value, before);
+        }
+        0xFF4D if cgb => assert_eq!(read & 0x7F, 0x7E | (value & 1), "KEY1 read {:02X} after writing {:02X}", read, value),
+        0xFF4F if cgb => assert_eq!(read, 0xFE | (value & 1), "VBK read {:02X} after writing {:02X}", read, value),
+        0xFF70 if cgb => {
+            assert_eq!(read, 0xF8 | (value & 0x07).max(1), "SVBK read {:02X} after writing {:02X}", read, value);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,63 @@
+use crate::mmu::MMU;
+
+/// SCY the logo starts from, below the screen's top edge; it scrolls down a line a frame
+/// until SCY reaches 0.
+const START_SCY: u8 = 0x64;
+
+/// Frames the logo then rests in place before the game starts, where the console plays
+/// its chime.
+const PAUSE_FRAMES: u32 = 64;
+
+/// Frames the whole animation lasts.
+pub const FRAMES: u32 = START_SCY as u32 + PAUSE_FRAMES;
+
+/// The ® mark drawn after the logo, one byte per row.
+const REGISTERED: [u8; 8] = [0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0xA5, 0x42, 0x3C];
+
+/// Sets the screen up as the DMG boot ROM does before scrolling the logo: VRAM cleared,
+/// the logo from the cartridge header (0104–0133) decoded into tiles 1–24 with the ®
+/// as tile 25, both rows of it in the middle of the BG map, and the LCD switched on.
+/// The logo is drawn whatever the header holds; unlike the console, a bad one doesn't
+/// stop the game from starting.
+pub fn start(mmu: &mut MMU) {
+    for addr in 0x8000..=0x9FFF {
+        mmu.write_byte(addr, 0);
+    }
+
+    // Each header byte is two rows of 4 pixels, a nibble each; every pixel is doubled
+    // both ways, so a byte makes 4 rows of a tile and two bytes a whole tile
+    let mut addr = 0x8010;
+    for src in 0x0104..0x0134 {
+        let byte = mmu.read_byte(src);
+        for nibble in [byte >> 4, byte & 0x0F] {
+            let row = double_bits(nibble);
+            for _ in 0..2 {
+                mmu.write_byte(addr, row); // Low plane only: color 1
+                addr += 2;
+            }
+        }
+    }
+    for (row, &bits) in REGISTERED.iter().enumerate() {
+        mmu.write_byte(0x8190 + 2 * row as u16, bits);
+    }
+
+    for tile in 0..12u8 {
+        mmu.write_byte(0x9904 + tile as u16, tile + 1);
+        mmu.write_byte(0x9924 + tile as u16, tile + 13);
+    }
+    mmu.write_byte(0x9910, 0x19);
+
+    mmu.write_byte(0xFF42, START_SCY); // SCY
+    mmu.write_byte(0xFF47, 0xFC); // BGP: color 1 is black
+    mmu.write_byte(0xFF40, 0x91); // LCDC: LCD and BG on, tiles at 8000
+}
+
+/// Scrolls the logo for the frame that leaves `frames_left` of the animation to go.
+pub fn scroll(mmu: &mut MMU, frames_left: u32) {
+    mmu.write_byte(0xFF42, frames_left.saturating_sub(PAUSE_FRAMES) as u8);
+}
+
+/// A nibble's 4 bits each doubled into a byte (`abcd` → `aabbccdd`).
+fn double_bits(nibble: u8) -> u8 {
+    (0..4).filter(|&bit| (nibble >> bit) & 1 != 0).fold(0, |byte, bit| byte | (0b11 << (2 * bit)))
+}
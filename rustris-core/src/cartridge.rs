@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::EmulatorError;
+use crate::mbc::MbcKind;
+
+/// Header range of the game title (upper case ASCII, zero-padded; shorter on CGB games).
+const TITLE: std::ops::Range<usize> = 0x134..0x143;
+
+/// Header offset of the CGB support flag (0x80 = CGB-enhanced, 0xC0 = CGB only).
+const CGB_FLAG: usize = 0x143;
+
+/// Header offset of the cartridge type byte (MBC and extra hardware).
+const CART_TYPE: usize = 0x147;
+
+/// Header offset of the cartridge RAM size code.
+const RAM_SIZE: usize = 0x149;
+
+/// Header offset of the header checksum, over 0x134–0x14C.
+const HEADER_CHECKSUM: usize = 0x14D;
+
+/// Header offset of the global checksum (big-endian sum of every other ROM byte).
+const GLOBAL_CHECKSUM: usize = 0x14E;
+
+/// Size of everything up to the end of the header.
+const HEADER_END: usize = 0x150;
+
+/// Cartridge image: ROM-only, MBC1, MBC3 (without the clock) or MBC5.
+pub struct Cartridge {
+    pub rom: Vec<u8>,
+}
+
+// Cartridge emulation: reads the entire ROM file into memory as a byte vector.
+impl Cartridge {
+    /// Wraps a ROM image that is already in memory. It's checked when a machine is built
+    /// from it (see `GB::new`).
+    pub fn from_bytes(rom: Vec<u8>) -> Self {
+        Cartridge { rom }
+    }
+
+    /// Reads a ROM image from disk.
+    pub fn from_file(path: &str) -> Result<Self, EmulatorError> {
+        let rom = std::fs::read(path).map_err(|source| EmulatorError::RomLoad { path: PathBuf::from(path), source })?;
+        Ok(Cartridge { rom })
+    }
+
+    /// Returns `true` if the header declares battery-backed cartridge RAM.
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self.rom.get(CART_TYPE),
+            Some(0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF)
+        )
+    }
+
+    /// Returns `true` if the header declares an MBC5 cart with a rumble motor.
+    pub fn has_rumble(&self) -> bool {
+        matches!(self.rom.get(CART_TYPE), Some(0x1C..=0x1E))
+    }
+
+    /// Game title from the header, without padding.
+    pub fn title(&self) -> String {
+        let title = self.rom.get(TITLE).unwrap_or_default();
+        let end = title.iter().position(|&b| b == 0 || b >= 0x80).unwrap_or(title.len());
+        String::from_utf8_lossy(&title[..end]).trim_end().to_string()
+    }
+
+    /// Returns `true` if the header marks the game as Game Boy Color aware (enhanced or
+    /// CGB-only), i.e. it should run in color on a CGB.
+    pub fn supports_cgb(&self) -> bool {
+        self.rom.get(CGB_FLAG).is_some_and(|flag| flag & 0x80 != 0)
+    }
+
+    /// Header checksum byte as stored in the ROM.
+    pub fn header_checksum(&self) -> u8 {
+        self.rom.get(HEADER_CHECKSUM).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if the stored header checksum matches the header, as the boot ROM
+    /// checks before starting a game; a mismatch usually means a hacked or corrupt header.
+    pub fn header_checksum_valid(&self) -> bool {
+        let Some(header) = self.rom.get(0x134..HEADER_CHECKSUM) else { return false };
+        let sum = header.iter().fold(0u8, |x, &b| x.wrapping_sub(b).wrapping_sub(1));
+        sum == self.header_checksum()
+    }
+
+    /// Global checksum as stored in the ROM (never checked by the hardware).
+    pub fn global_checksum(&self) -> u16 {
+        match self.rom.get(GLOBAL_CHECKSUM..GLOBAL_CHECKSUM + 2) {
+            Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]),
+            _ => 0,
+        }
+    }
+
+    /// Memory bank controller declared by the header; fails for images too short to have
+    /// a header and for controllers the core doesn't emulate.
+    pub(crate) fn mbc(&self) -> Result<MbcKind, EmulatorError> {
+        if self.rom.len() < HEADER_END {
+            return Err(EmulatorError::InvalidRom(self.rom.len()));
+        }
+        let kind = self.rom[CART_TYPE];
+        let name = match kind {
+            0x00 | 0x08 | 0x09 => return Ok(MbcKind::None),
+            0x01..=0x03 => return Ok(MbcKind::Mbc1),
+            0x0F..=0x13 => return Ok(MbcKind::Mbc3),
+            0x19..=0x1E => return Ok(MbcKind::Mbc5),
+            0x05 | 0x06 => "MBC2",
+            0x0B..=0x0D => "MMM01",
+            0x20 => "MBC6",
+            0x22 => "MBC7",
+            0xFC => "Pocket Camera",
+            0xFD => "Bandai TAMA5",
+            0xFE => "HuC3",
+            0xFF => "HuC1",
+            _ => "unknown",
+        };
+        Err(EmulatorError::UnsupportedMapper { kind, name })
+    }
+
+    /// Cartridge RAM size in bytes declared by the header (MBC2/unknown codes count as none).
+    pub(crate) fn ram_size(&self) -> usize {
+        match self.rom.get(RAM_SIZE) {
+            Some(2) => 0x2000,
+            Some(3) => 0x8000,
+            Some(4) => 0x20000,
+            Some(5) => 0x10000,
+            _ => 0,
+        }
+    }
+
+    /// Path of the battery save file for a ROM: same name, `.sav` extension.
+    pub fn save_path(rom_path: &str) -> PathBuf {
+        Path::new(rom_path).with_extension("sav")
+    }
+}
@@ -1,4 +1,5 @@
-use crate::mmu::MMU;
+use crate::gb::Profile;
+use crate::savestate::{StateReader, StateWriter};
 
 /// CPU core: fetch–decode–execute loop for the Game Boy CPU (Sharp LR35902).
 /// Responsibilities:
@@ -27,6 +28,82 @@ pub struct CPU {
     // Interrupt state
     ei_pending: bool, // EI takes effect after the next instruction
     ime: bool, // master interrupt enable
+    halted: bool, // HALT: sleeping until IE & IF is nonzero
+    interrupt_mask: u8, // Interrupt sources serviced (VBlank and serial in the Tetris profile)
+    dispatched: Option<u8>, // Bit of the interrupt serviced since the last `take_dispatched`
+
+    breakpoint: bool, // LD B,B (software breakpoint) executed since the last `take_breakpoint`
+    locked: Option<u16>, // Illegal opcode the CPU locked up on
+
+    history: [TraceEntry; HISTORY_LEN], // Last instructions (ring buffer, for crash reports and trace dumps)
+    executed: u64, // Instructions executed; the next history slot is `executed % HISTORY_LEN`
+}
+
+/// Instructions kept in the CPU's history (a power of two).
+pub const HISTORY_LEN: usize = 1024;
+
+/// Memory as seen by the CPU. `MMU` is the real implementation; tests and fuzzers can
+/// plug in something simpler, such as flat RAM.
+pub trait Bus {
+    fn read_byte(&self, addr: u16) -> u8;
+    fn write_byte(&mut self, addr: u16, value: u8);
+
+    /// Reads a byte of an instruction (opcode or operand) rather than data; only buses
+    /// that tell the two apart (ROM coverage) need to override it.
+    fn fetch(&self, addr: u16) -> u8 {
+        self.read_byte(addr)
+    }
+
+    /// Writes a byte the CPU pushes onto the stack, and reads one it pops; only buses that
+    /// tell stack accesses from loads and stores (stack checking) need to override them.
+    fn push_byte(&mut self, addr: u16, value: u8) {
+        self.write_byte(addr, value)
+    }
+
+    fn pop_byte(&self, addr: u16) -> u8 {
+        self.read_byte(addr)
+    }
+
+    /// Called on STOP; returns `true` if it switched the CGB CPU speed.
+    fn stop(&mut self) -> bool {
+        false
+    }
+}
+
+/// Snapshot of the CPU registers, for test harnesses and debuggers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Registers {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+}
+
+/// Instruction in the CPU's history: the registers before it ran (so `pc` is its address)
+/// and its opcode. Recording one is a single store, cheap enough to keep on all the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub registers: Registers,
+    pub opcode: u8,
+}
+
+impl TraceEntry {
+    const EMPTY: TraceEntry = TraceEntry {
+        registers: Registers { a: 0, f: 0, b: 0, c: 0, d: 0, e: 0, h: 0, l: 0, sp: 0, pc: 0 },
+        opcode: 0,
+    };
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
@@ -45,18 +122,137 @@ impl CPU {
             l: 0x4D,
             ei_pending: false,
             ime: false,
+            halted: false,
+            interrupt_mask: 0x1F,
+            dispatched: None,
+            breakpoint: false,
+            locked: None,
+            history: [TraceEntry::EMPTY; HISTORY_LEN],
+            executed: 0,
+        }
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            a: self.a, f: self.f, b: self.b, c: self.c,
+            d: self.d, e: self.e, h: self.h, l: self.l,
+            sp: self.sp, pc: self.pc,
+        }
+    }
+
+    /// Overwrites all registers (e.g. to start a test or fuzz case from a given state).
+    pub fn set_registers(&mut self, r: &Registers) {
+        (self.a, self.f, self.b, self.c) = (r.a, r.f & 0xF0, r.b, r.c);
+        (self.d, self.e, self.h, self.l) = (r.d, r.e, r.h, r.l);
+        (self.sp, self.pc) = (r.sp, r.pc);
+    }
+
+    /// Selects the interrupt sources the CPU services: VBlank and serial (two-player
+    /// link) for the Tetris profile, all five otherwise.
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.interrupt_mask = match profile {
+            Profile::Tetris => 0x09,
+            Profile::Compatible | Profile::Accurate => 0x1F,
+        };
+    }
+
+    /// `true` while the CPU sleeps in HALT.
+    #[cfg_attr(not(feature = "internals"), allow(dead_code))]
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// `true` while the CPU sleeps in HALT with no enabled interrupt requested, so steps
+    /// do nothing but burn 4 T-cycles until one is.
+    pub fn sleeping(&self, mmu: &impl Bus) -> bool {
+        self.halted && mmu.read_byte(0xFFFF) & mmu.read_byte(0xFF0F) & self.interrupt_mask == 0
+    }
+
+    /// Illegal opcode (0xD3, 0xDB, ...) the CPU locked up on, if any.
+    pub fn unimplemented_opcode(&self) -> Option<u16> {
+        self.locked
+    }
+
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// The last instructions executed (up to `HISTORY_LEN`), oldest first.
+    pub fn history(&self) -> Vec<TraceEntry> {
+        let len = self.executed.min(HISTORY_LEN as u64) as usize;
+        let next = self.executed as usize % HISTORY_LEN;
+        (0..len).map(|i| self.history[(next + HISTORY_LEN - len + i) % HISTORY_LEN]).collect()
+    }
+
+    /// Returns `true` (once) if `LD B,B` was executed since the last call.
+    pub fn take_breakpoint(&mut self) -> bool {
+        std::mem::take(&mut self.breakpoint)
+    }
+
+    /// Returns (once) the bit of the interrupt serviced since the last call, if any; the
+    /// latest one if there were several.
+    pub fn take_dispatched(&mut self) -> Option<u8> {
+        self.dispatched.take()
+    }
+
+    /// Layout version of the `CPU ` save-state chunk.
+    pub const STATE_VERSION: u16 = 1;
+
+    /// Serializes all registers and interrupt state.
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.u16(self.pc);
+        w.u16(self.sp);
+        for r in [self.a, self.f, self.b, self.c, self.d, self.e, self.h, self.l] {
+            w.u8(r);
+        }
+        w.bool(self.ei_pending);
+        w.bool(self.ime);
+        w.bool(self.halted);
+    }
+
+    /// Restores the state written by `save_state`.
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.pc = r.u16()?;
+        self.sp = r.u16()?;
+        for reg in [
+            &mut self.a, &mut self.f, &mut self.b, &mut self.c,
+            &mut self.d, &mut self.e, &mut self.h, &mut self.l,
+        ] {
+            *reg = r.u8()?;
         }
+        self.ei_pending = r.bool()?;
+        self.ime = r.bool()?;
+        self.halted = r.bool()?;
+        self.locked = None;
+        Ok(())
     }
 
     /// Execute one CPU step:
-    /// - If IME is set and a VBlank interrupt (IE&IF bit 0) is pending, service it
-    ///   immediately (push PC, clear IF.VBlank, IME=0, jump to 0x0040) and return 20 T-cycles.
+    /// - While halted, burn 4 T-cycles until an enabled interrupt is requested (IE&IF),
+    ///   which wakes the CPU even with IME clear.
+    /// - If IME is set and an interrupt is pending, service the highest-priority one
+    ///   immediately (push PC, clear its IF bit, IME=0, jump to its vector) and return
+    ///   20 T-cycles. The Tetris profile only services VBlank and serial.
     /// - Otherwise fetch–decode–execute one opcode at PC and return its T-cycle cost.
     /// - EI takes effect after the *next* instruction (delayed IME enable).
-    /// Notes: 1 M-cycle = 4 T-cycles. This is a Tetris-only fast path (VBlank only).
-    pub fn step(&mut self, mmu: &mut MMU) -> u32 {
-        if self.ime && self.vblank_pending(mmu) {
-            let t = self.service_interrupt(mmu);
+    /// - An illegal opcode locks the CPU up, as on hardware: PC stays on it and every
+    ///   later step just burns 4 T-cycles.
+    /// Notes: 1 M-cycle = 4 T-cycles.
+    pub fn step(&mut self, mmu: &mut impl Bus) -> u32 {
+        if self.locked.is_some() {
+            return 4;
+        }
+
+        let requested = mmu.read_byte(0xFFFF) & mmu.read_byte(0xFF0F) & self.interrupt_mask;
+        if self.halted {
+            if requested == 0 {
+                return 4;
+            }
+            self.halted = false;
+        }
+
+        if self.ime && requested != 0 {
+            let t = self.service_interrupt(mmu, requested.trailing_zeros() as u8);
             return t;
         }
 
@@ -72,8 +268,10 @@ impl CPU {
 
     /// Fetch–decode–execute a single opcode at PC.
     /// Each opcode returns the number of t-cycles consumed.
-    fn opcode(&mut self, memory: &mut MMU) -> u32 {
-        let opcode = memory.read_byte(self.pc);
+    fn opcode(&mut self, memory: &mut impl Bus) -> u32 {
+        let opcode = memory.fetch(self.pc);
+        self.history[self.executed as usize % HISTORY_LEN] = TraceEntry { registers: self.registers(), opcode };
+        self.executed = self.executed.wrapping_add(1);
         self.pc = self.pc.wrapping_add(1);
 
         match opcode {
@@ -139,6 +337,14 @@ impl CPU {
                 4
             }
 
+            0x08 => {
+                // LD (a16),SP
+                let addr = self.fetch_u16(memory);
+                memory.write_byte(addr, (self.sp & 0xFF) as u8);
+                memory.write_byte(addr.wrapping_add(1), (self.sp >> 8) as u8);
+                20
+            }
+
             0x09 => {
                 // ADD HL,BC
                 let hl = self.get_hl();
@@ -191,6 +397,25 @@ impl CPU {
                 8
             }
 
+            0x0F => {
+                // RRCA
+                let carry = (self.a & 0x01) != 0;
+                self.a = self.a.rotate_right(1);
+                self.set_flag_z(false);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(carry);
+                4
+            }
+
+            0x10 => {
+                // STOP (the byte after it is skipped). Performs the CGB speed switch when
+                // armed; otherwise low-power mode isn't modeled and it acts as a NOP.
+                self.fetch_u8(memory);
+                memory.stop();
+                4
+            }
+
             0x11 => {
                 // LD DE,d16
                 let val = self.fetch_u16(memory);
@@ -211,6 +436,24 @@ impl CPU {
                 8
             }
 
+            0x14 => {
+                // INC D
+                self.d = self.d.wrapping_add(1);
+                self.set_flag_z(self.d == 0);
+                self.set_flag_n(false);
+                self.set_flag_h((self.d & 0x0F) == 0);
+                4
+            }
+
+            0x15 => {
+                // DEC D
+                self.d = self.d.wrapping_sub(1);
+                self.set_flag_z(self.d == 0);
+                self.set_flag_n(true);
+                self.set_flag_h((self.d & 0x0F) == 0x0F);
+                4
+            }
+
             0x16 => {
                 // LD D,d8
                 let val = self.fetch_u8(memory);
@@ -218,6 +461,17 @@ impl CPU {
                 8
             }
 
+            0x17 => {
+                // RLA
+                let carry = (self.a & 0x80) != 0;
+                self.a = (self.a << 1) | self.get_flag_c() as u8;
+                self.set_flag_z(false);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(carry);
+                4
+            }
+
             0x18 => {
                 // JR r8
                 let offset = self.fetch_u8(memory) as i8 as i16;
@@ -276,6 +530,17 @@ impl CPU {
                 8
             }
 
+            0x1F => {
+                // RRA
+                let carry = (self.a & 0x01) != 0;
+                self.a = (self.a >> 1) | ((self.get_flag_c() as u8) << 7);
+                self.set_flag_z(false);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(carry);
+                4
+            }
+
             0x20 => {
                 // JR NZ,r8
                 let offset = self.fetch_u8(memory) as i8 as i16;
@@ -309,6 +574,15 @@ impl CPU {
                 8
             }
 
+            0x24 => {
+                // INC H
+                self.h = self.h.wrapping_add(1);
+                self.set_flag_z(self.h == 0);
+                self.set_flag_n(false);
+                self.set_flag_h((self.h & 0x0F) == 0);
+                4
+            }
+
             0x25 => {
                 // DEC H
                 self.h = self.h.wrapping_sub(1);
@@ -368,6 +642,18 @@ impl CPU {
                 }
             }
 
+            0x29 => {
+                // ADD HL,HL
+                let hl = self.get_hl();
+                let val = self.get_hl();
+                let res = hl.wrapping_add(val);
+                self.set_flag_n(false);
+                self.set_flag_h(((hl & 0x0FFF) + (val & 0x0FFF)) > 0x0FFF);
+                self.set_flag_c(hl > 0xFFFF - val);
+                self.set_hl(res);
+                8
+            }
+
             0x2A => {
                 // LD A,(HL+)
                 let hl = self.get_hl();
@@ -442,6 +728,12 @@ impl CPU {
                 8
             }
 
+            0x33 => {
+                // INC SP
+                self.sp = self.sp.wrapping_add(1);
+                8
+            }
+
             0x34 => {
                 // INC (HL)
                 let addr = self.get_hl();
@@ -473,6 +765,14 @@ impl CPU {
                 12
             }
 
+            0x37 => {
+                // SCF
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(true);
+                4
+            }
+
             0x38 => {
                 // JR C,r8
                 let offset = self.fetch_u8(memory) as i8 as i16;
@@ -484,6 +784,24 @@ impl CPU {
                 }
             }
 
+            0x39 => {
+                // ADD HL,SP
+                let hl = self.get_hl();
+                let val = self.sp;
+                let res = hl.wrapping_add(val);
+                self.set_flag_n(false);
+                self.set_flag_h(((hl & 0x0FFF) + (val & 0x0FFF)) > 0x0FFF);
+                self.set_flag_c(hl > 0xFFFF - val);
+                self.set_hl(res);
+                8
+            }
+
+            0x3B => {
+                // DEC SP
+                self.sp = self.sp.wrapping_sub(1);
+                8
+            }
+
             0x3C => {
                 // INC A
                 let val = self.a;
@@ -518,8 +836,47 @@ impl CPU {
                 8
             }
 
+            0x3F => {
+                // CCF
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(!self.get_flag_c());
+                4
+            }
+
             0x40 => { 
-                // LD B,B
+                // LD B,B (used as a software breakpoint by emulator test suites)
+                self.breakpoint = true;
+                4
+            }
+
+            0x41 => {
+                // LD B,C
+                self.b = self.c;
+                4
+            }
+
+            0x42 => {
+                // LD B,D
+                self.b = self.d;
+                4
+            }
+
+            0x43 => {
+                // LD B,E
+                self.b = self.e;
+                4
+            }
+
+            0x44 => {
+                // LD B,H
+                self.b = self.h;
+                4
+            }
+
+            0x45 => {
+                // LD B,L
+                self.b = self.l;
                 4
             }
 
@@ -535,6 +892,41 @@ impl CPU {
                 4
             }
 
+            0x48 => {
+                // LD C,B
+                self.c = self.b;
+                4
+            }
+
+            0x49 => {
+                // LD C,C (no effect)
+                4
+            }
+
+            0x4A => {
+                // LD C,D
+                self.c = self.d;
+                4
+            }
+
+            0x4B => {
+                // LD C,E
+                self.c = self.e;
+                4
+            }
+
+            0x4C => {
+                // LD C,H
+                self.c = self.h;
+                4
+            }
+
+            0x4D => {
+                // LD C,L
+                self.c = self.l;
+                4
+            }
+
             0x4E => {
                 let addr = self.get_hl();
                 self.c = memory.read_byte(addr);
@@ -547,12 +939,41 @@ impl CPU {
                 4
             }
 
+            0x50 => {
+                // LD D,B
+                self.d = self.b;
+                4
+            }
+
+            0x51 => {
+                // LD D,C
+                self.d = self.c;
+                4
+            }
+
+            0x52 => {
+                // LD D,D (no effect)
+                4
+            }
+
+            0x53 => {
+                // LD D,E
+                self.d = self.e;
+                4
+            }
+
             0x54 => { 
                 // LD D,H
                 self.d = self.h;
                 4
             }
 
+            0x55 => {
+                // LD D,L
+                self.d = self.l;
+                4
+            }
+
             0x56 => {
                 // LD D,(HL)
                 let addr = self.get_hl();
@@ -566,6 +987,35 @@ impl CPU {
                 4
             }
 
+            0x58 => {
+                // LD E,B
+                self.e = self.b;
+                4
+            }
+
+            0x59 => {
+                // LD E,C
+                self.e = self.c;
+                4
+            }
+
+            0x5A => {
+                // LD E,D
+                self.e = self.d;
+                4
+            }
+
+            0x5B => {
+                // LD E,E (no effect)
+                4
+            }
+
+            0x5C => {
+                // LD E,H
+                self.e = self.h;
+                4
+            }
+
             0x5D => { 
                 // LD E,L
                 self.e = self.l;
@@ -603,24 +1053,76 @@ impl CPU {
                 4
             }
 
+            0x63 => {
+                // LD H,E
+                self.h = self.e;
+                4
+            }
+
+            0x64 => {
+                // LD H,H (no effect)
+                4
+            }
+
+            0x65 => {
+                // LD H,L
+                self.h = self.l;
+                4
+            }
+
+            0x66 => {
+                // LD H,(HL)
+                self.h = memory.read_byte(self.get_hl());
+                8
+            }
+
             0x67 => { 
                 // LD H,A
                 self.h = self.a;
                 4
             }
 
+            0x68 => {
+                // LD L,B
+                self.l = self.b;
+                4
+            }
+
             0x69 => { 
                 // LD L, C
                 self.l = self.c;
                 4
             }
 
+            0x6A => {
+                // LD L,D
+                self.l = self.d;
+                4
+            }
+
             0x6B => { 
                 // LD L,E
                 self.l = self.e;
                 4
             }
 
+            0x6C => {
+                // LD L,H
+                self.l = self.h;
+                4
+            }
+
+            0x6D => {
+                // LD L,L (no effect)
+                4
+            }
+
+            0x6E => {
+                // LD L,(HL)
+                self.l = memory.read_byte(self.get_hl());
+                8
+            }
+
             0x6F => { 
                 // LD L,A
                 self.l = self.a;
@@ -652,6 +1154,24 @@ impl CPU {
                 8
             }
 
+            0x74 => {
+                // LD (HL),H
+                memory.write_byte(self.get_hl(), self.h);
+                8
+            }
+
+            0x75 => {
+                // LD (HL),L
+                memory.write_byte(self.get_hl(), self.l);
+                8
+            }
+
+            0x76 => {
+                // HALT (sleep until an enabled interrupt is requested)
+                self.halted = true;
+                4
+            }
+
             0x77 => {
                 // LD (HL),A
                 memory.write_byte(self.get_hl(), self.a);
@@ -699,12 +1219,23 @@ impl CPU {
                 8
             }
 
+            0x7F => {
+                // LD A,A (no effect)
+                4
+            }
+
             0x80 => {
                 // ADD A,B
                 self.a = self.add8(self.a, self.b, false);
                 4
             }
 
+            0x81 => {
+                // ADD A,C
+                self.a = self.add8(self.a, self.c, false);
+                4
+            }
+
             0x82 => {
                 // ADD A,D
                 self.a = self.add8(self.a, self.d, false);
@@ -717,6 +1248,12 @@ impl CPU {
                 4
             }
 
+            0x84 => {
+                // ADD A,H
+                self.a = self.add8(self.a, self.h, false);
+                4
+            }
+
             0x85 => {
                 // ADD A,L
                 self.a = self.add8(self.a, self.l, false);
@@ -737,12 +1274,42 @@ impl CPU {
                 4
             }
 
+            0x88 => {
+                // ADC A,B
+                self.a = self.add8(self.a, self.b, self.get_flag_c());
+                4
+            }
+
             0x89 => { 
                 // ADC A,C
                 self.a = self.add8(self.a, self.c, self.get_flag_c());
                 4
             }
 
+            0x8A => {
+                // ADC A,D
+                self.a = self.add8(self.a, self.d, self.get_flag_c());
+                4
+            }
+
+            0x8B => {
+                // ADC A,E
+                self.a = self.add8(self.a, self.e, self.get_flag_c());
+                4
+            }
+
+            0x8C => {
+                // ADC A,H
+                self.a = self.add8(self.a, self.h, self.get_flag_c());
+                4
+            }
+
+            0x8D => {
+                // ADC A,L
+                self.a = self.add8(self.a, self.l, self.get_flag_c());
+                4
+            }
+
             0x8E => {
                 // ADC A,(HL)
                 let val = memory.read_byte(self.get_hl());
@@ -750,17 +1317,65 @@ impl CPU {
                 8
             }
 
+            0x8F => {
+                // ADC A,A
+                self.a = self.add8(self.a, self.a, self.get_flag_c());
+                4
+            }
+
             0x90 => {
                 // SUB B
                 self.a = self.sub8(self.a, self.b, false);
                 4
             }
 
-            0x96 => {
-                // SUB (HL)
-                let val = memory.read_byte(self.get_hl());
-                self.a = self.sub8(self.a, val, false);
-                8
+            0x91 => {
+                // SUB C
+                self.a = self.sub8(self.a, self.c, false);
+                4
+            }
+
+            0x92 => {
+                // SUB D
+                self.a = self.sub8(self.a, self.d, false);
+                4
+            }
+
+            0x93 => {
+                // SUB E
+                self.a = self.sub8(self.a, self.e, false);
+                4
+            }
+
+            0x94 => {
+                // SUB H
+                self.a = self.sub8(self.a, self.h, false);
+                4
+            }
+
+            0x95 => {
+                // SUB L
+                self.a = self.sub8(self.a, self.l, false);
+                4
+            }
+
+            0x96 => {
+                // SUB (HL)
+                let val = memory.read_byte(self.get_hl());
+                self.a = self.sub8(self.a, val, false);
+                8
+            }
+
+            0x97 => {
+                // SUB A
+                self.a = self.sub8(self.a, self.a, false);
+                4
+            }
+
+            0x98 => {
+                // SBC A,B
+                self.a = self.sub8(self.a, self.b, self.get_flag_c());
+                4
             }
 
             0x99 => {
@@ -778,6 +1393,43 @@ impl CPU {
                 4
             }
 
+            0x9A => {
+                // SBC A,D
+                self.a = self.sub8(self.a, self.d, self.get_flag_c());
+                4
+            }
+
+            0x9B => {
+                // SBC A,E
+                self.a = self.sub8(self.a, self.e, self.get_flag_c());
+                4
+            }
+
+            0x9C => {
+                // SBC A,H
+                self.a = self.sub8(self.a, self.h, self.get_flag_c());
+                4
+            }
+
+            0x9D => {
+                // SBC A,L
+                self.a = self.sub8(self.a, self.l, self.get_flag_c());
+                4
+            }
+
+            0x9E => {
+                // SBC A,(HL)
+                let val = memory.read_byte(self.get_hl());
+                self.a = self.sub8(self.a, val, self.get_flag_c());
+                8
+            }
+
+            0x9F => {
+                // SBC A,A
+                self.a = self.sub8(self.a, self.a, self.get_flag_c());
+                4
+            }
+
             0xA0 => {
                 // AND B
                 self.a &= self.b;
@@ -798,6 +1450,57 @@ impl CPU {
                 4
             }
 
+            0xA2 => {
+                // AND D
+                self.a &= self.d;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(true);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xA3 => {
+                // AND E
+                self.a &= self.e;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(true);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xA4 => {
+                // AND H
+                self.a &= self.h;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(true);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xA5 => {
+                // AND L
+                self.a &= self.l;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(true);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xA6 => {
+                // AND (HL)
+                let val = memory.read_byte(self.get_hl());
+                self.a &= val;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(true);
+                self.set_flag_c(false);
+                8
+            }
+
             0xA7 => {
                 // AND A
                 self.a &= self.a;
@@ -828,6 +1531,57 @@ impl CPU {
                 4
             }
 
+            0xAA => {
+                // XOR D
+                self.a ^= self.d;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xAB => {
+                // XOR E
+                self.a ^= self.e;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xAC => {
+                // XOR H
+                self.a ^= self.h;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xAD => {
+                // XOR L
+                self.a ^= self.l;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xAE => {
+                // XOR (HL)
+                let val = memory.read_byte(self.get_hl());
+                self.a ^= val;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                8
+            }
+
             0xAF => {
                 // XOR A
                 self.a = 0;
@@ -868,6 +1622,26 @@ impl CPU {
                 4
             }
 
+            0xB3 => {
+                // OR E
+                self.a |= self.e;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
+            0xB4 => {
+                // OR H
+                self.a |= self.h;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
             0xB5 => {
                 // OR L
                 self.a |= self.l;
@@ -878,6 +1652,17 @@ impl CPU {
                 4
             }
 
+            0xB6 => {
+                // OR (HL)
+                let val = memory.read_byte(self.get_hl());
+                self.a |= val;
+                self.set_flag_z(self.a == 0);
+                self.set_flag_n(false);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                8
+            }
+
             0xB7 => {
                 // OR A
                 self.a |= self.a;
@@ -908,6 +1693,46 @@ impl CPU {
                 4
             }
 
+            0xBA => {
+                // CP D
+                let res = self.a.wrapping_sub(self.d);
+                self.set_flag_z(res == 0);
+                self.set_flag_n(true);
+                self.set_flag_h((self.a & 0x0F) < (self.d & 0x0F));
+                self.set_flag_c(self.a < self.d);
+                4
+            }
+
+            0xBB => {
+                // CP E
+                let res = self.a.wrapping_sub(self.e);
+                self.set_flag_z(res == 0);
+                self.set_flag_n(true);
+                self.set_flag_h((self.a & 0x0F) < (self.e & 0x0F));
+                self.set_flag_c(self.a < self.e);
+                4
+            }
+
+            0xBC => {
+                // CP H
+                let res = self.a.wrapping_sub(self.h);
+                self.set_flag_z(res == 0);
+                self.set_flag_n(true);
+                self.set_flag_h((self.a & 0x0F) < (self.h & 0x0F));
+                self.set_flag_c(self.a < self.h);
+                4
+            }
+
+            0xBD => {
+                // CP L
+                let res = self.a.wrapping_sub(self.l);
+                self.set_flag_z(res == 0);
+                self.set_flag_n(true);
+                self.set_flag_h((self.a & 0x0F) < (self.l & 0x0F));
+                self.set_flag_c(self.a < self.l);
+                4
+            }
+
             0xBE => {
                 // CP (HL)
                 let val = memory.read_byte(self.get_hl());
@@ -919,6 +1744,15 @@ impl CPU {
                 8
             }
 
+            0xBF => {
+                // CP A (always equal)
+                self.set_flag_z(true);
+                self.set_flag_n(true);
+                self.set_flag_h(false);
+                self.set_flag_c(false);
+                4
+            }
+
             0xC2 => { 
                 // JP NZ,nn
                 let addr = self.fetch_u16(memory);
@@ -930,6 +1764,25 @@ impl CPU {
                 }
             }
 
+            0xC4 => {
+                // CALL NZ,nn
+                let addr = self.fetch_u16(memory);
+                if !self.get_flag_z() {
+                    self.push(memory, self.pc);
+                    self.pc = addr;
+                    24
+                } else {
+                    12
+                }
+            }
+
+            0xC7 => {
+                // RST 00H
+                self.push(memory, self.pc);
+                self.pc = 0x00;
+                16
+            }
+
             0xCA => {
                 // JP Z,nn
                 let addr = self.fetch_u16(memory);
@@ -998,6 +1851,18 @@ impl CPU {
                 16
             }
 
+            0xCC => {
+                // CALL Z,nn
+                let addr = self.fetch_u16(memory);
+                if self.get_flag_z() {
+                    self.push(memory, self.pc);
+                    self.pc = addr;
+                    24
+                } else {
+                    12
+                }
+            }
+
             0xCD => {
                 // CALL nn
                 let addr = self.fetch_u16(memory);
@@ -1006,6 +1871,20 @@ impl CPU {
                 24
             }
 
+            0xCE => {
+                // ADC A,d8
+                let val = self.fetch_u8(memory);
+                self.a = self.add8(self.a, val, self.get_flag_c());
+                8
+            }
+
+            0xCF => {
+                // RST 08H
+                self.push(memory, self.pc);
+                self.pc = 0x08;
+                16
+            }
+
             0xD0 => {
                 // RET NC
                 if !self.get_flag_c() {
@@ -1025,6 +1904,29 @@ impl CPU {
                 12
             }
 
+            0xD2 => { 
+                // JP NC,nn
+                let addr = self.fetch_u16(memory);
+                if !self.get_flag_c() {
+                    self.pc = addr;
+                    16
+                } else {
+                    12
+                }
+            }
+
+            0xD4 => {
+                // CALL NC,nn
+                let addr = self.fetch_u16(memory);
+                if !self.get_flag_c() {
+                    self.push(memory, self.pc);
+                    self.pc = addr;
+                    24
+                } else {
+                    12
+                }
+            }
+
             0xD5 => {
                 // PUSH DE
                 self.push_reg_pair(memory, self.d, self.e);
@@ -1044,6 +1946,13 @@ impl CPU {
                 8
             }
 
+            0xD7 => {
+                // RST 10H
+                self.push(memory, self.pc);
+                self.pc = 0x10;
+                16
+            }
+
             0xD8 => {
                 // RET C
                 if self.get_flag_c() {
@@ -1063,10 +1972,32 @@ impl CPU {
                 16
             }
 
+            0xDA => { 
+                // JP C,nn
+                let addr = self.fetch_u16(memory);
+                if self.get_flag_c() {
+                    self.pc = addr;
+                    16
+                } else {
+                    12
+                }
+            }
+
+            0xDC => {
+                // CALL C,nn
+                let addr = self.fetch_u16(memory);
+                if self.get_flag_c() {
+                    self.push(memory, self.pc);
+                    self.pc = addr;
+                    24
+                } else {
+                    12
+                }
+            }
+
             0xDE => {
                 // SBC A, d8
-                let value = memory.read_byte(self.pc);
-                self.pc = self.pc.wrapping_add(1);
+                let value = self.fetch_u8(memory);
 
                 let carry_in = if self.f & 0x10 != 0 { 1 } else { 0 }; // C flag
                 let result = self.a.wrapping_sub(value).wrapping_sub(carry_in);
@@ -1080,6 +2011,13 @@ impl CPU {
                 8
             }
 
+            0xDF => {
+                // RST 18H
+                self.push(memory, self.pc);
+                self.pc = 0x18;
+                16
+            }
+
             0xE0 => {
                 // LDH (n),A
                 let offset = self.fetch_u8(memory) as u16;
@@ -1119,6 +2057,24 @@ impl CPU {
                 8
             }
 
+            0xE7 => {
+                // RST 20H
+                self.push(memory, self.pc);
+                self.pc = 0x20;
+                16
+            }
+
+            0xE8 => {
+                // ADD SP,r8
+                let offset = self.fetch_u8(memory) as i8 as i16 as u16;
+                self.set_flag_z(false);
+                self.set_flag_n(false);
+                self.set_flag_h((self.sp & 0x0F) + (offset & 0x0F) > 0x0F);
+                self.set_flag_c((self.sp & 0xFF) + (offset & 0xFF) > 0xFF);
+                self.sp = self.sp.wrapping_add(offset);
+                16
+            }
+
             0xE9 => {
                 // JP (HL)
                 self.pc = self.get_hl();
@@ -1156,6 +2112,12 @@ impl CPU {
                 12
             }
 
+            0xF2 => {
+                // LD A,(FF00+C)
+                self.a = memory.read_byte(0xFF00 | self.c as u16);
+                8
+            }
+
             0xF6 => { 
                 // OR d8
                 let val = self.fetch_u8(memory);
@@ -1167,6 +2129,30 @@ impl CPU {
                 8
             }
 
+            0xF7 => {
+                // RST 30H
+                self.push(memory, self.pc);
+                self.pc = 0x30;
+                16
+            }
+
+            0xF8 => {
+                // LD HL,SP+r8
+                let offset = self.fetch_u8(memory) as i8 as i16 as u16;
+                self.set_flag_z(false);
+                self.set_flag_n(false);
+                self.set_flag_h((self.sp & 0x0F) + (offset & 0x0F) > 0x0F);
+                self.set_flag_c((self.sp & 0xFF) + (offset & 0xFF) > 0xFF);
+                self.set_hl(self.sp.wrapping_add(offset));
+                12
+            }
+
+            0xF9 => {
+                // LD SP,HL
+                self.sp = self.get_hl();
+                8
+            }
+
             0xFB => {
                 // EI (Enable Interrupts)
                 self.ei_pending = true; // IME will be enabled on next instruction
@@ -1210,291 +2196,112 @@ impl CPU {
                 8
             }
 
+            0xFF => {
+                // RST 38H
+                self.push(memory, self.pc);
+                self.pc = 0x38;
+                16
+            }
+
             0xCB => {
-                // PREFIX CB
+                // PREFIX CB: bits 0–2 pick the operand (B C D E H L (HL) A), bits 3–7 the operation
                 let cb_opcode = self.fetch_u8(memory);
-                match cb_opcode {
-                    0x27 => {
-                        // SLA A
-                        let carry = (self.a & 0x80) != 0;
-                        self.a <<= 1;
-                        self.set_flag_z(self.a == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(false);
-                        self.set_flag_c(carry);
-                        8
+                let index = cb_opcode & 0x07;
+                let value = self.read_operand(memory, index);
+                let bit = (cb_opcode >> 3) & 0x07;
+
+                let result = match cb_opcode {
+                    0x00..=0x07 => self.shift_flags(value.rotate_left(1), value & 0x80 != 0),  // RLC
+                    0x08..=0x0F => self.shift_flags(value.rotate_right(1), value & 0x01 != 0), // RRC
+                    0x10..=0x17 => {
+                        // RL (through carry)
+                        let res = (value << 1) | self.get_flag_c() as u8;
+                        self.shift_flags(res, value & 0x80 != 0)
                     }
-
-                    0x37 => {
-                        // SWAP A
-                        self.a = (self.a >> 4) | (self.a << 4);
-                        self.set_flag_z(self.a == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(false);
-                        self.set_flag_c(false);
-                        8
+                    0x18..=0x1F => {
+                        // RR (through carry)
+                        let res = (value >> 1) | ((self.get_flag_c() as u8) << 7);
+                        self.shift_flags(res, value & 0x01 != 0)
                     }
-
-                    0x3F => {
-                        // SRL A
-                        let carry = self.a & 0x01 != 0;
-                        self.a >>= 1;
-                        self.set_flag_z(self.a == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(false);
-                        self.set_flag_c(carry);
-                        8
-                    }
-
-                    0x40 => { 
-                        // BIT 0,B
-                        self.set_flag_z((self.b & (1 << 0)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x41 => { 
-                        // BIT 0,C
-                        self.set_flag_z((self.c & (1 << 0)) == 0);
+                    0x20..=0x27 => self.shift_flags(value << 1, value & 0x80 != 0),                 // SLA
+                    0x28..=0x2F => self.shift_flags((value >> 1) | (value & 0x80), value & 0x01 != 0), // SRA
+                    0x30..=0x37 => self.shift_flags(value.rotate_left(4), false),                   // SWAP
+                    0x38..=0x3F => self.shift_flags(value >> 1, value & 0x01 != 0),                 // SRL
+                    0x40..=0x7F => {
+                        // BIT n: flags only, nothing written back
+                        self.set_flag_z(value & (1 << bit) == 0);
                         self.set_flag_n(false);
                         self.set_flag_h(true);
-                        8
+                        return if index == 6 { 12 } else { 8 };
                     }
+                    0x80..=0xBF => value & !(1 << bit), // RES n
+                    0xC0..=0xFF => value | (1 << bit),  // SET n
+                };
 
-                    0x47 => { 
-                        // BIT 0,A
-                        self.set_flag_z((self.a & (1 << 0)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x48 => { 
-                        // BIT 1,B
-                        self.set_flag_z((self.b & (1 << 1)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x50 => { 
-                        // BIT 2,B
-                        self.set_flag_z((self.b & (1 << 2)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x57 => { 
-                        // BIT 2,A
-                        self.set_flag_z((self.a & (1 << 2)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x58 => { 
-                        // BIT 3,B
-                        self.set_flag_z((self.b & (1 << 3)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x5F => {
-                        // BIT 3,A
-                        let bit = (self.a >> 3) & 1;
-                        self.set_flag_z(bit == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x60 => { 
-                        // BIT 4,B
-                        self.set_flag_z((self.b & (1 << 4)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x61 => { 
-                        // BIT 4,C
-                        self.set_flag_z((self.c & (1 << 4)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x68 => { 
-                        // BIT 5,B
-                        self.set_flag_z((self.b & (1 << 5)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x69 => { 
-                        // BIT 5,C
-                        self.set_flag_z((self.c & (1 << 5)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x6F => { 
-                        // BIT 5,A
-                        self.set_flag_z((self.a & (1 << 5)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x70 => { 
-                        // BIT 6,B
-                        self.set_flag_z((self.b & (1 << 6)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x71 => {
-                        // BIT 6,C
-                        let bit = (self.c >> 6) & 1;
-                        self.set_flag_z(bit == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x77 => { 
-                        // BIT 6,A
-                        self.set_flag_z((self.a & (1 << 6)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x78 => { 
-                        // BIT 7,B
-                        self.set_flag_z((self.b & (1 << 7)) == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x79 => {
-                        // BIT 7,C
-                        let bit = (self.c >> 7) & 1;
-                        self.set_flag_z(bit == 0);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x7E => { 
-                        // LD A,(HL)
-                        self.a = memory.read_byte(self.get_hl());
-                        8
-                    }
-
-                    0x7F => {
-                        // BIT 7,A
-                        let bit_set = (self.a & (1 << 7)) != 0;
-                        self.set_flag_z(!bit_set);
-                        self.set_flag_n(false);
-                        self.set_flag_h(true);
-                        8
-                    }
-
-                    0x86 => { 
-                        // RES 0,(HL)
-                        let addr = self.get_hl();
-                        let mut val = memory.read_byte(addr);
-                        val &= !(1 << 0);
-                        memory.write_byte(addr, val);
-                        16
-                    }
-
-                    0x87 => {
-                        // RES 0,A
-                        self.a &= !(1 << 0);
-                        8
-                    }
-
-                    0x9E => {
-                        // RES 3,(HL)
-                        let addr = self.get_hl();
-                        let val = memory.read_byte(addr) & !(1 << 3);
-                        memory.write_byte(addr, val);
-                        16
-                    }
-
-                    0xBE => {
-                        // RES 7,(HL)
-                        let addr = self.get_hl();
-                        let mut val = memory.read_byte(addr);
-                        val &= !(1 << 7);
-                        memory.write_byte(addr, val);
-                        16
-                    }
-
-                    0xDE => {
-                        // SET 3,(HL)
-                        let addr = self.get_hl();
-                        let val = memory.read_byte(addr) | (1 << 3);
-                        memory.write_byte(addr, val);
-                        16
-                    }
-
-                    0xD8 => {
-                        // SET 3, B
-                        self.b |= 1 << 3;
-                        8
-                    }
-
-                    0xF8 => {
-                        // SET 7, B
-                        self.b |= 1 << 7;
-                        8
-                    }
-
-                    0xFE => {
-                        // SET 7, (HL)
-                        let addr = self.get_hl();
-                        let val = memory.read_byte(addr) | (1 << 7);
-                        memory.write_byte(addr, val);
-                        16
-                    }
+                self.write_operand(memory, index, result);
+                if index == 6 { 16 } else { 8 }
+            }
 
-                    _ => {
-                        eprintln!("Unknown CB opcode: 0x{:02X}", cb_opcode);
-                        std::process::exit(1);
-                    }
-                }
-            }  
-             _ => {
-                eprintln!("Unknown opcode: 0x{:02X}", opcode);
-                std::process::exit(1);
+            _ => {
+                // Illegal opcodes (0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB–0xED, 0xF4, 0xFC, 0xFD)
+                self.locked = Some(opcode as u16);
+                self.pc = self.pc.wrapping_sub(1);
+                4
             }
         }   
     }
 
     /// Read an immediate byte at PC (little-endian helper).
-    fn fetch_u8(&mut self, mmu: &MMU) -> u8 {
-        let b = mmu.read_byte(self.pc);
+    fn fetch_u8(&mut self, mmu: &impl Bus) -> u8 {
+        let b = mmu.fetch(self.pc);
         self.pc = self.pc.wrapping_add(1);
         b
     }
 
     /// Read an immediate word at PC: low byte then high byte.
-    fn fetch_u16(&mut self, mmu: &MMU) -> u16 {
+    fn fetch_u16(&mut self, mmu: &impl Bus) -> u16 {
         let lo = self.fetch_u8(mmu) as u16;
         let hi = self.fetch_u8(mmu) as u16;
         (hi << 8) | lo
     }
 
+    /// Reads the operand encoded in the low 3 bits of an opcode: B C D E H L (HL) A.
+    fn read_operand(&self, memory: &impl Bus, index: u8) -> u8 {
+        match index {
+            0 => self.b,
+            1 => self.c,
+            2 => self.d,
+            3 => self.e,
+            4 => self.h,
+            5 => self.l,
+            6 => memory.read_byte(self.get_hl()),
+            _ => self.a,
+        }
+    }
+
+    /// Writes the operand encoded in the low 3 bits of an opcode (see `read_operand`).
+    fn write_operand(&mut self, memory: &mut impl Bus, index: u8, value: u8) {
+        match index {
+            0 => self.b = value,
+            1 => self.c = value,
+            2 => self.d = value,
+            3 => self.e = value,
+            4 => self.h = value,
+            5 => self.l = value,
+            6 => memory.write_byte(self.get_hl(), value),
+            _ => self.a = value,
+        }
+    }
+
+    /// Flags of the CB rotates/shifts/SWAP: Z from the result, N=H=0, C as given.
+    fn shift_flags(&mut self, result: u8, carry: bool) -> u8 {
+        self.set_flag_z(result == 0);
+        self.set_flag_n(false);
+        self.set_flag_h(false);
+        self.set_flag_c(carry);
+        result
+    }
+
     /// 8-bit addition with optional carry-in; updates Z N H C.
     fn add8(&mut self, a: u8, b: u8, carry: bool) -> u8 {
         let c = if carry && self.get_flag_c() { 1 } else { 0 };
@@ -1520,37 +2327,37 @@ impl CPU {
     }
 
     /// Push a 16-bit value to the stack (little-endian in memory).
-    fn push(&mut self, mmu: &mut MMU, value: u16) {
+    fn push(&mut self, mmu: &mut impl Bus, value: u16) {
         self.sp = self.sp.wrapping_sub(2);
-        mmu.write_byte(self.sp, (value & 0xFF) as u8);      // Low byte
-        mmu.write_byte(self.sp.wrapping_add(1), (value >> 8) as u8); // High byte
+        mmu.push_byte(self.sp, (value & 0xFF) as u8);      // Low byte
+        mmu.push_byte(self.sp.wrapping_add(1), (value >> 8) as u8); // High byte
     }
 
     /// Pop a 16-bit value from the stack.
-    fn pop(&mut self, mmu: &mut MMU) -> u16 {
-        let lo = mmu.read_byte(self.sp) as u16;
-        let hi = mmu.read_byte(self.sp.wrapping_add(1)) as u16;
+    fn pop(&mut self, mmu: &mut impl Bus) -> u16 {
+        let lo = mmu.pop_byte(self.sp) as u16;
+        let hi = mmu.pop_byte(self.sp.wrapping_add(1)) as u16;
         self.sp = self.sp.wrapping_add(2);
         (hi << 8) | lo
     }
 
     /// Push/pop helpers for AF respect that the lower nibble of F is always zero.
-    fn push_af(&mut self, mmu: &mut MMU) { 
+    fn push_af(&mut self, mmu: &mut impl Bus) { 
         self.push(mmu, self.get_af()); 
     }
 
-    fn pop_af(&mut self, mmu: &mut MMU) {
+    fn pop_af(&mut self, mmu: &mut impl Bus) {
         let v = self.pop(mmu);
         self.set_af(v); // masks F a 0xF0
     }
 
     /// Push a 16-bit register pair to the stack.
-    fn push_reg_pair(&mut self, mmu: &mut MMU, high: u8, low: u8) {
+    fn push_reg_pair(&mut self, mmu: &mut impl Bus, high: u8, low: u8) {
         self.push(mmu, ((high as u16) << 8) | (low as u16));
     }
 
     // Pop a 16-bit register pair from the stack.
-    fn pop_reg_pair(&mut self, mmu: &mut MMU) -> (u8, u8) {
+    fn pop_reg_pair(&mut self, mmu: &mut impl Bus) -> (u8, u8) {
         let value = self.pop(mmu);
         ((value >> 8) as u8, (value & 0xFF) as u8)
     }
@@ -1643,19 +2450,16 @@ impl CPU {
         ((self.d as u16) << 8) | (self.e as u16)
     }
 
-    fn vblank_pending(&self, mmu: &MMU) -> bool {
-        (mmu.read_byte(0xFFFF) & mmu.read_byte(0xFF0F)) & 0x01 != 0
-    }
-
-    // Handle only VBlank (bit 0) for Tetris; ignore other sources.
-    fn service_interrupt(&mut self, mmu: &mut MMU) -> u32 {
-        // Clear IF.VBlank and jump to 0x0040
-        let iflag = mmu.read_byte(0xFF0F) & !0x01;
+    // Service interrupt `bit` (0 VBlank, 1 STAT, 2 Timer, 3 Serial, 4 Joypad).
+    fn service_interrupt(&mut self, mmu: &mut impl Bus, bit: u8) -> u32 {
+        // Clear its IF bit and jump to 0x0040 + 8 * bit
+        let iflag = mmu.read_byte(0xFF0F) & !(1 << bit);
         mmu.write_byte(0xFF0F, iflag);
 
         self.ime = false;
+        self.dispatched = Some(bit);
         self.push(mmu, self.pc);
-        self.pc = 0x0040; // VBlank vector
+        self.pc = 0x0040 + 8 * bit as u16;
         20 // t-cycles
     }
 }
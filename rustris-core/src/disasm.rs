@@ -0,0 +1,86 @@
+//! LR35902 disassembler, for debuggers and trace tools.
+//!
+//! Mnemonics follow the notation of the opcode comments in the CPU: `LD (HL+),A`,
+//! `LDH (a8),A` and so on, with operands in hex (`$`). Relative jumps show their target
+//! address rather than the offset.
+
+const R8: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const R16: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const R16_STACK: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const R16_MEMORY: [&str; 4] = ["(BC)", "(DE)", "(HL+)", "(HL-)"];
+const CONDITIONS: [&str; 4] = ["NZ", "Z", "NC", "C"];
+const ALU: [&str; 8] = ["ADD A,", "ADC A,", "SUB ", "SBC A,", "AND ", "XOR ", "OR ", "CP "];
+const ROTATIONS: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SWAP", "SRL"];
+
+/// Decodes the instruction whose bytes start `bytes` (only as many as it needs are read,
+/// at most 3; missing ones count as 0), located at `addr`. Returns its text and length
+/// in bytes. Opcodes the CPU doesn't have come out as `DB $xx`, one byte long.
+pub fn disassemble(addr: u16, bytes: &[u8]) -> (String, u16) {
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+    let (opcode, n8, n16) = (byte(0), byte(1), u16::from_le_bytes([byte(1), byte(2)]));
+    let (x, y, z) = (opcode >> 6, (opcode >> 3) & 7, opcode & 7);
+    let (p, q) = ((y >> 1) as usize, y & 1);
+    let relative = addr.wrapping_add(2).wrapping_add(n8 as i8 as u16);
+
+    let (text, len) = match (x, z) {
+        (0, 0) => match y {
+            0 => (String::from("NOP"), 1),
+            1 => (format!("LD (${:04X}),SP", n16), 3),
+            2 => (String::from("STOP"), 2),
+            3 => (format!("JR ${:04X}", relative), 2),
+            _ => (format!("JR {},${:04X}", CONDITIONS[y as usize - 4], relative), 2),
+        },
+        (0, 1) if q == 0 => (format!("LD {},${:04X}", R16[p], n16), 3),
+        (0, 1) => (format!("ADD HL,{}", R16[p]), 1),
+        (0, 2) if q == 0 => (format!("LD {},A", R16_MEMORY[p]), 1),
+        (0, 2) => (format!("LD A,{}", R16_MEMORY[p]), 1),
+        (0, 3) if q == 0 => (format!("INC {}", R16[p]), 1),
+        (0, 3) => (format!("DEC {}", R16[p]), 1),
+        (0, 4) => (format!("INC {}", R8[y as usize]), 1),
+        (0, 5) => (format!("DEC {}", R8[y as usize]), 1),
+        (0, 6) => (format!("LD {},${:02X}", R8[y as usize], n8), 2),
+        (0, _) => (String::from(["RLCA", "RRCA", "RLA", "RRA", "DAA", "CPL", "SCF", "CCF"][y as usize]), 1),
+        (1, 6) if y == 6 => (String::from("HALT"), 1),
+        (1, _) => (format!("LD {},{}", R8[y as usize], R8[z as usize]), 1),
+        (2, _) => (format!("{}{}", ALU[y as usize], R8[z as usize]), 1),
+        (3, 0) => match y {
+            0..=3 => (format!("RET {}", CONDITIONS[y as usize]), 1),
+            4 => (format!("LDH (${:02X}),A", n8), 2),
+            5 => (format!("ADD SP,{}", n8 as i8), 2),
+            6 => (format!("LDH A,(${:02X})", n8), 2),
+            _ => (format!("LD HL,SP{:+}", n8 as i8), 2),
+        },
+        (3, 1) if q == 0 => (format!("POP {}", R16_STACK[p]), 1),
+        (3, 1) => (String::from(["RET", "RETI", "JP HL", "LD SP,HL"][p]), 1),
+        (3, 2) => match y {
+            0..=3 => (format!("JP {},${:04X}", CONDITIONS[y as usize], n16), 3),
+            4 => (String::from("LD (C),A"), 1),
+            5 => (format!("LD (${:04X}),A", n16), 3),
+            6 => (String::from("LD A,(C)"), 1),
+            _ => (format!("LD A,(${:04X})", n16), 3),
+        },
+        (3, 3) => match y {
+            0 => (format!("JP ${:04X}", n16), 3),
+            1 => {
+                let (x, y, z) = (n8 >> 6, ((n8 >> 3) & 7) as usize, R8[(n8 & 7) as usize]);
+                let text = match x {
+                    0 => format!("{} {}", ROTATIONS[y], z),
+                    1 => format!("BIT {},{}", y, z),
+                    2 => format!("RES {},{}", y, z),
+                    _ => format!("SET {},{}", y, z),
+                };
+                (text, 2)
+            }
+            6 => (String::from("DI"), 1),
+            7 => (String::from("EI"), 1),
+            _ => (format!("DB ${:02X}", opcode), 1),
+        },
+        (3, 4) if y < 4 => (format!("CALL {},${:04X}", CONDITIONS[y as usize], n16), 3),
+        (3, 5) if q == 0 => (format!("PUSH {}", R16_STACK[p]), 1),
+        (3, 5) if p == 0 => (format!("CALL ${:04X}", n16), 3),
+        (3, 6) => (format!("{}${:02X}", ALU[y as usize], n8), 2),
+        (3, 7) => (format!("RST ${:02X}", y * 8), 1),
+        _ => (format!("DB ${:02X}", opcode), 1),
+    };
+    (text, len)
+}
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+/// Everything the core can fail at. Messages are meant to be shown to the user as they are.
+#[derive(Debug, thiserror::Error)]
+pub enum EmulatorError {
+    /// The ROM file couldn't be read.
+    #[error("cannot read {}: {source}", path.display())]
+    RomLoad {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The image is too short to hold a cartridge header, so it can't be a Game Boy ROM.
+    #[error("not a Game Boy ROM: {0} bytes is too short for a cartridge header")]
+    InvalidRom(usize),
+
+    /// The header asks for a memory bank controller the core doesn't emulate.
+    #[error("unsupported cartridge type 0x{kind:02X} ({name}); ROM-only, MBC1, MBC3 and MBC5 cartridges are supported")]
+    UnsupportedMapper { kind: u8, name: &'static str },
+
+    /// The CPU fetched an opcode that doesn't exist on the Game Boy and locked up.
+    #[error("{}", cpu_fault(*opcode, *pc))]
+    CpuFault { opcode: u16, pc: u16 }, // CB-prefixed opcodes are 0x1xx
+
+    /// A boot ROM image of the wrong size for the model (256 bytes for a DMG, 2304 for a CGB).
+    #[error("boot ROM is {len} bytes; this model's is {expected}")]
+    InvalidBootRom { len: usize, expected: usize },
+
+    /// A save state that is corrupt, truncated or from a newer core.
+    #[error("{0}")]
+    SaveState(String),
+}
+
+fn cpu_fault(opcode: u16, pc: u16) -> String {
+    if opcode > 0xFF {
+        format!("Unknown CB opcode: 0x{:02X} at 0x{:04X}", opcode & 0xFF, pc)
+    } else {
+        format!("Unknown opcode: 0x{:02X} at 0x{:04X}", opcode, pc)
+    }
+}
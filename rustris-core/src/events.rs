@@ -0,0 +1,154 @@
+//! Notifications from a running machine, for front-ends and tools that want to react to
+//! what the game does without polling it after every instruction.
+//!
+//! Handlers are registered with `GB::subscribe` and called synchronously, from inside
+//! `GB::step`, on whatever thread runs the machine. They only see the event, not the
+//! machine (which is busy stepping); forward it (e.g. over a channel) to act on it.
+//! A machine with no subscribers skips the bookkeeping entirely.
+
+/// Something that happened inside the machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A frame has been rendered (the PPU entered VBlank).
+    FrameComplete,
+    /// The game sent a byte over the link port (internal-clock transfer started). Test
+    /// ROMs print their results this way.
+    SerialByte(u8),
+    /// An interrupt was requested (its `IF` bit was set), whether or not the game has it
+    /// enabled. A request made while the bit is still pending isn't reported again.
+    Interrupt(Interrupt),
+    /// A save state was restored (`GB::load_state`).
+    StateLoaded,
+}
+
+/// The five interrupt sources, in priority order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    Stat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    pub const ALL: [Interrupt; 5] =
+        [Interrupt::VBlank, Interrupt::Stat, Interrupt::Timer, Interrupt::Serial, Interrupt::Joypad];
+
+    /// The interrupt's bit in `IF` / `IE`.
+    pub fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// An entry of the interrupt log (see `GB::log_interrupts`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterruptRecord {
+    pub interrupt: Interrupt,
+    pub dispatched: bool, // Serviced by the CPU; otherwise requested (its IF bit was set)
+    pub ly: u8,
+    pub dot: u16,         // T-cycles into the line (0–455)
+    pub cycle: u64,       // T-cycles since logging started
+    pub pc: u16,          // Instruction running when it was requested, or the one interrupted
+}
+
+/// The machine's DMA transfers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dma {
+    /// OAM DMA (FF46): 160 bytes from a page to the sprite table.
+    Oam,
+    /// CGB VRAM DMA (FF55), all at once.
+    General,
+    /// CGB VRAM DMA (FF55), 16 bytes as each visible line enters HBlank.
+    HBlank,
+}
+
+/// An entry of the DMA log (see `GB::log_dma`): a transfer as it was started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DmaRecord {
+    pub dma: Dma,
+    pub source: u16,
+    pub destination: u16,
+    pub length: u16, // Bytes
+    pub frame: u64,  // Frames completed since logging started
+    pub ly: u8,
+    pub dot: u16,    // T-cycles into the line (0–455)
+    pub mode: u8,    // The PPU's mode (0–3)
+    pub pc: u16,     // Instruction that started it
+}
+
+impl DmaRecord {
+    /// What looks wrong about the transfer, usually a game bug (or an emulation bug):
+    /// - A source the hardware can't read from: past work RAM, or VRAM for a VRAM DMA.
+    /// - An OAM DMA started while the PPU reads OAM (modes 2 and 3), or a VRAM DMA all at
+    ///   once while it reads VRAM (mode 3): that part of the picture sees the wrong data.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let bad_source = match self.dma {
+            Dma::Oam => self.source >= 0xE000,
+            Dma::General | Dma::HBlank => self.source >= 0xE000 || (0x8000..0xA000).contains(&self.source),
+        };
+        if bad_source {
+            warnings.push(format!("source 0x{:04X} can't be read by DMA", self.source));
+        }
+        let busy = match self.dma {
+            Dma::Oam => self.mode == 2 || self.mode == 3,
+            Dma::General => self.mode == 3,
+            Dma::HBlank => false,
+        };
+        if busy {
+            warnings.push(format!("started in mode {}, while the PPU reads {}", self.mode, if self.dma == Dma::Oam { "OAM" } else { "VRAM" }));
+        }
+        warnings
+    }
+}
+
+/// An entry of the I/O write log (see `GB::trace_io`): a write to a traced register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoWrite {
+    pub addr: u16,
+    pub value: u8,
+    pub frame: u64, // Frames completed since tracing started
+    pub ly: u8,
+    pub dot: u16,   // T-cycles into the line (0–455)
+    pub pc: u16,    // Instruction that wrote it
+}
+
+/// Identifies a handler for `GB::unsubscribe`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Handler = Box<dyn FnMut(&Event) + Send>;
+
+/// The machine's subscribers, called in the order they subscribed.
+#[derive(Default)]
+pub(crate) struct EventBus {
+    handlers: Vec<(SubscriptionId, Handler)>,
+    next_id: u64,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, handler: Handler) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.handlers.push((id, handler));
+        id
+    }
+
+    /// Returns `false` if `id` wasn't subscribed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let count = self.handlers.len();
+        self.handlers.retain(|(other, _)| *other != id);
+        self.handlers.len() != count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        for (_, handler) in &mut self.handlers {
+            handler(&event);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::frontend::{InputSource, VideoSink};
+use crate::gb::GB;
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Bytes per row of a `Frame`'s pixels.
+pub const FRAME_PITCH: usize = SCREEN_WIDTH as usize * 3;
+
+/// A completed frame, as `GB::frames` yields them.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub index: u64,      // Frames yielded before this one by the same iterator
+    pub pixels: Vec<u8>, // RGB24, `FRAME_PITCH` bytes per row
+    pub buttons: u8,     // Held as the frame ended, as a mask of `gb::BTN_*`
+}
+
+impl Frame {
+    /// The color of the pixel at `x`, `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = y * FRAME_PITCH + x * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+}
+
+/// Iterator over a machine's frames (see `GB::frames` and `GB::frames_with`): each `next`
+/// runs one frame, taking the buttons from its input source, and yields it. It never runs
+/// out while the machine runs; it ends once the CPU has locked up (see `GB::fault`), after
+/// the frame where it did.
+pub struct Frames<'a, I: InputSource> {
+    gb: &'a mut GB,
+    input: I,
+    screen: Screen,
+    index: u64,
+}
+
+/// Where the frames are drawn: kept from one to the next, as a window's texture would
+/// be, so a frame with the LCD off shows what the machine left there.
+struct Screen(Vec<u8>);
+
+impl VideoSink for Screen {
+    fn buffer(&mut self) -> (&mut [u8], usize) {
+        (&mut self.0, FRAME_PITCH)
+    }
+
+    fn present(&mut self) {}
+}
+
+impl<'a, I: InputSource> Frames<'a, I> {
+    pub(crate) fn new(gb: &'a mut GB, input: I) -> Self {
+        Self { gb, input, screen: Screen(vec![0; FRAME_PITCH * SCREEN_HEIGHT as usize]), index: 0 }
+    }
+
+    /// The machine, between frames.
+    pub fn gb(&mut self) -> &mut GB {
+        self.gb
+    }
+
+    /// The input source, to steer it between frames.
+    pub fn input(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Gives the input source back, ending the iteration.
+    pub fn into_input(self) -> I {
+        self.input
+    }
+}
+
+impl<I: InputSource> Iterator for Frames<'_, I> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        self.gb.fault().ok()?;
+        self.gb.run_frame(&mut self.screen, &mut self.input);
+        let frame = Frame { index: self.index, pixels: self.screen.0.clone(), buttons: self.gb.buttons() };
+        self.index += 1;
+        Some(frame)
+    }
+}
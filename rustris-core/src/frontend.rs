@@ -0,0 +1,44 @@
+//! Interfaces between the core and a front-end. `GB::run_frame` renders and polls
+//! through these, so a front-end only has to implement them for its own window,
+//! terminal, network stream or test harness.
+
+/// Destination of rendered frames.
+pub trait VideoSink {
+    /// RGB24 buffer the next frame is drawn into, and its pitch (bytes per row). It must
+    /// hold at least `SCREEN_HEIGHT` rows of `pitch` bytes, with `pitch >= SCREEN_WIDTH * 3`.
+    /// Scanlines are written as they are rendered, so this can be a locked texture.
+    fn buffer(&mut self) -> (&mut [u8], usize);
+
+    /// The frame in `buffer` is complete.
+    fn present(&mut self);
+}
+
+/// Source of joypad input.
+pub trait InputSource {
+    /// Buttons currently held, as a mask of `gb::BTN_*`. Polled at the start of every
+    /// frame and again whenever the game selects a joypad line (or `next_change` asks).
+    fn poll(&mut self) -> u8;
+
+    /// Like `poll`, told how far into the frame the machine is (see `GB::frame_dot`), for
+    /// input placed within frames, as TAS movies do. Defaults to `poll`.
+    fn poll_at(&mut self, _dot: u32) -> u8 {
+        self.poll()
+    }
+
+    /// The frame dot of the next change to the buttons, if one is due later in the frame.
+    /// The machine polls again after the first step that reaches it, whether or not the
+    /// game reads the joypad there, so the change lands on that instruction (and a press
+    /// raises the joypad interrupt there). Defaults to none: the buttons only change when
+    /// they're polled anyway.
+    fn next_change(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// A closure returning the buttons to hold is an input source too, e.g. for
+/// `GB::frames_with`.
+impl<F: FnMut() -> u8> InputSource for F {
+    fn poll(&mut self) -> u8 {
+        self()
+    }
+}
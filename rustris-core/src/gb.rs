@@ -0,0 +1,995 @@
+use crate::boot;
+use crate::cpu::{Registers, TraceEntry, CPU};
+use crate::cartridge::Cartridge;
+use crate::error::EmulatorError;
+use crate::events::{DmaRecord, Event, EventBus, Interrupt, InterruptRecord, IoWrite, SubscriptionId};
+use crate::frames::Frames;
+use crate::frontend::{InputSource, VideoSink};
+use crate::mmu::MMU;
+use crate::ppu::{FrameTiming, Palette, PPU};
+use crate::romdb::{self, RomInfo};
+use crate::savestate::{StateChunks, StateReader, StateWriter};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// GB façade: connects the CPU, MMU (bus/memory), and PPU together.
+///
+/// Responsibilities:
+/// - Initializes the system: sets up the MMU with the cartridge and instantiates CPU and PPU.
+/// - Executes one CPU instruction per call to `step`, then advances the timer and
+///   the PPU by the number of T-cycles the instruction consumed.
+/// - Exposes input methods that pass button states to the MMU.
+/// - When the PPU signals a completed frame, the RGB data is written directly
+///   into the provided SDL framebuffer and `step` returns `true`.
+///
+/// Timing contract:
+/// - `CPU::step` returns the number of T-cycles taken by the executed instruction.
+/// - `PPU::step(mmu, t_cycles, framebuffer, pitch)` advances video timing by that amount.
+/// - `is_frame_ready()` indicates whether a full frame has been rendered since the last check.
+pub const BTN_RIGHT:  u8 = 1 << 0;
+pub const BTN_LEFT:   u8 = 1 << 1;
+pub const BTN_UP:     u8 = 1 << 2;
+pub const BTN_DOWN:   u8 = 1 << 3;
+pub const BTN_A:      u8 = 1 << 4;
+pub const BTN_B:      u8 = 1 << 5;
+pub const BTN_SELECT: u8 = 1 << 6;
+pub const BTN_START:  u8 = 1 << 7;
+
+/// Flags of a ROM coverage map (see `GB::track_coverage`), one byte per ROM byte. Code and
+/// data are the bits of the common `.cdl` code/data logs; written is ours.
+pub const COVERAGE_CODE: u8 = 0x01;    // Fetched by the CPU as part of an instruction
+pub const COVERAGE_DATA: u8 = 0x02;    // Read by the CPU as data
+pub const COVERAGE_WRITTEN: u8 = 0x80; // Written to by the CPU (a bank controller command)
+
+/// Entries kept by the interrupt log (see `GB::log_interrupts`).
+const INTERRUPT_LOG_LEN: usize = 256;
+
+/// Entries kept by the DMA log (see `GB::log_dma`).
+const DMA_LOG_LEN: usize = 256;
+
+/// Entries kept by the I/O write log (see `GB::trace_io`): a few frames of a register
+/// written every line.
+const IO_LOG_LEN: usize = 1024;
+
+/// Hardware model to emulate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// Original Game Boy.
+    Dmg,
+    /// Game Boy Color. Color-aware games get the CGB registers, banking and palettes;
+    /// other games run in compatibility mode and render with the host `Palette`, as on DMG.
+    Cgb,
+}
+
+impl Model {
+    /// The model a cartridge is meant for: CGB for color-aware games, DMG otherwise.
+    pub fn for_cartridge(cartridge: &Cartridge) -> Self {
+        if cartridge.supports_cgb() { Model::Cgb } else { Model::Dmg }
+    }
+}
+
+/// Accuracy/speed trade-off of the emulation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// The original fast path, enough for Tetris: only VBlank and serial interrupts, no timer (DIV
+    /// returns noise), STAT is never updated, and the renderer skips the window layer,
+    /// 8×16 sprites and sprite priority.
+    Tetris,
+    /// General emulation: all five interrupts, the DIV/TIMA timer, STAT modes, LYC and
+    /// their interrupts, the joypad interrupt, LCD off, the window layer, 8×16 sprites,
+    /// and sprite-to-sprite and OBJ-to-BG priority.
+    Compatible,
+    /// Everything `Compatible` does, with slower paths closer to the hardware where
+    /// games can tell the difference:
+    /// - OAM DMA takes its 160 M-cycles, copying a byte per M-cycle, and the CPU can
+    ///   only reach `FF00`–`FFFF` (I/O, HRAM, IE) meanwhile; other reads return `FF`
+    ///   and writes are lost. `Compatible` copies at once with no restriction.
+    /// - Pixel transfer (mode 3) lasts longer with the fine scroll (`SCX % 8`), the
+    ///   window and each sprite on the line (6 dots each, up to 10), delaying HBlank and
+    ///   its STAT interrupt. `Compatible` always ends it at dot 252.
+    ///
+    /// The renderer still draws whole lines at the start of HBlank, not through a pixel
+    /// FIFO, so mid-line register changes aren't seen.
+    Accurate,
+}
+
+impl Profile {
+    /// The profile a cartridge needs: the one the ROM database lists for it (the fast
+    /// path for Tetris), `Compatible` for everything else.
+    pub fn for_cartridge(cartridge: &Cartridge) -> Self {
+        match romdb::lookup(cartridge) {
+            Some(info) if !cartridge.supports_cgb() => info.profile,
+            _ => Profile::Compatible,
+        }
+    }
+}
+
+/// Contents of RAM after a power cycle (`GB::power_cycle`). Real SRAM comes up holding
+/// whatever its cells settle to, which some games and test ROMs look at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RamInit {
+    /// Every byte 0x00, as most emulators do (and as a new `GB` starts).
+    Zero,
+    /// Noise from the machine's random source (reproducible with `GB::seed`).
+    Random,
+}
+
+/// A stack access that looks wrong (see `GB::check_stack`): usually a game bug, a
+/// corrupted game or an emulation bug.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackFault {
+    pub kind: StackFaultKind,
+    pub addr: u16, // The byte pushed to or popped from
+    pub pc: u16,   // The instruction that did it (or was interrupted, for a dispatch)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StackFaultKind {
+    /// A push outside work RAM and high RAM: SP has run down into I/O, OAM, VRAM or
+    /// ROM, or wrapped around.
+    Overflow,
+    /// A pop outside work RAM and high RAM, e.g. past FFFE into IE.
+    Underflow,
+    /// A push over a high RAM byte the game keeps a variable in (has read as data from
+    /// outside the stack).
+    Collision,
+}
+
+/// What lives at `addr` in the address space, e.g. "work RAM".
+pub fn memory_region(addr: u16) -> &'static str {
+    match addr {
+        0x0000..=0x7FFF => "ROM",
+        0x8000..=0x9FFF => "VRAM",
+        0xA000..=0xBFFF => "cartridge RAM",
+        0xC000..=0xDFFF => "work RAM",
+        0xE000..=0xFDFF => "echo RAM",
+        0xFE00..=0xFE9F => "OAM",
+        0xFEA0..=0xFEFF => "unusable memory",
+        0xFF00..=0xFF7F => "I/O registers",
+        0xFF80..=0xFFFE => "high RAM",
+        0xFFFF => "IE",
+    }
+}
+
+impl fmt::Display for StackFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let region = memory_region(self.addr);
+        match self.kind {
+            StackFaultKind::Overflow => write!(f, "Stack overflow: push to 0x{:04X} ({})", self.addr, region)?,
+            StackFaultKind::Underflow => write!(f, "Stack underflow: pop from 0x{:04X} ({})", self.addr, region)?,
+            StackFaultKind::Collision => write!(f, "Stack collision: push over the variable at 0x{:04X}", self.addr)?,
+        }
+        write!(f, " at 0x{:04X}", self.pc)
+    }
+}
+
+/// High-level Game Boy system wrapper that orchestrates CPU, MMU, and PPU.
+pub struct GB {
+    cpu: CPU,
+    mmu: MMU,
+    ppu: PPU,
+    ppu_debt: u32, // Dots the PPU is behind the CPU, fewer than its `quiet_dots`
+    overclock: u32,       // log2 of the CPU's clock multiplier (see `set_overclock`)
+    overclock_carry: u32, // CPU T-cycles not yet passed on to the rest of the machine
+    boot_animation: bool, // Play the logo animation on reset without a boot ROM (see `set_boot_animation`)
+    boot_frames: u32,     // Frames of the logo animation left to play; the CPU waits until 0
+    model: Model,
+    profile: Profile,
+    battery: bool, // Cartridge RAM is battery-backed and should be persisted
+    header_title: String,
+    rom_info: Option<&'static RomInfo>,
+    events: EventBus,
+    interrupt_log: Option<InterruptLog>, // While logging interrupts
+    dma_log: Option<DmaLog>,             // While logging DMA transfers
+    io_log: Option<IoLog>,               // While tracing I/O writes
+    ppu_time: Option<Duration>,          // Host time spent in the PPU, while timing it
+}
+
+/// The last interrupt requests and dispatches (see `GB::log_interrupts`).
+struct InterruptLog {
+    records: VecDeque<InterruptRecord>, // Oldest first, at most `INTERRUPT_LOG_LEN`
+    cycles: u64,                        // T-cycles since logging started
+}
+
+impl InterruptLog {
+    fn push(&mut self, record: InterruptRecord) {
+        if self.records.len() == INTERRUPT_LOG_LEN {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+}
+
+/// The last DMA transfers started (see `GB::log_dma`).
+struct DmaLog {
+    records: VecDeque<DmaRecord>, // Oldest first, at most `DMA_LOG_LEN`
+    frames: u64,                  // Frames completed since logging started
+}
+
+/// The last writes to traced I/O registers (see `GB::trace_io`).
+struct IoLog {
+    records: VecDeque<IoWrite>, // Oldest first, at most `IO_LOG_LEN`
+    frames: u64,                // Frames completed since tracing started
+}
+
+impl GB {
+    /// Creates a new Game Boy instance with the given cartridge loaded, picking the
+    /// model and profile from the cartridge header (see `Model::for_cartridge` and
+    /// `Profile::for_cartridge`). Fails if the image has no header or needs a memory bank
+    /// controller that isn't emulated.
+    pub fn new(cartridge: Cartridge) -> Result<Self, EmulatorError> {
+        let model = Model::for_cartridge(&cartridge);
+        Self::with_model(cartridge, model)
+    }
+
+    /// Creates a Game Boy of the given model with the cartridge loaded; the profile is
+    /// still picked from the header (see `set_profile`).
+    pub fn with_model(cartridge: Cartridge, model: Model) -> Result<Self, EmulatorError> {
+        let profile = Profile::for_cartridge(&cartridge);
+        let battery = cartridge.has_battery();
+        let header_title = cartridge.title();
+        let rom_info = romdb::lookup(&cartridge);
+        let cgb_mode = model == Model::Cgb && cartridge.supports_cgb();
+        let mut gb = GB {
+            cpu: CPU::new(),
+            mmu: MMU::new(cartridge, cgb_mode)?,
+            ppu: PPU::new(),
+            ppu_debt: 0,
+            overclock: 0,
+            overclock_carry: 0,
+            boot_animation: false,
+            boot_frames: 0,
+            model,
+            profile,
+            battery,
+            header_title,
+            rom_info,
+            events: EventBus::default(),
+            interrupt_log: None,
+            dma_log: None,
+            io_log: None,
+            ppu_time: None,
+        };
+        gb.boot();
+        gb.set_profile(profile);
+        Ok(gb)
+    }
+
+    /// Sets up where execution starts: the boot ROM at 0000 if one is mapped, otherwise
+    /// the state it would leave behind (the I/O registers only for the Compatible profile,
+    /// Tetris doesn't need them).
+    fn boot(&mut self) {
+        if self.mmu.boot_rom_mapped() {
+            self.cpu.set_registers(&Registers {
+                a: 0, f: 0, b: 0, c: 0, d: 0, e: 0, h: 0, l: 0, sp: 0, pc: 0,
+            });
+            return;
+        }
+        if self.profile != Profile::Tetris {
+            self.mmu.post_boot();
+        }
+        if self.model == Model::Cgb {
+            // Post-boot registers on a CGB; A=0x11 is how games detect it
+            self.cpu.set_registers(&Registers {
+                a: 0x11, f: 0x80, b: 0x00, c: 0x00, d: 0xFF, e: 0x56, h: 0x00, l: 0x0D,
+                sp: 0xFFFE, pc: 0x0100,
+            });
+        }
+    }
+
+    /// Installs a boot ROM (a dump of the console's own: 256 bytes for a DMG, 2304 for a
+    /// CGB) that every later `reset` and `power_cycle` runs before handing over to the
+    /// game, instead of loading its end state directly. It shows the logo, and refuses
+    /// cartridges whose header fails its checks, as the console does.
+    pub fn set_boot_rom(&mut self, data: Vec<u8>) -> Result<(), EmulatorError> {
+        let expected = match self.model {
+            Model::Dmg => 0x100,
+            Model::Cgb => 0x900,
+        };
+        if data.len() != expected {
+            return Err(EmulatorError::InvalidBootRom { len: data.len(), expected });
+        }
+        self.mmu.set_boot_rom(data);
+        Ok(())
+    }
+
+    /// `true` while the boot ROM runs (see `set_boot_rom`) or the logo animation plays
+    /// (see `set_boot_animation`), before the game has started.
+    pub fn boot_rom_running(&self) -> bool {
+        self.mmu.boot_rom_mapped() || self.boot_frames > 0
+    }
+
+    /// Plays the startup logo animation without a boot ROM: on every later `reset` and
+    /// `power_cycle` of a DMG with none installed, the logo from the cartridge header
+    /// scrolls down the screen and rests there before the game starts, drawn by the
+    /// emulator instead of a dump of the console's code. The CPU waits meanwhile, and the
+    /// game then starts from the same state as without the animation (plus the logo left
+    /// in VRAM, as the console leaves it). There's no sound emulation, so the chime is
+    /// left out; the pause it plays over is kept. Ignored on a CGB and with a boot ROM,
+    /// which shows its own; a setting like the palette, kept across resets and out of save
+    /// states (loading one skips the rest of the animation).
+    pub fn set_boot_animation(&mut self, on: bool) {
+        self.boot_animation = on;
+    }
+
+    /// Warm reset, as when the console's RESET line is pulled: the CPU, I/O registers,
+    /// bank controller and PPU return to their power-on state and the game starts over,
+    /// through the boot ROM if there's one. RAM keeps its contents, which is how games
+    /// tell a reset from a cold start (and what some copy protections probe).
+    pub fn reset(&mut self) {
+        self.mmu.reset();
+        self.restart();
+        self.start_boot_animation();
+    }
+
+    /// Cold start: like `reset`, but RAM is refilled as `init` says first. Cartridge RAM
+    /// only survives if it's battery-backed.
+    pub fn power_cycle(&mut self, init: RamInit) {
+        self.mmu.reset();
+        self.mmu.fill_ram(init, !self.battery);
+        self.restart();
+        self.start_boot_animation();
+    }
+
+    /// Common end of `reset` and `power_cycle`, once the MMU is back at power-on.
+    fn restart(&mut self) {
+        let palette = self.ppu.get_palette();
+        let sprite_colors = *self.ppu.sprite_colors();
+        self.cpu = CPU::new();
+        self.ppu = PPU::new();
+        self.ppu_debt = 0;
+        self.ppu.set_palette(palette);
+        for (tile, color) in sprite_colors.into_iter().enumerate() {
+            self.ppu.set_sprite_color(tile as u8, color);
+        }
+        self.boot();
+        self.set_profile(self.profile);
+    }
+
+    /// Starts the logo animation if it's on and there's nothing else to show it (see
+    /// `set_boot_animation`).
+    fn start_boot_animation(&mut self) {
+        self.boot_frames = 0;
+        if self.boot_animation && self.model == Model::Dmg && !self.mmu.boot_rom_mapped() {
+            boot::start(&mut self.mmu);
+            self.boot_frames = boot::FRAMES;
+        }
+    }
+
+    /// Moves the logo animation on once a frame is done; after the last one, puts the
+    /// machine back at power-on, VRAM aside, and hands over to the game.
+    fn boot_animation_frame(&mut self) {
+        self.boot_frames -= 1;
+        if self.boot_frames > 0 {
+            boot::scroll(&mut self.mmu, self.boot_frames);
+        } else {
+            self.mmu.reset();
+            self.restart();
+        }
+    }
+
+    /// The hardware model being emulated.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// What the ROM database knows about the loaded game, if it's listed.
+    pub fn rom_info(&self) -> Option<&'static RomInfo> {
+        self.rom_info
+    }
+
+    /// Name to show for the loaded game: the canonical title from the ROM database, or
+    /// the header title.
+    pub fn game_title(&self) -> &str {
+        self.rom_info.map_or(&self.header_title, |info| info.title)
+    }
+
+    /// The accuracy profile in use.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Switches the accuracy profile; takes effect from the next step.
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.ppu.skip(std::mem::take(&mut self.ppu_debt));
+        self.profile = profile;
+        self.cpu.set_profile(profile);
+        self.mmu.set_profile(profile);
+        self.ppu.set_profile(profile);
+    }
+
+    /// The CPU's clock multiplier (1, 2 or 4; see `set_overclock`).
+    pub fn overclock(&self) -> u32 {
+        1 << self.overclock
+    }
+
+    /// Runs the CPU `factor` times (1, 2 or 4; others round down to one of those) as fast as
+    /// the rest of the machine: each instruction takes that many times fewer T-cycles of
+    /// the PPU, timers, DMA and serial clock, so frames still come at 59.73 Hz but the game
+    /// gets more instructions into each. Games that drop frames when busy (Tetris's line
+    /// clears, sprite-heavy scenes) run them at full rate; anything timed by counting
+    /// instructions runs fast. 1 is the real hardware; a setting like the palette, kept
+    /// across resets and out of save states.
+    pub fn set_overclock(&mut self, factor: u32) {
+        self.overclock = factor.clamp(1, 4).ilog2();
+        self.overclock_carry = 0;
+    }
+
+    /// Executes a single CPU instruction and advances the PPU accordingly.
+    ///
+    /// The framebuffer passed in is an SDL texture buffer; the PPU writes RGB
+    /// pixels directly into it using the provided `pitch` (bytes per row).
+    ///
+    /// In CGB double-speed mode the PPU advances half as many dots as the CPU's T-cycles.
+    ///
+    /// The PPU catches up lazily: its dots are only counted until its next mode or line
+    /// change is due (or the LCD is switched on or off), then run all at once, so most
+    /// instructions don't call into it. Everything the CPU can see (LY, STAT, interrupts,
+    /// the rendered lines) happens at those changes, so it sees the same as before.
+    ///
+    /// A halted CPU sleeps until the next of those changes (or timer overflow) in one step
+    /// instead of one step per M-cycle, so `run_frame_with` hooks see fewer steps then.
+    ///
+    /// Returns `true` if a new frame has been rendered and is ready to be presented.
+    pub fn step(&mut self, framebuffer: &mut [u8], pitch: usize) -> bool {
+        self.step_cycles(framebuffer, pitch).1
+    }
+
+    /// Like `step`, also returning the T-cycles the instruction took, of the machine's clock
+    /// (fewer than the CPU's own when overclocked, see `set_overclock`).
+    pub(crate) fn step_cycles(&mut self, framebuffer: &mut [u8], pitch: usize) -> (u32, bool) {
+        if !self.events.is_empty() || self.interrupt_log.is_some() || self.dma_log.is_some() || self.io_log.is_some() || self.mmu.checking_stack() {
+            return self.step_with_events(framebuffer, pitch);
+        }
+        let t = self.step_cpu();
+        self.mmu.tick(t);
+        (t, self.step_ppu(t, framebuffer, pitch))
+    }
+
+    /// Runs one CPU step and returns its T-cycles, divided down to the machine's clock
+    /// when overclocked. What doesn't divide evenly (or, in double-speed mode, make whole
+    /// PPU dots) is carried over to the next step. A CPU sleeping in HALT (see
+    /// `sleep_cycles`) skips ahead to its next chance of waking in a single step.
+    fn step_cpu(&mut self) -> u32 {
+        match self.sleep_cycles() {
+            Some(t) => t,
+            None => {
+                let t = self.cpu.step(&mut self.mmu) + self.overclock_carry;
+                let shift = self.overclock + self.mmu.double_speed() as u32;
+                self.overclock_carry = t & ((1 << shift) - 1);
+                (t - self.overclock_carry) >> self.overclock
+            }
+        }
+    }
+
+    /// While the CPU sleeps in HALT, the T-cycles until something may wake it: the PPU's
+    /// next mode or line change or the timer's next overflow, rounded up to whole
+    /// M-cycles, as stepping 4 T-cycles at a time would land. Input is only set between
+    /// steps, so a button press is seen on the step after it either way.
+    ///
+    /// `None` if the CPU is awake, or linked: the partner's transfer can wake it at any
+    /// point, and `run_linked_frame` keeps the two within an instruction of each other.
+    /// While the logo animation plays (see `set_boot_animation`) the CPU counts as asleep,
+    /// linked or not, as it hasn't started yet.
+    fn sleep_cycles(&self) -> Option<u32> {
+        let asleep = self.boot_frames > 0 || !self.mmu.linked() && self.cpu.sleeping(&self.mmu);
+        if !asleep {
+            return None;
+        }
+        let dots = self.ppu.quiet_dots() - self.ppu_debt;
+        let ppu = if self.mmu.double_speed() { dots * 2 } else { dots };
+        let t = self.mmu.timer_overflow_cycles().map_or(ppu, |timer| timer.min(ppu));
+        Some(t.div_ceil(4).max(1) * 4)
+    }
+
+    /// Owes the PPU the dots of `t` T-cycles, running it if it's time (see `step`).
+    /// Returns `true` if a frame was completed.
+    fn step_ppu(&mut self, t: u32, framebuffer: &mut [u8], pitch: usize) -> bool {
+        let dots = if self.mmu.double_speed() { t / 2 } else { t };
+        if self.ppu_debt + dots < self.ppu.quiet_dots() && !self.ppu.lcd_switched(&self.mmu) {
+            self.ppu_debt += dots;
+            return false;
+        }
+        let start = self.ppu_time.is_some().then(Instant::now);
+        // Dots owed from earlier instructions went by before any LCDC switch
+        self.ppu.skip(std::mem::take(&mut self.ppu_debt));
+        self.ppu.step(&mut self.mmu, dots, framebuffer, pitch);
+        if let (Some(start), Some(time)) = (start, &mut self.ppu_time) {
+            *time += start.elapsed();
+        }
+        let ready = self.ppu.is_frame_ready();
+        if ready && self.boot_frames > 0 {
+            self.boot_animation_frame();
+        }
+        ready
+    }
+
+    /// `step_cycles` for a machine with subscribers, an interrupt, DMA or I/O log or a stack
+    /// check: compares the interrupt flags and the serial log around the step to tell them
+    /// what happened during it, and tells the stack check where the step starts.
+    fn step_with_events(&mut self, framebuffer: &mut [u8], pitch: usize) -> (u32, bool) {
+        let flags = self.mmu.interrupt_flags();
+        let sent = self.mmu.serial_log().len();
+        let Registers { pc, sp, .. } = self.cpu.registers();
+        let (ly, dot) = self.ppu.position(self.ppu_debt);
+        self.mmu.stack_position(pc, sp);
+
+        let t = self.step_cpu();
+        self.mmu.tick(t);
+        let frame_done = self.step_ppu(t, framebuffer, pitch);
+
+        // Bits set during the step; one set and serviced within the same step is missed,
+        // but the CPU only dispatches at the start of a step, before anything is raised
+        let raised = self.mmu.interrupt_flags() & !flags;
+        if let Some(log) = &mut self.interrupt_log {
+            // A dispatch is the whole step, so it happened where the step started
+            if let Some(bit) = self.cpu.take_dispatched() {
+                let interrupt = Interrupt::ALL[bit as usize];
+                log.push(InterruptRecord { interrupt, dispatched: true, ly, dot, cycle: log.cycles, pc });
+            }
+            log.cycles += t as u64;
+            let (ly, dot) = self.ppu.position(self.ppu_debt);
+            for interrupt in Interrupt::ALL {
+                if raised & interrupt.mask() != 0 {
+                    log.push(InterruptRecord { interrupt, dispatched: false, ly, dot, cycle: log.cycles, pc });
+                }
+            }
+        }
+        if let Some(log) = &mut self.dma_log {
+            if let Some((dma, source, destination, length)) = self.mmu.take_dma_started() {
+                // Started by the step's last write, so about where it ends
+                let (ly, dot) = self.ppu.position(self.ppu_debt);
+                let record = DmaRecord { dma, source, destination, length, frame: log.frames, ly, dot, mode: self.ppu.mode(), pc };
+                if log.records.len() == DMA_LOG_LEN {
+                    log.records.pop_front();
+                }
+                log.records.push_back(record);
+            }
+            log.frames += frame_done as u64;
+        }
+        if let Some(log) = &mut self.io_log {
+            let (ly, dot) = self.ppu.position(self.ppu_debt);
+            for (addr, value) in self.mmu.take_io_writes() {
+                if log.records.len() == IO_LOG_LEN {
+                    log.records.pop_front();
+                }
+                log.records.push_back(IoWrite { addr, value, frame: log.frames, ly, dot, pc });
+            }
+            log.frames += frame_done as u64;
+        }
+        for interrupt in Interrupt::ALL {
+            if raised & interrupt.mask() != 0 {
+                self.events.emit(Event::Interrupt(interrupt));
+            }
+        }
+        for &byte in self.mmu.serial_log().get(sent..).unwrap_or_default() {
+            self.events.emit(Event::SerialByte(byte));
+        }
+        if frame_done {
+            self.events.emit(Event::FrameComplete);
+        }
+        (t, frame_done)
+    }
+
+    /// Starts or stops logging interrupt requests and dispatches (see `interrupt_log`).
+    /// While logging, every step compares the interrupt flags before and after, as with
+    /// event subscribers, which is a little slower.
+    pub fn log_interrupts(&mut self, on: bool) {
+        if on == self.interrupt_log.is_some() {
+            return;
+        }
+        self.interrupt_log = on.then(|| InterruptLog { records: VecDeque::with_capacity(INTERRUPT_LOG_LEN), cycles: 0 });
+        // Forget a dispatch from before logging started
+        self.cpu.take_dispatched();
+    }
+
+    /// The last interrupt requests and dispatches, oldest first; empty unless
+    /// `log_interrupts` is on. Requests are logged where the instruction that raised them
+    /// ends, dispatches where they start.
+    pub fn interrupt_log(&self) -> Vec<InterruptRecord> {
+        self.interrupt_log.as_ref().map_or_else(Vec::new, |log| log.records.iter().copied().collect())
+    }
+
+    /// Starts or stops logging the DMA transfers the game starts (see `dma_log`). Like the
+    /// interrupt log, this takes the slower stepping of machines with subscribers.
+    pub fn log_dma(&mut self, on: bool) {
+        if on == self.dma_log.is_some() {
+            return;
+        }
+        self.dma_log = on.then(|| DmaLog { records: VecDeque::with_capacity(DMA_LOG_LEN), frames: 0 });
+        // Forget a transfer from before logging started
+        self.mmu.take_dma_started();
+    }
+
+    /// The last DMA transfers started, oldest first; empty unless `log_dma` is on.
+    pub fn dma_log(&self) -> Vec<DmaRecord> {
+        self.dma_log.as_ref().map_or_else(Vec::new, |log| log.records.iter().copied().collect())
+    }
+
+    /// Traces the writes to `registers` (I/O register addresses) from now on, or stops
+    /// tracing with none (see `io_log`). Changing the registers keeps the writes already
+    /// logged. Like the DMA log, this takes the slower stepping
+    /// of machines with subscribers.
+    pub fn trace_io(&mut self, registers: &[u16]) {
+        if registers == self.mmu.traced_io() {
+            return;
+        }
+        self.mmu.trace_io(registers);
+        if registers.is_empty() {
+            self.io_log = None;
+        } else if self.io_log.is_none() {
+            self.io_log = Some(IoLog { records: VecDeque::with_capacity(IO_LOG_LEN), frames: 0 });
+        }
+    }
+
+    /// The last writes to the registers traced, oldest first; empty unless `trace_io` is
+    /// on. Like DMA transfers, a write is logged where the instruction that made it ends.
+    pub fn io_log(&self) -> Vec<IoWrite> {
+        self.io_log.as_ref().map_or_else(Vec::new, |log| log.records.iter().copied().collect())
+    }
+
+    /// Starts or stops recording how the PPU spends each frame (see `frame_timing`). Cheap:
+    /// a few entries per line.
+    pub fn record_timing(&mut self, on: bool) {
+        self.ppu.record_timing(on);
+    }
+
+    /// How the PPU spent the last whole frame while `record_timing` was on: each line's
+    /// modes and where it requested STAT and VBlank interrupts.
+    pub fn frame_timing(&self) -> Option<FrameTiming> {
+        self.ppu.frame_timing().cloned()
+    }
+
+    /// Starts or stops measuring how much host time goes into the PPU (see `take_ppu_time`).
+    /// Reading the clock each time the PPU catches up costs a little, so it's off by default.
+    pub fn time_ppu(&mut self, on: bool) {
+        if on != self.ppu_time.is_some() {
+            self.ppu_time = on.then_some(Duration::ZERO);
+        }
+    }
+
+    /// Host time spent in the PPU since the last call, while `time_ppu` is on.
+    pub fn take_ppu_time(&mut self) -> Duration {
+        self.ppu_time.as_mut().map_or(Duration::ZERO, std::mem::take)
+    }
+
+    /// Calls `handler` with every `Event` from now on (see the `events` module), until
+    /// `unsubscribe` is called with the returned id.
+    pub fn subscribe<F>(&mut self, handler: F) -> SubscriptionId
+    where
+        F: FnMut(&Event) + Send + 'static,
+    {
+        self.events.subscribe(Box::new(handler))
+    }
+
+    /// Removes a handler added with `subscribe`. Returns `false` if it was already gone.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.events.unsubscribe(id)
+    }
+
+    /// Runs until the next frame is complete, rendering into `video` and taking the
+    /// joypad state from `input` (at the start of the frame, on every joypad strobe, so
+    /// late presses still land in this frame, and at the changes it schedules, see
+    /// `InputSource::next_change`). Calls `video.present()` at VBlank.
+    pub fn run_frame<V: VideoSink, I: InputSource>(&mut self, video: &mut V, input: &mut I) {
+        self.run_frame_with(video, input, |_, _| {});
+    }
+
+    /// Like `run_frame`, calling `on_step(gb, frame_done)` after every instruction; for
+    /// scripts and debuggers. `frame_done` is set on the step that enters VBlank, before
+    /// the frame is presented.
+    pub fn run_frame_with<V, I, F>(&mut self, video: &mut V, input: &mut I, mut on_step: F)
+    where
+        V: VideoSink,
+        I: InputSource,
+        F: FnMut(&mut GB, bool),
+    {
+        self.run_frame_until(video, input, |gb, frame_done| {
+            on_step(gb, frame_done);
+            false
+        });
+    }
+
+    /// Like `run_frame_with`, but stops early after a step at which `on_step` returns
+    /// `true`; for debuggers (breakpoints, single steps). Returns `true` if the frame was
+    /// completed and presented. After a stop the machine is mid-frame: calling this again
+    /// carries on with the same frame, into the same `video` buffer.
+    pub fn run_frame_until<V, I, F>(&mut self, video: &mut V, input: &mut I, mut on_step: F) -> bool
+    where
+        V: VideoSink,
+        I: InputSource,
+        F: FnMut(&mut GB, bool) -> bool,
+    {
+        self.set_buttons(input.poll_at(self.frame_dot()));
+        let (framebuffer, pitch) = video.buffer();
+        loop {
+            let done = self.step(framebuffer, pitch);
+            let stop = on_step(self, done);
+            if done {
+                break;
+            }
+            if stop {
+                return false;
+            }
+            if self.take_joypad_poll() || input.next_change().is_some_and(|dot| self.frame_dot() >= dot) {
+                self.set_buttons(input.poll_at(self.frame_dot()));
+            }
+        }
+        video.present();
+        true
+    }
+
+    /// Iterates over the frames from here on, with no buttons held: each step runs one
+    /// frame and yields its picture (see `Frames`).
+    pub fn frames(&mut self) -> Frames<'_, fn() -> u8> {
+        self.frames_with(|| 0)
+    }
+
+    /// Like `frames`, taking the buttons from `input` as `run_frame` does; a closure
+    /// returning the buttons to hold will do.
+    pub fn frames_with<I: InputSource>(&mut self, input: I) -> Frames<'_, I> {
+        Frames::new(self, input)
+    }
+
+    /// Replaces the whole joypad state with `mask`.
+    pub fn set_buttons(&mut self, mask: u8) {
+        self.mmu.input_release(!mask);
+        self.mmu.input_press(mask);
+    }
+
+    /// The buttons held, as last set.
+    pub fn buttons(&self) -> u8 {
+        self.mmu.buttons()
+    }
+
+    /// Marks one or more input buttons as pressed.
+    pub fn input_press(&mut self, mask: u8) {
+        self.mmu.input_press(mask);
+    }
+
+    /// Marks one or more input buttons as released.
+    pub fn input_release(&mut self, mask: u8) {
+        self.mmu.input_release(mask);
+    }
+
+    /// How far the current frame has got, in dots (the 4.19 MHz video clock: 456 a line,
+    /// 70224 a frame): 0 as VBlank begins (LY 144), where `run_frame` returns, so line L of
+    /// the screen starts at (10 + L) × 456. While the LCD is off, counted from the last
+    /// blank frame. Input sources place changes within a frame with it (see
+    /// `InputSource::next_change`).
+    pub fn frame_dot(&self) -> u32 {
+        self.ppu.frame_dots(self.ppu_debt)
+    }
+
+    /// Returns `true` (once) when the game has just selected a joypad line, a good point
+    /// for front-ends to feed in fresh input mid-frame.
+    pub fn take_joypad_poll(&mut self) -> bool {
+        self.mmu.take_joypad_poll()
+    }
+
+    /// Copies the 64KB address space into `out` (which must be 0x10000 bytes long).
+    pub fn read_memory(&self, out: &mut [u8]) {
+        self.mmu.dump(out);
+    }
+
+    /// Reads one byte through the memory bus, exactly like a CPU load.
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        self.mmu.read_byte(addr)
+    }
+
+    /// Writes one byte through the memory bus, exactly like a CPU store.
+    pub fn write_byte(&mut self, addr: u16, value: u8) {
+        self.mmu.write_byte(addr, value);
+    }
+
+    /// Logs every write to `start..=end` from now on; fetch them with `take_watched_writes`.
+    pub fn watch_writes(&mut self, start: u16, end: u16) {
+        self.mmu.watch_writes(start, end);
+    }
+
+    /// Returns the `(address, value)` writes to watched ranges since the last call.
+    pub fn take_watched_writes(&mut self) -> Vec<(u16, u8)> {
+        self.mmu.take_watched_writes()
+    }
+
+    /// Current CPU registers.
+    pub fn registers(&self) -> Registers {
+        self.cpu.registers()
+    }
+
+    /// Overwrites the CPU registers (F's low nibble always reads 0), e.g. to run a test
+    /// vector from a given state.
+    pub fn set_registers(&mut self, registers: &Registers) {
+        self.cpu.set_registers(registers);
+    }
+
+    /// The opcode the CPU locked up on, if it hit one that isn't implemented
+    /// (`0xCBxx` for CB-prefixed opcodes). The PPU keeps running, but the game is stuck.
+    pub fn unimplemented_opcode(&self) -> Option<u16> {
+        self.cpu.unimplemented_opcode()
+    }
+
+    /// `EmulatorError::CpuFault` if the CPU has locked up on an unimplemented opcode (see
+    /// `unimplemented_opcode`).
+    pub fn fault(&self) -> Result<(), EmulatorError> {
+        match self.cpu.unimplemented_opcode() {
+            Some(opcode) => Err(EmulatorError::CpuFault { opcode, pc: self.cpu.registers().pc }),
+            None => Ok(()),
+        }
+    }
+
+    /// Instructions executed since power-on (interrupt dispatch and HALT idling don't count).
+    pub fn instruction_count(&self) -> u64 {
+        self.cpu.executed()
+    }
+
+    /// Addresses of the last instructions executed (up to 64), oldest first; for debuggers.
+    pub fn recent_instructions(&self) -> Vec<u16> {
+        let history = self.cpu.history();
+        history[history.len().saturating_sub(64)..].iter().map(|entry| entry.registers.pc).collect()
+    }
+
+    /// The last instructions executed (up to 1024), oldest first, with
+    /// the registers before each; for crash reports and trace dumps ("how did we get here?").
+    pub fn trace(&self) -> Vec<TraceEntry> {
+        self.cpu.history()
+    }
+
+    /// Starts or stops checking the stack: pushes and pops outside work RAM and high RAM,
+    /// and pushes over the game's high RAM variables, are kept for `take_stack_fault`.
+    /// Checking goes through the slower stepping machines with event subscribers use.
+    pub fn check_stack(&mut self, on: bool) {
+        self.mmu.check_stack(on);
+    }
+
+    /// Returns (once) the first stack fault since the last call, while `check_stack` is on.
+    pub fn take_stack_fault(&mut self) -> Option<StackFault> {
+        self.mmu.take_stack_fault()
+    }
+
+    /// Returns `true` (once) if the game executed `LD B,B`, the software breakpoint
+    /// used by test suites such as mooneye-gb to signal completion.
+    pub fn take_breakpoint(&mut self) -> bool {
+        self.cpu.take_breakpoint()
+    }
+
+    /// Returns the bytes the game sent over the link port since the last call. Test ROMs
+    /// (e.g. Blargg's) print their results this way.
+    pub fn take_serial_output(&mut self) -> Vec<u8> {
+        self.mmu.take_serial()
+    }
+
+    /// Captures a snapshot of the whole machine (CPU, memory, PPU timing): a header with
+    /// the core version, then one versioned chunk per component.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::with_header();
+        w.chunk(b"CPU ", CPU::STATE_VERSION, |w| self.cpu.save_state(w));
+        w.chunk(b"MMU ", MMU::STATE_VERSION, |w| self.mmu.save_state(w));
+        w.chunk(b"PPU ", PPU::STATE_VERSION, |w| self.ppu.save_state(w, self.ppu_debt));
+        w.finish()
+    }
+
+    /// Restores a snapshot taken with `save_state` by this or an earlier build, migrating
+    /// older chunk layouts. Fails on states written by a newer core with layouts this one
+    /// doesn't know.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmulatorError> {
+        self.read_state(data).map_err(EmulatorError::SaveState)?;
+        self.events.emit(Event::StateLoaded);
+        Ok(())
+    }
+
+    fn read_state(&mut self, data: &[u8]) -> Result<(), String> {
+        self.ppu_debt = 0;
+        self.boot_frames = 0;
+        match StateChunks::parse(data)? {
+            Some(chunks) => {
+                let (mut r, _) = chunks.get(b"CPU ", CPU::STATE_VERSION)?;
+                self.cpu.load_state(&mut r)?;
+                let (mut r, version) = chunks.get(b"MMU ", MMU::STATE_VERSION)?;
+                self.mmu.load_state(&mut r, version)?;
+                let (mut r, version) = chunks.get(b"PPU ", PPU::STATE_VERSION)?;
+                self.ppu.load_state(&mut r, version)?;
+            }
+            // Unversioned snapshot: the first layout of each component back to back
+            None => {
+                let mut r = StateReader::new(data);
+                self.cpu.load_state(&mut r)?;
+                self.mmu.load_state(&mut r, 1)?;
+                self.ppu.load_state(&mut r, 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the cartridge RAM if the cartridge keeps it battery-backed.
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then(|| self.mmu.eram())
+    }
+
+    /// Returns `true` if the cartridge has a rumble motor (see `take_rumble`).
+    pub fn has_rumble(&self) -> bool {
+        self.mmu.has_rumble()
+    }
+
+    /// How much of the emulated time since the last call (0–1) the cartridge's rumble motor
+    /// was on; always 0 for carts without one. Games pulse the motor to vary its strength,
+    /// so taken once a frame this reads as the strength it was meant to have.
+    pub fn take_rumble(&mut self) -> f32 {
+        self.mmu.take_rumble()
+    }
+
+    /// Starts recording which ROM bytes the CPU executes, reads and writes, continuing from
+    /// `map` (an earlier `coverage` of the same ROM; empty or another size starts afresh).
+    /// Only the CPU's accesses count, not debuggers' or scripts' reads.
+    pub fn track_coverage(&mut self, map: &[u8]) {
+        self.mmu.track_coverage(map);
+    }
+
+    /// The coverage map, if `track_coverage` was called: a `COVERAGE_*` flag byte for each
+    /// byte of the ROM, in ROM order (bank 0 first).
+    pub fn coverage(&self) -> Option<Vec<u8>> {
+        self.mmu.coverage()
+    }
+
+    /// Restores battery-backed cartridge RAM (e.g. from a `.sav` file).
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        self.mmu.load_eram(data);
+    }
+
+    /// Returns the palette currently used to render frames (DMG games only; color games
+    /// use the palettes they program).
+    pub fn palette(&self) -> Palette {
+        self.ppu.get_palette()
+    }
+
+    /// Selects the palette used to render subsequent scanlines.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.ppu.set_palette(palette);
+    }
+
+    /// Draws the sprites using `tile` in shades of `color` instead of the palette's (DMG
+    /// games only), for example to give each Tetris piece its own color; `None` undoes it.
+    /// Like the palette, this is a display setting: it survives resets and isn't saved.
+    pub fn set_sprite_color(&mut self, tile: u8, color: Option<[u8; 3]>) {
+        self.ppu.set_sprite_color(tile, color);
+    }
+
+    /// Draws a dark outline a pixel wide around the sprites as each frame is finished, so
+    /// they stand out from the background; a display setting like the palette.
+    pub fn set_sprite_outlines(&mut self, on: bool) {
+        self.ppu.set_sprite_outlines(on);
+    }
+
+    /// Attaches (or detaches) a link cable partner; see `link::run_linked_frame`. Unlinked,
+    /// serial transfers complete at once with nothing on the other end.
+    pub fn set_linked(&mut self, linked: bool) {
+        self.mmu.set_linked(linked);
+    }
+
+    /// Completes this machine's internal-clock transfer once its byte is clocked out: the
+    /// bytes are swapped if `partner` is waiting on the external clock, otherwise 0xFF
+    /// comes back (nobody listening).
+    pub(crate) fn link_transfer(&mut self, partner: &mut GB) {
+        if !self.mmu.take_serial_transfer() {
+            return;
+        }
+        if partner.mmu.serial_waiting() {
+            let sent = self.mmu.finish_serial(partner.mmu.serial_out());
+            partner.mmu.finish_serial(sent);
+            partner.events.emit(Event::Interrupt(Interrupt::Serial));
+        } else {
+            self.mmu.finish_serial(0xFF);
+        }
+        // Raised between steps, so `step_with_events` can't see it
+        self.events.emit(Event::Interrupt(Interrupt::Serial));
+    }
+
+    /// Reseeds the machine's random source (the Tetris profile's DIV noise). Each `GB`
+    /// draws a fresh seed from the OS; two machines given the same seed, cartridge and
+    /// input run identically, which is what differential testing needs.
+    pub fn seed(&mut self, seed: u64) {
+        self.mmu.seed(seed);
+    }
+}
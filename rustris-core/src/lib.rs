@@ -0,0 +1,106 @@
+//! Game Boy (DMG) emulation core of RUSTЯIS, with no front-end dependencies.
+//!
+//! - `GB` ties the CPU, memory bus and PPU together; call `GB::step` in a loop and it
+//!   renders straight into any RGB24 buffer you hand it.
+//! - Input is a bitmask of the `gb::BTN_*` constants, passed to `GB::input_press` /
+//!   `GB::input_release`.
+//! - Machine state can be captured and restored as opaque bytes (`GB::save_state`) that
+//!   later versions of the core keep loading, and battery-backed cartridge RAM is exposed
+//!   for `.sav` files.
+//!
+//! Window, input devices and frame pacing are left to the front-end, which plugs in
+//! through the `VideoSink` and `InputSource` traits; the `rustris` binary is one such
+//! front-end (SDL2). There is no APU, so no sound.
+//!
+//! ```no_run
+//! use rustris_core::{gb, Cartridge, InputSource, VideoSink, GB, SCREEN_HEIGHT, SCREEN_WIDTH};
+//!
+//! struct Screen(Vec<u8>);
+//!
+//! impl VideoSink for Screen {
+//!     fn buffer(&mut self) -> (&mut [u8], usize) {
+//!         (&mut self.0, SCREEN_WIDTH as usize * 3)
+//!     }
+//!     fn present(&mut self) {
+//!         // Show `self.0` (RGB24)
+//!     }
+//! }
+//!
+//! struct HoldStart;
+//!
+//! impl InputSource for HoldStart {
+//!     fn poll(&mut self) -> u8 {
+//!         gb::BTN_START
+//!     }
+//! }
+//!
+//! let mut screen = Screen(vec![0; SCREEN_WIDTH as usize * 3 * SCREEN_HEIGHT as usize]);
+//! let mut gb = GB::new(Cartridge::from_file("tetris.gb")?)?;
+//! for _ in 0..60 {
+//!     gb.run_frame(&mut screen, &mut HoldStart);
+//! }
+//! # Ok::<(), rustris_core::EmulatorError>(())
+//! ```
+//!
+//! Programs that just consume the output can pull it instead: `GB::frames` (or
+//! `GB::frames_with`, taking the buttons from an input source or a closure) is an
+//! iterator over the completed frames.
+//!
+//! ```no_run
+//! use rustris_core::{gb, Cartridge, GB};
+//! use std::cell::Cell;
+//!
+//! let mut gb = GB::new(Cartridge::from_file("tetris.gb")?)?;
+//! let held = Cell::new(0);
+//! for frame in gb.frames_with(|| held.get()).take(600) {
+//!     // `frame.pixels` is RGB24; press Start every other second
+//!     held.set(if frame.index / 60 % 2 == 1 { gb::BTN_START } else { 0 });
+//! }
+//! # Ok::<(), rustris_core::EmulatorError>(())
+//! ```
+//!
+//! Front-ends and tools can also subscribe to what happens inside the machine (frames,
+//! serial bytes, interrupts, restored states) with `GB::subscribe`; see `events`.
+//!
+//! Failures (unreadable or unsupported ROMs, bad save states, CPU lockups) are reported as
+//! `EmulatorError`s.
+
+mod boot;
+mod cpu;
+mod error;
+mod frames;
+mod mbc;
+mod mmu;
+mod ppu;
+mod savestate;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+
+pub mod cartridge;
+pub mod disasm;
+pub mod events;
+pub mod frontend;
+pub mod gb;
+pub mod link;
+pub mod romdb;
+
+pub use cartridge::Cartridge;
+pub use cpu::{Registers, TraceEntry};
+pub use error::EmulatorError;
+pub use events::{Dma, DmaRecord, Event, Interrupt, InterruptRecord, IoWrite};
+pub use frames::{Frame, Frames, FRAME_PITCH};
+pub use frontend::{InputSource, VideoSink};
+pub use gb::{memory_region, Model, Profile, RamInit, StackFault, StackFaultKind, GB};
+pub use romdb::RomInfo;
+pub use ppu::{FrameTiming, Palette, COLORBLIND_PALETTE, COLOR_PALETTE, GREEN_PALETTE, HIGH_CONTRAST_PALETTE, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Individual components, exposed for the micro-benchmarks (`cargo bench --features internals`)
+/// and the fuzz targets.
+/// Not part of the stable API.
+#[cfg(feature = "internals")]
+#[doc(hidden)]
+pub mod internals {
+    pub use crate::cpu::{Bus, CPU};
+    pub use crate::mmu::MMU;
+    pub use crate::ppu::PPU;
+}
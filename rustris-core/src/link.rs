@@ -0,0 +1,33 @@
+//! Link cable between two machines in the same process.
+
+use crate::gb::GB;
+
+/// Runs two linked machines (see `GB::set_linked`) until both have completed a frame.
+///
+/// - The machine that is behind in T-cycles steps next, so the two stay within one
+///   instruction of each other; once one finishes its frame it waits for the other.
+/// - When a transfer started on the internal clock has shifted out its 8 bits, the bytes
+///   are swapped with the partner (if it's waiting on the external clock) and both get
+///   the serial interrupt.
+/// - Input isn't polled: set each machine's buttons (`GB::set_buttons`) beforehand.
+///   That keeps the pair deterministic, which rollback netplay relies on.
+pub fn run_linked_frame(machines: &mut [GB; 2], framebuffers: [&mut [u8]; 2], pitch: usize) {
+    let [a, b] = machines;
+    let [fa, fb] = framebuffers;
+    let (mut ca, mut cb) = (0u64, 0u64);
+    let (mut done_a, mut done_b) = (false, false);
+
+    while !(done_a && done_b) {
+        if !done_a && (done_b || ca <= cb) {
+            let (t, done) = a.step_cycles(fa, pitch);
+            ca += t as u64;
+            done_a = done;
+        } else {
+            let (t, done) = b.step_cycles(fb, pitch);
+            cb += t as u64;
+            done_b = done;
+        }
+        a.link_transfer(b);
+        b.link_transfer(a);
+    }
+}
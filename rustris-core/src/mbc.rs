@@ -0,0 +1,120 @@
+use crate::savestate::{StateReader, StateWriter};
+
+/// Memory bank controller types understood by `Mbc`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MbcKind {
+    None,
+    Mbc1,
+    Mbc3,
+    Mbc5,
+}
+
+/// Memory bank controller: turns writes to the ROM area into bank switches and maps
+/// CPU addresses to offsets in the ROM and cartridge RAM images.
+/// - MBC1: 5+2 bit ROM bank, RAM banking in mode 1.
+/// - MBC3: 7 bit ROM bank, 4 RAM banks; the real-time clock registers read as 0xFF.
+/// - MBC5: 9 bit ROM bank, 16 RAM banks; 8 on rumble carts, where bit 3 of the RAM bank
+///   register drives the motor instead.
+/// - ROM-only cartridges keep the flat 32KB map and their RAM always enabled.
+pub struct Mbc {
+    kind: MbcKind,
+    ram_enabled: bool,
+    rom_bank: u16, // Low ROM bank register (MBC1: 5 bits, MBC3: 7 bits, MBC5: 9 bits)
+    ram_bank: u8,  // RAM bank register (MBC1: upper 2 bits, also ROM bits 5–6)
+    mode: bool,    // MBC1 banking mode (false = ROM, true = RAM/advanced)
+    rumble: bool,  // MBC5 cart with a rumble motor
+    motor: bool,   // The rumble motor is on
+    rom_banks: usize,
+    ram_banks: usize,
+}
+
+impl Mbc {
+    pub fn new(kind: MbcKind, rom_len: usize, ram_len: usize, rumble: bool) -> Self {
+        Self {
+            kind,
+            ram_enabled: kind == MbcKind::None,
+            rom_bank: 1,
+            ram_bank: 0,
+            mode: false,
+            rumble: kind == MbcKind::Mbc5 && rumble,
+            motor: false,
+            rom_banks: (rom_len / 0x4000).max(2),
+            ram_banks: (ram_len / 0x2000).max(1),
+        }
+    }
+
+    pub fn kind(&self) -> MbcKind {
+        self.kind
+    }
+
+    pub fn has_rumble(&self) -> bool {
+        self.rumble
+    }
+
+    pub fn motor(&self) -> bool {
+        self.motor
+    }
+
+    /// Handles a write to 0x0000–0x7FFF.
+    pub fn write(&mut self, addr: u16, value: u8) {
+        match (self.kind, addr) {
+            (MbcKind::None, _) => {}
+            (_, 0x0000..=0x1FFF) => self.ram_enabled = value & 0x0F == 0x0A,
+            (MbcKind::Mbc1, 0x2000..=0x3FFF) => self.rom_bank = (value & 0x1F).max(1) as u16,
+            (MbcKind::Mbc3, 0x2000..=0x3FFF) => self.rom_bank = (value & 0x7F).max(1) as u16,
+            (MbcKind::Mbc5, 0x2000..=0x2FFF) => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            (MbcKind::Mbc5, 0x3000..=0x3FFF) => self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 1) << 8),
+            (MbcKind::Mbc1, 0x4000..=0x5FFF) => self.ram_bank = value & 0x03,
+            (MbcKind::Mbc3, 0x4000..=0x5FFF) => self.ram_bank = value & 0x0F, // 0x08+ select the clock
+            (MbcKind::Mbc5, 0x4000..=0x5FFF) if self.rumble => {
+                self.ram_bank = value & 0x07;
+                self.motor = value & 0x08 != 0;
+            }
+            (MbcKind::Mbc5, 0x4000..=0x5FFF) => self.ram_bank = value & 0x0F,
+            (MbcKind::Mbc1, 0x6000..=0x7FFF) => self.mode = value & 1 != 0,
+            _ => {} // MBC3 clock latch, unused ranges
+        }
+    }
+
+    /// Offset in the ROM image of a CPU address in 0x0000–0x7FFF.
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match (self.kind, addr) {
+            (MbcKind::None, _) => return addr as usize,
+            (MbcKind::Mbc1, 0x0000..=0x3FFF) if self.mode => (self.ram_bank as usize) << 5,
+            (_, 0x0000..=0x3FFF) => 0,
+            (MbcKind::Mbc1, _) => ((self.ram_bank as usize) << 5) | self.rom_bank as usize,
+            _ => self.rom_bank as usize,
+        };
+        (bank % self.rom_banks) * 0x4000 + (addr as usize & 0x3FFF)
+    }
+
+    /// Offset in the cartridge RAM image of a CPU address in 0xA000–0xBFFF, or `None`
+    /// when RAM is disabled or a clock register is selected.
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        let bank = match self.kind {
+            MbcKind::Mbc1 if !self.mode => 0,
+            MbcKind::Mbc3 if self.ram_bank >= 0x08 => return None,
+            MbcKind::None => 0,
+            _ => self.ram_bank as usize,
+        };
+        Some((bank % self.ram_banks) * 0x2000 + (addr as usize & 0x1FFF))
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.bool(self.ram_enabled);
+        w.u16(self.rom_bank);
+        w.u8(self.ram_bank);
+        w.bool(self.mode);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.ram_enabled = r.bool()?;
+        self.rom_bank = r.u16()?;
+        self.ram_bank = r.u8()?;
+        self.mode = r.bool()?;
+        Ok(())
+    }
+}
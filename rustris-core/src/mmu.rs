@@ -0,0 +1,955 @@
+use crate::cartridge::Cartridge;
+use crate::cpu::Bus;
+use crate::error::EmulatorError;
+use crate::events::Dma;
+use crate::gb::RamInit;
+use crate::mbc::Mbc;
+use crate::savestate::{StateReader, StateWriter};
+
+use std::cell::Cell;
+
+use crate::gb::{Profile, StackFault, StackFaultKind, COVERAGE_CODE, COVERAGE_DATA, COVERAGE_WRITTEN, BTN_RIGHT, BTN_LEFT, BTN_UP, BTN_DOWN, BTN_A, BTN_B, BTN_SELECT, BTN_START};
+
+// MMU: implements the DMG/CGB memory map and bus access.
+// Responsibilities:
+//   - Owns VRAM/WRAM/OAM/HRAM, I/O registers, and IE.
+//   - Serves CPU reads/writes and mirrors (e.g., E000–FDFF mirrors C000–DDFF).
+//   - Maps ROM and cartridge RAM through the cartridge's memory bank controller.
+//   - Exposes the joypad matrix via P1 (0xFF00).
+//   - Performs DMA-OAM transfers on writes to 0xFF46: at once, or timed and blocking the
+//     CPU's bus (see `Bus for MMU`) in the Accurate profile.
+//   - Serial port: logs outgoing bytes; when linked, times transfers for `link`.
+//   - Compatible profile: runs the DIV/TIMA timer, keeps the STAT mode/LYC bits
+//     read-only and raises the joypad interrupt on button presses.
+//   - In CGB mode: VRAM/WRAM banking (VBK/SVBK), color palette RAM (BCPS/BCPD,
+//     OCPS/OCPD), VRAM DMA (HDMA1–5) and the double-speed switch (KEY1).
+//   - Loads the post-boot I/O registers in `post_boot()` (Compatible profile), or maps a
+//     boot ROM over the cartridge until the game writes FF50.
+//   - Warm resets (`reset`) keep memory; power cycles also refill it (`fill_ram`).
+
+/// T-cycles to clock one byte out at the normal 8192 Hz serial rate.
+const SERIAL_BYTE_CYCLES: u32 = 4096;
+
+/// T-cycles an OAM DMA takes: one M-cycle per byte.
+const OAM_DMA_CYCLES: u32 = 0xA0 * 4;
+
+pub struct MMU {
+    rom: Vec<u8>,       // Whole ROM image (at least 32KB)
+    mbc: Mbc,           // Bank controller mapping `rom` and `eram`
+    vram: [u8; 0x4000], // 2 × 8KB VRAM banks (bank 1 is CGB only)
+    eram: Vec<u8>,      // Cartridge RAM (at least 8KB)
+    wram: [u8; 0x8000], // 8 × 4KB WRAM banks (banks 2–7 are CGB only)
+    oam: [u8; 0xA0],    // 160 bytes Object Attribute Memory
+    io: [u8; 0x80],     // 128 bytes IO registers
+    hram: [u8; 0x7F],   // 127 bytes HRAM
+    ie: u8,             // Interrupt Enable
+    buttons: u8,        // Input buttons
+    joypad_poll: bool,  // P1 select lines were written since the last `take_joypad_poll`
+    watches: Vec<(u16, u16)>,      // Address ranges whose writes are logged (scripting)
+    watch_log: Vec<(u16, u8)>,     // Logged writes since the last `take_watched_writes`
+    traced_io: Vec<u16>,           // Registers whose writes are traced (debugger)
+    io_writes: Vec<(u16, u8)>,     // Traced writes since the last `take_io_writes`
+    serial: Vec<u8>,               // Bytes sent over the link port since the last `take_serial`
+    profile: Profile,              // Tetris: DIV is noise and there's no timer
+    div: u16,                      // Internal divider; DIV is its upper byte
+    noise: Cell<u64>,              // xorshift64 state behind the Tetris profile's DIV noise
+    linked: bool,                  // A link cable partner is attached (see `link`)
+    serial_clock: u32,             // T-cycles left in an internal-clock transfer (linked only)
+    serial_done: bool,             // That transfer has clocked out all 8 bits
+    oam_dma: Option<(u16, u32)>,   // OAM DMA in progress (Accurate profile): source, T-cycles run
+    dma_started: Option<(Dma, u16, u16, u16)>, // Last DMA started, until taken: kind, source, destination, length
+    boot_rom: Vec<u8>,             // Run at reset (empty: none); 256 bytes, or 2304 on a CGB
+    boot_mapped: bool,             // The boot ROM hides the cartridge until FF50 is written
+    coverage: Option<Box<[Cell<u8>]>>, // Per ROM byte `COVERAGE_*` flags, while tracking
+    stack_check: Option<StackCheck>,   // While checking the stack
+    rumble_cycles: [u64; 2],           // T-cycles since the last `take_rumble`: with the motor on, in all
+
+    // CGB state (unused in DMG mode)
+    cgb: bool,                // Running a color game on a CGB
+    vram_bank: u8,            // VBK: VRAM bank at 0x8000 (0–1)
+    wram_bank: u8,            // SVBK: WRAM bank at 0xD000 (1–7)
+    speed_armed: bool,        // KEY1 bit 0: the next STOP switches speed
+    double_speed: bool,       // KEY1 bit 7: CPU runs at 2× (8 MHz)
+    bg_palettes: [u8; 64],    // 8 BG palettes × 4 colors × RGB555
+    obj_palettes: [u8; 64],   // 8 OBJ palettes × 4 colors × RGB555
+    hdma_src: u16,            // Next VRAM DMA source address
+    hdma_dst: u16,            // Next VRAM DMA destination (0x8000–0x9FF0)
+    hdma_blocks: u8,          // 16-byte blocks left in an HBlank DMA (0 = idle)
+}
+
+impl MMU {
+    /// Creates the memory map for `cartridge`; `cgb` enables the Game Boy Color
+    /// registers and banking (only for color games on a CGB).
+    pub fn new(cartridge: Cartridge, cgb: bool) -> Result<Self, EmulatorError> {
+        let kind = cartridge.mbc()?;
+        let eram = vec![0; cartridge.ram_size().max(0x2000)];
+        let mut rom = cartridge.rom.clone();
+        if rom.len() < 0x8000 {
+            rom.resize(0x8000, 0xFF);
+        }
+        let mbc = Mbc::new(kind, rom.len(), eram.len(), cartridge.has_rumble());
+        let mut mmu = Self::with_memory(rom, mbc, eram, cgb);
+        mmu.compose_p1();
+        Ok(mmu)
+    }
+
+    /// The power-on state around a ROM image, its bank controller and cartridge RAM.
+    fn with_memory(rom: Vec<u8>, mbc: Mbc, eram: Vec<u8>, cgb: bool) -> Self {
+        Self {
+            mbc,
+            rom,
+            vram: [0; 0x4000],
+            eram,
+            wram: [0; 0x8000],
+            oam:  [0; 0xA0],
+            hram: [0; 0x7F],
+            io:   [0; 0x80],
+            ie: 0,
+            buttons: 0,
+            joypad_poll: false,
+            watches: Vec::new(),
+            watch_log: Vec::new(),
+            traced_io: Vec::new(),
+            io_writes: Vec::new(),
+            serial: Vec::new(),
+            profile: Profile::Compatible,
+            div: 0,
+            noise: Cell::new(rand::random::<u64>() | 1),
+            linked: false,
+            serial_clock: 0,
+            serial_done: false,
+            oam_dma: None,
+            dma_started: None,
+            cgb,
+            vram_bank: 0,
+            wram_bank: 1,
+            speed_armed: false,
+            double_speed: false,
+            bg_palettes: [0xFF; 64],
+            obj_palettes: [0xFF; 64],
+            hdma_src: 0,
+            hdma_dst: 0x8000,
+            hdma_blocks: 0,
+            boot_rom: Vec::new(),
+            boot_mapped: false,
+            coverage: None,
+            stack_check: None,
+            rumble_cycles: [0; 2],
+        }
+    }
+
+    /// Puts the registers, the bank controller and the CGB state back to their power-on
+    /// values, as the RESET line does, and maps the boot ROM again if there's one. Memory
+    /// (VRAM, WRAM, OAM, HRAM and cartridge RAM) keeps its contents, and so does what
+    /// belongs to the host: buttons, write watches and traces, the serial log, profile, link, noise,
+    /// coverage and the stack check.
+    pub fn reset(&mut self) {
+        let (rom, eram) = (std::mem::take(&mut self.rom), std::mem::take(&mut self.eram));
+        let mbc = Mbc::new(self.mbc.kind(), rom.len(), eram.len(), self.mbc.has_rumble());
+        let fresh = Self::with_memory(rom, mbc, eram, self.cgb);
+        let old = std::mem::replace(self, fresh);
+        (self.vram, self.wram, self.oam, self.hram) = (old.vram, old.wram, old.oam, old.hram);
+        (self.buttons, self.watches, self.serial) = (old.buttons, old.watches, old.serial);
+        self.traced_io = old.traced_io;
+        (self.profile, self.noise, self.linked) = (old.profile, old.noise, old.linked);
+        (self.coverage, self.stack_check) = (old.coverage, old.stack_check);
+        self.boot_mapped = !old.boot_rom.is_empty();
+        self.boot_rom = old.boot_rom;
+        self.compose_p1();
+    }
+
+    /// Overwrites VRAM, WRAM, OAM and HRAM as `init` says, and cartridge RAM too if
+    /// `cartridge_ram` (it isn't battery-backed). Random contents come from the noise
+    /// generator, so a seeded machine (see `seed`) gets the same bytes every time.
+    pub fn fill_ram(&mut self, init: RamInit, cartridge_ram: bool) {
+        let noise = &self.noise;
+        let fill = |memory: &mut [u8]| match init {
+            RamInit::Zero => memory.fill(0),
+            RamInit::Random => memory.iter_mut().for_each(|byte| *byte = (next_noise(noise) >> 32) as u8),
+        };
+        fill(&mut self.vram);
+        fill(&mut self.wram);
+        fill(&mut self.oam);
+        fill(&mut self.hram);
+        if cartridge_ram {
+            fill(&mut self.eram);
+        }
+    }
+
+    /// Installs the boot ROM mapped by the next `reset`.
+    pub fn set_boot_rom(&mut self, data: Vec<u8>) {
+        self.boot_rom = data;
+    }
+
+    /// `true` while the boot ROM is mapped (it hasn't handed over to the cartridge yet).
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.boot_mapped
+    }
+
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF04 if self.profile != Profile::Tetris => (self.div >> 8) as u8,
+            0xFF04 => {
+                // DIV (Divider register = upper 8 bits of an internal 16-bit counter).
+                // The Tetris profile returns a random byte instead of emulating the divider/timers.
+                // Proper behavior: DIV = (divider >> 8), increments at ~16,384 Hz (every 256 T-cycles),
+                // and writing to FF04 resets it to 0, as implemented in write_byte.
+                (next_noise(&self.noise) >> 32) as u8
+            }
+
+            0xFF4D if self.cgb => (self.double_speed as u8) << 7 | 0x7E | self.speed_armed as u8,
+            0xFF4F if self.cgb => 0xFE | self.vram_bank,
+            0xFF55 if self.cgb => match self.hdma_blocks {
+                0 => 0xFF,
+                n => n - 1, // Bit 7 clear: transfer active
+            },
+            0xFF69 if self.cgb => self.bg_palettes[(self.io[0x68] & 0x3F) as usize],
+            0xFF6B if self.cgb => self.obj_palettes[(self.io[0x6A] & 0x3F) as usize],
+            0xFF70 if self.cgb => 0xF8 | self.wram_bank,
+
+            // A CGB boot ROM leaves a window at 0100–01FF for the cartridge header
+            0x0000..=0x00FF | 0x0200..=0x08FF if self.boot_mapped && (addr as usize) < self.boot_rom.len() => {
+                self.boot_rom[addr as usize]
+            }
+            0x0000..=0x7FFF => self.rom[self.mbc.rom_offset(addr)],
+            0x8000..=0x9FFF => self.vram[self.vram_bank as usize * 0x2000 + (addr - 0x8000) as usize],
+            0xA000..=0xBFFF => match self.mbc.ram_offset(addr) {
+                Some(offset) => self.eram[offset],
+                None => 0xFF,
+            },
+            0xC000..=0xFDFF => self.wram[self.wram_offset(addr)],
+            0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize],
+            0xFEA0..=0xFEFF => 0xFF,
+            0xFF00..=0xFF7F => self.io[(addr - 0xFF00) as usize],
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize],
+            0xFFFF => self.ie,
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: u16, value: u8) {
+        if !self.watches.is_empty() && self.watches.iter().any(|&(start, end)| (start..=end).contains(&addr)) {
+            self.watch_log.push((addr, value));
+        }
+        if !self.traced_io.is_empty() && self.traced_io.contains(&addr) {
+            self.io_writes.push((addr, value));
+        }
+
+        match addr {
+            0x0000..=0x7FFF => self.mbc.write(addr, value),
+            0x8000..=0x9FFF => self.vram[self.vram_bank as usize * 0x2000 + (addr - 0x8000) as usize] = value,
+            0xA000..=0xBFFF => {
+                if let Some(offset) = self.mbc.ram_offset(addr) {
+                    self.eram[offset] = value;
+                }
+            }
+            0xC000..=0xFDFF => {
+                let offset = self.wram_offset(addr);
+                self.wram[offset] = value;
+            }
+            0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize] = value,
+            0xFEA0..=0xFEFF => {}
+            0xFF00..=0xFF7F => {
+                match addr {
+                    0xFF00 => {
+                        // Bits 4–5 select group (0 = selected). Bits 6–7 are always 1.
+                        let cur = self.io[0x00];
+                        let newp1 = (cur & 0b1100_1111) | (value & 0b0011_0000) | 0b1100_0000;
+                        self.io[0x00] = newp1;
+                        self.compose_p1();
+                        self.joypad_poll = true;
+                        return;
+                    }
+                    0xFF02 if value & 0x81 == 0x81 => {
+                        // Serial transfer on the internal clock. The byte in SB goes to the
+                        // serial log (test ROMs report through it). With no partner, 0xFF is
+                        // shifted in and the transfer completes at once (IF bit 3); a linked
+                        // machine takes 8 bits' time and swaps bytes with the partner.
+                        self.serial.push(self.io[0x01]);
+                        if self.linked {
+                            self.io[0x02] = value;
+                            self.serial_clock = SERIAL_BYTE_CYCLES;
+                        } else {
+                            self.io[0x02] = value & 0x7F;
+                            self.finish_serial(0xFF);
+                        }
+                        return;
+                    }
+                    0xFF04 => { self.io[(addr - 0xFF00) as usize] = 0; self.div = 0; return; }
+                    // Any write with bit 0 set unmaps the boot ROM for good (until reset)
+                    0xFF50 if value & 1 != 0 => self.boot_mapped = false,
+                    0xFF41 if self.profile != Profile::Tetris => {
+                        // Mode and LYC coincidence bits are read-only (the PPU owns them)
+                        self.io[0x41] = (self.io[0x41] & 0x07) | (value & 0x78) | 0x80;
+                        return;
+                    }
+                    0xFF46 => {
+                        // OAM DMA: copy 160 bytes from (value << 8) .. (value << 8) + 0x9F to OAM,
+                        // at once or, Accurate profile, a byte per M-cycle (see `tick`)
+                        self.oam_dma = Some(((value as u16) << 8, 0));
+                        self.dma_started = Some((Dma::Oam, (value as u16) << 8, 0xFE00, 0xA0));
+                        if self.profile != Profile::Accurate {
+                            self.run_oam_dma(OAM_DMA_CYCLES);
+                        }
+                    }
+                    0xFF4D if self.cgb => { self.speed_armed = value & 1 != 0; return; }
+                    0xFF4F if self.cgb => { self.vram_bank = value & 1; return; }
+                    0xFF55 if self.cgb => { self.start_vram_dma(value); return; }
+                    0xFF69 if self.cgb => { write_palette(&mut self.bg_palettes, &mut self.io[0x68], value); return; }
+                    0xFF6B if self.cgb => { write_palette(&mut self.obj_palettes, &mut self.io[0x6A], value); return; }
+                    0xFF70 if self.cgb => { self.wram_bank = (value & 0x07).max(1); return; }
+                    _ => {}
+                }
+                self.io[(addr - 0xFF00) as usize] = value;
+            }
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize] = value,
+            0xFFFF => self.ie = value,
+        }
+    }
+
+    /// Layout version of the `MMU ` save-state chunk:
+    /// - 1: up to DIV.
+    /// - 2: adds the DIV noise generator and the serial transfer in flight.
+    /// - 3: adds whether the boot ROM is still mapped.
+    /// - 4: adds the OAM DMA in progress.
+    pub const STATE_VERSION: u16 = 4;
+
+    /// Serializes all RAM and registers. ROM is not included (it comes from the cartridge)
+    /// and neither is the live button state, which belongs to the host.
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.bytes(&self.vram);
+        w.bytes(&self.eram);
+        w.bytes(&self.wram);
+        w.bytes(&self.oam);
+        w.bytes(&self.io);
+        w.bytes(&self.hram);
+        w.u8(self.ie);
+        self.mbc.save_state(w);
+        w.u8(self.vram_bank);
+        w.u8(self.wram_bank);
+        w.bool(self.speed_armed);
+        w.bool(self.double_speed);
+        w.bytes(&self.bg_palettes);
+        w.bytes(&self.obj_palettes);
+        w.u16(self.hdma_src);
+        w.u16(self.hdma_dst);
+        w.u8(self.hdma_blocks);
+        w.u16(self.div);
+        w.u64(self.noise.get());
+        w.u32(self.serial_clock);
+        w.bool(self.serial_done);
+        w.bool(self.boot_mapped);
+        let (source, run) = self.oam_dma.unwrap_or_default();
+        w.bool(self.oam_dma.is_some());
+        w.u16(source);
+        w.u32(run);
+    }
+
+    /// Restores the state written by `save_state` with layout `version`. Older layouts keep
+    /// the current noise generator and no serial transfer in flight, had finished booting
+    /// and have no OAM DMA running.
+    pub fn load_state(&mut self, r: &mut StateReader, version: u16) -> Result<(), String> {
+        r.bytes(&mut self.vram)?;
+        r.bytes(&mut self.eram)?;
+        r.bytes(&mut self.wram)?;
+        r.bytes(&mut self.oam)?;
+        r.bytes(&mut self.io)?;
+        r.bytes(&mut self.hram)?;
+        self.ie = r.u8()?;
+        self.mbc.load_state(r)?;
+        self.vram_bank = r.u8()? & 1;
+        self.wram_bank = (r.u8()? & 0x07).max(1);
+        self.speed_armed = r.bool()?;
+        self.double_speed = r.bool()?;
+        r.bytes(&mut self.bg_palettes)?;
+        r.bytes(&mut self.obj_palettes)?;
+        self.hdma_src = r.u16()?;
+        self.hdma_dst = r.u16()?;
+        self.hdma_blocks = r.u8()?;
+        self.div = r.u16()?;
+        if version >= 2 {
+            self.noise.set(r.u64()? | 1);
+            self.serial_clock = r.u32()?;
+            self.serial_done = r.bool()?;
+        } else {
+            self.serial_clock = 0;
+            self.serial_done = false;
+        }
+        // Mapping a boot ROM this machine doesn't have would run the cartridge from 0000
+        self.boot_mapped = version >= 3 && r.bool()? && !self.boot_rom.is_empty();
+        self.oam_dma = None;
+        if version >= 4 {
+            let (active, source, run) = (r.bool()?, r.u16()?, r.u32()?);
+            self.oam_dma = active.then_some((source, run.min(OAM_DMA_CYCLES)));
+        }
+        // The buttons are the host's, not the state's
+        self.compose_p1();
+        Ok(())
+    }
+
+    /// Copies the whole 64KB address space as the CPU would currently read it.
+    pub fn dump(&self, out: &mut [u8]) {
+        for bank in [0x0000, 0x4000] {
+            let offset = self.mbc.rom_offset(bank);
+            out[bank as usize..bank as usize + 0x4000].copy_from_slice(&self.rom[offset..offset + 0x4000]);
+        }
+        if self.boot_mapped {
+            for (addr, &byte) in self.boot_rom.iter().enumerate() {
+                if !(0x100..0x200).contains(&addr) {
+                    out[addr] = byte;
+                }
+            }
+        }
+        let vram = self.vram_bank as usize * 0x2000;
+        out[0x8000..0xA000].copy_from_slice(&self.vram[vram..vram + 0x2000]);
+        match self.mbc.ram_offset(0xA000) {
+            Some(offset) => out[0xA000..0xC000].copy_from_slice(&self.eram[offset..offset + 0x2000]),
+            None => out[0xA000..0xC000].fill(0xFF),
+        }
+        let wram = self.wram_bank as usize * 0x1000;
+        out[0xC000..0xD000].copy_from_slice(&self.wram[..0x1000]);
+        out[0xD000..0xE000].copy_from_slice(&self.wram[wram..wram + 0x1000]);
+        out.copy_within(0xC000..0xDE00, 0xE000);
+        out[0xFE00..0xFEA0].copy_from_slice(&self.oam);
+        out[0xFEA0..0xFF00].fill(0xFF);
+        out[0xFF00..0xFF80].copy_from_slice(&self.io);
+        out[0xFF80..0xFFFF].copy_from_slice(&self.hram);
+        out[0xFFFF] = self.ie;
+        // Registers whose reads are computed
+        out[0xFF04] = match self.profile {
+            Profile::Compatible | Profile::Accurate => self.read_byte(0xFF04),
+            // Don't draw from the noise: inspecting memory mustn't change what the game sees
+            Profile::Tetris => (self.noise.get() >> 32) as u8,
+        };
+        if self.cgb {
+            for addr in [0xFF4D, 0xFF4F, 0xFF55, 0xFF69, 0xFF6B, 0xFF70] {
+                out[addr as usize] = self.read_byte(addr);
+            }
+        }
+    }
+
+    /// Offset in `wram` of an address in 0xC000–0xFDFF (echo RAM included).
+    fn wram_offset(&self, addr: u16) -> usize {
+        let addr = if addr >= 0xE000 { addr - 0x2000 } else { addr };
+        match addr {
+            0xC000..=0xCFFF => (addr - 0xC000) as usize,
+            _ => self.wram_bank as usize * 0x1000 + (addr - 0xD000) as usize,
+        }
+    }
+
+    /// Selects the accuracy profile (see `Profile`).
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = profile;
+        if profile != Profile::Accurate {
+            self.run_oam_dma(OAM_DMA_CYCLES);
+        }
+    }
+
+    /// Reseeds the DIV noise of the Tetris profile, making it reproducible.
+    pub fn seed(&mut self, seed: u64) {
+        // Any nonzero state works for xorshift; spread small seeds over the word
+        self.noise.set(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+    }
+
+    /// Attaches or detaches the link cable.
+    pub fn set_linked(&mut self, linked: bool) {
+        self.linked = linked;
+        if !linked {
+            self.serial_clock = 0;
+            self.serial_done = false;
+        }
+    }
+
+    /// Returns `true` (once) when an internal-clock transfer has clocked out its byte and
+    /// waits for the partner's.
+    pub fn take_serial_transfer(&mut self) -> bool {
+        std::mem::take(&mut self.serial_done)
+    }
+
+    /// `true` when the game waits for a byte on the external clock (SC = 0x80).
+    pub fn serial_waiting(&self) -> bool {
+        self.io[0x02] & 0x81 == 0x80
+    }
+
+    /// The byte waiting to be shifted out (SB).
+    pub fn serial_out(&self) -> u8 {
+        self.io[0x01]
+    }
+
+    /// Ends a transfer on this side: `received` is shifted into SB, SC bit 7 clears and
+    /// the serial interrupt is requested. Returns the byte that was shifted out.
+    pub fn finish_serial(&mut self, received: u8) -> u8 {
+        let sent = self.io[0x01];
+        self.io[0x01] = received;
+        self.io[0x02] &= 0x7F;
+        self.io[0x0F] |= 0x08;
+        sent
+    }
+
+    /// Loads the I/O registers the boot ROM leaves behind (LCD on, BG palette set), which
+    /// games other than Tetris rely on.
+    pub fn post_boot(&mut self) {
+        self.io[0x40] = 0x91; // LCDC
+        self.io[0x41] = 0x85; // STAT
+        self.io[0x47] = 0xFC; // BGP
+        self.io[0x48] = 0xFF; // OBP0
+        self.io[0x49] = 0xFF; // OBP1
+    }
+
+    /// Advances a linked serial transfer, and the divider and TIMA (Compatible profile
+    /// only), by `tcycles` CPU cycles. TIMA counts falling edges of a divider bit picked by
+    /// TAC; on overflow it's reloaded from TMA and raises the timer interrupt.
+    pub fn tick(&mut self, tcycles: u32) {
+        if self.mbc.has_rumble() {
+            self.rumble_cycles[0] += tcycles as u64 * self.mbc.motor() as u64;
+            self.rumble_cycles[1] += tcycles as u64;
+        }
+        if self.oam_dma.is_some() {
+            self.run_oam_dma(tcycles);
+        }
+        if self.serial_clock > 0 {
+            self.serial_clock = self.serial_clock.saturating_sub(tcycles);
+            self.serial_done = self.serial_clock == 0;
+        }
+        if self.profile == Profile::Tetris {
+            return;
+        }
+        let old = self.div as u32;
+        self.div = self.div.wrapping_add(tcycles as u16);
+
+        let tac = self.io[0x07];
+        if tac & 0x04 == 0 {
+            return;
+        }
+        let period = match tac & 0x03 {
+            0 => 1024,
+            1 => 16,
+            2 => 64,
+            _ => 256,
+        };
+        // Periods divide 0x10000, so counting across the wrap-around still works
+        for _ in 0..((old + tcycles) / period - old / period) {
+            if self.io[0x05] == 0xFF {
+                self.io[0x05] = self.io[0x06]; // TIMA = TMA
+                self.io[0x0F] |= 0x04;
+            } else {
+                self.io[0x05] += 1;
+            }
+        }
+    }
+
+    /// T-cycles until the timer next overflows and requests its interrupt; `None` while
+    /// it's stopped.
+    pub fn timer_overflow_cycles(&self) -> Option<u32> {
+        let tac = self.io[0x07];
+        if self.profile == Profile::Tetris || tac & 0x04 == 0 {
+            return None;
+        }
+        let period = match tac & 0x03 {
+            0 => 1024,
+            1 => 16,
+            2 => 64,
+            _ => 256,
+        };
+        let increments = 0x100 - self.io[0x05] as u32;
+        Some(increments * period - self.div as u32 % period)
+    }
+
+    /// Advances the OAM DMA in progress by `tcycles`, copying a byte per M-cycle.
+    fn run_oam_dma(&mut self, tcycles: u32) {
+        let Some((source, run)) = self.oam_dma else { return };
+        let end = (run + tcycles).min(OAM_DMA_CYCLES);
+        for i in run / 4..end / 4 {
+            self.oam[i as usize] = self.read_byte(source + i as u16);
+        }
+        self.oam_dma = (end < OAM_DMA_CYCLES).then_some((source, end));
+    }
+
+    /// `true` if an OAM DMA in progress keeps the CPU off `addr`: only `FF00`–`FFFF`
+    /// stays reachable.
+    fn dma_blocks(&self, addr: u16) -> bool {
+        self.oam_dma.is_some() && addr < 0xFF00
+    }
+
+    /// Updates the read-only STAT bits: mode (0–3) and LY == LYC coincidence.
+    pub fn set_stat(&mut self, mode: u8, coincidence: bool) {
+        self.io[0x41] = (self.io[0x41] & 0xF8) | mode | (coincidence as u8) << 2;
+    }
+
+    /// `true` when the CGB registers and banking are enabled.
+    pub fn cgb(&self) -> bool {
+        self.cgb
+    }
+
+    /// `true` while the link cable is attached.
+    pub fn linked(&self) -> bool {
+        self.linked
+    }
+
+    /// `true` while the CPU runs in CGB double-speed mode.
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    /// Performs the CGB speed switch if it was armed through KEY1; called by STOP.
+    /// Returns `true` if the speed changed.
+    pub fn speed_switch(&mut self) -> bool {
+        if !(self.cgb && self.speed_armed) {
+            return false;
+        }
+        self.speed_armed = false;
+        self.double_speed = !self.double_speed;
+        true
+    }
+
+    /// One VRAM bank, regardless of VBK, indexed from 0x8000 (for the PPU, which reads it
+    /// as a plain array instead of through `read_byte`).
+    pub fn vram_bank(&self, bank: usize) -> &[u8; 0x2000] {
+        self.vram[bank * 0x2000..][..0x2000].try_into().unwrap()
+    }
+
+    /// Sprite attribute table, indexed from 0xFE00 (for the PPU).
+    pub fn oam(&self) -> &[u8; 0xA0] {
+        &self.oam
+    }
+
+    /// RGB888 color `color` (0–3) of CGB palette `palette` (0–7), BG or OBJ.
+    pub fn cgb_color(&self, obj: bool, palette: u8, color: u8) -> [u8; 3] {
+        let ram = if obj { &self.obj_palettes } else { &self.bg_palettes };
+        let i = (palette as usize & 7) * 8 + color as usize * 2;
+        let rgb555 = u16::from_le_bytes([ram[i], ram[i + 1]]);
+        let scale = |c: u16| ((c << 3) | (c >> 2)) as u8;
+        [scale(rgb555 & 0x1F), scale((rgb555 >> 5) & 0x1F), scale((rgb555 >> 10) & 0x1F)]
+    }
+
+    /// HDMA5 write: starts a general-purpose VRAM DMA (copied at once; the CPU stall
+    /// is not modeled), starts an HBlank DMA (bit 7), or cancels the active HBlank DMA.
+    fn start_vram_dma(&mut self, value: u8) {
+        if self.hdma_blocks > 0 && value & 0x80 == 0 {
+            self.hdma_blocks = 0;
+            return;
+        }
+        self.hdma_src = u16::from_be_bytes([self.io[0x51], self.io[0x52]]) & 0xFFF0;
+        self.hdma_dst = 0x8000 | (u16::from_be_bytes([self.io[0x53], self.io[0x54]]) & 0x1FF0);
+        let blocks = (value & 0x7F) + 1;
+        let dma = if value & 0x80 != 0 { Dma::HBlank } else { Dma::General };
+        self.dma_started = Some((dma, self.hdma_src, self.hdma_dst, blocks as u16 * 16));
+        if value & 0x80 != 0 {
+            self.hdma_blocks = blocks;
+        } else {
+            for _ in 0..blocks {
+                self.copy_vram_block();
+            }
+        }
+    }
+
+    /// Copies the next 16 bytes of an HBlank DMA; the PPU calls this as each visible
+    /// line enters HBlank.
+    pub fn hblank_dma(&mut self) {
+        if self.hdma_blocks > 0 {
+            self.copy_vram_block();
+            self.hdma_blocks -= 1;
+        }
+    }
+
+    fn copy_vram_block(&mut self) {
+        for _ in 0..16 {
+            let b = self.read_byte(self.hdma_src);
+            let dst = self.vram_bank as usize * 0x2000 + (self.hdma_dst & 0x1FFF) as usize;
+            self.vram[dst] = b;
+            self.hdma_src = self.hdma_src.wrapping_add(1);
+            self.hdma_dst = 0x8000 | (self.hdma_dst.wrapping_add(1) & 0x1FFF);
+        }
+    }
+
+    /// Starts recording ROM coverage from `map` (see `GB::track_coverage`).
+    pub fn track_coverage(&mut self, map: &[u8]) {
+        let flags = if map.len() == self.rom.len() { map.to_vec() } else { vec![0; self.rom.len()] };
+        self.coverage = Some(flags.into_iter().map(Cell::new).collect());
+    }
+
+    pub fn coverage(&self) -> Option<Vec<u8>> {
+        self.coverage.as_ref().map(|flags| flags.iter().map(Cell::get).collect())
+    }
+
+    /// Marks the ROM byte the CPU reached through `addr` with `flag`, while tracking coverage.
+    fn cover(&self, addr: u16, flag: u8) {
+        let Some(flags) = &self.coverage else { return };
+        let boot = self.boot_mapped && (addr < 0x100 || (0x200..0x900).contains(&addr)) && (addr as usize) < self.boot_rom.len();
+        if addr < 0x8000 && !boot {
+            let cell = &flags[self.mbc.rom_offset(addr)];
+            cell.set(cell.get() | flag);
+        }
+    }
+
+    /// Starts or stops checking the stack (see `GB::check_stack`).
+    pub fn check_stack(&mut self, on: bool) {
+        if on != self.stack_check.is_some() {
+            self.stack_check = on.then(|| StackCheck { pc: 0, sp: 0, variables: Cell::new(0), fault: Cell::new(None) });
+        }
+    }
+
+    /// The last DMA started since the last call: kind, source, destination and length.
+    pub fn take_dma_started(&mut self) -> Option<(Dma, u16, u16, u16)> {
+        self.dma_started.take()
+    }
+
+    pub fn checking_stack(&self) -> bool {
+        self.stack_check.is_some()
+    }
+
+    /// Where the instruction about to run is and where SP is, for the stack check.
+    pub fn stack_position(&mut self, pc: u16, sp: u16) {
+        if let Some(check) = &mut self.stack_check {
+            (check.pc, check.sp) = (pc, sp);
+        }
+    }
+
+    pub fn take_stack_fault(&mut self) -> Option<StackFault> {
+        self.stack_check.as_ref().and_then(|check| check.fault.take())
+    }
+
+    /// Starts logging writes to `start..=end` (see `take_watched_writes`).
+    pub fn watch_writes(&mut self, start: u16, end: u16) {
+        self.watches.push((start, end));
+    }
+
+    /// Returns the watched writes logged since the last call, oldest first.
+    pub fn take_watched_writes(&mut self) -> Vec<(u16, u8)> {
+        std::mem::take(&mut self.watch_log)
+    }
+
+    /// Traces the writes to `registers` from now on instead (see `take_io_writes`); none
+    /// stops tracing.
+    pub fn trace_io(&mut self, registers: &[u16]) {
+        self.traced_io = registers.to_vec();
+        self.io_writes.clear();
+    }
+
+    pub fn traced_io(&self) -> &[u16] {
+        &self.traced_io
+    }
+
+    /// Returns the traced writes since the last call, oldest first: register and value.
+    pub fn take_io_writes(&mut self) -> Vec<(u16, u8)> {
+        std::mem::take(&mut self.io_writes)
+    }
+
+    pub fn has_rumble(&self) -> bool {
+        self.mbc.has_rumble()
+    }
+
+    /// Share of the T-cycles since the last call the rumble motor was on (0 without one).
+    pub fn take_rumble(&mut self) -> f32 {
+        let [on, all] = std::mem::take(&mut self.rumble_cycles);
+        if all == 0 { 0.0 } else { on as f32 / all as f32 }
+    }
+
+    /// Returns the bytes sent over the serial port since the last call.
+    pub fn take_serial(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.serial)
+    }
+
+    /// The bytes sent over the serial port since the last `take_serial`.
+    pub fn serial_log(&self) -> &[u8] {
+        &self.serial
+    }
+
+    /// Pending interrupt requests (`IF`, bits 0–4).
+    pub fn interrupt_flags(&self) -> u8 {
+        self.io[0x0F] & 0x1F
+    }
+
+    /// External (cartridge) RAM contents, for battery saves (all banks).
+    pub fn eram(&self) -> &[u8] {
+        &self.eram
+    }
+
+    /// Restores external RAM from a battery save; extra bytes are ignored.
+    pub fn load_eram(&mut self, data: &[u8]) {
+        let n = data.len().min(self.eram.len());
+        self.eram[..n].copy_from_slice(&data[..n]);
+    }
+
+    /// Composes P1 (0xFF00) from the select lines and the buttons held. Games read it in
+    /// tight loops, so it's kept in `io` and only recomposed when either changes.
+    fn compose_p1(&mut self) {
+        let p1 = self.io[0x00];
+        let sel_buttons = (p1 & 0b0010_0000) == 0; // P15=0
+        let sel_dpad    = (p1 & 0b0001_0000) == 0; // P14=0
+
+        let mut low = 0b0000_1111; // default: no buttons pressed
+
+        match (sel_buttons, sel_dpad) {
+            (true, false) => {
+                // Buttons only (A B Select Start) -> bits 0..3
+                if (self.buttons & BTN_A)      != 0 { low &= !0b0001; }
+                if (self.buttons & BTN_B)      != 0 { low &= !0b0010; }
+                if (self.buttons & BTN_SELECT) != 0 { low &= !0b0100; }
+                if (self.buttons & BTN_START)  != 0 { low &= !0b1000; }
+            }
+            (false, true) => {
+                // D-pad only (Right Left Up Down) -> bits 0..3
+                if (self.buttons & BTN_RIGHT) != 0 { low &= !0b0001; } // bit0 = Right
+                if (self.buttons & BTN_LEFT)  != 0 { low &= !0b0010; } // bit1 = Left
+                if (self.buttons & BTN_UP)    != 0 { low &= !0b0100; } // bit2 = Up
+                if (self.buttons & BTN_DOWN)  != 0 { low &= !0b1000; } // bit3 = Down
+            }
+            _ => {
+                // Neither or both groups selected: don't mix nibbles.
+                // Keep low at 0x0F (no press)
+            }
+        }
+
+        self.io[0x00] = (p1 & 0b0011_0000) | 0b1100_0000 | low;
+    }
+
+    /// Returns `true` (once) if the game has selected a joypad line since the last call,
+    /// i.e. it's about to read the buttons.
+    pub fn take_joypad_poll(&mut self) -> bool {
+        std::mem::take(&mut self.joypad_poll)
+    }
+
+    pub fn buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub fn input_press(&mut self, mask: u8) {
+        // Anti-ghosting for opposite directions
+        let mut new = self.buttons | mask;
+        if (new & BTN_RIGHT) != 0 { new &= !BTN_LEFT; }
+        if (new & BTN_LEFT)  != 0 { new &= !BTN_RIGHT; }
+        if (new & BTN_UP)    != 0 { new &= !BTN_DOWN; }
+        if (new & BTN_DOWN)  != 0 { new &= !BTN_UP; }
+
+        // A fresh press pulls a P1 line low: joypad interrupt
+        if self.profile != Profile::Tetris && new & !self.buttons != 0 {
+            self.io[0x0F] |= 0x10;
+        }
+        self.buttons = new;
+        self.compose_p1();
+    }
+
+    pub fn input_release(&mut self, mask: u8) {
+        self.buttons &= !mask;
+        self.compose_p1();
+    }
+}
+
+/// Stack checking state (see `GB::check_stack`).
+struct StackCheck {
+    pc: u16,                         // The instruction running
+    sp: u16,                         // SP as it started
+    variables: Cell<u128>,           // High RAM bytes (bit n for FF80 + n) read as data from outside the stack
+    fault: Cell<Option<StackFault>>, // The first since `take_stack_fault`
+}
+
+impl StackCheck {
+    fn report(&self, kind: StackFaultKind, addr: u16) {
+        if self.fault.get().is_none() {
+            self.fault.set(Some(StackFault { kind, addr, pc: self.pc }));
+        }
+    }
+
+    /// A data read from high RAM. At or above SP may be the stack's; below it, or with the
+    /// stack elsewhere, it's a variable.
+    fn read(&self, addr: u16) {
+        if !(is_hram(self.sp) && addr >= self.sp) {
+            self.variables.set(self.variables.get() | 1 << (addr - 0xFF80));
+        }
+    }
+
+    /// A push reports a collision with a variable once; the byte is the stack's from then on.
+    fn push(&self, addr: u16) {
+        if !is_stack_memory(addr) {
+            self.report(StackFaultKind::Overflow, addr);
+        } else if is_hram(addr) && self.variables.get() & 1 << (addr - 0xFF80) != 0 {
+            self.report(StackFaultKind::Collision, addr);
+            self.variables.set(self.variables.get() & !(1 << (addr - 0xFF80)));
+        }
+    }
+
+    fn pop(&self, addr: u16) {
+        if !is_stack_memory(addr) {
+            self.report(StackFaultKind::Underflow, addr);
+        }
+    }
+}
+
+fn is_hram(addr: u16) -> bool {
+    (0xFF80..=0xFFFE).contains(&addr)
+}
+
+/// Where a stack belongs: work RAM or high RAM.
+fn is_stack_memory(addr: u16) -> bool {
+    (0xC000..=0xDFFF).contains(&addr) || is_hram(addr)
+}
+
+/// The CPU's view of the bus, which an OAM DMA in progress restricts (see `dma_blocks`).
+impl Bus for MMU {
+    fn read_byte(&self, addr: u16) -> u8 {
+        if self.dma_blocks(addr) {
+            return 0xFF;
+        }
+        self.cover(addr, COVERAGE_DATA);
+        if is_hram(addr) && let Some(check) = &self.stack_check {
+            check.read(addr);
+        }
+        MMU::read_byte(self, addr)
+    }
+
+    fn fetch(&self, addr: u16) -> u8 {
+        if self.dma_blocks(addr) {
+            return 0xFF;
+        }
+        self.cover(addr, COVERAGE_CODE);
+        MMU::read_byte(self, addr)
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        if !self.dma_blocks(addr) {
+            self.cover(addr, COVERAGE_WRITTEN);
+            MMU::write_byte(self, addr, value)
+        }
+    }
+
+    fn push_byte(&mut self, addr: u16, value: u8) {
+        if let Some(check) = &self.stack_check {
+            check.push(addr);
+        }
+        Bus::write_byte(self, addr, value)
+    }
+
+    fn pop_byte(&self, addr: u16) -> u8 {
+        if let Some(check) = &self.stack_check {
+            check.pop(addr);
+        }
+        if self.dma_blocks(addr) {
+            return 0xFF;
+        }
+        self.cover(addr, COVERAGE_DATA);
+        MMU::read_byte(self, addr)
+    }
+
+    fn stop(&mut self) -> bool {
+        self.speed_switch()
+    }
+}
+
+/// Steps the xorshift64 generator behind the Tetris profile's DIV noise and random RAM.
+fn next_noise(state: &Cell<u64>) -> u64 {
+    let mut x = state.get();
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    state.set(x);
+    x
+}
+
+/// BCPD/OCPD write: stores `value` at the index in the BCPS/OCPS register `spec` and
+/// advances the index if its auto-increment bit (7) is set.
+fn write_palette(ram: &mut [u8; 64], spec: &mut u8, value: u8) {
+    ram[(*spec & 0x3F) as usize] = value;
+    if *spec & 0x80 != 0 {
+        *spec = 0x80 | (spec.wrapping_add(1) & 0x3F);
+    }
+}
@@ -0,0 +1,892 @@
+use crate::events::Interrupt;
+use crate::gb::Profile;
+use crate::mmu::MMU;
+use crate::savestate::{StateReader, StateWriter};
+
+/// T-cycles in one frame (154 lines × 456 dots), also kept while the LCD is off.
+const FRAME_DOTS: u32 = 154 * 456;
+
+/// Each bit of a bitplane byte moved to the low bit of its pixel's 2-bit lane, leftmost
+/// pixel (bit 7) in lane 0: a tile row's color IDs are `SPREAD[low] | SPREAD[high] << 1`.
+const SPREAD: [u16; 256] = spread_table();
+
+const fn spread_table() -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut px = 0;
+        while px < 8 {
+            if byte & (0x80 >> px) != 0 {
+                table[byte] |= 1 << (px * 2);
+            }
+            px += 1;
+        }
+        byte += 1;
+    }
+    table
+}
+
+/// The eight 2-bit color IDs of a tile row from its two bitplanes, pixel `px` (0 =
+/// leftmost) in bits `2*px..2*px+2`.
+fn decode_row(low: u8, high: u8) -> u16 {
+    SPREAD[low as usize] | SPREAD[high as usize] << 1
+}
+
+/// LCD size in pixels.
+pub const SCREEN_WIDTH:  u8 = 160;
+pub const SCREEN_HEIGHT: u8 = 144;
+
+/// PPU: DMG scanline-based renderer with a simple timing model.
+/// - Tracks LY (current scanline), the dot counter within the line, and the LCD mode.
+/// - Produces an RGB framebuffer (WIDTH*HEIGHT*3).
+/// - Triggers VBlank IRQ and optional STAT IRQs according to mode/LYC.
+/// - The Compatible profile adds STAT mode/LYC updates and interrupts, LCD off, the
+///   window layer, 8×16 sprites and sprite priority (see `Profile`); the Accurate profile
+///   also stretches pixel transfer as the hardware does (see `transfer_end`).
+/// - In CGB mode, uses the BG map attributes (VRAM bank 1), both tile banks and the
+///   color palette RAM, and applies BG/OBJ priority; colors bypass the host `Palette`.
+/// - DMG sprites whose tile has a color (see `set_sprite_color`) are drawn in shades of
+///   that color instead of the palette's, after the OBJ palette register is applied.
+/// - With sprite outlines on (see `set_sprite_outlines`), the pixels around the sprites
+///   drawn are darkened once the frame is finished.
+pub struct PPU {
+    ly: u8,              // Current scanline (0..153)
+    mode: PPUMode,       // Current LCD mode (0, 1, 2, 3)
+    dot: u16,            // T-cycles elapsed within the current line (0..455)
+                         // Increments every T-cycle and wraps at 456 (T-cycles needed per scanline)
+    frame_ready: bool,
+    palette: Palette,
+    bg_line: [u8; SCREEN_WIDTH as usize], // BG color index per pixel, bit 7 = CGB BG priority
+    profile: Profile,
+    window_line: u8,     // Window rows drawn so far this frame
+    lcd_on: bool,        // Compatible profile: LCDC bit 7 as last seen
+    off_dots: u32,       // Dots since the frame started while the LCD is off
+    hblank_dot: u16,     // Dot at which this line's pixel transfer ends (see `transfer_end`)
+    sprite_colors: [Option<[u8; 3]>; 256], // Host color per sprite tile index (DMG only)
+    sprite_mask: Option<Vec<bool>>, // Sprite outlines on: which pixels sprites drew this frame
+    timing: Option<Timing>, // While recording frame timing
+}
+
+/// How the PPU spent a frame, LY 0 to 153, for timing diagrams (see `GB::record_timing`).
+/// Lines 144–153 are VBlank (mode 1) throughout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// The visible lines drawn: LY and the dot at which pixel transfer ended and HBlank
+    /// began. Each spends dots 0–79 in mode 2 and from 80 to there in mode 3.
+    pub lines: Vec<(u8, u16)>,
+    /// The STAT and VBlank interrupts the PPU requested: LY, dot and which.
+    pub interrupts: Vec<(u8, u16, Interrupt)>,
+}
+
+/// The frame being timed and the last one finished.
+#[derive(Default)]
+struct Timing {
+    current: FrameTiming,
+    last: Option<FrameTiming>,
+}
+
+/// RGB colors used for the four DMG shades.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 4], // 4 shades; each is [R,G,B]
+}
+
+/// Classic DMG greenish palette (light to dark).
+pub const GREEN_PALETTE: Palette = Palette {
+    colors: [
+        [224, 248, 208], // #E0F8D0
+        [136, 192, 112], // #88C070
+        [52,  104,  86], // #346856
+        [8,    24,  32], // #081820
+    ],
+};
+
+/// Example color palette (light to dark).
+pub const COLOR_PALETTE: Palette = Palette {
+    colors: [
+        [255, 255, 255], // #FFFFFF
+        [255, 255,   0], // #FFFF00
+        [255,   0,   0], // #FF0000
+        [  0,   0,   0], // #000000
+    ],
+};
+
+/// Palette for red-green color blindness (light to dark): the middle shades are the orange
+/// and blue of the Okabe–Ito set, told apart by hue and by brightness.
+pub const COLORBLIND_PALETTE: Palette = Palette {
+    colors: [
+        [255, 255, 255], // #FFFFFF
+        [230, 159,   0], // #E69F00
+        [  0, 114, 178], // #0072B2
+        [  0,   0,   0], // #000000
+    ],
+};
+
+/// Evenly spaced grays from white to black (light to dark), for the most contrast between
+/// neighboring shades.
+pub const HIGH_CONTRAST_PALETTE: Palette = Palette {
+    colors: [
+        [255, 255, 255], // #FFFFFF
+        [170, 170, 170], // #AAAAAA
+        [ 85,  85,  85], // #555555
+        [  0,   0,   0], // #000000
+    ],
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PPUMode {
+    HBlank = 0,  // ~204 T-cycles (end of scanline; CPU can access OAM/VRAM)
+    VBlank = 1,  // 10 lines × 456 T-cycles (lines 144..153)
+    Oam    = 2,  // 80 T-cycles (OAM search)
+    Vram   = 3,  // 172 T-cycles (pixel transfer)
+}
+
+impl Default for PPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PPU {
+    /// Creates a PPU with LY=0, OAM mode, and the default (color) palette.
+    pub fn new() -> Self {
+        Self {
+            ly: 0,
+            mode: PPUMode::Oam,
+            dot: 0,
+            frame_ready: false,
+            palette: COLOR_PALETTE,
+            bg_line: [0; SCREEN_WIDTH as usize],
+            profile: Profile::Compatible,
+            window_line: 0,
+            lcd_on: true,
+            off_dots: 0,
+            hblank_dot: 252,
+            sprite_colors: [None; 256],
+            sprite_mask: None,
+            timing: None,
+        }
+    }
+
+    /// Advances the PPU state by a given number of T-cycles.
+    ///
+    /// ## Behavior:
+    /// - Increments the `dot` counter (0–455), wrapping every **456 dots** to start a new scanline.
+    /// - Updates `LY` via [`next_line`](Self::next_line) when a scanline completes.
+    /// - Determines the current PPU mode:
+    ///   - **OAM** (Mode 2): Dots 0–79
+    ///   - **VRAM** (Mode 3): Dots 80–251 (longer in the Accurate profile, see `transfer_end`)
+    ///   - **HBlank** (Mode 0): Dots 252–455
+    ///   - **VBlank** (Mode 1): All dots during `LY >= 144`
+    /// - On entering **HBlank** for a visible scanline (`LY < 144`), renders:
+    ///   - The background scanline.
+    ///   - The sprites on that scanline.
+    /// - Compatible profile: mirrors mode and LYC coincidence into STAT and raises the
+    ///   STAT interrupts it enables. While the LCD is off (`LCDC` bit 7), LY stays 0, the
+    ///   screen is blank and frames are still completed every 70224 dots so front-ends
+    ///   keep their pace.
+    ///
+    /// ## Timing Notes:
+    /// - 456 dots per scanline.
+    /// - 154 total scanlines (0–143 visible, 144–153 VBlank).
+    /// - Nothing happens between mode boundaries, so the dot counter jumps from one
+    ///   boundary to the next (see [`next_event`](Self::next_event)) instead of counting
+    ///   every T-cycle; each boundary is handled on the dot it falls on, as before.
+    ///
+    /// ## Parameters:
+    /// - `mmu`: Memory interface for register and VRAM access.
+    /// - `tcycles`: Number of T-cycles to simulate.
+    /// - `framebuffer`: Target buffer for pixel output.
+    /// - `pitch`: Bytes per row in the framebuffer.
+    pub fn step(&mut self, mmu: &mut MMU, tcycles: u32, framebuffer: &mut [u8], pitch: usize) {
+        let compatible = self.profile != Profile::Tetris;
+        if compatible && !self.lcd_enabled(mmu) {
+            self.step_lcd_off(mmu, tcycles, framebuffer, pitch);
+            return;
+        }
+
+        let mut remaining = tcycles;
+        loop {
+            // Jump to the next boundary, or as far as the budget goes
+            let event = self.next_event();
+            let distance = (event - self.dot) as u32;
+            if distance > remaining {
+                self.dot += remaining as u16;
+                return;
+            }
+            remaining -= distance;
+            self.dot = event;
+
+            let prev_mode = self.mode;
+            let prev_ly = self.ly;
+
+            // End of scanline: wrap dot counter and advance LY
+            if self.dot == 456 {
+                self.dot = 0;
+                self.next_line(mmu); // Handles VBlank entry and LY wrapping
+                if self.ly == 144 {
+                    self.draw_outlines(mmu, framebuffer, pitch);
+                }
+            }
+
+            if self.dot == 80 && self.ly < 144 {
+                self.hblank_dot = self.transfer_end(mmu);
+                if let Some(timing) = &mut self.timing {
+                    timing.current.lines.push((self.ly, self.hblank_dot));
+                }
+            }
+
+            // Determine PPU mode based on LY and dot position
+            self.mode = if self.ly >= 144 {
+                PPUMode::VBlank // All lines after 143 are VBlank
+            } else if self.dot < 80 {
+                PPUMode::Oam // Mode 2: OAM scan (sprite attribute fetch)
+            } else if self.dot < self.hblank_dot {
+                PPUMode::Vram // Mode 3: Pixel transfer (rendering)
+            } else {
+                PPUMode::HBlank // Mode 0: Horizontal blanking
+            };
+            if compatible && (self.mode != prev_mode || self.ly != prev_ly) {
+                self.update_stat(mmu, self.mode != prev_mode, self.ly != prev_ly);
+            }
+
+            // When entering HBlank on a visible scanline, render the line
+            if self.mode == PPUMode::HBlank && self.dot == self.hblank_dot && self.ly < 144 {
+                // Render background pixels for this scanline
+                self.render_bg_line(mmu, framebuffer, pitch);
+
+                // Render sprites for this scanline
+                self.render_sprites_line(mmu, framebuffer, pitch);
+
+                // CGB HBlank DMA moves one 16-byte block per line
+                if mmu.cgb() {
+                    mmu.hblank_dma();
+                }
+            }
+        }
+    }
+
+    /// Dots the PPU can be left behind without missing anything: until its next mode or
+    /// line change (or, LCD off, the next blank frame). While fewer dots than this are
+    /// owed, `skip` stands in for `step`, unless the LCD has just been switched on or off
+    /// (see `lcd_switched`).
+    pub fn quiet_dots(&self) -> u32 {
+        if self.profile != Profile::Tetris && !self.lcd_on {
+            FRAME_DOTS - self.off_dots
+        } else {
+            (self.next_event() - self.dot) as u32
+        }
+    }
+
+    /// Advances by fewer than `quiet_dots` dots, where only the dot counter moves.
+    pub fn skip(&mut self, dots: u32) {
+        if self.profile != Profile::Tetris && !self.lcd_on {
+            self.off_dots += dots;
+        } else {
+            self.dot += dots as u16;
+        }
+    }
+
+    /// LY and the dot within the line, counting the `behind` dots still owed (fewer than
+    /// `quiet_dots`). While the LCD is off, where it stopped.
+    pub fn position(&self, behind: u32) -> (u8, u16) {
+        if self.profile != Profile::Tetris && !self.lcd_on {
+            (self.ly, self.dot)
+        } else {
+            (self.ly, self.dot + behind as u16)
+        }
+    }
+
+    /// Dots since the frame started (LY 144, as VBlank begins), counting the `behind` dots
+    /// still owed. While the LCD is off, since the last blank frame.
+    pub fn frame_dots(&self, behind: u32) -> u32 {
+        if self.profile != Profile::Tetris && !self.lcd_on {
+            self.off_dots + behind
+        } else {
+            (self.ly as u32 + 10) % 154 * 456 + self.dot as u32 + behind
+        }
+    }
+
+    /// The current mode (0–3). Dots still owed don't change it: they stop short of the
+    /// next mode change (see `quiet_dots`).
+    pub fn mode(&self) -> u8 {
+        self.mode as u8
+    }
+
+    /// Starts or stops recording how each frame's time is spent (see `FrameTiming`).
+    pub fn record_timing(&mut self, on: bool) {
+        if on != self.timing.is_some() {
+            self.timing = on.then(Timing::default);
+        }
+    }
+
+    /// The last frame finished since recording started, if one has been.
+    pub fn frame_timing(&self) -> Option<&FrameTiming> {
+        self.timing.as_ref().and_then(|timing| timing.last.as_ref())
+    }
+
+    /// Compatible profile: whether LCDC bit 7 changed since the PPU last looked, which
+    /// `step` has to see right away.
+    pub fn lcd_switched(&self, mmu: &MMU) -> bool {
+        self.profile != Profile::Tetris && (mmu.read_byte(0xFF40) & 0x80 != 0) != self.lcd_on
+    }
+
+    /// The next dot of the line at which the mode or line changes: the start of pixel
+    /// transfer (80), of HBlank (252, or `hblank_dot`) or of the next line (456, reached
+    /// before wrapping).
+    fn next_event(&self) -> u16 {
+        match self.dot {
+            _ if self.ly >= 144 => 456,
+            0..80 => 80,
+            dot if dot < self.hblank_dot => self.hblank_dot,
+            _ => 456,
+        }
+    }
+
+    /// The dot at which the current line's pixel transfer ends, worked out as it starts:
+    /// 252, or in the Accurate profile later by the dots the hardware spends discarding
+    /// the fine scroll (`SCX % 8`), restarting the fetch for the window (6) and fetching
+    /// each sprite on the line (6, for at most 10 sprites).
+    fn transfer_end(&self, mmu: &MMU) -> u16 {
+        if self.profile != Profile::Accurate {
+            return 252;
+        }
+        let lcdc = mmu.read_byte(0xFF40);
+        let (wy, wx) = (mmu.read_byte(0xFF4A), mmu.read_byte(0xFF4B));
+        let window = lcdc & 0x20 != 0 && self.ly >= wy && wx <= 166;
+        let height = if lcdc & 0x04 != 0 { 16 } else { 8 };
+        let sprites = match lcdc & 0x02 {
+            0 => 0,
+            _ => mmu.oam().chunks_exact(4).filter(|entry| (self.ly as u16 + 16).wrapping_sub(entry[0] as u16) < height).count().min(10),
+        };
+        252 + (mmu.read_byte(0xFF43) % 8) as u16 + if window { 6 } else { 0 } + 6 * sprites as u16
+    }
+
+    /// Advances the PPU to the next scanline, handling VBlank entry and LY wrapping.
+    ///
+    /// ## Behavior:
+    /// - Increments the `LY` (current scanline) register and writes it to `0xFF44`.
+    /// - When `LY` reaches **144**, the PPU enters **VBlank** mode:
+    ///   - Sets mode to `VBlank` (`PPUMode::VBlank`).
+    ///   - Sets the VBlank interrupt flag (`IF` bit 0).
+    ///   - Marks the current frame as ready to display (`frame_ready = true`).
+    /// - When `LY` exceeds **153**, it wraps back to **0** and the PPU enters OAM mode.
+    /// - For lines **0–143**, the PPU is in OAM mode, preparing to draw visible scanlines.
+    /// - For lines **145–153**, the PPU stays in VBlank mode.
+    ///
+    /// ## Timing Notes:
+    /// - `LY` increments once per scanline.
+    /// - `LY=144..153` are the vertical blanking period.
+    /// - `LY=0..143` are visible scanlines.
+    ///
+    /// ## Parameters:
+    /// - `mmu`: Memory interface, used to write `LY` and set interrupt flags.
+    fn next_line(&mut self, mmu: &mut MMU) {
+        self.dot = 0; // Reset cycle counter for the new scanline
+        self.ly = self.ly.wrapping_add(1); // Increment LY (wrap at 256, later adjusted)
+        mmu.write_byte(0xFF44, self.ly); // Write LY to the hardware register
+
+        if self.ly == 144 {
+            // Reached the first VBlank line
+            self.mode = PPUMode::VBlank; // Enter VBlank mode
+
+            // Raise VBlank interrupt (IF bit 0)
+            let iflag = mmu.read_byte(0xFF0F) | 0x01;
+            mmu.write_byte(0xFF0F, iflag);
+            if let Some(timing) = &mut self.timing {
+                timing.current.interrupts.push((self.ly, self.dot, Interrupt::VBlank));
+            }
+
+            // Signal that a full frame has been rendered
+            self.frame_ready = true;
+
+        } else if self.ly > 153 {
+            // End of VBlank period, wrap to first visible line
+            self.ly = 0;
+            mmu.write_byte(0xFF44, self.ly); // Update LY register
+            self.mode = PPUMode::Oam; // Start OAM search for the new frame
+            self.window_line = 0;
+            if let Some(timing) = &mut self.timing {
+                timing.last = Some(std::mem::take(&mut timing.current));
+            }
+
+        } else if self.ly < 144 {
+            // Still in visible scanlines
+            self.mode = PPUMode::Oam; // Begin OAM search for the new line
+
+        } else {
+            // Lines 145..153: middle of the VBlank period
+            self.mode = PPUMode::VBlank;
+        }
+    }
+
+    /// Compatible profile: tracks LCDC bit 7. Turning the LCD off resets LY and the
+    /// mode to 0; turning it back on restarts the frame at line 0.
+    fn lcd_enabled(&mut self, mmu: &mut MMU) -> bool {
+        let on = mmu.read_byte(0xFF40) & 0x80 != 0;
+        if on != self.lcd_on {
+            self.lcd_on = on;
+            self.ly = 0;
+            self.dot = 0;
+            self.off_dots = 0;
+            self.window_line = 0;
+            self.mode = if on { PPUMode::Oam } else { PPUMode::HBlank };
+            if let Some(timing) = &mut self.timing {
+                // The frame being drawn is cut short
+                timing.current = FrameTiming::default();
+            }
+            mmu.write_byte(0xFF44, 0);
+            self.update_stat(mmu, false, false);
+        }
+        on
+    }
+
+    /// LCD off: no rendering or interrupts; a blank frame is delivered every
+    /// `FRAME_DOTS` so the machine keeps producing frames.
+    fn step_lcd_off(&mut self, mmu: &MMU, tcycles: u32, fb: &mut [u8], pitch: usize) {
+        self.off_dots += tcycles;
+        if self.off_dots < FRAME_DOTS {
+            return;
+        }
+        self.off_dots -= FRAME_DOTS;
+        if let Some(mask) = &mut self.sprite_mask {
+            mask.fill(false);
+        }
+        let blank = if mmu.cgb() { [0xFF; 3] } else { self.palette.colors[0] };
+        for y in 0..SCREEN_HEIGHT as usize {
+            for x in 0..SCREEN_WIDTH as usize {
+                put_rgb(fb, pitch, x, y, blank);
+            }
+        }
+        self.frame_ready = true;
+    }
+
+    /// Compatible profile: writes mode and LY == LYC into STAT and raises the STAT
+    /// interrupt (IF bit 1) for a mode entry or coincidence that STAT enables.
+    fn update_stat(&mut self, mmu: &mut MMU, mode_changed: bool, line_changed: bool) {
+        let stat = mmu.read_byte(0xFF41);
+        let coincidence = self.ly == mmu.read_byte(0xFF45);
+        mmu.set_stat(self.mode as u8, coincidence);
+
+        let mode_irq = mode_changed && match self.mode {
+            PPUMode::HBlank => stat & 0x08 != 0,
+            PPUMode::VBlank => stat & 0x10 != 0,
+            PPUMode::Oam    => stat & 0x20 != 0,
+            PPUMode::Vram   => false,
+        };
+        let lyc_irq = line_changed && coincidence && stat & 0x40 != 0;
+        if mode_irq || lyc_irq {
+            let iflag = mmu.read_byte(0xFF0F) | 0x02;
+            mmu.write_byte(0xFF0F, iflag);
+            if let Some(timing) = &mut self.timing {
+                timing.current.interrupts.push((self.ly, self.dot, Interrupt::Stat));
+            }
+        }
+    }
+
+    /// Renders the current background scanline using LCDC and scroll registers.
+    ///
+    /// ## Requirements:
+    /// - LCD must be enabled (`LCDC` bit 7).
+    /// - Background rendering must be enabled (`LCDC` bit 0).
+    ///
+    /// ## Features & Limitations:
+    /// - Applies SCX (scroll X) and SCY (scroll Y) with wrapping to determine source pixels.
+    /// - Selects background tile map base at:
+    ///   - `0x9800` when `LCDC` bit 3 = 0.
+    ///   - `0x9C00` when `LCDC` bit 3 = 1.
+    /// - Selects tile data from:
+    ///   - `0x8000` (unsigned tile index) when `LCDC` bit 4 = 1.
+    ///   - `0x8800`/`0x9000` (signed tile index) when `LCDC` bit 4 = 0.
+    /// - Each pixel's 2-bit color index is mapped through the `BGP` register (`0xFF47`).
+    /// - CGB mode: each map entry's attribute byte (VRAM bank 1) selects the color palette,
+    ///   tile bank, flips and BG priority; `LCDC` bit 0 no longer hides the background.
+    /// - The window layer is drawn in the Compatible profile only.
+    ///
+    /// ## Rendering Process:
+    /// 1. Determine the source Y position using `LY` + `SCY` (with wrapping).
+    /// 2. Identify the tile row and row offset inside the tile.
+    /// 3. For each screen pixel (0..159), compute the source X position (`SCX` wrapping).
+    /// 4. Fetch the tile index from the background tile map.
+    /// 5. Compute the address of the tile graphics in VRAM.
+    /// 6. Read the corresponding bitplanes and decode the 2-bit color index (`decode_row`).
+    /// 7. Map the line's color indices via `BGP` and draw it (CGB: each pixel as it's decoded).
+    ///
+    /// ## Parameters:
+    /// - `mmu`: Memory interface for reading registers, tile maps, and tile data.
+    /// - `fb`: Framebuffer buffer (indexed colors).
+    /// - `pitch`: Bytes per row in the framebuffer.
+    fn render_bg_line(&mut self, mmu: &MMU, fb: &mut [u8], pitch: usize) {
+        let y = self.ly; // Current scanline (0..143)
+        if y >= 144 { return; } // Outside visible area
+        self.bg_line = [0; SCREEN_WIDTH as usize];
+
+        // Read LCDC control register
+        let lcdc = mmu.read_byte(0xFF40);
+        let cgb = mmu.cgb();
+        if (lcdc & 0x80) == 0 { return; } // LCD disabled
+        if (lcdc & 0x01) == 0 && !cgb {
+            // Background disabled (DMG); the Compatible profile blanks the line
+            if self.profile != Profile::Tetris {
+                for x in 0..SCREEN_WIDTH {
+                    put_px(fb, pitch, x as usize, y as usize, 0, self.palette);
+                }
+            }
+            return;
+        }
+
+        // Read scroll registers and background palette
+        let scx = mmu.read_byte(0xFF43); // Scroll X
+        let scy = mmu.read_byte(0xFF42); // Scroll Y
+        let bgp = mmu.read_byte(0xFF47); // Background palette
+
+        // Select background map base depending on LCDC bit 3
+        let bg_map_base = if (lcdc & 0x08) != 0 { 0x9C00 } else { 0x9800 };
+
+        // Window (Compatible profile): replaces the BG from WX-7 on lines from WY down,
+        // using its own map (LCDC bit 6) and line counter
+        let wy = mmu.read_byte(0xFF4A);
+        let wx = mmu.read_byte(0xFF4B);
+        let window = self.profile != Profile::Tetris && (lcdc & 0x20) != 0 && y >= wy && wx <= 166;
+        let win_start = wx as i16 - 7;
+        let win_map_base = if (lcdc & 0x40) != 0 { 0x9C00 } else { 0x9800 };
+
+        // Tile maps and tile data are read straight from VRAM, offsets from 0x8000
+        let vram = [mmu.vram_bank(0), mmu.vram_bank(1)];
+
+        // Loop over each screen pixel
+        for x in 0..SCREEN_WIDTH {
+            // Position in the BG map (wraps at 256) or in the window
+            let (map_base, src_x, src_y) = if window && x as i16 >= win_start {
+                (win_map_base, (x as i16 - win_start) as u8, self.window_line)
+            } else {
+                (bg_map_base, x.wrapping_add(scx), y.wrapping_add(scy))
+            };
+            let map_addr = map_base + (src_y as u16 / 8) * 32 + src_x as u16 / 8; // 32 tiles per map row
+            let map_offset = (map_addr - 0x8000) as usize;
+            let row_in_tile = (src_y % 8) as u16; // Which pixel row inside the tile
+
+            // Read tile index from the map, and its attributes (CGB only)
+            let tile_index = vram[0][map_offset];
+            let attr = if cgb { vram[1][map_offset] } else { 0 };
+
+            // Determine tile data address depending on LCDC bit 4
+            let tile_addr = if (lcdc & 0x10) != 0 {
+                // Unsigned tile index (0x8000..)
+                0x8000 + (tile_index as u16) * 16
+            } else {
+                // Signed tile index (0x8800.. / 0x9000..)
+                0x9000u16.wrapping_add((tile_index as i8 as i16 as u16) * 16)
+            };
+
+            // Pixel position in the tile's row (0 = leftmost, unless X-flipped)
+            let px = if (attr & 0x20) != 0 { 7 - src_x % 8 } else { src_x % 8 };
+            let row = if (attr & 0x40) != 0 { 7 - row_in_tile } else { row_in_tile };
+            let bank = ((attr >> 3) & 1) as usize;
+
+            // Fetch the two bitplanes for this row of the tile
+            let row_offset = (tile_addr - 0x8000 + row * 2) as usize;
+            let b0 = vram[bank][row_offset];     // Low bitplane
+            let b1 = vram[bank][row_offset + 1]; // High bitplane
+
+            // Combine bits from both planes to form a 2-bit color index (0..3)
+            let color_id = (decode_row(b0, b1) >> (px * 2)) as u8 & 0b11;
+            self.bg_line[x as usize] = color_id | (attr & 0x80);
+
+            if cgb {
+                put_rgb(fb, pitch, x as usize, y as usize, mmu.cgb_color(false, attr & 0x07, color_id));
+            }
+        }
+
+        if !cgb {
+            // Map each color index through BGP to get the shade (0..3), then draw the line
+            let shades = self.bg_line.map(|color_id| (bgp >> (color_id * 2)) & 0b11);
+            write_shades(&mut fb[y as usize * pitch..], &shades, &self.palette);
+        }
+
+        if window && win_start < SCREEN_WIDTH as i16 {
+            self.window_line += 1;
+        }
+    }
+
+    /// Renders all sprites that intersect the current scanline.
+    ///
+    /// ## Requirements:
+    /// - LCD must be enabled (`LCDC` bit 7).
+    /// - OBJ (sprite) rendering must be enabled (`LCDC` bit 1).
+    ///
+    /// ## Assumptions & Limitations:
+    /// - 8×16 sprites (`LCDC` bit 2) in the Compatible profile only; the Tetris profile
+    ///   draws everything as 8×8.
+    /// - Selects sprites in OAM order, respecting the DMG limit of **10 sprites per scanline**.
+    /// - Uses `OBP0` or `OBP1` palette according to the OAM attribute bit 4.
+    /// - Supports horizontal (`X flip`, OAM bit 5) and vertical (`Y flip`, OAM bit 6) flipping.
+    /// - Compatible profile: the lowest X wins between overlapping sprites (OAM order on
+    ///   ties), and OAM bit 7 puts the sprite behind BG colors 1–3. The Tetris profile
+    ///   draws in OAM order over the background.
+    /// - Color index 0 is treated as transparent and will not overwrite the framebuffer.
+    /// - CGB mode: OAM bits 0–2 pick the color palette and bit 3 the tile bank; earlier OAM
+    ///   entries win over later ones, and BG priority (OAM bit 7 or the BG attribute) hides
+    ///   sprite pixels over nonzero BG colors unless `LCDC` bit 0 is clear.
+    ///
+    /// ## Rendering Details:
+    /// - Sprite coordinates are adjusted for the Game Boy's hardware offset:
+    ///   - Y position in OAM is offset by -16 pixels.
+    ///   - X position in OAM is offset by -8 pixels.
+    /// - Each sprite pixel's final shade is determined by:
+    ///   1. Extracting the 2-bit color ID from the sprite tile data.
+    ///   2. Mapping that ID through the selected OBJ palette register.
+    ///   3. Writing the resulting shade to the framebuffer if nonzero.
+    ///
+    /// ## Parameters:
+    /// - `mmu`: Memory interface for reading LCDC, OAM, palette registers, and tile data.
+    /// - `fb`: Framebuffer (8-bit per pixel indices into the system palette).
+    /// - `pitch`: Number of bytes per framebuffer row.
+    ///
+    /// This function should be called once per scanline during PPU rendering.
+    fn render_sprites_line(&mut self, mmu: &MMU, fb: &mut [u8], pitch: usize) {
+        let y = self.ly as i16; // Current scanline (LY register)
+        if y >= SCREEN_HEIGHT as i16 { return; } // Ignore lines beyond screen height
+
+        // Read LCDC control register
+        let lcdc = mmu.read_byte(0xFF40);
+        if (lcdc & 0x80) == 0 { return; } // LCD disabled
+        if (lcdc & 0x02) == 0 { return; } // OBJ rendering disabled
+
+        // Read sprite palette registers
+        let obp0 = mmu.read_byte(0xFF48);
+        let obp1 = mmu.read_byte(0xFF49);
+
+        // Sprite attribute table and tile data, read straight from memory
+        let oam = mmu.oam();
+        let vram = [mmu.vram_bank(0), mmu.vram_bank(1)];
+        let cgb = mmu.cgb();
+        let compatible = self.profile != Profile::Tetris;
+        let height: i16 = if compatible && (lcdc & 0x04) != 0 { 16 } else { 8 }; // LCDC bit 2: 8×16 sprites
+
+        // Select the sprites on this line: the first 10 in OAM order (hardware limit)
+        let mut sprites = [(0i16, 0i16, 0u8, 0u8); 10];
+        let mut count = 0;
+        for entry in oam.chunks_exact(4) { // OAM has 40 sprite entries of 4 bytes
+            if count >= 10 { break; }
+
+            let sy = entry[0] as i16 - 16; // Y position (offset by -16 per hardware)
+            let sx = entry[1] as i16 - 8;  // X position (offset by -8 per hardware)
+            let tile = entry[2];           // Tile index in VRAM
+            let attr = entry[3];           // Attribute flags (palette, flip, priority)
+
+            // Skip if the current scanline is outside this sprite's vertical range
+            if y < sy || y >= sy + height { continue; }
+            sprites[count] = (sy, sx, tile, attr);
+            count += 1;
+        }
+
+        // Priority between sprites: on DMG the lowest X wins (then OAM order), on CGB
+        // OAM order alone. The Tetris profile just lets later sprites overwrite.
+        let resolve = compatible || cgb;
+        if compatible && !cgb {
+            sprites[..count].sort_by_key(|&(_, sx, _, _)| sx); // Stable: ties keep OAM order
+        }
+        let mut owned = [false; SCREEN_WIDTH as usize]; // Pixel taken by a higher-priority sprite
+
+        for &(sy, sx, tile, attr) in &sprites[..count] {
+            // Select palette: OBP0 or OBP1
+            let pal = if (attr & 0x10) != 0 { obp1 } else { obp0 };
+
+            // Determine which line of the tile to fetch (handle Y flip)
+            let line = if (attr & 0x40) != 0 {
+                (height - 1 - (y - sy)) as u16 // Y-flip: read from opposite row
+            } else {
+                (y - sy) as u16 // Normal orientation
+            };
+            // 8×16 sprites use an even/odd tile pair
+            let tile = if height == 16 { tile & 0xFE } else { tile };
+
+            // Offset in VRAM of the sprite's tile line (2 bytes per row)
+            let row_offset = (tile as usize) * 16 + line as usize * 2;
+            let bank = if cgb { ((attr >> 3) & 1) as usize } else { 0 };
+            let b0 = vram[bank][row_offset];     // Low bitplane
+            let b1 = vram[bank][row_offset + 1]; // High bitplane
+            let colors = decode_row(b0, b1);        // All 8 color IDs of the row
+
+            // Iterate over each pixel in the 8-pixel sprite row
+            for px in 0..8 {
+                // Handle X flip: read the row from the other end
+                let lane = if (attr & 0x20) != 0 { 7 - px } else { px };
+
+                // 2-bit color ID of the pixel
+                let color_id = (colors >> (lane * 2)) as u8 & 0b11;
+                if color_id == 0 { continue; } // Transparent pixel (color 0)
+
+                // Calculate on-screen X position
+                let x = sx + px as i16;
+                if x < 0 || x >= SCREEN_WIDTH as i16 { continue; } // Skip off-screen pixels
+
+                if resolve {
+                    // A hidden pixel still blocks lower-priority sprites
+                    if std::mem::replace(&mut owned[x as usize], true) { continue; }
+                    let bg = self.bg_line[x as usize];
+                    let behind_bg = (bg & 0x80) != 0 || (attr & 0x80) != 0;
+                    let bg_on_top = !cgb || (lcdc & 0x01) != 0; // CGB: LCDC bit 0 is the master priority
+                    if bg_on_top && (bg & 0x03) != 0 && behind_bg { continue; }
+                }
+                if let Some(mask) = &mut self.sprite_mask {
+                    mask[y as usize * SCREEN_WIDTH as usize + x as usize] = true;
+                }
+
+                if cgb {
+                    put_rgb(fb, pitch, x as usize, y as usize, mmu.cgb_color(true, attr & 0x07, color_id));
+                    continue;
+                }
+
+                // Map color ID through palette register to get shade
+                let shade = (pal >> (color_id * 2)) & 0b11;
+
+                // Write pixel to framebuffer, in the tile's own color if it has one
+                match self.sprite_colors[tile as usize] {
+                    Some(color) => put_rgb(fb, pitch, x as usize, y as usize, tint(color, shade)),
+                    None => put_px(fb, pitch, x as usize, y as usize, shade, self.palette),
+                }
+            }
+        }
+    }
+
+    /// Layout version of the `PPU ` save-state chunk:
+    /// - 1: up to the LCD-off dot counter.
+    /// - 2: adds the dot at which the line's pixel transfer ends.
+    pub const STATE_VERSION: u16 = 2;
+
+    /// Serializes the timing state, as it will be once the `behind` dots still owed (fewer
+    /// than `quiet_dots`) are skipped. The palette is a host display setting and is not saved.
+    pub fn save_state(&self, w: &mut StateWriter, behind: u32) {
+        let lcd_off = self.profile != Profile::Tetris && !self.lcd_on;
+        w.u8(self.ly);
+        w.u8(self.mode as u8);
+        w.u16(if lcd_off { self.dot } else { self.dot + behind as u16 });
+        w.bool(self.frame_ready);
+        w.u8(self.window_line);
+        w.bool(self.lcd_on);
+        w.u32(if lcd_off { self.off_dots + behind } else { self.off_dots });
+        w.u16(self.hblank_dot);
+    }
+
+    /// Restores the state written by `save_state` with layout `version`. Older layouts
+    /// end pixel transfer at dot 252.
+    pub fn load_state(&mut self, r: &mut StateReader, version: u16) -> Result<(), String> {
+        self.ly = r.u8()?;
+        self.mode = match r.u8()? {
+            0 => PPUMode::HBlank,
+            1 => PPUMode::VBlank,
+            2 => PPUMode::Oam,
+            3 => PPUMode::Vram,
+            m => return Err(format!("invalid PPU mode in save state: {}", m)),
+        };
+        self.dot = r.u16()?;
+        self.frame_ready = r.bool()?;
+        self.window_line = r.u8()?;
+        self.lcd_on = r.bool()?;
+        self.off_dots = r.u32()?;
+        self.hblank_dot = if version >= 2 { r.u16()?.clamp(80, 455) } else { 252 };
+        Ok(())
+    }
+
+    /// Returns `true` if a frame has just been completed; clears the flag.
+    pub fn is_frame_ready(&mut self) -> bool {
+        let r = self.frame_ready;
+        self.frame_ready = false;
+        r
+    }
+
+    /// Selects the accuracy profile (see `Profile`).
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = profile;
+        self.lcd_on = true;
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) { 
+        self.palette = palette; 
+    }
+    
+    pub fn get_palette(&self) -> Palette { 
+        self.palette
+    }
+
+    /// Colors the DMG sprites drawn with `tile` (`None` goes back to the palette).
+    pub fn set_sprite_color(&mut self, tile: u8, color: Option<[u8; 3]>) {
+        self.sprite_colors[tile as usize] = color;
+    }
+
+    pub fn sprite_colors(&self) -> &[Option<[u8; 3]>; 256] {
+        &self.sprite_colors
+    }
+
+    /// Turns sprite outlines on or off (see `draw_outlines`).
+    pub fn set_sprite_outlines(&mut self, on: bool) {
+        if on != self.sprite_mask.is_some() {
+            self.sprite_mask = on.then(|| vec![false; SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize]);
+        }
+    }
+
+    /// Post-process for a finished frame with sprite outlines on: every pixel next to one a
+    /// sprite drew (diagonals included) but not drawn by one itself takes the darkest shade
+    /// (black in CGB mode), so sprites stand out from the background by a pixel all round.
+    fn draw_outlines(&mut self, mmu: &MMU, fb: &mut [u8], pitch: usize) {
+        let Some(mask) = &mut self.sprite_mask else { return };
+        let (width, height) = (SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize);
+        let dark = if mmu.cgb() { [0; 3] } else { self.palette.colors[3] };
+        // Sprites cover little of the screen: go out from their pixels
+        for i in (0..mask.len()).filter(|&i| mask[i]) {
+            let (x, y) = (i % width, i / width);
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    if !mask[ny * width + nx] {
+                        put_rgb(fb, pitch, nx, ny, dark);
+                    }
+                }
+            }
+        }
+        mask.fill(false);
+    }
+}
+
+/// Shade (0 lightest – 3 darkest) of a sprite color: a light tint, the color itself and
+/// two darker tones, like the palette's four shades.
+fn tint(color: [u8; 3], shade: u8) -> [u8; 3] {
+    color.map(|c| match shade {
+        0 => ((c as u16 + 255) / 2) as u8,
+        1 => c,
+        2 => (c as u16 * 3 / 5) as u8,
+        _ => (c as u16 / 4) as u8,
+    })
+}
+
+#[inline]
+fn put_rgb(fb: &mut [u8], pitch: usize, x: usize, y: usize, rgb: [u8; 3]) {
+    let i = y * pitch + x * 3;
+    fb[i..i + 3].copy_from_slice(&rgb);
+}
+
+/// Draws a line of DMG shades from the start of `row`; with the `simd` feature, eight
+/// pixels at a time on x86-64 CPUs with SSSE3.
+fn write_shades(row: &mut [u8], shades: &[u8; SCREEN_WIDTH as usize], palette: &Palette) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if std::is_x86_feature_detected!("ssse3") {
+        // SAFETY: SSSE3 is there
+        unsafe { crate::simd::write_shades(row, shades, palette) };
+        return;
+    }
+    for (pixel, &shade) in row.chunks_exact_mut(3).zip(shades) {
+        pixel.copy_from_slice(&palette.colors[shade as usize]);
+    }
+}
+
+#[inline]
+fn put_px(fb: &mut [u8], pitch: usize, x: usize, y: usize, shade: u8, palette: Palette) {
+    // Use SDL pitch (stride) in case lines have padding
+    let row_start = y * pitch;
+    let i = row_start + x * 3;
+    let c = palette.colors[shade as usize];
+    fb[i]     = c[0];
+    fb[i + 1] = c[1];
+    fb[i + 2] = c[2];
+}
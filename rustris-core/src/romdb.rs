@@ -0,0 +1,82 @@
+use crate::cartridge::Cartridge;
+use crate::gb::Profile;
+
+/// What the database knows about a game.
+#[derive(Debug)]
+pub struct RomInfo {
+    pub title: &'static str,  // Canonical title, No-Intro style
+    pub region: &'static str, // "World", "Japan", "USA, Europe", ...
+    pub profile: Profile,     // Profile the game runs with
+    pub quirks: &'static str, // Known mapper/header oddities, empty if none
+    pub high_scores: Option<(u16, u16)>, // WRAM range of a high-score table the game doesn't save
+}
+
+/// One database row. `checksums` pins a specific dump (header checksum at 0x14D, global
+/// checksum at 0x14E–0x14F); rows without it match any ROM with that header title.
+struct Entry {
+    header_title: &'static str,
+    checksums: Option<(u8, u16)>,
+    info: RomInfo,
+}
+
+/// Multicart note shared by the MBC1M collections.
+const MBC1M: &str = "MBC1 multicart wiring (MBC1M); only the menu game works, the others need the multicart mapper";
+
+/// Tetris's score tables, three entries (3-byte BCD score + 6-letter name) per table: B-Type
+/// (10 levels × 6 heights) at D000–D653, then A-Type (10 levels) at D654–D761.
+const TETRIS_SCORES: Option<(u16, u16)> = Some((0xD000, 0xD761));
+
+const DATABASE: &[Entry] = &[
+    Entry {
+        header_title: "TETRIS",
+        checksums: Some((0x0A, 0x16BF)),
+        info: RomInfo { title: "Tetris (World)", region: "World", profile: Profile::Tetris, quirks: "", high_scores: TETRIS_SCORES },
+    },
+    Entry {
+        header_title: "TETRIS",
+        checksums: Some((0x0B, 0x4638)),
+        info: RomInfo { title: "Tetris (World) (Rev 1)", region: "World", profile: Profile::Tetris, quirks: "", high_scores: TETRIS_SCORES },
+    },
+    // Hacks and unknown revisions keep the Tetris fast path
+    Entry {
+        header_title: "TETRIS",
+        checksums: None,
+        info: RomInfo { title: "Tetris (unknown revision)", region: "World", profile: Profile::Tetris, quirks: "", high_scores: TETRIS_SCORES },
+    },
+    Entry {
+        header_title: "TETRIS DX",
+        checksums: None,
+        info: RomInfo { title: "Tetris DX", region: "World", profile: Profile::Compatible, quirks: "", high_scores: None },
+    },
+    Entry {
+        header_title: "BOMCOL",
+        checksums: None,
+        info: RomInfo { title: "Bomberman Collection", region: "Japan", profile: Profile::Compatible, quirks: MBC1M, high_scores: None },
+    },
+    Entry {
+        header_title: "GENCOL",
+        checksums: None,
+        info: RomInfo { title: "Genjin Collection", region: "Japan", profile: Profile::Compatible, quirks: MBC1M, high_scores: None },
+    },
+    Entry {
+        header_title: "MOMOCOL",
+        checksums: None,
+        info: RomInfo { title: "Momotarou Collection", region: "Japan", profile: Profile::Compatible, quirks: MBC1M, high_scores: None },
+    },
+];
+
+/// Looks a cartridge up in the small built-in ROM database:
+/// - A row whose title and both header checksums match wins (a known dump).
+/// - Otherwise a title-only row, if there's one for the header title.
+///
+/// Returns `None` for games the database doesn't list; front-ends fall back to the
+/// header title and the core to its default profile.
+pub fn lookup(cartridge: &Cartridge) -> Option<&'static RomInfo> {
+    let title = cartridge.title();
+    let checksums = (cartridge.header_checksum(), cartridge.global_checksum());
+    let mut rows = DATABASE.iter().filter(|e| e.header_title == title);
+    rows.clone()
+        .find(|e| e.checksums == Some(checksums))
+        .or_else(|| rows.find(|e| e.checksums.is_none()))
+        .map(|e| &e.info)
+}
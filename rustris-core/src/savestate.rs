@@ -0,0 +1,176 @@
+/// Save-state container:
+/// - Header: `MAGIC`, the container `FORMAT_VERSION` (u16) and the version of the core
+///   that wrote it (u8 length + UTF-8).
+/// - Then one chunk per component: a 4-byte tag, the component's layout version (u16),
+///   the payload length (u32) and the payload.
+///
+/// Each component bumps its own version when its layout changes and keeps reading the
+/// older layouts (`load_state` gets the chunk's version), so states stay loadable across
+/// builds. Unknown chunks are skipped. Data without the magic is a snapshot from before
+/// the container existed: the components' first layouts back to back.
+pub const MAGIC: [u8; 4] = *b"RTSS";
+
+/// Version of the header and chunk framing (not of the components' payloads).
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Minimal binary encoding used by the components' `save_state`/`load_state`.
+///
+/// Values are written in a fixed order with no tags, little-endian; each component
+/// reads back exactly what it wrote for the chunk version it's given.
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub fn u16(&mut self, v: u16) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn bool(&mut self, v: bool) {
+        self.buf.push(v as u8);
+    }
+
+    pub fn bytes(&mut self, v: &[u8]) {
+        self.buf.extend_from_slice(v);
+    }
+
+    /// Starts a versioned state with the container header.
+    pub fn with_header() -> Self {
+        let mut w = Self::new();
+        let core_version = env!("CARGO_PKG_VERSION");
+        w.bytes(&MAGIC);
+        w.u16(FORMAT_VERSION);
+        w.u8(core_version.len() as u8);
+        w.bytes(core_version.as_bytes());
+        w
+    }
+
+    /// Writes a chunk whose payload is whatever `write` puts in.
+    pub fn chunk(&mut self, tag: &[u8; 4], version: u16, write: impl FnOnce(&mut Self)) {
+        self.bytes(tag);
+        self.u16(version);
+        let len_at = self.buf.len();
+        self.u32(0);
+        write(self);
+        let len = (self.buf.len() - len_at - 4) as u32;
+        self.buf[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// A parsed versioned state: who wrote it and where each chunk is.
+pub struct StateChunks<'a> {
+    core_version: String,
+    chunks: Vec<([u8; 4], u16, &'a [u8])>,
+}
+
+impl<'a> StateChunks<'a> {
+    /// Splits a state into its chunks. Returns `None` for a legacy snapshot (no magic).
+    pub fn parse(data: &'a [u8]) -> Result<Option<Self>, String> {
+        let Some(body) = data.strip_prefix(&MAGIC) else {
+            return Ok(None);
+        };
+        let mut r = StateReader::new(body);
+        let format = r.u16()?;
+        if format > FORMAT_VERSION {
+            return Err(format!("save state format {} is newer than this build supports ({})", format, FORMAT_VERSION));
+        }
+        let len = r.u8()? as usize;
+        let core_version = String::from_utf8_lossy(r.take(len)?).into_owned();
+
+        let mut chunks = Vec::new();
+        while !r.data.is_empty() {
+            let tag: [u8; 4] = r.take(4)?.try_into().unwrap();
+            let version = r.u16()?;
+            let len = r.u32()? as usize;
+            chunks.push((tag, version, r.take(len)?));
+        }
+        Ok(Some(Self { core_version, chunks }))
+    }
+
+    /// Reader over the chunk `tag` and the version it was written with. Fails if the chunk
+    /// is missing or newer than `supported`.
+    pub fn get(&self, tag: &[u8; 4], supported: u16) -> Result<(StateReader<'a>, u16), String> {
+        let name = String::from_utf8_lossy(tag);
+        let &(_, version, payload) = self
+            .chunks
+            .iter()
+            .find(|(t, _, _)| t == tag)
+            .ok_or_else(|| format!("save state has no {} chunk", name.trim_end()))?;
+        if version > supported {
+            return Err(format!(
+                "save state from rustris-core {} has {} v{}, this build reads up to v{}",
+                self.core_version, name.trim_end(), version, supported,
+            ));
+        }
+        Ok((StateReader::new(payload), version))
+    }
+}
+
+/// Reader counterpart of [`StateWriter`]; every getter fails on truncated input.
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < n {
+            return Err(String::from("truncated save state"));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        let b = self.take(8)?;
+        Ok(u64::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, String> {
+        Ok(self.u8()? != 0)
+    }
+
+    /// Fills `out` with the next `out.len()` bytes.
+    pub fn bytes(&mut self, out: &mut [u8]) -> Result<(), String> {
+        out.copy_from_slice(self.take(out.len())?);
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+//! SSSE3 pixel output for the PPU (`simd` feature, x86-64 only): a line of DMG shades
+//! goes to the framebuffer eight pixels (24 bytes) per step, looking the colors up with
+//! byte shuffles instead of one pixel at a time.
+
+use crate::ppu::Palette;
+
+use std::arch::x86_64::*;
+
+/// Writes `shades` (0–3 each, a multiple of 8 of them) as RGB24 pixels of `palette` to
+/// the start of `row`.
+///
+/// # Safety
+/// The CPU must support SSSE3 (`is_x86_feature_detected!("ssse3")`).
+#[target_feature(enable = "ssse3")]
+pub unsafe fn write_shades(row: &mut [u8], shades: &[u8], palette: &Palette) {
+    // Shade s's color at bytes 3s..3s+3
+    let mut colors = [0u8; 16];
+    for (shade, rgb) in palette.colors.iter().enumerate() {
+        colors[shade * 3..shade * 3 + 3].copy_from_slice(rgb);
+    }
+    // SAFETY: `colors` is 16 bytes
+    let colors = unsafe { _mm_loadu_si128(colors.as_ptr() as *const __m128i) };
+
+    // For each output byte: the pixel it belongs to and its channel. Pixels 0–4 and the
+    // red of pixel 5 make the first 16 bytes, the rest of pixel 5 to pixel 7 the last 8.
+    let first_pixel = _mm_setr_epi8(0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4, 5);
+    let first_channel = _mm_setr_epi8(0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0);
+    let last_pixel = _mm_setr_epi8(5, 5, 6, 6, 6, 7, 7, 7, -1, -1, -1, -1, -1, -1, -1, -1);
+    let last_channel = _mm_setr_epi8(1, 2, 0, 1, 2, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0);
+
+    for (pixels, out) in shades.chunks_exact(8).zip(row.chunks_exact_mut(24)) {
+        // SAFETY: `pixels` is 8 bytes
+        let pixels = unsafe { _mm_loadl_epi64(pixels.as_ptr() as *const __m128i) };
+        let first = lookup(colors, pixels, first_pixel, first_channel);
+        let last = lookup(colors, pixels, last_pixel, last_channel);
+        // SAFETY: `out` is 24 bytes
+        unsafe {
+            _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, first);
+            _mm_storel_epi64(out[16..].as_mut_ptr() as *mut __m128i, last);
+        }
+    }
+}
+
+/// The color byte for each output byte: `colors[3 × shade + channel]`, the shade taken
+/// from `pixels` at the positions in `pixel`.
+#[inline]
+#[target_feature(enable = "ssse3")]
+fn lookup(colors: __m128i, pixels: __m128i, pixel: __m128i, channel: __m128i) -> __m128i {
+    let shade = _mm_shuffle_epi8(pixels, pixel);
+    let index = _mm_add_epi8(_mm_add_epi8(shade, shade), _mm_add_epi8(shade, channel));
+    _mm_shuffle_epi8(colors, index)
+}
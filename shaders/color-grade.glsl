@@ -0,0 +1,18 @@
+// Color grading: contrast, saturation and a tint, applied to every pixel. Tweak the
+// constants with the game running; the picture updates as the file is saved.
+#version 110
+
+uniform sampler2D Source;
+varying vec2 TexCoord;
+
+const float CONTRAST = 1.10;
+const float SATURATION = 0.85;
+const vec3 TINT = vec3(1.00, 0.97, 0.90);
+
+void main() {
+    vec3 color = texture2D(Source, TexCoord).rgb;
+    color = (color - 0.5) * CONTRAST + 0.5;
+    float luma = dot(color, vec3(0.299, 0.587, 0.114));
+    color = mix(vec3(luma), color, SATURATION) * TINT;
+    gl_FragColor = vec4(clamp(color, 0.0, 1.0), 1.0);
+}
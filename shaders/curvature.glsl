@@ -0,0 +1,24 @@
+// Curvature: bends the picture as a CRT's glass would, with dark corners and faint
+// scanlines. The DMG never had a tube; this is for the arcade-cabinet look.
+#version 110
+
+uniform sampler2D Source;
+uniform vec2 SourceSize;
+varying vec2 TexCoord;
+
+// How far the edges bend in; 0 is flat
+const float CURVE = 0.12;
+
+void main() {
+    vec2 centered = TexCoord * 2.0 - 1.0;
+    centered *= 1.0 + CURVE * dot(centered, centered) * 0.25;
+    vec2 uv = centered * 0.5 + 0.5;
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec3 color = texture2D(Source, uv).rgb;
+    float scanline = 0.85 + 0.15 * cos(6.2832 * uv.y * SourceSize.y);
+    float vignette = clamp(pow(16.0 * uv.x * uv.y * (1.0 - uv.x) * (1.0 - uv.y), 0.25), 0.0, 1.0);
+    gl_FragColor = vec4(color * scanline * vignette, 1.0);
+}
@@ -0,0 +1,19 @@
+// LCD grid: darkens the gaps between the DMG's pixels once the picture is scaled up
+// enough to show them, and lightens the pixels a touch toward the panel's green-grey.
+#version 110
+
+uniform sampler2D Source;
+uniform vec2 SourceSize;
+uniform vec2 OutputSize;
+varying vec2 TexCoord;
+
+void main() {
+    vec3 color = texture2D(Source, TexCoord).rgb;
+    // Where in its pixel this fragment is, 0–1 both ways
+    vec2 cell = fract(TexCoord * SourceSize);
+    // Output pixels per screen pixel: the gap is one of them, none below 3×
+    vec2 scale = OutputSize / SourceSize;
+    vec2 gap = step(scale.x < 3.0 ? vec2(2.0) : vec2(1.0) - 1.0 / scale, cell);
+    color *= 1.0 - 0.35 * max(gap.x, gap.y);
+    gl_FragColor = vec4(mix(color, vec3(0.78, 0.80, 0.72), 0.08), 1.0);
+}
@@ -0,0 +1,101 @@
+use rustris_core::GB;
+
+use std::fmt;
+
+/// A comparison an assertion makes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    const ALL: [(&str, Op); 6] = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)];
+
+    fn holds(self, a: u8, b: u8) -> bool {
+        match self {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+        }
+    }
+}
+
+/// An invariant on a byte of memory, written `ADDR OP VALUE` with the address and the value
+/// in hex (with or without `$` or `0x`) and OP one of `==`, `!=`, `<`, `<=`, `>`, `>=`:
+/// `C0DE <= 14` says the byte at C0DE never exceeds 0x14.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Assertion {
+    addr: u16,
+    op: Op,
+    value: u8,
+}
+
+impl Assertion {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid assertion (expected e.g. \"C0DE <= 14\"): {}", text);
+        let (symbol, op, at) = Op::ALL
+            .iter()
+            .find_map(|&(symbol, op)| text.find(symbol).map(|at| (symbol, op, at)))
+            .ok_or_else(invalid)?;
+        let addr = parse_hex(&text[..at]).ok_or_else(invalid)?;
+        let value = parse_hex(&text[at + symbol.len()..]).and_then(|value| u8::try_from(value).ok()).ok_or_else(invalid)?;
+        Ok(Self { addr, op, value })
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = Op::ALL.iter().find(|(_, op)| *op == self.op).map_or("", |(symbol, _)| symbol);
+        write!(f, "{:04X} {} {:02X}", self.addr, symbol, self.value)
+    }
+}
+
+fn parse_hex(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let hex = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")).unwrap_or(text);
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// Assertions checked after every instruction, each reported when it starts failing and
+/// not again until it has held in between, so going on from a stop doesn't stop again
+/// at once.
+#[derive(Default)]
+pub struct Assertions {
+    list: Vec<(Assertion, bool)>, // The assertion, failing
+}
+
+impl Assertions {
+    pub fn new(list: &[Assertion]) -> Self {
+        Self { list: list.iter().map(|assertion| (assertion.clone(), false)).collect() }
+    }
+
+    /// Adds `assertion`, unless it's already there.
+    pub fn add(&mut self, assertion: Assertion) {
+        if self.list.iter().all(|(a, _)| *a != assertion) {
+            self.list.push((assertion, false));
+        }
+    }
+
+    /// Call after every instruction: describes the first assertion that has just failed.
+    pub fn after_step(&mut self, gb: &GB) -> Option<String> {
+        let mut failed = None;
+        for (assertion, failing) in &mut self.list {
+            let value = gb.read_byte(assertion.addr);
+            let was_failing = std::mem::replace(failing, !assertion.op.holds(value, assertion.value));
+            if *failing && !was_failing && failed.is_none() {
+                failed = Some((assertion.to_string(), value));
+            }
+        }
+        let (assertion, value) = failed?;
+        let pc = gb.recent_instructions().last().copied().unwrap_or_default();
+        Some(format!("Assertion failed: {} (it's {:02X}) after the instruction at 0x{:04X}", assertion, value, pc))
+    }
+}
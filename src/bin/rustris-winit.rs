@@ -0,0 +1,203 @@
+use pixels::{Pixels, SurfaceTexture};
+use rustris_core::gb::{self, GB};
+use rustris_core::{Cartridge, VideoSink, SCREEN_HEIGHT, SCREEN_WIDTH};
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::window::WindowBuilder;
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: rustris-winit [--zoom N] [ROM]";
+
+/// Frame period of the real DMG: ~59.7275 FPS.
+const FRAME: Duration = Duration::from_nanos(16_742_706);
+
+/// Player 1's keys, as the SDL front end has them by default.
+const KEYS: [(VirtualKeyCode, u8); 8] = [
+    (VirtualKeyCode::Right,  gb::BTN_RIGHT),
+    (VirtualKeyCode::Left,   gb::BTN_LEFT),
+    (VirtualKeyCode::Up,     gb::BTN_UP),
+    (VirtualKeyCode::Down,   gb::BTN_DOWN),
+    (VirtualKeyCode::X,      gb::BTN_A),
+    (VirtualKeyCode::Z,      gb::BTN_B),
+    (VirtualKeyCode::Space,  gb::BTN_SELECT),
+    (VirtualKeyCode::Return, gb::BTN_START),
+];
+
+/// Lean front end (`--features winit`): one game in a window, drawn with pixels (wgpu, so
+/// Vulkan, Metal, DirectX 12 or OpenGL, whichever the system has) and driven by winit, for
+/// systems without the SDL2 libraries the full front end needs:
+/// - Keys as the full front end's defaults; Escape quits, P pauses.
+/// - Frames are paced to the DMG's rate by the event loop's timer, with the picture scaled
+///   to the largest whole multiple that fits the window.
+/// - Battery RAM is loaded and saved next to the ROM, as the full front end does, so the
+///   two can take turns on the same game.
+///
+/// Everything else (settings, debugger, save states, netplay, scripting) is the full front
+/// end's.
+fn main() -> ExitCode {
+    let (rom_path, zoom) = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut gb = match Cartridge::from_file(&rom_path).and_then(GB::new) {
+        Ok(gb) => gb,
+        Err(e) => {
+            eprintln!("Error loading {}: {}", rom_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let save_path = Cartridge::save_path(&rom_path);
+    if gb.battery_ram().is_some() && let Ok(data) = std::fs::read(&save_path) {
+        gb.load_battery_ram(&data);
+    }
+
+    let (width, height) = (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+    let event_loop = EventLoop::new();
+    let window = match WindowBuilder::new()
+        .with_title(title(gb.game_title(), false))
+        .with_inner_size(LogicalSize::new(width * zoom, height * zoom))
+        .with_min_inner_size(LogicalSize::new(width, height))
+        .build(&event_loop)
+    {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("Can't open the window: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let size = window.inner_size();
+    let pixels = match Pixels::new(width, height, SurfaceTexture::new(size.width, size.height, &window)) {
+        Ok(pixels) => pixels,
+        Err(e) => {
+            eprintln!("Can't draw to the window: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut screen = Screen { pixels, frame: vec![0; width as usize * height as usize * 3] };
+    let mut held = 0u8;
+    let mut paused = false;
+    let mut deadline = Instant::now();
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent { event, .. } => match event {
+            WindowEvent::CloseRequested => control_flow.set_exit(),
+            WindowEvent::Resized(size) => {
+                if let Err(e) = screen.pixels.resize_surface(size.width, size.height) {
+                    eprintln!("Can't resize the picture: {}", e);
+                    control_flow.set_exit();
+                }
+            }
+            WindowEvent::Focused(false) => held = 0,
+            WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(key), state, .. }, .. } => {
+                let pressed = state == ElementState::Pressed;
+                match key {
+                    VirtualKeyCode::Escape if pressed => control_flow.set_exit(),
+                    VirtualKeyCode::P if pressed => {
+                        paused = !paused;
+                        deadline = Instant::now();
+                        window.set_title(&title(gb.game_title(), paused));
+                    }
+                    _ => {
+                        if let Some(&(_, button)) = KEYS.iter().find(|(k, _)| *k == key) {
+                            if pressed { held |= button } else { held &= !button }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        },
+
+        // Runs the frame that's due, if any, and sleeps until the next one
+        Event::MainEventsCleared => {
+            if paused {
+                control_flow.set_wait();
+                return;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                let buttons = held;
+                gb.run_frame(&mut screen, &mut || buttons);
+                window.request_redraw();
+                // Late: resync rather than run the missed frames back to back
+                deadline = if now - deadline > FRAME { now + FRAME } else { deadline + FRAME };
+            }
+            control_flow.set_wait_until(deadline);
+        }
+
+        Event::RedrawRequested(_) => {
+            if let Err(e) = screen.pixels.render() {
+                eprintln!("Can't draw the picture: {}", e);
+                control_flow.set_exit();
+            }
+        }
+
+        Event::LoopDestroyed => {
+            if let Some(ram) = gb.battery_ram() && let Err(e) = std::fs::write(&save_path, ram) {
+                eprintln!("Error writing {}: {}", save_path.display(), e);
+            }
+        }
+        _ => {}
+    })
+}
+
+/// The ROM to play (`tetris.gb` in the working directory by default, as with the full
+/// front end) and the window's zoom.
+fn parse_args() -> Result<(String, u32), String> {
+    let mut rom_path = String::from("tetris.gb");
+    let mut zoom = 4;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--zoom" => {
+                let value = args.next().ok_or("--zoom requires a value")?;
+                zoom = match value.parse() {
+                    Ok(zoom @ 1..=16) => zoom,
+                    _ => return Err(format!("zoom must be between 1 and 16: {}", value)),
+                };
+            }
+            "-h" | "--help" => return Err(String::from("Plays a Game Boy ROM in a window.")),
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ => rom_path = arg,
+        }
+    }
+    if !Path::new(&rom_path).is_file() {
+        return Err(format!("no ROM at {}", rom_path));
+    }
+    Ok((rom_path, zoom))
+}
+
+fn title(game: &str, paused: bool) -> String {
+    let mut title = String::from("RUSTЯIS");
+    if !game.is_empty() {
+        title.push_str(&format!(" - {}", game));
+    }
+    if paused {
+        title.push_str(" - paused");
+    }
+    title
+}
+
+/// Where the core draws: an RGB24 frame, copied into the window's RGBA one once done.
+struct Screen {
+    pixels: Pixels,
+    frame: Vec<u8>,
+}
+
+impl VideoSink for Screen {
+    fn buffer(&mut self) -> (&mut [u8], usize) {
+        (&mut self.frame, SCREEN_WIDTH as usize * 3)
+    }
+
+    fn present(&mut self) {
+        for (rgba, rgb) in self.pixels.frame_mut().chunks_exact_mut(4).zip(self.frame.chunks_exact(3)) {
+            rgba.copy_from_slice(&[rgb[0], rgb[1], rgb[2], 0xFF]);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use rustris_core::gb::{BTN_A, BTN_DOWN, BTN_LEFT, BTN_RIGHT, BTN_START};
+use rustris_core::GB;
+use crate::tetris::{self, normalize, Board, Cells, Piece, TetrisState, WELL_HEIGHT, WELL_WIDTH};
+
+/// Frames with no piece in play before the bot presses Start, which gets it from the
+/// title screen and menus into a game (and out of the game-over screen).
+const START_DELAY: u32 = 150;
+
+/// Taps spent lining a piece up before the bot gives up and drops it where it is (a
+/// rotation against a wall or the stack doesn't happen).
+const MAX_TAPS: u32 = 16;
+
+/// How much each property of the board after a placement counts (higher scores win).
+/// The defaults are a well-known hand-tuned set; change them to experiment.
+#[derive(Clone, Copy)]
+pub struct Weights {
+    pub height: f64,    // Per cell of summed column heights
+    pub lines: f64,     // Per line cleared
+    pub holes: f64,     // Per empty cell with a filled one above it
+    pub bumpiness: f64, // Per cell of height difference between neighboring columns
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self { height: -0.510066, lines: 0.760666, holes: -0.35663, bumpiness: -0.184483 }
+    }
+}
+
+/// Tetris autoplayer (`--autoplay`, F6), a demo mode and a sandbox for placement AIs:
+/// - Each frame it reads the well and the falling and next pieces (see `TetrisState`),
+///   which come from what's on screen, like a player's view.
+/// - When a piece appears (the well changed), every orientation and column is tried for
+///   it and, for each, every placement of the next piece; the pair leaving the best board
+///   (see `Weights`: height, holes, bumpiness, lines) picks where the piece goes.
+/// - The piece is then steered there by tapping A (rotate) and Left/Right, one button
+///   every other frame so each tap registers, comparing its shape and column with the
+///   target every frame, then soft-dropped by holding Down.
+/// - With no piece in play for a while it presses Start, so it goes from the title
+///   screen into a game and starts a new one after a game over.
+///
+/// Only the original Tetris is understood (games with the Tetris profile).
+pub struct Bot {
+    enabled: bool,
+    weights: Weights,
+    plan: Option<(Board, Placement)>, // Where the falling piece goes, and the well it was chosen for
+    taps: u32,                        // Taps spent on the falling piece
+    tapped: bool,                     // A button was tapped last frame; release it this one
+    idle: u32,                        // Frames without a piece in play
+    memory: Vec<u8>,                  // Copy of the address space to decode
+}
+
+impl Bot {
+    /// A bot for `gb`'s game, playing from the start if `enabled`; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB, enabled: bool) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self {
+            enabled,
+            weights: Weights::default(),
+            plan: None,
+            taps: 0,
+            tapped: false,
+            idle: 0,
+            memory: vec![0; 0x10000],
+        })
+    }
+
+    /// Turns playing on or off; when off the bot presses nothing.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.plan = None;
+        self.tapped = false;
+        self.idle = 0;
+    }
+
+    /// Call before every frame: the buttons to hold during it.
+    pub fn buttons(&mut self, gb: &GB) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        gb.read_memory(&mut self.memory);
+        let TetrisState { board, active, next, .. } = TetrisState::decode(&self.memory);
+        let Some(active) = active else {
+            self.plan = None;
+            self.idle += 1;
+            if self.idle < START_DELAY {
+                return 0;
+            }
+            self.idle = 0;
+            return BTN_START;
+        };
+        self.idle = 0;
+
+        if self.plan.as_ref().is_none_or(|(planned, _)| *planned != board) {
+            self.plan = best_placement(&board, active.piece, next, &self.weights).map(|target| (board, target));
+            self.taps = 0;
+        }
+        let Some((_, target)) = &self.plan else {
+            // Nowhere to go: the game is lost either way
+            return BTN_DOWN;
+        };
+        if self.tapped {
+            self.tapped = false;
+            return 0;
+        }
+
+        let column = active.cells.iter().map(|&(c, _)| c).min().unwrap_or(0);
+        let tap = if self.taps >= MAX_TAPS {
+            0
+        } else if normalize(active.cells) != target.shape {
+            BTN_A
+        } else if column < target.column {
+            BTN_RIGHT
+        } else if column > target.column {
+            BTN_LEFT
+        } else {
+            0
+        };
+        if tap == 0 {
+            return BTN_DOWN;
+        }
+        self.taps += 1;
+        self.tapped = true;
+        tap
+    }
+}
+
+/// Where a piece goes: its shape (normalized, see `normalize`) and leftmost column.
+struct Placement {
+    shape: Cells,
+    column: i32,
+}
+
+impl Board {
+    /// Drops `shape` straight down from the top of the well at `column`. Returns the well
+    /// after it locks and full rows are cleared, and the number of rows cleared; `None` if
+    /// it doesn't fit or would lock sticking out of the top.
+    fn drop(&self, shape: &Cells, column: i32) -> Option<(Board, u32)> {
+        let at = |row: i32| shape.map(|(c, r)| (c + column, r + row));
+        if !self.fits(&at(0)) {
+            return None;
+        }
+        let mut row = 0;
+        while self.fits(&at(row + 1)) {
+            row += 1;
+        }
+
+        let mut board = self.clone();
+        for (c, r) in at(row) {
+            if r < 0 {
+                return None;
+            }
+            board.0[r as usize][c as usize] = true;
+        }
+        let remaining: Vec<_> = board.0.iter().filter(|cells| !cells.iter().all(|&filled| filled)).copied().collect();
+        let lines = (WELL_HEIGHT - remaining.len()) as u32;
+        let mut cleared = Board([[false; WELL_WIDTH]; WELL_HEIGHT]);
+        cleared.0[lines as usize..].copy_from_slice(&remaining);
+        Some((cleared, lines))
+    }
+
+    /// How good the well is after clearing `lines` rows to get here.
+    fn score(&self, lines: u32, weights: &Weights) -> f64 {
+        let mut heights = [0i32; WELL_WIDTH];
+        let mut holes = 0;
+        for (column, height) in heights.iter_mut().enumerate() {
+            let top = (0..WELL_HEIGHT).find(|&row| self.0[row][column]).unwrap_or(WELL_HEIGHT);
+            *height = (WELL_HEIGHT - top) as i32;
+            holes += (top..WELL_HEIGHT).filter(|&row| !self.0[row][column]).count();
+        }
+        let bumpiness: i32 = heights.windows(2).map(|pair| (pair[0] - pair[1]).abs()).sum();
+        weights.height * heights.iter().sum::<i32>() as f64
+            + weights.lines * lines as f64
+            + weights.holes * holes as f64
+            + weights.bumpiness * bumpiness as f64
+    }
+
+    /// Every place `piece` can be dropped: the placement, the well after it and the rows
+    /// it clears.
+    fn placements(&self, piece: Piece) -> impl Iterator<Item = (Placement, Board, u32)> + '_ {
+        piece.orientations().into_iter().flat_map(move |shape| {
+            let width = shape.iter().map(|&(c, _)| c).max().unwrap_or(0) + 1;
+            (0..=WELL_WIDTH as i32 - width).filter_map(move |column| {
+                let (board, lines) = self.drop(&shape, column)?;
+                Some((Placement { shape, column }, board, lines))
+            })
+        })
+    }
+}
+
+/// The best placement for `piece`, looking one piece ahead when the next one is known.
+fn best_placement(board: &Board, piece: Piece, next: Option<Piece>, weights: &Weights) -> Option<Placement> {
+    let mut best: Option<(f64, Placement)> = None;
+    for (placement, after, lines) in board.placements(piece) {
+        let score = next
+            .and_then(|next| {
+                after
+                    .placements(next)
+                    .map(|(_, last, more)| last.score(lines + more, weights))
+                    .max_by(f64::total_cmp)
+            })
+            .unwrap_or_else(|| after.score(lines, weights));
+        if best.as_ref().is_none_or(|(top, _)| score > *top) {
+            best = Some((score, placement));
+        }
+    }
+    best.map(|(_, placement)| placement)
+}
@@ -0,0 +1,48 @@
+use crate::tetris::{self, Piece, TetrisState};
+
+use rustris_core::GB;
+
+/// Colors of the tetrominoes in today's Tetris games.
+fn guideline_color(piece: Piece) -> [u8; 3] {
+    match piece {
+        Piece::I => [0x00, 0xF0, 0xF0], // Cyan
+        Piece::O => [0xF0, 0xF0, 0x00], // Yellow
+        Piece::T => [0xA0, 0x00, 0xF0], // Purple
+        Piece::S => [0x00, 0xF0, 0x00], // Green
+        Piece::Z => [0xF0, 0x00, 0x00], // Red
+        Piece::J => [0x00, 0x00, 0xF0], // Blue
+        Piece::L => [0xF0, 0xA0, 0x00], // Orange
+    }
+}
+
+/// Piece colors (`--colorize`): the falling Tetris piece and the preview are drawn in
+/// their guideline colors while the well and everything else keep the palette:
+/// - The 1989 game draws each tetromino with its own tiles, so the colors go by tile (see
+///   `GB::set_sprite_color`), applied by the sprite renderer after the game's palette.
+/// - Which tile is which piece is learned as the game runs, from the sprites that make up
+///   the pieces `TetrisState` recognizes; a piece is colored from its first appearance on.
+/// - Pieces that have locked are part of the background and stay monochrome.
+pub struct Colorizer {
+    tiles: [Option<Piece>; 256], // Piece each tile was last seen drawing
+    memory: Vec<u8>,             // Copy of the address space to decode
+}
+
+impl Colorizer {
+    /// The colorizer for `gb`'s game; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self { tiles: [None; 256], memory: vec![0; 0x10000] })
+    }
+
+    /// Call after every emulated frame: colors the tiles of the pieces on screen that
+    /// weren't colored yet.
+    pub fn after_frame(&mut self, gb: &mut GB) {
+        gb.read_memory(&mut self.memory);
+        let state = TetrisState::decode(&self.memory);
+        for (tile, piece) in tetris::piece_tiles(&self.memory, &state) {
+            if self.tiles[tile as usize] != Some(piece) {
+                self.tiles[tile as usize] = Some(piece);
+                gb.set_sprite_color(tile, Some(guideline_color(piece)));
+            }
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use std::path::{Path, PathBuf};
+
+/// Written as `config.toml` when there's none yet. Every setting is commented out at its
+/// default, so the file documents them without pinning them.
+const TEMPLATE: &str = r#"# RUSTЯIS settings. Command-line flags override anything set here.
+# Settings changed while playing (panel or hotkeys) are written back here on exit.
+# There is no [audio] section: the emulator has no sound yet.
+
+[video]
+# palette = "color"          # DMG games: "color", "green", "colorblind" or "high-contrast"
+# rotation = 0               # Clockwise degrees: 0, 90, 180 or 270
+# fullscreen = false
+# background = "000000"      # RRGGBB around the picture
+# zoom = 4                   # Window size in multiples of 160×144 at a display scale of 1.0
+# hud = false                # Tetris score/level/lines readout beside the picture
+# input_display = false      # Held buttons in the picture's bottom-left corner
+# ghost = false              # Where the falling Tetris piece would land
+# colorize = false           # Tetris pieces in their modern colors
+# sprite_outlines = false    # Dark outline around sprites, to pick them out of the background
+# shader = "lcd-grid.glsl"   # GLSL fragment shader the picture is drawn through
+
+[emulation]
+# speed = 100                # Percent, 25–400
+# speed_step = 25            # Percent per press of - / =
+# pause_on_focus_loss = false
+# pacing = "hybrid"          # "hybrid", "sleep", "spin" or "vsync" (60/120 Hz displays)
+# spin_margin = 500          # Microseconds hybrid pacing spins before each frame
+# sleep_slack = 0            # Microseconds sleep pacing wakes early, if frames come late
+# rewind_seconds = 30        # 0 disables rewind
+# model = "auto"             # "auto", "dmg" or "cgb"
+# profile = "auto"           # "auto", "tetris", "compatible" or "accurate"
+# overclock = 1              # CPU speed multiplier, 1, 2 or 4: less slowdown in busy scenes
+# frame_stats = false
+# boot_animation = false     # Scroll the logo at power-on without a boot ROM (DMG, in the window)
+# ram_init = "zero"          # RAM at power-on: "zero" or "random"
+# save_high_scores = false   # Keep Tetris's high scores in a .hiscore file next to the ROM
+# auto_resume = false        # Save the game on exit, and offer to continue from there next time
+# session_stats = false      # Track Tetris games, summed up on exit and added to sessions.csv
+# hard_drop = false          # Patch Tetris so Up drops the falling piece (not in the original)
+
+[input]
+# Keys are SDL key names: https://wiki.libsdl.org/SDL2/SDL_Scancode
+# right = "Right"
+# left = "Left"
+# up = "Up"
+# down = "Down"
+# a = "X"
+# b = "Z"
+# select = "Space"
+# start = "Return"
+# turbo = ["S=A", "A=B"]     # KEY=BUTTON
+# turbo_hz = 10
+# macros = ["Q", "W"]        # Keys that play input macros; Shift+key records one
+# das = false                # Held Left/Right as auto-repeat pulses timed below, not by the game
+# das_delay = 16             # Frames from the first pulse to the second, 2–60
+# das_repeat = 6             # Frames between later pulses, 2–30
+# rumble = 100               # Controller vibration for rumble carts, in percent (0: off)
+
+[input.player2]              # Second machine with --dual
+# right = "L"
+# left = "J"
+# up = "I"
+# down = "K"
+# a = "G"
+# b = "F"
+# select = "T"
+# start = "Y"
+
+[paths]
+# rom = "tetris.gb"          # ROM opened when none is given
+# boot_rom = "dmg_boot.bin"  # Boot ROM dumps run at power-on (none: skip the boot animation)
+# cgb_boot_rom = "cgb_boot.bin"
+
+[debug]
+# assert = ["C0DE <= 14"]    # Stop in the debugger when a byte breaks one of these (ADDR OP VALUE, in hex)
+"#;
+
+/// A setting's value. Only the parts of TOML the settings need are understood: strings,
+/// integers, booleans and arrays of them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn parse(text: &str) -> Option<Value> {
+        let text = text.trim();
+        if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            return split_array(inner)?
+                .iter()
+                .map(|item| Value::parse(item))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array);
+        }
+        if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            return unescape(inner).map(Value::Str);
+        }
+        match text {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => text.replace('_', "").parse().ok().map(Value::Int),
+        }
+    }
+
+    fn to_toml(&self) -> String {
+        match self {
+            Value::Str(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Value::Int(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(Value::to_toml).collect();
+                format!("[{}]", items.join(", "))
+            }
+        }
+    }
+
+    /// The value as an option argument would spell it (arrays have none).
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Value::Str(s) => Some(s.clone()),
+            Value::Int(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            Value::Array(_) => None,
+        }
+    }
+}
+
+/// Splits the inside of an array at top-level commas (not those in strings).
+fn split_array(inner: &str) -> Option<Vec<String>> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in inner.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                items.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if in_string {
+        return None;
+    }
+    items.push(current);
+    // A trailing comma is allowed
+    Some(items.into_iter().filter(|item| !item.trim().is_empty()).collect())
+}
+
+fn unescape(inner: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            c @ ('"' | '\\') => c,
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+/// Strips a `#` comment that isn't inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// `text` with the comment that ends `line` (from byte `comment_at`, empty if there's none)
+/// after it, in the column it had if `text` leaves room.
+fn keep_comment(line: &str, comment_at: usize, text: String) -> String {
+    let comment = &line[comment_at..];
+    if comment.is_empty() {
+        return text;
+    }
+    let column = line[..comment_at].chars().count();
+    format!("{:<width$} {}", text, comment, width = column.saturating_sub(1))
+}
+
+/// Section name of a `[section]` line.
+fn section_header(line: &str) -> Option<&str> {
+    let line = strip_comment(line).trim();
+    line.strip_prefix('[')?.strip_suffix(']').map(str::trim)
+}
+
+/// One setting: where it lives and what it says.
+struct Entry {
+    section: String,
+    key: String,
+    value: Value,
+    line: usize, // Index in `Config::lines`
+}
+
+/// The settings file (`config.toml` in the config directory, see `config_dir`):
+/// - Loaded once at startup; `Options::parse` starts from it and command-line flags
+///   override it.
+/// - `set` + `save` update single lines in place, so comments and layout survive.
+/// - Missing keys keep their built-in defaults; a file that doesn't exist yet is created
+///   from a template that lists every setting.
+pub struct Config {
+    path: Option<PathBuf>, // None: not saved (no config directory)
+    lines: Vec<String>,
+    entries: Vec<Entry>,
+}
+
+impl Config {
+    /// Reads the user's config file, creating it if needed.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return Self::parse(None, "");
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(Some(path), &text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let config = Self::parse(Some(path), TEMPLATE)?;
+                config.save();
+                Ok(config)
+            }
+            Err(e) => Err(format!("Error reading {}: {}", path.display(), e)),
+        }
+    }
+
+    fn parse(path: Option<PathBuf>, text: &str) -> Result<Self, String> {
+        let name = path.as_deref().map_or(String::from("config"), |p| p.display().to_string());
+        let mut section = String::new();
+        let mut entries = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            if let Some(header) = section_header(line) {
+                section = header.to_string();
+                continue;
+            }
+            let content = strip_comment(line).trim();
+            if content.is_empty() {
+                continue;
+            }
+            let error = || format!("{}:{}: invalid setting: {}", name, n + 1, content);
+            let (key, value) = content.split_once('=').ok_or_else(error)?;
+            let value = Value::parse(value).ok_or_else(error)?;
+            entries.push(Entry { section: section.clone(), key: key.trim().to_string(), value, line: n });
+        }
+        Ok(Self { path, lines: text.lines().map(String::from).collect(), entries })
+    }
+
+    /// Where the file lives, for messages.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The value of `key` in `[section]`, if the file sets it.
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|e| e.section == section && e.key == key).map(|e| &e.value)
+    }
+
+    /// Every `section.key` the file sets, for spotting typos.
+    pub fn keys(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries.iter().map(|e| format!("{}.{}", e.section, e.key))
+    }
+
+    /// Sets `key` in `[section]`: rewrites its line, or the template's commented-out line
+    /// for it, or adds a line at the end of the section (adding the section if needed). A
+    /// comment at the end of the line rewritten is kept, in its column if there's room.
+    pub fn set(&mut self, section: &str, key: &str, value: Value) {
+        let text = format!("{} = {}", key, value.to_toml());
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.section == section && e.key == key) {
+            entry.value = value;
+            let old = &self.lines[entry.line];
+            self.lines[entry.line] = keep_comment(old, strip_comment(old).len(), text);
+            return;
+        }
+
+        let (start, end) = self.section_lines(section);
+        let commented = (start..end).find(|&i| {
+            let line = self.lines[i].trim_start();
+            line.strip_prefix('#')
+                .and_then(|rest| rest.trim_start().strip_prefix(key))
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        });
+        let line = match commented {
+            Some(i) => {
+                // The comment after the commented-out setting, not the `#` that disables it
+                let old = &self.lines[i];
+                let hash = old.find('#').unwrap_or_default() + 1;
+                self.lines[i] = keep_comment(old, hash + strip_comment(&old[hash..]).len(), text);
+                i
+            }
+            None => {
+                self.insert_line(end, text);
+                end
+            }
+        };
+        self.entries.push(Entry { section: section.to_string(), key: key.to_string(), value, line });
+    }
+
+    /// Line range of a section's body (after its header, before the next one), creating
+    /// the section at the end of the file if it's missing.
+    fn section_lines(&mut self, section: &str) -> (usize, usize) {
+        let start = match self.lines.iter().position(|l| section_header(l) == Some(section)) {
+            Some(header) => header + 1,
+            None => {
+                if self.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(format!("[{}]", section));
+                self.lines.len()
+            }
+        };
+        let mut end = (start..self.lines.len()).find(|&i| section_header(&self.lines[i]).is_some()).unwrap_or(self.lines.len());
+        // Keep the blank lines that separate sections
+        while end > start && self.lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+        (start, end)
+    }
+
+    fn insert_line(&mut self, at: usize, text: String) {
+        self.lines.insert(at, text);
+        for entry in &mut self.entries {
+            if entry.line >= at {
+                entry.line += 1;
+            }
+        }
+    }
+
+    /// Writes the file back, reporting (not failing on) errors.
+    pub fn save(&self) {
+        let Some(path) = &self.path else { return };
+        let text: String = self.lines.iter().map(|l| format!("{}\n", l)).collect();
+        let written = match path.parent() {
+            Some(dir) => std::fs::create_dir_all(dir).and_then(|_| std::fs::write(path, text)),
+            None => std::fs::write(path, text),
+        };
+        if let Err(e) = written {
+            eprintln!("Error writing {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Directory for the front-end's own files: `$XDG_CONFIG_HOME/rustris` (or
+/// `~/.config/rustris`), `%APPDATA%\rustris` on Windows and
+/// `~/Library/Application Support/rustris` on macOS. `None` if the environment doesn't
+/// say where home is.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        }
+    };
+    Some(base.join("rustris"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(config: &Config) -> String {
+        config.lines.join("\n")
+    }
+
+    #[test]
+    fn values_round_trip_through_toml() {
+        let values = [
+            Value::Str(String::from(r#"quote " backslash \ tab \t"#)),
+            Value::Str(String::from("new\nline\tand tab")),
+            Value::Int(-42),
+            Value::Bool(true),
+            Value::Array(vec![Value::Str(String::from("S=A")), Value::Str(String::from("a, \"b\"")), Value::Int(7)]),
+        ];
+        for value in values {
+            assert_eq!(Value::parse(&value.to_toml()), Some(value));
+        }
+        assert_eq!(Value::parse(r#""bad \q escape""#), None);
+        assert_eq!(Value::parse("1_000"), Some(Value::Int(1000)));
+    }
+
+    #[test]
+    fn hash_inside_a_string_is_not_a_comment() {
+        let config = Config::parse(None, "[video]\nshader = \"crt#2.glsl\"  # the # here is a comment\n").unwrap();
+        assert_eq!(config.get("video", "shader"), Some(&Value::Str(String::from("crt#2.glsl"))));
+    }
+
+    #[test]
+    fn arrays_take_a_trailing_comma() {
+        let config = Config::parse(None, "[input]\nturbo = [\"S=A\", \"A=B\",]\n").unwrap();
+        let expected = Value::Array(vec![Value::Str(String::from("S=A")), Value::Str(String::from("A=B"))]);
+        assert_eq!(config.get("input", "turbo"), Some(&expected));
+        assert_eq!(Value::parse("[]"), Some(Value::Array(Vec::new())));
+        assert_eq!(Value::parse(r#"["open]"#), None);
+    }
+
+    #[test]
+    fn set_replaces_the_templates_commented_out_line() {
+        let mut config = Config::parse(None, TEMPLATE).unwrap();
+        config.set("emulation", "speed", Value::Int(150));
+        let written = text(&config);
+        // The comment stays lined up with the others
+        assert!(written.contains("\nspeed = 150                  # Percent, 25–400\n"), "{}", written);
+        assert!(written.contains("\n# speed_step = 25"), "speed must not match speed_step");
+        assert_eq!(written.lines().count(), TEMPLATE.lines().count());
+
+        let reread = Config::parse(None, &written).unwrap();
+        assert_eq!(reread.get("emulation", "speed"), Some(&Value::Int(150)));
+        assert_eq!(reread.get("emulation", "speed_step"), None);
+    }
+
+    #[test]
+    fn set_keeps_the_comment_of_an_existing_line() {
+        let mut config = Config::parse(None, "[video]\nzoom = 3     # big enough\npalette = \"green\"\n").unwrap();
+        config.set("video", "zoom", Value::Int(5));
+        config.set("video", "palette", Value::Str(String::from("a much longer palette name")));
+        assert_eq!(text(&config), "[video]\nzoom = 5     # big enough\npalette = \"a much longer palette name\"");
+
+        config.set("video", "zoom", Value::Str(String::from("longer than the column")));
+        assert_eq!(config.lines[1], "zoom = \"longer than the column\" # big enough");
+    }
+
+    #[test]
+    fn set_adds_missing_keys_and_sections() {
+        let mut config = Config::parse(None, "[video]\nzoom = 3\n\n[paths]\nrom = \"tetris.gb\"\n").unwrap();
+        config.set("video", "hud", Value::Bool(true));
+        config.set("debug", "assert", Value::Array(vec![Value::Str(String::from("C0DE <= 14"))]));
+        assert_eq!(
+            text(&config),
+            "[video]\nzoom = 3\nhud = true\n\n[paths]\nrom = \"tetris.gb\"\n\n[debug]\nassert = [\"C0DE <= 14\"]"
+        );
+
+        // Later sets find the lines they added
+        config.set("video", "zoom", Value::Int(2));
+        let reread = Config::parse(None, &text(&config)).unwrap();
+        assert_eq!(reread.get("video", "zoom"), Some(&Value::Int(2)));
+        assert_eq!(reread.get("video", "hud"), Some(&Value::Bool(true)));
+        assert_eq!(reread.get("debug", "assert"), config.get("debug", "assert"));
+    }
+}
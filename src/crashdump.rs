@@ -0,0 +1,91 @@
+use crate::error::Error;
+use crate::trace;
+
+use rustris_core::GB;
+
+use std::fmt::Write as _;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes a crash report for a machine that can't go on and returns where it went:
+/// - Why it stopped, the model and the profile.
+/// - The CPU registers and the last instructions executed, with the registers before each
+///   (see `trace::history_line`).
+/// - The I/O registers (FF00–FF7F and IE) and a hex dump of RAM (8000–FFFF).
+///
+/// The file is `rustris-crash-<unix time>.txt` in the current directory.
+pub fn write(gb: &GB, reason: &str) -> std::io::Result<PathBuf> {
+    let mut memory = vec![0u8; 0x10000];
+    gb.read_memory(&mut memory);
+    let r = gb.registers();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "RUSTЯIS crash dump");
+    let _ = writeln!(out, "Reason: {}", reason);
+    let _ = writeln!(out, "Model: {:?}, profile: {:?}\n", gb.model(), gb.profile());
+
+    let _ = writeln!(out, "Registers");
+    let _ = writeln!(
+        out,
+        "  AF={:02X}{:02X} BC={:02X}{:02X} DE={:02X}{:02X} HL={:02X}{:02X} SP={:04X} PC={:04X}\n",
+        r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, r.pc,
+    );
+
+    let _ = writeln!(out, "Last instructions (oldest first)");
+    for entry in gb.trace() {
+        let _ = writeln!(out, "  {}", trace::history_line(gb, &entry));
+    }
+
+    let _ = writeln!(out, "\nI/O registers");
+    hex_dump(&mut out, &memory, 0xFF00..0xFF80);
+    let _ = writeln!(out, "  FFFF: {:02X}", memory[0xFFFF]);
+
+    let _ = writeln!(out, "\nMemory");
+    hex_dump(&mut out, &memory, 0x8000..0x10000);
+
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = PathBuf::from(format!("rustris-crash-{}.txt", time));
+    std::fs::write(&path, out)?;
+    Ok(path)
+}
+
+fn hex_dump(out: &mut String, memory: &[u8], range: std::ops::Range<usize>) {
+    for row in range.step_by(16) {
+        let bytes: Vec<String> = memory[row..row + 16].iter().map(|b| format!("{:02X}", b)).collect();
+        let _ = writeln!(out, "  {:04X}: {}", row, bytes.join(" "));
+    }
+}
+
+/// Writes a crash dump for a machine that stopped on `reason` (see `write`) and tells the
+/// user where it is. The caller reports the reason itself.
+pub fn report(gb: &GB, reason: &str) {
+    match write(gb, reason) {
+        Ok(path) => eprintln!("Crash dump written to {}", path.display()),
+        Err(e) => eprintln!("Error writing crash dump: {}", e),
+    }
+}
+
+/// Reports a fatal emulation error: prints `reason`, writes a crash dump (see `report`) and
+/// ends the process with status 1. Only for the modes that run on the main thread and have
+/// nothing to flush on the way out (traces, `--versus`, netplay, `--gbs`); the window and
+/// headless runs stop and shut down properly instead.
+pub fn fatal(gb: &GB, reason: &str) -> ! {
+    eprintln!("{}", reason);
+    report(gb, reason);
+    std::process::exit(1);
+}
+
+/// Runs `f` on the machine, turning a panic inside it (a broken invariant in the core) into
+/// `Error::Panic`; the machine is left as the panic found it, for the crash dump. The panic
+/// message itself has already been printed by the default hook.
+pub fn guard<R>(gb: &mut GB, f: impl FnOnce(&mut GB) -> R) -> Result<R, Error> {
+    panic::catch_unwind(AssertUnwindSafe(|| f(&mut *gb))).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Error::Panic(message)
+    })
+}
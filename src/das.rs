@@ -0,0 +1,69 @@
+use rustris_core::gb;
+
+/// Delayed auto-shift timing, in frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DasTiming {
+    pub delay: u32,  // From the first pulse to the second
+    pub repeat: u32, // Between later pulses
+}
+
+impl Default for DasTiming {
+    fn default() -> Self {
+        Self { delay: 16, repeat: 6 }
+    }
+}
+
+/// Delayed auto-shift input layer: held Left and Right reach the game as a series of
+/// one-frame presses instead of a steady hold, so the player picks how fast pieces (or
+/// anything else) move sideways rather than the game's own auto-repeat.
+///
+/// - A direction is pressed on the frame its key goes down, again `delay` frames later
+///   and then every `repeat` frames while it's held; it reads as released in between, so
+///   the game sees each pulse as a fresh tap.
+/// - Left and Right are timed apart, and only the regular keys are shaped: turbo, scripts,
+///   the autoplayer and demos pass through unchanged.
+/// - Off (no timing), held directions pass straight through.
+pub struct Das {
+    timing: Option<DasTiming>,
+    held_for: [u32; 2], // Whole frames Left and Right have been held so far
+    last: u8,           // The keys held when the last frame ended
+}
+
+/// The directions shaped, in `held_for` order.
+const DIRECTIONS: [u8; 2] = [gb::BTN_LEFT, gb::BTN_RIGHT];
+
+impl Das {
+    pub fn new(timing: Option<DasTiming>) -> Self {
+        Self { timing, held_for: [0; 2], last: 0 }
+    }
+
+    /// Changes the timing, or turns shaping off with `None`.
+    pub fn set_timing(&mut self, timing: Option<DasTiming>) {
+        self.timing = timing;
+    }
+
+    /// `held` (the buttons held through the keys) with Left and Right as they read this
+    /// frame.
+    pub fn shape(&self, held: u8) -> u8 {
+        let Some(timing) = self.timing else { return held };
+        let mut buttons = held & !(gb::BTN_LEFT | gb::BTN_RIGHT);
+        for (&direction, &frames) in DIRECTIONS.iter().zip(&self.held_for) {
+            let pulse = frames == 0 || (frames >= timing.delay && (frames - timing.delay).is_multiple_of(timing.repeat));
+            if held & direction != 0 && pulse {
+                buttons |= direction;
+            }
+        }
+        buttons
+    }
+
+    /// Advances by one frame, `held` being the buttons held through the keys. A direction
+    /// pressed during the frame only starts counting with the next one, so the first pulse
+    /// isn't lost if the game read the joypad before the key went down.
+    pub fn tick(&mut self, held: u8) {
+        let throughout = held & self.last;
+        for (&direction, frames) in DIRECTIONS.iter().zip(&mut self.held_for) {
+            *frames = if throughout & direction != 0 { frames.saturating_add(1) } else { 0 };
+        }
+        self.last = held;
+    }
+}
@@ -0,0 +1,89 @@
+use crate::debugger::DebugCommand;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Debugger state saved per game next to the ROM (`<rom>.dbg`) whenever it changes, and
+/// given back the next time that game is debugged:
+/// - The breakpoints, kept in step with the commands sent to the debugger (see `apply`).
+/// - Which sections of the debugger window are unfolded.
+///
+/// The file has one entry per line: `breakpoint ADDR` with the address in hex, or
+/// `open NAME` / `closed NAME` for a section of the window. `#` starts a comment.
+pub struct DebugSession {
+    breakpoints: BTreeSet<u16>,
+    sections: BTreeMap<String, bool>, // Section name, unfolded
+    restored: bool,                   // The breakpoints have been handed to the debugger
+    path: PathBuf,
+}
+
+impl DebugSession {
+    /// The session saved for the ROM at `rom_path`, or an empty one that will be saved there.
+    pub fn load(rom_path: &str) -> Self {
+        let path = Path::new(rom_path).with_extension("dbg");
+        let mut session = Self { breakpoints: BTreeSet::new(), sections: BTreeMap::new(), restored: false, path };
+        let Ok(text) = std::fs::read_to_string(&session.path) else { return session };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let valid = match (key, value.trim()) {
+                ("breakpoint", addr) => u16::from_str_radix(addr, 16).map(|addr| session.breakpoints.insert(addr)).is_ok(),
+                ("open" | "closed", name) if !name.is_empty() => {
+                    session.sections.insert(name.to_string(), key == "open");
+                    true
+                }
+                _ => false,
+            };
+            if !valid {
+                eprintln!("{}:{}: invalid debugger session entry: {}", session.path.display(), n + 1, line);
+            }
+        }
+        session
+    }
+
+    /// The saved breakpoints the first time the game is debugged; none after that, since
+    /// the debugger has them by then.
+    pub fn restore_breakpoints(&mut self) -> BTreeSet<u16> {
+        if std::mem::replace(&mut self.restored, true) { BTreeSet::new() } else { self.breakpoints.clone() }
+    }
+
+    /// Follows a command on its way to the debugger, saving the breakpoints it changes.
+    pub fn apply(&mut self, command: &DebugCommand) {
+        match command {
+            DebugCommand::ToggleBreakpoint(addr) => {
+                if !self.breakpoints.remove(addr) {
+                    self.breakpoints.insert(*addr);
+                }
+            }
+            DebugCommand::Detach if !self.breakpoints.is_empty() => self.breakpoints.clear(),
+            _ => return,
+        }
+        self.save();
+    }
+
+    /// The debugger window's sections as they were left (see `DebugWindow::set_layout`).
+    pub fn sections(&self) -> &BTreeMap<String, bool> {
+        &self.sections
+    }
+
+    pub fn set_sections(&mut self, sections: BTreeMap<String, bool>) {
+        self.sections = sections;
+        self.save();
+    }
+
+    fn save(&self) {
+        let mut text = String::new();
+        for addr in &self.breakpoints {
+            text += &format!("breakpoint {:04X}\n", addr);
+        }
+        for (name, open) in &self.sections {
+            text += &format!("{} {}\n", if *open { "open" } else { "closed" }, name);
+        }
+        if let Err(e) = std::fs::write(&self.path, text) {
+            eprintln!("Error writing {}: {}", self.path.display(), e);
+        }
+    }
+}
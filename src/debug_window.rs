@@ -0,0 +1,662 @@
+use crate::debugger::{DebugCommand, Snapshot};
+use crate::ioregs;
+use crate::serial::SerialConsole;
+
+use egui::{Color32, RichText};
+use rustris_core::{memory_region, Dma, Interrupt};
+
+use std::collections::BTreeMap;
+use std::ops::{Range, RangeInclusive};
+
+/// Instructions already executed shown above PC, and instructions shown from PC on.
+const HISTORY_SHOWN: usize = 4;
+const AHEAD_SHOWN: usize = 16;
+
+/// Tiles in VRAM (8000–97FF) and how the viewer lays them out.
+const TILES: usize = 384;
+const TILES_PER_ROW: usize = 16;
+
+/// Shades of the tile viewer for DMG colors 0–3, after BGP.
+const SHADES: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+
+/// Dots per line and lines per frame, drawn 1 and 2 pixels wide in the PPU timing diagram.
+const LINE_DOTS: u16 = 456;
+const FRAME_LINES: u8 = 154;
+const DOT_WIDTH: f32 = 1.0;
+const LINE_HEIGHT: f32 = 2.0;
+
+/// Colors of the PPU's modes 0–3 in the timing diagram, and its names for them.
+const MODES: [(Color32, &str); 4] = [
+    (Color32::from_rgb(70, 70, 70), "HBlank"),
+    (Color32::from_rgb(80, 90, 150), "VBlank"),
+    (Color32::from_rgb(70, 140, 70), "OAM scan"),
+    (Color32::from_rgb(210, 150, 40), "Pixel transfer"),
+];
+
+/// Memory the editor shows, one region at a time (the banks mapped in, on CGB).
+const MEMORY_REGIONS: [(&str, RangeInclusive<u16>); 4] =
+    [("VRAM", 0x8000..=0x9FFF), ("WRAM", 0xC000..=0xDFFF), ("OAM", 0xFE00..=0xFE9F), ("HRAM", 0xFF80..=0xFFFE)];
+
+/// Debugger window (F5): the graphical counterpart of the terminal debugger (see `Tui`).
+/// - Continue / Stop / Step / Step back, the registers and the code around PC; clicking a
+///   line of code sets or clears a breakpoint there, and addresses can be typed in too.
+/// - The I/O registers, a trace of the writes to those chosen (see `IoTrace`), the
+///   interrupt log, the DMA log (with transfers that look wrong flagged), a timing diagram
+///   of the last frame and the sprite table (OAM).
+/// - A hex editor for VRAM, work RAM, OAM and high RAM (see `MemoryEditor`).
+/// - The serial console: what the game has sent over the link port.
+/// - Buttons queue commands for the emulation thread, taken with `take_commands`.
+/// - The sections unfolded are kept per game (see `DebugSession`), through `set_layout`
+///   and `take_layout`.
+pub struct DebugWindow {
+    breakpoint: String, // Text of the breakpoint field
+    back: u64,          // Instructions Step back goes back
+    error: Option<String>,
+    serial: SerialConsole,
+    memory: MemoryEditor,
+    io_trace: IoTrace,
+    layout: Layout,
+    commands: Vec<DebugCommand>,
+}
+
+/// Hex editor: a region of memory 16 bytes a row, live while the game runs. Clicking a
+/// byte opens it for typing; Enter writes the new value through the bus (see
+/// `DebugCommand::Write`), so the game sees it like one of its own stores, and Escape or
+/// clicking elsewhere drops it.
+#[derive(Default)]
+struct MemoryEditor {
+    region: usize,                  // Index in `MEMORY_REGIONS`
+    editing: Option<(u16, String)>, // The byte open for typing and the text typed
+    focus: bool,                    // The field was just opened and needs the keyboard
+}
+
+/// Trace of the writes to a few I/O registers, lighter than watching memory and handy for
+/// following how a game sets up the display. The filter names the registers (`LCDC SCX BGP`,
+/// or hex addresses for those the window doesn't list); Enter starts tracing them (see
+/// `DebugCommand::TraceIo`) and an empty filter stops. Writes are listed newest first, with
+/// the frame (counted from when tracing started), line, dot and the instruction that made
+/// them.
+#[derive(Default)]
+struct IoTrace {
+    filter: String,
+    error: Option<String>, // What's wrong with the filter last entered
+}
+
+/// Which of the window's collapsing sections are unfolded.
+#[derive(Default)]
+struct Layout {
+    sections: BTreeMap<String, bool>, // Those not as they start by default: name, unfolded
+    generation: u32,                  // Bumped by `set_layout`, so egui forgets the last game's folds
+    changed: bool,
+}
+
+impl Layout {
+    /// A collapsing section, unfolded as it was left (`default_open` if it never was
+    /// changed). `body` gets the layout back for sections within it.
+    fn section(&mut self, ui: &mut egui::Ui, name: &str, default_open: bool, body: impl FnOnce(&mut egui::Ui, &mut Layout)) {
+        let was_open = self.sections.get(name).copied().unwrap_or(default_open);
+        let response = egui::CollapsingHeader::new(name)
+            .id_salt((name, self.generation))
+            .default_open(was_open)
+            .show(ui, |ui| body(ui, self));
+        // The body is shown until a section has finished folding
+        let open = response.body_response.is_some();
+        if open != was_open {
+            if open == default_open {
+                self.sections.remove(name);
+            } else {
+                self.sections.insert(name.to_string(), open);
+            }
+            self.changed = true;
+        }
+    }
+}
+
+impl DebugWindow {
+    pub fn new(serial: SerialConsole) -> Self {
+        Self {
+            breakpoint: String::new(),
+            back: 1,
+            error: None,
+            serial,
+            memory: MemoryEditor::default(),
+            io_trace: IoTrace::default(),
+            layout: Layout::default(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Commands from the window since the last call.
+    pub fn take_commands(&mut self) -> Vec<DebugCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// Folds and unfolds the sections as a saved session left them: name, unfolded. The
+    /// others start as they do by default.
+    pub fn set_layout(&mut self, sections: BTreeMap<String, bool>) {
+        self.layout.sections = sections;
+        self.layout.generation += 1;
+        self.layout.changed = false;
+    }
+
+    /// The sections left unfolded or folded against their default, if that has changed
+    /// since the last call.
+    pub fn take_layout(&mut self) -> Option<BTreeMap<String, bool>> {
+        std::mem::take(&mut self.layout.changed).then(|| self.layout.sections.clone())
+    }
+
+    /// The debugger, filling its window. `snapshot` is the latest copy from the emulation
+    /// thread.
+    pub fn show(&mut self, ctx: &egui::Context, snapshot: Option<&Snapshot>) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let Some(snapshot) = snapshot else {
+                    ui.label("Waiting for the emulator...");
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    if snapshot.stopped {
+                        match &snapshot.reason {
+                            Some(reason) => ui.colored_label(Color32::RED, format!("Stopped: {}", reason)),
+                            None => ui.colored_label(Color32::RED, "Stopped"),
+                        };
+                        if ui.button("Continue").clicked() {
+                            self.commands.push(DebugCommand::Continue);
+                        }
+                        if ui.button("Step").clicked() {
+                            self.commands.push(DebugCommand::Step);
+                        }
+                        if ui.button("Step back").on_hover_text("Replays from the last rewind snapshot before then").clicked() {
+                            self.commands.push(DebugCommand::StepBack(self.back));
+                        }
+                        ui.add(egui::DragValue::new(&mut self.back).range(1..=1_000_000).suffix(" instructions"));
+                    } else {
+                        ui.label("Running");
+                        if ui.button("Stop").clicked() {
+                            self.commands.push(DebugCommand::Break);
+                        }
+                    }
+                });
+                ui.separator();
+
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| registers(ui, snapshot));
+                    ui.separator();
+                    ui.vertical(|ui| self.code(ui, snapshot));
+                });
+                ui.separator();
+                self.breakpoints(ui, snapshot);
+
+                let Self { layout, serial, memory, io_trace, commands, .. } = self;
+                layout.section(ui, "I/O registers", false, |ui, layout| io_registers(ui, layout, snapshot));
+                layout.section(ui, "I/O writes", false, |ui, _| io_trace.show(ui, snapshot, commands));
+                layout.section(ui, "Interrupts", false, |ui, _| interrupts(ui, snapshot));
+                layout.section(ui, "DMA", false, |ui, _| dma(ui, snapshot));
+                layout.section(ui, "PPU timing", false, |ui, _| ppu_timing(ui, snapshot));
+                layout.section(ui, "OAM", false, |ui, _| oam(ui, snapshot));
+                layout.section(ui, "Memory", false, |ui, _| memory.show(ui, snapshot, commands));
+                layout.section(ui, "Serial output", false, |ui, _| serial_console(ui, serial));
+            });
+        });
+    }
+
+    fn code(&mut self, ui: &mut egui::Ui, snapshot: &Snapshot) {
+        for line in snapshot.code(HISTORY_SHOWN, AHEAD_SHOWN) {
+            let marker = if line.breakpoint { '●' } else { ' ' };
+            let mut text = RichText::new(format!("{} {:04X}  {:<9} {}", marker, line.addr, line.bytes, line.text)).monospace();
+            if line.current {
+                text = text.background_color(ui.visuals().selection.bg_fill);
+            } else if line.executed {
+                text = text.weak();
+            }
+            if line.breakpoint {
+                text = text.color(Color32::RED);
+            }
+            let label = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+            if label.on_hover_text("Set or clear a breakpoint").clicked() {
+                self.commands.push(DebugCommand::ToggleBreakpoint(line.addr));
+            }
+        }
+    }
+
+    fn breakpoints(&mut self, ui: &mut egui::Ui, snapshot: &Snapshot) {
+        ui.horizontal(|ui| {
+            ui.label("Breakpoint at");
+            let field = ui.add(egui::TextEdit::singleline(&mut self.breakpoint).desired_width(48.0));
+            let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Set / clear").clicked() || entered {
+                match parse_addr(&self.breakpoint) {
+                    Some(addr) => {
+                        self.commands.push(DebugCommand::ToggleBreakpoint(addr));
+                        self.breakpoint.clear();
+                        self.error = None;
+                    }
+                    None => self.error = Some(format!("Not an address: {}", self.breakpoint)),
+                }
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+        ui.horizontal_wrapped(|ui| {
+            for &addr in &snapshot.breakpoints {
+                if ui.small_button(format!("{:04X} ✕", addr)).on_hover_text("Clear").clicked() {
+                    self.commands.push(DebugCommand::ToggleBreakpoint(addr));
+                }
+            }
+        });
+    }
+
+}
+
+/// Tile viewer (Shift+F5): VRAM's tiles (8000–97FF) in rows of `TILES_PER_ROW`, shaded
+/// through BGP, scaled up to fill its window by whole pixels. Hovering a tile names it.
+#[derive(Default)]
+pub struct TileViewer {
+    texture: Option<egui::TextureHandle>, // Kept between frames
+}
+
+impl TileViewer {
+    /// The tiles, filling their window. `snapshot` is the latest copy from the emulation
+    /// thread.
+    pub fn show(&mut self, ctx: &egui::Context, snapshot: Option<&Snapshot>) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(snapshot) = snapshot else {
+                ui.label("Waiting for the emulator...");
+                return;
+            };
+            let (w, h) = (TILES_PER_ROW * 8, TILES / TILES_PER_ROW * 8);
+            let bgp = snapshot.byte(0xFF47);
+            let mut rgb = vec![0u8; w * h * 3];
+            for tile in 0..TILES {
+                let (tx, ty) = (tile % TILES_PER_ROW * 8, tile / TILES_PER_ROW * 8);
+                for row in 0..8 {
+                    let addr = tile_addr(tile) + row as u16 * 2;
+                    let (low, high) = (snapshot.byte(addr), snapshot.byte(addr + 1));
+                    for col in 0..8 {
+                        let bit = 7 - col;
+                        let color = ((high >> bit) & 1) << 1 | ((low >> bit) & 1);
+                        let shade = SHADES[((bgp >> (color * 2)) & 3) as usize];
+                        let i = ((ty + row) * w + tx + col) * 3;
+                        rgb[i..i + 3].fill(shade);
+                    }
+                }
+            }
+            let image = egui::ColorImage::from_rgb([w, h], &rgb);
+            let texture = match &mut self.texture {
+                Some(texture) => {
+                    texture.set(image, egui::TextureOptions::NEAREST);
+                    texture
+                }
+                None => self.texture.insert(ctx.load_texture("vram_tiles", image, egui::TextureOptions::NEAREST)),
+            };
+
+            let available = ui.available_size();
+            let scale = (available.x / w as f32).min(available.y / h as f32).floor().max(1.0);
+            let image = ui.image((texture.id(), egui::vec2(w as f32, h as f32) * scale));
+            if let Some(pos) = image.hover_pos() {
+                let at = (pos - image.rect.min) / (8.0 * scale);
+                let tile = at.y as usize * TILES_PER_ROW + at.x as usize;
+                if tile < TILES {
+                    image.on_hover_text(format!("Tile {} at {:04X}", tile, tile_addr(tile)));
+                }
+            }
+        });
+    }
+}
+
+/// Where tile `n` of the viewer starts in VRAM.
+fn tile_addr(n: usize) -> u16 {
+    0x8000 + n as u16 * 16
+}
+
+impl MemoryEditor {
+    fn show(&mut self, ui: &mut egui::Ui, snapshot: &Snapshot, commands: &mut Vec<DebugCommand>) {
+        ui.horizontal(|ui| {
+            for (i, (name, _)) in MEMORY_REGIONS.iter().enumerate() {
+                ui.selectable_value(&mut self.region, i, *name);
+            }
+        });
+        let range = MEMORY_REGIONS[self.region].1.clone();
+        let (start, len) = (*range.start(), range.len());
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical().id_salt(("debug_memory", self.region)).max_height(240.0).show_rows(
+            ui,
+            row_height,
+            len.div_ceil(16),
+            |ui, rows| {
+                for row in rows {
+                    let addr = start + row as u16 * 16;
+                    ui.horizontal(|ui| {
+                        ui.monospace(format!("{:04X}", addr));
+                        for i in 0..(len - row * 16).min(16) as u16 {
+                            self.byte(ui, snapshot, addr + i, commands);
+                        }
+                    });
+                }
+            },
+        );
+    }
+
+    fn byte(&mut self, ui: &mut egui::Ui, snapshot: &Snapshot, addr: u16, commands: &mut Vec<DebugCommand>) {
+        if let Some((at, text)) = &mut self.editing
+            && *at == addr
+        {
+            let field = ui.add(
+                egui::TextEdit::singleline(text).font(egui::TextStyle::Monospace).char_limit(2).desired_width(row_width(ui, 2)),
+            );
+            if std::mem::take(&mut self.focus) {
+                field.request_focus();
+            }
+            if field.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Enter))
+                    && let Ok(value) = u8::from_str_radix(text.trim(), 16)
+                {
+                    commands.push(DebugCommand::Write(addr, value));
+                }
+                self.editing = None;
+            }
+            return;
+        }
+        let value = snapshot.byte(addr);
+        let label = ui.add(egui::Label::new(RichText::new(format!("{:02X}", value)).monospace()).sense(egui::Sense::click()));
+        if label.on_hover_text(format!("{:04X}: click to change", addr)).clicked() {
+            self.editing = Some((addr, format!("{:02X}", value)));
+            self.focus = true;
+        }
+    }
+}
+
+impl IoTrace {
+    fn show(&mut self, ui: &mut egui::Ui, snapshot: &Snapshot, commands: &mut Vec<DebugCommand>) {
+        ui.horizontal(|ui| {
+            ui.label("Trace");
+            let field = ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("LCDC SCX BGP").desired_width(200.0));
+            if field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match parse_registers(&self.filter) {
+                    Ok(registers) => {
+                        commands.push(DebugCommand::TraceIo(registers));
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+        if snapshot.io_writes.is_empty() {
+            ui.label("No writes traced yet");
+            return;
+        }
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            egui::Grid::new("debug_io_writes").striped(true).show(ui, |ui| {
+                for heading in ["Frame", "LY", "Dot", "PC", "Register", "Value", ""] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+                for write in snapshot.io_writes.iter().rev() {
+                    let register = ioregs::at(write.addr);
+                    ui.monospace(write.frame.to_string());
+                    ui.monospace(write.ly.to_string());
+                    ui.monospace(write.dot.to_string());
+                    ui.monospace(format!("{:04X}", write.pc)).on_hover_text("Instruction that wrote it");
+                    ui.strong(register.map_or_else(|| format!("{:04X}", write.addr), |register| register.name.to_string()));
+                    ui.monospace(format!("{:02X}", write.value));
+                    ui.label(register.map_or_else(String::new, |register| (register.decode)(write.value)));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+/// The I/O registers named in `text`, by name or hex address, separated by spaces or commas.
+fn parse_registers(text: &str) -> Result<Vec<u16>, String> {
+    let mut registers = Vec::new();
+    for word in text.split([' ', ',']).filter(|word| !word.is_empty()) {
+        let addr = match ioregs::named(word) {
+            Some(register) => register.addr,
+            None => parse_addr(word)
+                .filter(|&addr| (0xFF00..=0xFF7F).contains(&addr) || addr == 0xFFFF)
+                .ok_or_else(|| format!("Not an I/O register: {}", word))?,
+        };
+        if !registers.contains(&addr) {
+            registers.push(addr);
+        }
+    }
+    Ok(registers)
+}
+
+/// Width of `chars` monospace characters.
+fn row_width(ui: &egui::Ui, chars: usize) -> f32 {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    ui.fonts_mut(|fonts| fonts.glyph_width(&font, '0')) * chars as f32
+}
+
+/// The serial console, kept scrolled to the newest output.
+fn serial_console(ui: &mut egui::Ui, serial: &SerialConsole) {
+    let text = serial.text();
+    if ui.button("Clear").clicked() {
+        serial.clear();
+    }
+    egui::ScrollArea::vertical().max_height(240.0).stick_to_bottom(true).show(ui, |ui| {
+        if text.is_empty() {
+            ui.weak("Nothing sent yet");
+        } else {
+            ui.monospace(text);
+        }
+    });
+}
+
+/// CPU registers and flags.
+fn registers(ui: &mut egui::Ui, snapshot: &Snapshot) {
+    let r = snapshot.registers;
+    let flag = |bit: u8, name: char| if r.f & bit != 0 { name } else { '-' };
+    egui::Grid::new("debug_registers").show(ui, |ui| {
+        for (name, value) in [
+            ("AF", u16::from_be_bytes([r.a, r.f])),
+            ("BC", u16::from_be_bytes([r.b, r.c])),
+            ("DE", u16::from_be_bytes([r.d, r.e])),
+            ("HL", u16::from_be_bytes([r.h, r.l])),
+            ("SP", r.sp),
+            ("PC", r.pc),
+        ] {
+            ui.strong(name);
+            ui.monospace(format!("{:04X}", value));
+            ui.end_row();
+        }
+        ui.strong("Flags");
+        ui.monospace(format!("{}{}{}{}", flag(0x80, 'Z'), flag(0x40, 'N'), flag(0x20, 'H'), flag(0x10, 'C')));
+        ui.end_row();
+    });
+}
+
+/// `ioregs::GROUPS`, each register with its value and what its bits say. The sound
+/// registers start folded.
+fn io_registers(ui: &mut egui::Ui, layout: &mut Layout, snapshot: &Snapshot) {
+    for (i, (group, registers)) in ioregs::GROUPS.iter().enumerate() {
+        layout.section(ui, group, !group.starts_with("Sound"), |ui, _| {
+            egui::Grid::new(("debug_io", i)).striped(true).show(ui, |ui| {
+                for register in *registers {
+                    let value = snapshot.byte(register.addr);
+                    ui.strong(register.name).on_hover_text(format!("{:04X}", register.addr));
+                    ui.monospace(format!("{:02X}", value));
+                    ui.monospace(format!("{:08b}", value));
+                    ui.label((register.decode)(value));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+/// The interrupt log, newest first. Cycles count T-cycles from when the window was opened;
+/// a dispatch's wait is the T-cycles since its request.
+fn interrupts(ui: &mut egui::Ui, snapshot: &Snapshot) {
+    let entries = snapshot.interrupts();
+    if entries.is_empty() {
+        ui.label("No interrupts yet");
+        return;
+    }
+    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+        egui::Grid::new("debug_interrupts").striped(true).show(ui, |ui| {
+            for heading in ["Cycle", "Interrupt", "", "LY", "Dot", "PC", "Waited"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for (record, waited) in entries {
+                ui.monospace(record.cycle.to_string());
+                ui.label(format!("{:?}", record.interrupt));
+                if record.dispatched {
+                    ui.label("dispatched");
+                } else {
+                    ui.weak("requested");
+                }
+                ui.monospace(record.ly.to_string());
+                ui.monospace(record.dot.to_string());
+                ui.monospace(format!("{:04X}", record.pc))
+                    .on_hover_text(if record.dispatched { "Instruction interrupted" } else { "Instruction that requested it" });
+                ui.monospace(waited.map_or_else(String::new, |t| t.to_string()));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// The DMA log, newest first. Frames count from when the window was opened; a transfer that
+/// looks wrong is flagged in red (see `DmaRecord::warnings`).
+fn dma(ui: &mut egui::Ui, snapshot: &Snapshot) {
+    if snapshot.dma.is_empty() {
+        ui.label("No DMA transfers yet");
+        return;
+    }
+    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+        egui::Grid::new("debug_dma").striped(true).show(ui, |ui| {
+            for heading in ["Frame", "DMA", "Source", "", "To", "Bytes", "LY", "Dot", "Mode", "PC", ""] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for record in snapshot.dma.iter().rev() {
+                ui.monospace(record.frame.to_string());
+                ui.label(match record.dma {
+                    Dma::Oam => "OAM",
+                    Dma::General => "VRAM",
+                    Dma::HBlank => "VRAM (HBlank)",
+                });
+                ui.monospace(format!("{:04X}", record.source));
+                ui.weak(memory_region(record.source));
+                ui.monospace(format!("{:04X}", record.destination));
+                ui.monospace(record.length.to_string());
+                ui.monospace(record.ly.to_string());
+                ui.monospace(record.dot.to_string());
+                ui.monospace(record.mode.to_string());
+                ui.monospace(format!("{:04X}", record.pc)).on_hover_text("Instruction that started it");
+                ui.colored_label(Color32::RED, record.warnings().join("; "));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Timing diagram of the last frame: a row per line, a pixel per dot, colored by the PPU's
+/// mode, with a dot where it requested each STAT (red) and VBlank (yellow) interrupt.
+/// Hovering a line describes it.
+fn ppu_timing(ui: &mut egui::Ui, snapshot: &Snapshot) {
+    let Some(timing) = &snapshot.timing else {
+        ui.label("No whole frame yet (is the LCD off?)");
+        return;
+    };
+    let size = egui::vec2(LINE_DOTS as f32 * DOT_WIDTH, FRAME_LINES as f32 * LINE_HEIGHT);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let origin = response.rect.min;
+    let area = |lines: Range<u8>, dots: Range<u16>| {
+        egui::Rect::from_min_max(
+            origin + egui::vec2(dots.start as f32 * DOT_WIDTH, lines.start as f32 * LINE_HEIGHT),
+            origin + egui::vec2(dots.end as f32 * DOT_WIDTH, lines.end as f32 * LINE_HEIGHT),
+        )
+    };
+    for &(ly, hblank) in &timing.lines {
+        painter.rect_filled(area(ly..ly + 1, 0..80), 0.0, MODES[2].0);
+        painter.rect_filled(area(ly..ly + 1, 80..hblank), 0.0, MODES[3].0);
+        painter.rect_filled(area(ly..ly + 1, hblank..LINE_DOTS), 0.0, MODES[0].0);
+    }
+    painter.rect_filled(area(144..FRAME_LINES, 0..LINE_DOTS), 0.0, MODES[1].0);
+    for &(ly, dot, interrupt) in &timing.interrupts {
+        let color = if interrupt == Interrupt::VBlank { Color32::YELLOW } else { Color32::RED };
+        painter.circle_filled(area(ly..ly + 1, dot..dot).center(), LINE_HEIGHT, color);
+    }
+
+    if let Some(pos) = response.hover_pos() {
+        let ly = ((pos.y - origin.y) / LINE_HEIGHT).clamp(0.0, FRAME_LINES as f32 - 1.0) as u8;
+        let mut text = match timing.lines.iter().find(|&&(line, _)| line == ly) {
+            Some(&(_, hblank)) => {
+                format!("LY {}: OAM scan 0–79, pixel transfer 80–{} ({} dots), HBlank from {}", ly, hblank - 1, hblank - 80, hblank)
+            }
+            None if ly >= 144 => format!("LY {}: VBlank", ly),
+            None => format!("LY {}: not drawn", ly),
+        };
+        for &(_, dot, interrupt) in timing.interrupts.iter().filter(|&&(line, ..)| line == ly) {
+            text += &format!("\n{:?} interrupt requested at dot {}", interrupt, dot);
+        }
+        response.on_hover_text_at_pointer(text);
+    }
+
+    ui.horizontal(|ui| {
+        for (color, name) in MODES {
+            ui.colored_label(color, "■");
+            ui.label(name);
+        }
+        ui.colored_label(Color32::RED, "●");
+        ui.label("STAT");
+        ui.colored_label(Color32::YELLOW, "●");
+        ui.label("VBlank");
+    });
+    if let (Some(shortest), Some(longest)) =
+        (timing.lines.iter().map(|&(_, hblank)| hblank).min(), timing.lines.iter().map(|&(_, hblank)| hblank).max())
+    {
+        ui.weak(format!("Pixel transfer took {}–{} dots a line", shortest - 80, longest - 80));
+    }
+}
+
+/// The 40 sprites' entries.
+fn oam(ui: &mut egui::Ui, snapshot: &Snapshot) {
+    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+        egui::Grid::new("debug_oam").striped(true).show(ui, |ui| {
+            for heading in ["#", "Y", "X", "Tile", "Flags", ""] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for sprite in 0..40u16 {
+                let addr = 0xFE00 + sprite * 4;
+                let [y, x, tile, flags] = [0, 1, 2, 3].map(|i| snapshot.byte(addr + i));
+                ui.monospace(sprite.to_string());
+                ui.monospace(format!("{:02X}", y));
+                ui.monospace(format!("{:02X}", x));
+                ui.monospace(format!("{:02X}", tile));
+                ui.monospace(format!("{:02X}", flags));
+                let mut notes = Vec::new();
+                if flags & 0x80 != 0 {
+                    notes.push("behind BG");
+                }
+                if flags & 0x40 != 0 {
+                    notes.push("Y flip");
+                }
+                if flags & 0x20 != 0 {
+                    notes.push("X flip");
+                }
+                notes.push(if flags & 0x10 != 0 { "OBP1" } else { "OBP0" });
+                ui.label(notes.join(", "));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Parses an address typed in hex, with or without a `$` or `0x` prefix.
+fn parse_addr(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let hex = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")).unwrap_or(text);
+    u16::from_str_radix(hex, 16).ok()
+}
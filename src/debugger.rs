@@ -0,0 +1,612 @@
+use crate::emu_thread::Command;
+use crate::rewind::Rewind;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use rustris_core::{disasm, DmaRecord, EmulatorError, FrameTiming, InterruptRecord, IoWrite, Registers, GB};
+
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Instructions already executed shown above PC, and instructions shown from PC on, in
+/// the terminal.
+const HISTORY_SHOWN: usize = 4;
+const AHEAD_SHOWN: usize = 12;
+
+/// Latest interrupt log entries shown in the terminal.
+const INTERRUPTS_SHOWN: usize = AHEAD_SHOWN;
+
+/// Rows of 16 bytes in the memory view.
+const MEMORY_ROWS: u16 = 16;
+
+/// How often the terminal is redrawn while no key is pressed.
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Requests from the debugger's terminal or window to the emulation thread.
+#[derive(Clone)]
+pub enum DebugCommand {
+    /// Stop at the next instruction.
+    Break,
+    Continue,
+    /// Run one instruction, then stop again.
+    Step,
+    /// Go back this many instructions and stop there (see `Debugger::step_back`).
+    StepBack(u64),
+    ToggleBreakpoint(u16),
+    /// Write a byte through the bus, with the side effects a CPU store has (see
+    /// `GB::write_byte`).
+    Write(u16, u8),
+    /// Trace writes to these I/O registers while the window is open, or to none (see
+    /// `GB::trace_io`).
+    TraceIo(Vec<u16>),
+    /// The terminal is closing: drop the breakpoints and carry on.
+    Detach,
+}
+
+/// What the debugger's terminal and window show, published by the emulation thread after
+/// every frame and every stop while either is open. Cloning gives another handle to the
+/// same copy.
+#[derive(Clone, Default)]
+pub struct DebugView(Arc<Mutex<Shared>>);
+
+#[derive(Default)]
+struct Shared {
+    wanted: bool,               // The debugger window or the tile viewer is open
+    terminal: bool,             // The terminal debugger is running
+    closing: bool,              // The terminal has been asked to close
+    stop: Option<String>,       // Why the debugger last stopped by itself, until taken
+    snapshot: Option<Snapshot>, // None until the first publish
+}
+
+/// The machine as the emulation thread last published it.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub stopped: bool,
+    pub reason: Option<String>, // Why it stopped, if the debugger stopped it by itself
+    pub registers: Registers,
+    pub memory: Vec<u8>,
+    pub recent: Vec<u16>, // Addresses of the last instructions executed, oldest first
+    pub breakpoints: Vec<u16>,
+    pub interrupts: Vec<InterruptRecord>, // The interrupt log, oldest first
+    pub dma: Vec<DmaRecord>,              // The DMA log, oldest first
+    pub timing: Option<FrameTiming>,      // The last frame's PPU timing (window only)
+    pub io_writes: Vec<IoWrite>,          // Writes to the traced I/O registers, oldest first (window only)
+}
+
+/// One line of disassembly (see `Snapshot::code`).
+pub struct CodeLine {
+    pub addr: u16,
+    pub bytes: String, // In hex
+    pub text: String,
+    pub executed: bool, // Already run, from the recent instructions
+    pub current: bool,  // At PC
+    pub breakpoint: bool,
+}
+
+impl DebugView {
+    /// Starts or stops publishing for the debugger window and the tile viewer.
+    pub fn set_wanted(&self, wanted: bool) {
+        self.lock().wanted = wanted;
+    }
+
+    /// The last published copy, if there's one.
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        self.lock().snapshot.clone()
+    }
+
+    /// Why the debugger stopped the machine by itself (a stack fault, a broken assertion),
+    /// if it has since the last call; the front-end then shows it.
+    pub fn take_stop(&self) -> Option<String> {
+        self.lock().stop.take()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl Snapshot {
+    pub fn byte(&self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+
+    /// Disassembly around PC: up to `history` of the last instructions executed, then
+    /// `ahead` instructions from PC on.
+    pub fn code(&self, history: usize, ahead: usize) -> Vec<CodeLine> {
+        let decode = |addr: u16, executed: bool| {
+            let bytes = [self.byte(addr), self.byte(addr.wrapping_add(1)), self.byte(addr.wrapping_add(2))];
+            let (text, len) = disasm::disassemble(addr, &bytes);
+            let hex: Vec<String> = bytes[..len as usize].iter().map(|b| format!("{:02X}", b)).collect();
+            let line = CodeLine {
+                addr,
+                bytes: hex.join(" "),
+                text,
+                executed,
+                current: !executed && addr == self.registers.pc,
+                breakpoint: self.breakpoints.contains(&addr),
+            };
+            (line, len)
+        };
+        let mut lines: Vec<CodeLine> =
+            self.recent[self.recent.len().saturating_sub(history)..].iter().map(|&addr| decode(addr, true).0).collect();
+        let mut addr = self.registers.pc;
+        for _ in 0..ahead {
+            let (line, len) = decode(addr, false);
+            lines.push(line);
+            addr = addr.wrapping_add(len);
+        }
+        lines
+    }
+
+    /// The interrupt log, newest first. Each dispatch comes with the T-cycles it waited
+    /// since its request, if the request is still in the log.
+    pub fn interrupts(&self) -> Vec<(InterruptRecord, Option<u64>)> {
+        let mut requested = [None; 5]; // Cycle of each source's last request
+        let mut entries: Vec<(InterruptRecord, Option<u64>)> = self
+            .interrupts
+            .iter()
+            .map(|&record| {
+                let last = &mut requested[record.interrupt as usize];
+                if record.dispatched {
+                    (record, last.take().map(|cycle| record.cycle - cycle))
+                } else {
+                    *last = Some(record.cycle);
+                    (record, None)
+                }
+            })
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
+
+/// Debugger (F5 window, `--debug` terminal), emulation side: holds the machine while it's stopped, runs
+/// single steps and stops at breakpoints (checked after each instruction against the
+/// next PC, so the instruction there hasn't run yet). With `--stack-break` it also stops
+/// after an instruction that made a stack fault (see `GB::check_stack`), and `stop` stops
+/// it for other reasons (broken assertions). Driven by the debugger window and `Tui`
+/// through `Command::Debug`.
+///
+/// It also counts the steps run and logs the buttons held, so it can step back in time
+/// from the rewind buffer's snapshots (see `step_back`).
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    stopped: bool,
+    step: bool,                  // Run one instruction before stopping again
+    stack_break: bool,           // Stop at stack faults
+    reason: Option<String>,      // Why it stopped by itself or couldn't step back, until told to go on
+    clock: u64,                  // Steps run, wound back with the machine
+    inputs: VecDeque<(u64, u8)>, // Buttons held from each step where they changed on
+    cuts: Vec<u64>,              // Steps where the machine jumped (a reset, a poke), which replays can't redo
+    traced_io: Vec<u16>,         // I/O registers whose writes the window traces
+    view: DebugView,
+}
+
+impl Debugger {
+    /// Starts with `breakpoints` set (e.g. from the game's saved session). With `stopped`,
+    /// the machine waits for a command before the game's first instruction. With
+    /// `stack_break`, it stops at stack faults; the machine must be checking its stack.
+    pub fn new(view: DebugView, breakpoints: BTreeSet<u16>, stopped: bool, stack_break: bool) -> Self {
+        Self {
+            breakpoints,
+            stopped,
+            step: false,
+            stack_break,
+            reason: None,
+            clock: 0,
+            inputs: VecDeque::new(),
+            cuts: Vec::new(),
+            traced_io: Vec::new(),
+            view,
+        }
+    }
+
+    /// `true` while emulation must wait for a command.
+    pub fn holding(&self) -> bool {
+        self.stopped && !self.step
+    }
+
+    pub fn apply(&mut self, command: DebugCommand, gb: &mut GB) {
+        match command {
+            DebugCommand::Break => self.stopped = true,
+            DebugCommand::Continue => {
+                self.stopped = false;
+                self.reason = None;
+            }
+            DebugCommand::Step => {
+                self.step = true;
+                self.reason = None;
+            }
+            DebugCommand::ToggleBreakpoint(addr) => {
+                if !self.breakpoints.remove(&addr) {
+                    self.breakpoints.insert(addr);
+                }
+            }
+            // Needs the rewind buffer: the emulation thread calls `step_back` instead
+            DebugCommand::StepBack(_) => {}
+            DebugCommand::Write(addr, value) => {
+                gb.write_byte(addr, value);
+                self.cut();
+            }
+            DebugCommand::TraceIo(registers) => self.traced_io = registers,
+            DebugCommand::Detach => {
+                self.breakpoints.clear();
+                self.stopped = false;
+                self.reason = None;
+            }
+        }
+    }
+
+    /// Call after every instruction: returns `true` if emulation has to stop there.
+    pub fn after_step(&mut self, gb: &mut GB) -> bool {
+        self.clock += 1;
+        let buttons = gb.buttons();
+        if self.inputs.back().is_none_or(|&(_, held)| held != buttons) {
+            self.inputs.push_back((self.clock, buttons));
+        }
+        if std::mem::take(&mut self.step) || (!self.breakpoints.is_empty() && self.breakpoints.contains(&gb.registers().pc)) {
+            self.stopped = true;
+        }
+        if self.stack_break && let Some(fault) = gb.take_stack_fault() {
+            self.stop(fault.to_string());
+        }
+        self.stopped
+    }
+
+    /// Stops the machine after the current instruction, saying why.
+    pub fn stop(&mut self, reason: String) {
+        self.stopped = true;
+        self.view.lock().stop = Some(reason.clone());
+        self.reason = Some(reason);
+    }
+
+    /// Steps run so far: the clock the rewind buffer's snapshots are placed on.
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    /// The machine has just jumped somewhere running it wouldn't take it (a reset, a
+    /// retried piece, a byte poked): stepping back stops here.
+    pub fn cut(&mut self) {
+        self.cuts.push(self.clock);
+    }
+
+    /// The machine went back to a rewind snapshot taken at `position`.
+    pub fn rewound(&mut self, position: u64) {
+        self.clock = position;
+        self.inputs.retain(|&(step, _)| step <= position);
+        self.cuts.retain(|&step| step <= position);
+    }
+
+    /// Forgets the history from before `position`, which the rewind buffer no longer
+    /// reaches.
+    pub fn forget_before(&mut self, position: u64) {
+        // The buttons in effect at `position` are the last change at or before it
+        while self.inputs.len() > 1 && self.inputs[1].0 <= position {
+            self.inputs.pop_front();
+        }
+        self.cuts.retain(|&step| step >= position);
+    }
+
+    /// Goes back `count` steps and stops there: restores the latest rewind snapshot from
+    /// before then (dropping the newer ones) and runs forward again to that step, holding
+    /// the buttons that were held the first time, into `framebuffer`. Goes only as far
+    /// back as the rewind buffer and the last cut, and says so in the stop reason.
+    ///
+    /// Only the instructions and the buttons are redone: changes made between frames by
+    /// scripts and the Tetris helpers (piece queue, hard drop...) aren't.
+    pub fn step_back(
+        &mut self,
+        gb: &mut GB,
+        rewind: &mut Rewind,
+        count: u64,
+        framebuffer: &mut [u8],
+        pitch: usize,
+    ) -> Result<(), EmulatorError> {
+        self.stopped = true;
+        self.step = false;
+        self.reason = None;
+        let since = self.cuts.last().copied().unwrap_or(0);
+        let wanted = self.clock.saturating_sub(count);
+        let reachable = || rewind.positions().filter(|&position| position >= since && position <= self.clock);
+        let Some(start) = reachable().rfind(|&position| position <= wanted).or_else(|| reachable().next()) else {
+            self.reason = Some(String::from("Can't step back: rewind is off, or has nothing since the last reset or poke"));
+            return Ok(());
+        };
+        let target = wanted.max(start);
+        if target > wanted {
+            self.reason = Some(format!("Stepped back only {} instructions: the history starts there", self.clock - target));
+        }
+
+        let state = loop {
+            match rewind.pop() {
+                Some((state, position)) if position == start => break state,
+                Some(_) => {}
+                None => unreachable!("rewind snapshot {} vanished", start),
+            }
+        };
+        gb.load_state(&state)?;
+        rewind.push(state, start);
+        for step in start + 1..=target {
+            let changes = self.inputs.partition_point(|&(from, _)| from <= step);
+            let held = changes.checked_sub(1).map_or(0, |i| self.inputs[i].1);
+            if held != gb.buttons() {
+                gb.set_buttons(held);
+            }
+            gb.step(framebuffer, pitch);
+        }
+        // Already passed on the first time through
+        gb.take_serial_output();
+        gb.take_stack_fault();
+        self.rewound(target);
+        Ok(())
+    }
+
+    /// Copies what the terminal and window show, if either is open. The machine logs
+    /// interrupts and DMA transfers only meanwhile, and times frames only for the window.
+    pub fn publish(&self, gb: &mut GB) {
+        let mut view = self.view.lock();
+        gb.log_interrupts(view.wanted || view.terminal);
+        gb.log_dma(view.wanted || view.terminal);
+        gb.record_timing(view.wanted);
+        gb.trace_io(if view.wanted { &self.traced_io } else { &[] });
+        if !view.wanted && !view.terminal {
+            return;
+        }
+        // Reuses the last copy's memory
+        let mut memory = view.snapshot.take().map(|snapshot| snapshot.memory).unwrap_or_default();
+        memory.resize(0x10000, 0);
+        gb.read_memory(&mut memory);
+        view.snapshot = Some(Snapshot {
+            stopped: self.holding(),
+            reason: self.reason.clone(),
+            registers: gb.registers(),
+            memory,
+            recent: gb.recent_instructions(),
+            breakpoints: self.breakpoints.iter().copied().collect(),
+            interrupts: gb.interrupt_log(),
+            dma: gb.dma_log(),
+            timing: gb.frame_timing(),
+            io_writes: gb.io_log(),
+        });
+    }
+}
+
+/// Debugger terminal (`--debug`): a text interface on the terminal rustris was started
+/// from, run on its own thread while the window keeps showing the game.
+/// - Shows the registers, the disassembly around PC (the last instructions executed
+///   above it), the latest interrupts, a hex dump of memory and the breakpoints.
+/// - Keys: `c` continue, `s` step one instruction, `r` step back one and `R` a number of
+///   them (typed in decimal), `p` stop, `b` toggle a breakpoint (typed in hex), `g` go to
+///   an address in the memory view, Up/Down and PgUp/PgDn scroll it, `q` closes the
+///   debugger and lets the game run, Ctrl+C quits rustris.
+pub struct Tui {
+    handle: JoinHandle<()>,
+    view: DebugView,
+}
+
+/// What the `b`, `g` and `R` keys ask for.
+#[derive(Clone, Copy)]
+enum Prompt {
+    Breakpoint,
+    Memory,
+    StepBack,
+}
+
+/// State of the terminal thread.
+struct Screen {
+    commands: Sender<Command>,
+    memory_addr: u16,                 // First address of the memory view
+    prompt: Option<(Prompt, String)>, // Address (or count) being typed
+    message: String,                  // Last error, shown on the status line
+}
+
+impl Tui {
+    /// Takes over the terminal and starts the debugger's thread. Fails if the terminal
+    /// can't be put in raw mode or the thread can't be started.
+    pub fn open(view: DebugView, commands: Sender<Command>) -> std::io::Result<Self> {
+        let terminal = ratatui::try_init()?;
+        view.lock().terminal = true;
+        let screen = Screen { commands, memory_addr: 0xC000, prompt: None, message: String::new() };
+        let shown = view.clone();
+        let handle = thread::Builder::new().name(String::from("debugger")).spawn(move || {
+            if let Err(e) = screen.run(terminal, &shown) {
+                ratatui::restore();
+                eprintln!("Debugger: {}", e);
+            }
+            shown.lock().terminal = false;
+        });
+        match handle {
+            Ok(handle) => Ok(Self { handle, view }),
+            Err(e) => {
+                view.lock().terminal = false;
+                ratatui::restore();
+                Err(e)
+            }
+        }
+    }
+
+    /// Closes the debugger and gives the terminal back.
+    pub fn close(self) {
+        self.view.lock().closing = true;
+        let _ = self.handle.join();
+    }
+}
+
+impl Screen {
+    fn run(mut self, mut terminal: DefaultTerminal, view: &DebugView) -> std::io::Result<()> {
+        loop {
+            let (snapshot, closing) = {
+                let shared = view.lock();
+                (shared.snapshot.clone(), shared.closing)
+            };
+            if closing {
+                break;
+            }
+            terminal.draw(|frame| self.draw(frame, snapshot.as_ref()))?;
+            if event::poll(REDRAW)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.key(key.code, key.modifiers)
+            {
+                break;
+            }
+        }
+        ratatui::restore();
+        Ok(())
+    }
+
+    /// Handles a key press. Returns `false` when the debugger is closed.
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+            let _ = self.commands.send(Command::Quit);
+            return false;
+        }
+        if let Some((prompt, text)) = &mut self.prompt {
+            let count = matches!(prompt, Prompt::StepBack);
+            match code {
+                KeyCode::Char(c) if count && c.is_ascii_digit() && text.len() < 7 => text.push(c),
+                KeyCode::Char(c) if !count && c.is_ascii_hexdigit() && text.len() < 4 => text.push(c.to_ascii_uppercase()),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => {
+                    match (*prompt, u16::from_str_radix(text, 16)) {
+                        (Prompt::StepBack, _) => match text.parse() {
+                            Ok(count) => self.send(DebugCommand::StepBack(count)),
+                            Err(_) => self.message = String::from("Not a number"),
+                        },
+                        (Prompt::Breakpoint, Ok(addr)) => self.send(DebugCommand::ToggleBreakpoint(addr)),
+                        (Prompt::Memory, Ok(addr)) => self.memory_addr = addr & 0xFFF0,
+                        (_, Err(_)) => self.message = String::from("Not an address"),
+                    }
+                    self.prompt = None;
+                }
+                KeyCode::Esc => self.prompt = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        self.message.clear();
+        match code {
+            KeyCode::Char('c') => self.send(DebugCommand::Continue),
+            KeyCode::Char('s') => self.send(DebugCommand::Step),
+            KeyCode::Char('r') => self.send(DebugCommand::StepBack(1)),
+            KeyCode::Char('R') => self.prompt = Some((Prompt::StepBack, String::new())),
+            KeyCode::Char('p') => self.send(DebugCommand::Break),
+            KeyCode::Char('b') => self.prompt = Some((Prompt::Breakpoint, String::new())),
+            KeyCode::Char('g') => self.prompt = Some((Prompt::Memory, String::new())),
+            KeyCode::Up => self.memory_addr = self.memory_addr.wrapping_sub(0x10),
+            KeyCode::Down => self.memory_addr = self.memory_addr.wrapping_add(0x10),
+            KeyCode::PageUp => self.memory_addr = self.memory_addr.wrapping_sub(MEMORY_ROWS * 0x10),
+            KeyCode::PageDown => self.memory_addr = self.memory_addr.wrapping_add(MEMORY_ROWS * 0x10),
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.send(DebugCommand::Detach);
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn send(&self, command: DebugCommand) {
+        let _ = self.commands.send(Command::Debug(command));
+    }
+
+    fn draw(&self, frame: &mut Frame, snapshot: Option<&Snapshot>) {
+        let [top, memory, status] =
+            Layout::vertical([Constraint::Min(AHEAD_SHOWN as u16 + 2), Constraint::Length(MEMORY_ROWS + 2), Constraint::Length(2)])
+                .areas(frame.area());
+        let [registers, code, interrupts] =
+            Layout::horizontal([Constraint::Length(30), Constraint::Min(30), Constraint::Length(44)]).areas(top);
+
+        let Some(snapshot) = snapshot else {
+            frame.render_widget(Paragraph::new("Waiting for the machine..."), frame.area());
+            return;
+        };
+        let r = snapshot.registers;
+
+        // Registers, flags and the LCD state
+        let flag = |bit: u8, name: char| if r.f & bit != 0 { name } else { '-' };
+        let state = if snapshot.stopped { Span::styled("Stopped", Style::new().fg(Color::Red)) } else { Span::raw("Running") };
+        let lines = vec![
+            Line::from(state),
+            Line::raw(""),
+            Line::raw(format!("AF {:02X}{:02X}   BC {:02X}{:02X}", r.a, r.f, r.b, r.c)),
+            Line::raw(format!("DE {:02X}{:02X}   HL {:02X}{:02X}", r.d, r.e, r.h, r.l)),
+            Line::raw(format!("SP {:04X}   PC {:04X}", r.sp, r.pc)),
+            Line::raw(format!("Flags {}{}{}{}", flag(0x80, 'Z'), flag(0x40, 'N'), flag(0x20, 'H'), flag(0x10, 'C'))),
+            Line::raw(""),
+            Line::raw(format!("LCDC {:02X}   STAT {:02X}", snapshot.byte(0xFF40), snapshot.byte(0xFF41))),
+            Line::raw(format!("LY   {:02X}   LYC  {:02X}", snapshot.byte(0xFF44), snapshot.byte(0xFF45))),
+            Line::raw(format!("IE   {:02X}   IF   {:02X}", snapshot.byte(0xFFFF), snapshot.byte(0xFF0F))),
+        ];
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Registers ")), registers);
+
+        // Disassembly: recent instructions, then from PC on
+        let lines: Vec<Line> = snapshot
+            .code(HISTORY_SHOWN, AHEAD_SHOWN)
+            .into_iter()
+            .map(|line| {
+                let marker = if line.breakpoint { '●' } else { ' ' };
+                let style = match (line.current, line.executed) {
+                    (true, _) => Style::new().add_modifier(Modifier::REVERSED),
+                    (_, true) => Style::new().add_modifier(Modifier::DIM),
+                    _ => Style::new(),
+                };
+                Line::styled(format!("{} {:04X}  {:<9} {}", marker, line.addr, line.bytes, line.text), style)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Code ")), code);
+
+        // Latest interrupts, with how long dispatches waited
+        let lines: Vec<Line> = snapshot
+            .interrupts()
+            .into_iter()
+            .take(INTERRUPTS_SHOWN)
+            .map(|(record, waited)| {
+                let what = if record.dispatched { "run" } else { "req" };
+                let waited = waited.map_or_else(String::new, |t| format!(" +{}", t));
+                let text = format!("{:<6} {} LY{:>3}:{:<3} PC {:04X}{}", format!("{:?}", record.interrupt), what, record.ly, record.dot, record.pc, waited);
+                if record.dispatched { Line::raw(text) } else { Line::styled(text, Style::new().add_modifier(Modifier::DIM)) }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Interrupts ")), interrupts);
+
+        // Memory
+        let lines: Vec<Line> = (0..MEMORY_ROWS)
+            .map(|row| {
+                let start = self.memory_addr.wrapping_add(row * 0x10);
+                let bytes: Vec<u8> = (0..0x10).map(|i| snapshot.byte(start.wrapping_add(i))).collect();
+                let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                let text: String = bytes.iter().map(|&b| if b.is_ascii_graphic() { b as char } else { '.' }).collect();
+                Line::raw(format!("{:04X}  {}  {}", start, hex.join(" "), text))
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Memory ")), memory);
+
+        // Why it stopped and the breakpoints, then the prompt, the last error or the keys
+        let breakpoints: Vec<String> = snapshot.breakpoints.iter().map(|addr| format!("{:04X}", addr)).collect();
+        let mut first = Line::raw(format!("Breakpoints: {}", breakpoints.join(" ")));
+        if let Some(reason) = &snapshot.reason {
+            first.spans.insert(0, Span::styled(format!("{}   ", reason), Style::new().fg(Color::Red)));
+        }
+        let help = match &self.prompt {
+            Some((Prompt::Breakpoint, text)) => format!("Toggle breakpoint at: {}_", text),
+            Some((Prompt::Memory, text)) => format!("Show memory at: {}_", text),
+            Some((Prompt::StepBack, text)) => format!("Instructions to step back: {}_", text),
+            None if !self.message.is_empty() => self.message.clone(),
+            None => String::from("c continue  s step  r/R step back  p stop  b breakpoint  g go to memory  q close  Ctrl+C quit"),
+        };
+        let lines = vec![first, Line::raw(help)];
+        frame.render_widget(Paragraph::new(lines), status);
+    }
+}
@@ -0,0 +1,289 @@
+use rustris_core::{Registers, GB};
+
+use std::path::{Path, PathBuf};
+
+/// First bytes of a demo file with checkpoints.
+const MAGIC: &[u8; 4] = b"RDM3";
+
+/// First bytes of a demo file from before there were checkpoints, still loaded.
+const MAGIC_V2: &[u8; 4] = b"RDM2";
+
+/// First bytes of a demo file from before there were changes within frames, still loaded.
+const MAGIC_V1: &[u8; 4] = b"RDMO";
+
+/// Bytes per change within a frame in a demo file.
+const CHANGE_LEN: usize = 9;
+
+/// Bytes per checkpoint in a demo file.
+const CHECKPOINT_LEN: usize = 20;
+
+/// Memory a checkpoint's digest covers: VRAM, cartridge RAM, work RAM (not its echo), OAM
+/// and high RAM.
+const CHECKED_RAM: [std::ops::Range<usize>; 3] = [0x8000..0xE000, 0xFE00..0xFEA0, 0xFF80..0xFFFF];
+
+/// An input movie: the machine's state when recording started, the buttons held as each
+/// frame from there starts, and the changes to them within frames, at the dot they land
+/// on (see `GB::frame_dot`), for tricks that need a press between two joypad reads of one
+/// frame. Checkpoints of how each frame ended let a replay be checked against the
+/// recording (see `replay::verify`). File layout, numbers little endian:
+/// - `MAGIC`, the state's length (u32), the state (`GB::save_state`).
+/// - The number of frames (u32), then one byte of buttons per frame.
+/// - The number of changes within frames (u32), then 9 bytes each in the order they
+///   happen: the frame (u32, counted from 0), the dot (u32) and the buttons held from
+///   there.
+/// - The checkpoints to the end, 20 bytes each, one per frame from the first (a recording
+///   cut short mid-frame misses the last): A, F, B, C, D, E, H, L, SP (u16) and PC (u16)
+///   as the frame ended, then the digest of RAM then (u64, see `Checkpoint`).
+///
+/// Files starting with `MAGIC_V2` have no checkpoints, the changes running to the end
+/// without a count; those starting with `MAGIC_V1` have no changes within frames either,
+/// the state being followed by the buttons of each frame to the end.
+pub struct Demo {
+    state: Vec<u8>,
+    buttons: Vec<u8>,
+    changes: Vec<Change>,         // In the order they happen
+    checkpoints: Vec<Checkpoint>, // One per frame, if recorded
+}
+
+/// A change to the buttons within a frame of a demo.
+#[derive(Clone, Copy)]
+pub struct Change {
+    pub frame: u32,
+    pub dot: u32,    // Where in the frame it lands (see `GB::frame_dot`)
+    pub buttons: u8, // Held from there
+}
+
+/// How a frame ended, to check a replay against: the CPU registers and a digest (FNV-1a)
+/// of the memory the game can write, `CHECKED_RAM`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub registers: Registers,
+    pub ram: u64,
+}
+
+impl Checkpoint {
+    pub fn of(gb: &GB) -> Self {
+        let mut memory = vec![0u8; 0x10000];
+        gb.read_memory(&mut memory);
+        let mut ram: u64 = 0xCBF2_9CE4_8422_2325;
+        for byte in CHECKED_RAM.into_iter().flat_map(|range| &memory[range]) {
+            ram ^= *byte as u64;
+            ram = ram.wrapping_mul(0x0100_0000_01B3);
+        }
+        Self { registers: gb.registers(), ram }
+    }
+
+    fn write(&self, data: &mut Vec<u8>) {
+        let r = &self.registers;
+        data.extend_from_slice(&[r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l]);
+        data.extend_from_slice(&r.sp.to_le_bytes());
+        data.extend_from_slice(&r.pc.to_le_bytes());
+        data.extend_from_slice(&self.ram.to_le_bytes());
+    }
+
+    fn read(data: &[u8]) -> Self {
+        let word = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let registers = Registers {
+            a: data[0], f: data[1], b: data[2], c: data[3], d: data[4], e: data[5], h: data[6], l: data[7],
+            sp: word(8),
+            pc: word(10),
+        };
+        Self { registers, ram: u64::from_le_bytes(data[12..20].try_into().unwrap()) }
+    }
+}
+
+impl Demo {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let (magic, rest) = data.split_first_chunk::<4>().ok_or("file too short")?;
+        if ![MAGIC, MAGIC_V2, MAGIC_V1].contains(&magic) {
+            return Err(String::from("not a demo file"));
+        }
+        let (state, rest) = split_prefixed(rest)?;
+        if magic == MAGIC_V1 {
+            return Ok(Self { state: state.to_vec(), buttons: rest.to_vec(), changes: Vec::new(), checkpoints: Vec::new() });
+        }
+        let (buttons, rest) = split_prefixed(rest)?;
+        let (change_data, rest) = if magic == MAGIC {
+            let (count, rest) = rest.split_first_chunk::<4>().ok_or("file too short")?;
+            let len = (u32::from_le_bytes(*count) as usize).checked_mul(CHANGE_LEN).filter(|&len| len <= rest.len());
+            rest.split_at(len.ok_or("file too short")?)
+        } else {
+            (rest, &[][..])
+        };
+        if !change_data.len().is_multiple_of(CHANGE_LEN) || !rest.len().is_multiple_of(CHECKPOINT_LEN) {
+            return Err(String::from("file truncated"));
+        }
+        let mut changes: Vec<Change> = Vec::with_capacity(change_data.len() / CHANGE_LEN);
+        for change in change_data.chunks_exact(CHANGE_LEN) {
+            let frame = u32::from_le_bytes(change[0..4].try_into().unwrap());
+            let dot = u32::from_le_bytes(change[4..8].try_into().unwrap());
+            if frame as usize >= buttons.len() || changes.last().is_some_and(|last| (last.frame, last.dot) > (frame, dot)) {
+                return Err(format!("change out of order at frame {}", frame));
+            }
+            changes.push(Change { frame, dot, buttons: change[8] });
+        }
+        if rest.len() / CHECKPOINT_LEN > buttons.len() {
+            return Err(String::from("more checkpoints than frames"));
+        }
+        let checkpoints = rest.chunks_exact(CHECKPOINT_LEN).map(Checkpoint::read).collect();
+        Ok(Self { state: state.to_vec(), buttons: buttons.to_vec(), changes, checkpoints })
+    }
+
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&(self.state.len() as u32).to_le_bytes());
+        data.extend_from_slice(&self.state);
+        data.extend_from_slice(&(self.buttons.len() as u32).to_le_bytes());
+        data.extend_from_slice(&self.buttons);
+        data.extend_from_slice(&(self.changes.len() as u32).to_le_bytes());
+        for change in &self.changes {
+            data.extend_from_slice(&change.frame.to_le_bytes());
+            data.extend_from_slice(&change.dot.to_le_bytes());
+            data.push(change.buttons);
+        }
+        for checkpoint in &self.checkpoints {
+            checkpoint.write(&mut data);
+        }
+        std::fs::write(path, data)
+    }
+
+    /// The machine's state when recording started.
+    pub fn state(&self) -> &[u8] {
+        &self.state
+    }
+
+    /// How the recorded frames ended, from the first; empty if the file has none.
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// The buttons held as `frame` starts, and the changes within it.
+    pub fn frame(&self, frame: usize) -> (u8, &[Change]) {
+        let start = self.changes.partition_point(|change| (change.frame as usize) < frame);
+        let end = self.changes.partition_point(|change| change.frame as usize <= frame);
+        (self.buttons[frame], &self.changes[start..end])
+    }
+}
+
+/// Splits a u32 length off the front of `data`: the bytes it counts, and what follows them.
+fn split_prefixed(data: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    let (len, rest) = data.split_first_chunk::<4>().ok_or("file too short")?;
+    let len = u32::from_le_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(String::from("file too short"));
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Demo recorder (`--record-demo`): F10 starts a recording from the current state and
+/// F10 again writes it to the file, which `Attract` plays back. While recording, the
+/// buttons are taken as each frame starts and whenever the game reads them during it,
+/// and kept only as read then, so they play back exactly as they were recorded;
+/// quitting mid-recording saves what was recorded.
+pub struct Recorder {
+    path: PathBuf,
+    demo: Option<Demo>, // The recording in progress
+}
+
+impl Recorder {
+    pub fn new(path: &str) -> Self {
+        Self { path: PathBuf::from(path), demo: None }
+    }
+
+    pub fn recording(&self) -> bool {
+        self.demo.is_some()
+    }
+
+    /// Starts recording from `gb`'s state, or stops and saves the recording in progress.
+    pub fn toggle(&mut self, gb: &GB) {
+        if self.demo.is_some() {
+            self.finish();
+        } else {
+            self.demo = Some(Demo { state: gb.save_state(), buttons: Vec::new(), changes: Vec::new(), checkpoints: Vec::new() });
+            println!("Demo: recording to {} (F10 to stop)", self.path.display());
+        }
+    }
+
+    /// Call at the start of each frame while recording, with the frame's buttons.
+    pub fn record(&mut self, buttons: u8) {
+        if let Some(demo) = &mut self.demo {
+            demo.buttons.push(buttons);
+        }
+    }
+
+    /// Call at the end of each frame while recording, with the changes to the buttons
+    /// during it (the frame dot each landed on and the buttons held from there), before
+    /// anything else touches the machine: records them and the frame's checkpoint.
+    pub fn record_frame_end(&mut self, gb: &GB, changes: &[(u32, u8)]) {
+        if let Some(demo) = &mut self.demo {
+            let frame = demo.buttons.len().saturating_sub(1) as u32;
+            demo.changes.extend(changes.iter().map(|&(dot, buttons)| Change { frame, dot, buttons }));
+            demo.checkpoints.push(Checkpoint::of(gb));
+        }
+    }
+
+    /// Saves the recording in progress, if any.
+    pub fn finish(&mut self) {
+        let Some(demo) = self.demo.take() else { return };
+        match demo.save(&self.path) {
+            Ok(()) => println!("Demo: recorded {} frames to {}", demo.buttons.len(), self.path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// Attract mode (`--attract`), for kiosks: once nobody has pressed a button for a while,
+/// a recorded demo (see `Recorder`) plays by itself:
+/// - The state the machine was idling in (normally the title screen) is kept, the demo's
+///   starting state is loaded and its buttons replace the player's, frame by frame and
+///   at the points within frames where they were recorded changing.
+/// - When the demo ends, or as soon as a button is pressed, the kept state comes back, so
+///   the game returns to where it was (the title screen) and anything the demo did is
+///   undone. After another idle period the demo plays again.
+pub struct Attract {
+    demo: Option<Demo>, // Dropped if it can't be loaded into the machine
+    idle_frames: u64,   // Frames without input before the demo starts
+    idle: u64,          // Frames without input so far
+    playback: Option<(usize, Vec<u8>)>, // Frame of the demo playing, and the state to return to
+}
+
+impl Attract {
+    pub fn new(demo: Demo, idle_seconds: u32) -> Self {
+        Self { demo: Some(demo), idle_frames: idle_seconds as u64 * 60, idle: 0, playback: None }
+    }
+
+    /// Call at the start of each frame with the buttons the player holds: starts, advances
+    /// and stops the demo. While it plays, returns the buttons to use as this frame
+    /// starts and their changes during it.
+    pub fn before_frame(&mut self, gb: &mut GB, player: u8) -> Option<(u8, &[Change])> {
+        if self.playback.is_none() {
+            let demo = self.demo.as_ref()?;
+            self.idle = if player == 0 { self.idle + 1 } else { 0 };
+            if self.idle < self.idle_frames {
+                return None;
+            }
+            let resume = gb.save_state();
+            if let Err(e) = gb.load_state(&demo.state) {
+                eprintln!("Attract: can't play the demo: {}", e);
+                let _ = gb.load_state(&resume);
+                self.demo = None;
+                return None;
+            }
+            self.playback = Some((0, resume));
+        }
+
+        let (frame, resume) = self.playback.as_mut()?;
+        let demo = self.demo.as_ref()?;
+        if player == 0 && *frame < demo.buttons.len() {
+            *frame += 1;
+            return Some(demo.frame(*frame - 1));
+        }
+        if let Err(e) = gb.load_state(resume) {
+            eprintln!("Attract: can't return from the demo: {}", e);
+        }
+        self.playback = None;
+        self.idle = 0;
+        None
+    }
+}
@@ -0,0 +1,827 @@
+use rustris_core::GB;
+use rustris_core::{EmulatorError, InputSource, Palette, RamInit, VideoSink, SCREEN_HEIGHT};
+use crate::assertions::Assertions;
+use crate::bot::Bot;
+use crate::colorize::Colorizer;
+use crate::crashdump;
+use crate::debugger::{DebugCommand, Debugger};
+use crate::demo::{Attract, Recorder};
+use crate::error::Error;
+use crate::macros::Macros;
+use crate::ghost::{Ghost, GhostCells};
+use crate::osd;
+use crate::headless::PITCH;
+use crate::hiscores::HighScores;
+use crate::memsearch::MemoryView;
+use crate::pacing::Limiter;
+use crate::practice::Practice;
+use crate::remote::RemoteInput;
+use crate::rewind::Rewind;
+use crate::rumble::Motor;
+use crate::script::{Script, ScriptOutput};
+use crate::serial::SerialOutput;
+use crate::session::Session;
+use crate::speedrun::Speedrun;
+use crate::stack::StackWarnings;
+use crate::stream::FrameFeed;
+use crate::telemetry::Telemetry;
+use crate::trace;
+use crate::stats::{FrameStats, FrameTimes};
+use crate::tetris::{HardDrop, PieceQueue};
+use crate::das::{Das, DasTiming};
+use crate::turbo::Turbo;
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Frame period:
+/// - Real DMG cadence: 59.7275 FPS → 16_742_706 ns per frame.
+pub const GB_FRAME_NS: u64 = 16_742_706;    // ~59.7275 FPS (Game Boy)
+
+/// Frames between two rewind snapshots.
+pub const REWIND_INTERVAL: u64 = 2;
+
+/// Frames the emulation thread may run ahead of the UI before it starts dropping them.
+const FRAME_QUEUE: usize = 2;
+
+/// Size of one RGB24 frame buffer.
+const FRAME_LEN: usize = PITCH * SCREEN_HEIGHT as usize;
+
+/// Requests sent from the UI thread to the emulation thread.
+#[derive(Clone)]
+pub enum Command {
+    /// Regular button mapping pressed/released.
+    Button(u8, bool),
+    /// Turbo button mapping pressed/released.
+    Turbo(u8, bool),
+    /// Release every button (e.g. on focus loss).
+    ReleaseAll,
+    SetSpeed(u32),
+    SetPaused(bool),
+    SetPalette(Palette),
+    /// Outline the sprites or not (see `GB::set_sprite_outlines`).
+    SetSpriteOutlines(bool),
+    /// CPU clock multiplier (see `GB::set_overclock`).
+    SetOverclock(u32),
+    SetTurboRate(u32),
+    /// Left/Right auto-repeat timing, or `None` to pass them straight through (see `Das`).
+    SetDas(Option<DasTiming>),
+    /// Rewind key held/released.
+    Rewind(bool),
+    /// Show/hide the frame-time overlay.
+    ShowStats(bool),
+    /// Show/hide the held buttons (see `osd::draw_buttons`).
+    ShowInput(bool),
+    /// Show/hide the Tetris ghost piece (see `Ghost`).
+    ShowGhost(bool),
+    /// Warm reset: the game restarts with RAM intact (see `GB::reset`).
+    Reset,
+    /// Cold start with RAM refilled (see `GB::power_cycle`).
+    PowerCycle(RamInit),
+    /// Turn the Tetris autoplayer on/off (see `Bot`).
+    SetAutoplay(bool),
+    /// Go back to when the falling piece appeared (see `Practice`).
+    RetryPiece,
+    /// Start or stop recording a demo (see `Recorder`).
+    RecordDemo,
+    /// Play the input macro on a key, named as SDL names it (see `Macros`).
+    PlayMacro(String),
+    /// Start or stop recording the input macro on a key.
+    RecordMacro(String),
+    /// Turn the Tetris hard-drop patch on/off (see `HardDrop`).
+    SetHardDrop(bool),
+    /// From the debugger's terminal or window (see `Debugger`).
+    Debug(DebugCommand),
+    /// Write the last instructions executed to a file (see `trace::dump`).
+    DumpTrace,
+    /// Write the machine's state to a file (see `GB::save_state`).
+    SaveState(PathBuf),
+    /// Go back to a state written by `SaveState`.
+    LoadState(PathBuf),
+    Quit,
+}
+
+/// Game-specific code the emulation thread runs around each frame.
+pub struct Hooks {
+    pub script: Option<Script>,
+    pub high_scores: Option<HighScores>, // Restores the game's table, saved when the thread ends
+    pub bot: Option<Bot>,                // Plays the game while enabled
+    pub practice: Option<Practice>,      // Keeps the state at each piece spawn for retrying
+    pub pieces: Option<PieceQueue>,      // Deals a chosen piece sequence
+    pub session: Option<Session>,        // Tracks the games played, reported when the thread ends
+    pub speedrun: Option<Speedrun>,      // Times runs, drawn over each frame
+    pub telemetry: Option<Telemetry>,    // Sends the game's state to the telemetry server
+    pub ghost: Option<Ghost>,            // Places the ghost piece as each frame is finished
+    pub colorizer: Option<Colorizer>,    // Colors the pieces' sprite tiles
+    pub recorder: Option<Recorder>,      // Records demos, saved when the thread ends
+    pub attract: Option<Attract>,        // Plays a demo when the game is left idle
+    pub macros: Option<Macros>,          // Plays and records the input macros, saved as recorded
+    pub hard_drop: Option<HardDrop>,     // Drops the piece on Up while enabled
+    pub debugger: Option<Debugger>,      // Stops at breakpoints and single steps
+    pub serial: Option<SerialOutput>,    // Passes on what the game sends over the link port
+    pub stack: Option<StackWarnings>,    // Warns about stack faults
+    pub assertions: Option<Assertions>,  // Stop the debugger when broken
+    pub motor: Option<Motor>,            // Passes on how hard the cart's rumble motor runs
+    pub remote: Option<RemoteInput>,     // Buttons pressed by remote input clients
+    pub stream: Option<FrameFeed>,       // Sends each finished frame to the streaming server
+}
+
+/// UI-side handle to the emulation thread.
+/// - Commands go in through `send`.
+/// - Completed RGB24 frames (`PITCH` bytes per row) come out of `frames`; the UI
+///   hands each buffer back through `recycle` once it's done with it, so steady-state
+///   emulation doesn't allocate.
+/// - If the machine can't go on (the CPU locked up, a snapshot didn't load or the core
+///   panicked), the thread stops and the error comes out of `frames` last; the machine
+///   `stop` hands back is as it stopped, for the crash dump.
+pub struct EmuThread {
+    commands: Sender<Command>,
+    pub frames: Receiver<Result<Vec<u8>, Error>>,
+    recycle: Sender<Vec<u8>>,
+    handle: JoinHandle<GB>,
+}
+
+impl EmuThread {
+    /// Moves `gb` onto its own thread and starts emulating, paced by `limiter`, keeping
+    /// `rewind_seconds` of history for rewinding and running `hooks`.
+    /// Frame timings are recorded into `stats`, and memory is published to `memory` after
+    /// each frame while a memory tool wants it. Fails only if the OS won't start a thread.
+    pub fn spawn(
+        gb: GB,
+        limiter: Limiter,
+        turbo_hz: u32,
+        rewind_seconds: u32,
+        hooks: Hooks,
+        stats: FrameStats,
+        memory: MemoryView,
+    ) -> std::io::Result<Self> {
+        let Hooks { script, high_scores, bot, practice, pieces, session, speedrun, telemetry, ghost, colorizer, recorder, attract, macros, hard_drop, debugger, serial, stack, assertions, motor, remote, stream } = hooks;
+        let (commands, command_rx) = mpsc::channel();
+        let (frame_tx, frames) = mpsc::sync_channel(FRAME_QUEUE);
+        let (recycle, recycle_rx) = mpsc::channel();
+
+        let handle = thread::Builder::new()
+            .name(String::from("emulation"))
+            .spawn(move || {
+                let output = script.as_ref().map(Script::output);
+                let mut emu = Emulator {
+                    gb,
+                    input: Input {
+                        commands: command_rx,
+                        held: 0,
+                        turbo: Turbo::new(turbo_hz),
+                        das: Das::new(None),
+                        script: output.clone(),
+                        autoplay: 0,
+                        remote: 0,
+                        playing: None,
+                        latched: None,
+                        scheduled: Vec::new(),
+                        recording: None,
+                        deferred: Vec::new(),
+                        disconnected: false,
+                    },
+                    video: FrameQueue {
+                        frames: frame_tx,
+                        recycle: recycle_rx,
+                        spare: Vec::new(),
+                        current: vec![0u8; FRAME_LEN],
+                        script: output,
+                        stats: stats.clone(),
+                        show_stats: false,
+                        show_input: false,
+                        buttons: 0,
+                        speedrun,
+                        ghost: ghost.as_ref().map(Ghost::cells),
+                        stream,
+                        disconnected: false,
+                    },
+                    limiter,
+                    paused: false,
+                    quit: false,
+                    failure: None,
+                    rewind: Rewind::new((rewind_seconds as u64 * 60 / REWIND_INTERVAL) as usize),
+                    rewinding: false,
+                    frame_count: 0,
+                    script,
+                    high_scores,
+                    bot,
+                    practice,
+                    pieces,
+                    session,
+                    telemetry,
+                    ghost,
+                    colorizer,
+                    recorder,
+                    attract,
+                    macros,
+                    hard_drop,
+                    debugger,
+                    mid_frame: false,
+                    serial,
+                    stack,
+                    assertions,
+                    motor,
+                    remote,
+                    stats,
+                    memory,
+                };
+                // Lets the debugger step back as far as the first instruction
+                if emu.debugger.is_some() {
+                    emu.rewind.push(emu.gb.save_state(), 0);
+                }
+                emu.run();
+                if let Some(error) = emu.failure.take() {
+                    let _ = emu.video.frames.send(Err(error));
+                }
+                if let Some(high_scores) = &mut emu.high_scores {
+                    high_scores.save(&emu.gb);
+                }
+                if let Some(session) = &mut emu.session {
+                    session.finish();
+                }
+                if let Some(speedrun) = &mut emu.video.speedrun {
+                    speedrun.finish();
+                }
+                if let Some(recorder) = &mut emu.recorder {
+                    recorder.finish();
+                }
+                if let Some(macros) = &mut emu.macros {
+                    macros.finish();
+                }
+                emu.gb
+            })?;
+
+        Ok(Self { commands, frames, recycle, handle })
+    }
+
+    pub fn send(&self, command: Command) {
+        // If the thread is gone the UI notices through `frames` disconnecting
+        let _ = self.commands.send(command);
+    }
+
+    /// Returns a presented frame buffer to the emulation thread for reuse.
+    pub fn recycle(&self, frame: Vec<u8>) {
+        let _ = self.recycle.send(frame);
+    }
+
+    /// Stops the emulation thread and hands the machine back.
+    pub fn stop(self) -> GB {
+        self.send(Command::Quit);
+        drop(self.frames); // Unblock a pending frame send
+        self.handle.join().expect("emulation thread panicked")
+    }
+}
+
+/// State owned by the emulation thread.
+struct Emulator {
+    gb: GB,
+    input: Input,
+    video: FrameQueue,
+    limiter: Limiter,
+    paused: bool,
+    quit: bool,
+    failure: Option<Error>, // Why the machine can't go on, sent to the UI once the loop ends
+    rewind: Rewind,
+    rewinding: bool,
+    frame_count: u64,
+    script: Option<Script>,
+    high_scores: Option<HighScores>,
+    bot: Option<Bot>,
+    practice: Option<Practice>,
+    pieces: Option<PieceQueue>,
+    session: Option<Session>,
+    telemetry: Option<Telemetry>,
+    ghost: Option<Ghost>,
+    colorizer: Option<Colorizer>,
+    recorder: Option<Recorder>,
+    attract: Option<Attract>,
+    macros: Option<Macros>,
+    hard_drop: Option<HardDrop>,
+    debugger: Option<Debugger>,
+    mid_frame: bool, // The debugger stopped the machine before the frame was finished
+    serial: Option<SerialOutput>,
+    stack: Option<StackWarnings>,
+    assertions: Option<Assertions>,
+    motor: Option<Motor>,
+    remote: Option<RemoteInput>,
+    stats: FrameStats,
+    memory: MemoryView,
+}
+
+impl Emulator {
+    /// Emulation loop:
+    /// - Applies pending commands, then runs one frame into a spare buffer and queues it
+    ///   for the UI (dropping it if the UI has fallen `FRAME_QUEUE` frames behind).
+    ///   Input commands are also picked up mid-frame, whenever the game selects a joypad line.
+    /// - Paces the frames with its `Limiter`: to the clock, as precisely as its pacing
+    ///   asks, independently of how long the UI takes to present, or to the display's
+    ///   refresh with vsync pacing.
+    /// - While paused, blocks on the command channel instead of spinning.
+    /// - Records a rewind snapshot every `REWIND_INTERVAL` frames; while the rewind key is
+    ///   held, each frame instead restores the previous snapshot and renders from there.
+    /// - Runs the user script's hooks around each frame (see `Script`), and lets the
+    ///   autoplayer and the remote input clients pick their buttons before each one (see
+    ///   `Bot`, `RemoteInput`).
+    /// - While a macro plays, its buttons replace the player's a frame at a time (see `Macros`).
+    /// - While a demo plays or is recorded, the buttons only change where the demo has them
+    ///   change (see `Attract`), or where the game reads them (see `Recorder`).
+    /// - Records where each frame's time went (see `FrameStats`).
+    /// - Publishes a copy of memory for the memory tools while they're open (see `MemoryView`).
+    /// - Under the debugger, stops where it asks, possibly mid-frame, and holds the machine
+    ///   like a pause until told to go on; a stopped frame is finished before any of the
+    ///   per-frame hooks run (see `Debugger`). Broken assertions and the script's `pause`
+    ///   stop it the same way.
+    /// - Stops for good when the machine can't go on, leaving the reason in `failure`.
+    fn run(&mut self) {
+        while !self.quit {
+            // --- Commands from the UI ---
+            if !self.poll_commands() || self.failure.is_some() {
+                return;
+            }
+
+            if self.paused || self.debugger.as_ref().is_some_and(Debugger::holding) {
+                match self.input.commands.recv_timeout(Duration::from_millis(100)) {
+                    Ok(command) => self.accept(command),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                self.memory.publish(&self.gb);
+                if let Some(debugger) = &self.debugger {
+                    debugger.publish(&mut self.gb);
+                }
+                // A paused game doesn't keep the pad shaking
+                if let Some(motor) = &self.motor {
+                    motor.set(0.0);
+                }
+                // Restart the limiter so we don't try to catch up on the paused time
+                self.limiter.restart();
+                continue;
+            }
+
+            // --- One emulated frame ---
+            let frame_start = Instant::now();
+            let (mut core, mut ppu) = (Duration::ZERO, Duration::ZERO);
+            let mut emulate = true;
+            if self.rewinding && !self.mid_frame {
+                // Step back one snapshot; hold the last picture once history runs out
+                match self.rewind.pop() {
+                    Some((state, position)) => {
+                        if let Err(e) = self.gb.load_state(&state) {
+                            self.fail(snapshot_error("rewind", e));
+                            return;
+                        }
+                        if let Some(debugger) = &mut self.debugger {
+                            debugger.rewound(position);
+                        }
+                    }
+                    None => emulate = false,
+                }
+            }
+
+            if emulate {
+                if !self.mid_frame {
+                    if let Some(script) = &mut self.script {
+                        script.frame_start(&mut self.gb);
+                    }
+                    if let Some(bot) = &mut self.bot {
+                        self.input.autoplay = bot.buttons(&self.gb);
+                    }
+                    if let Some(remote) = &mut self.remote {
+                        self.input.remote = remote.before_frame();
+                    }
+                    let player = self.input.held | self.input.turbo.buttons() | self.input.remote;
+                    self.input.latched = None;
+                    self.input.scheduled.clear();
+                    self.input.recording = None;
+                    if let Some(macros) = &mut self.macros {
+                        self.input.playing = macros.next_frame();
+                        macros.record(self.input.buttons());
+                    }
+                    if let Some((buttons, changes)) = self.attract.as_mut().and_then(|attract| attract.before_frame(&mut self.gb, player)) {
+                        self.input.latched = Some(buttons);
+                        self.input.scheduled.extend(changes.iter().map(|change| (change.dot, change.buttons)));
+                    } else if let Some(recorder) = self.recorder.as_mut().filter(|r| r.recording()) {
+                        let buttons = self.input.buttons();
+                        recorder.record(buttons);
+                        self.input.latched = Some(buttons);
+                        self.input.recording = Some(Vec::new());
+                    }
+                    self.video.buttons = self.input.buttons();
+                }
+
+                self.gb.time_ppu(self.video.show_stats);
+                let (video, input, script, ghost, debugger, assertions) =
+                    (&mut self.video, &mut self.input, &mut self.script, &mut self.ghost, &mut self.debugger, &mut self.assertions);
+                let core_start = Instant::now();
+                let ran = crashdump::guard(&mut self.gb, |gb| {
+                    gb.run_frame_until(video, input, |gb, frame_done| {
+                        let mut stop = None;
+                        if let Some(script) = script {
+                            script.after_step(gb, frame_done);
+                            stop = script.take_pause();
+                        }
+                        if frame_done && let Some(ghost) = ghost {
+                            ghost.place(gb);
+                        }
+                        if let Some(assertions) = assertions {
+                            stop = stop.or(assertions.after_step(gb));
+                        }
+                        let Some(debugger) = debugger else { return false };
+                        if let Some(reason) = stop {
+                            debugger.stop(reason);
+                        }
+                        debugger.after_step(gb)
+                    })
+                });
+                core = core_start.elapsed();
+                ppu = self.gb.take_ppu_time();
+                let completed = match ran {
+                    Ok(completed) => completed,
+                    Err(e) => {
+                        self.fail(e);
+                        return;
+                    }
+                };
+                self.mid_frame = !completed;
+                if let Some(debugger) = &self.debugger {
+                    debugger.publish(&mut self.gb);
+                }
+                // Drained even with nowhere to go, so it doesn't pile up
+                let sent = self.gb.take_serial_output();
+                if let Some(serial) = &mut self.serial {
+                    serial.write(&sent);
+                }
+                if !completed {
+                    // Stopped by the debugger: hold here until it lets the frame finish
+                    continue;
+                }
+                if let Err(fault) = self.gb.fault() {
+                    self.fail(fault.into());
+                    return;
+                }
+                if let (Some(recorder), Some(changes)) = (&mut self.recorder, &self.input.recording) {
+                    recorder.record_frame_end(&self.gb, changes);
+                }
+                if let Some(high_scores) = &mut self.high_scores {
+                    high_scores.after_frame(&mut self.gb);
+                }
+                // Deal before the practice snapshot, so retrying keeps the dealt preview
+                if let Some(pieces) = &mut self.pieces {
+                    pieces.after_frame(&mut self.gb);
+                }
+                if let Some(hard_drop) = &mut self.hard_drop {
+                    hard_drop.after_frame(&mut self.gb, self.video.buttons);
+                }
+                if let Some(practice) = &mut self.practice {
+                    practice.after_frame(&self.gb);
+                }
+                if let Some(session) = &mut self.session {
+                    session.after_frame(&self.gb);
+                }
+                if let Some(speedrun) = &mut self.video.speedrun {
+                    speedrun.after_frame(&self.gb);
+                }
+                if let Some(telemetry) = &mut self.telemetry {
+                    telemetry.after_frame(&self.gb);
+                }
+                if let Some(colorizer) = &mut self.colorizer {
+                    colorizer.after_frame(&mut self.gb);
+                }
+                if let Some(stack) = &mut self.stack {
+                    stack.after_frame(&mut self.gb);
+                }
+                if let Some(motor) = &self.motor {
+                    motor.set(self.gb.take_rumble());
+                }
+                self.memory.publish(&self.gb);
+                if self.input.disconnected || self.video.disconnected {
+                    return;
+                }
+
+                if !self.rewinding {
+                    // Advance auto-fire and auto-repeat once per emulated frame
+                    self.input.turbo.tick();
+                    self.input.das.tick(self.input.held);
+                    self.frame_count += 1;
+                    if self.frame_count.is_multiple_of(REWIND_INTERVAL) {
+                        let clock = self.debugger.as_ref().map_or(0, Debugger::clock);
+                        self.rewind.push(self.gb.save_state(), clock);
+                        if let Some(debugger) = &mut self.debugger
+                            && let Some(oldest) = self.rewind.positions().next()
+                        {
+                            debugger.forget_before(oldest);
+                        }
+                    }
+                }
+            }
+
+            // --- Frame limiter (see `Limiter`) ---
+            let mut times = FrameTimes { emulate: frame_start.elapsed(), core, ppu, ..Default::default() };
+            self.limiter.wait(&mut times);
+            self.stats.record_frame(times);
+        }
+    }
+
+    /// Applies every pending command, including those deferred while the input source
+    /// was polled mid-frame. Returns `false` once the UI side is gone.
+    fn poll_commands(&mut self) -> bool {
+        loop {
+            match self.input.commands.try_recv() {
+                Ok(command) => self.accept(command),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+        for command in std::mem::take(&mut self.input.deferred) {
+            self.apply(command);
+        }
+        !self.input.disconnected
+    }
+
+    fn accept(&mut self, command: Command) {
+        if let Some(command) = self.input.accept(command) {
+            self.apply(command);
+        }
+    }
+
+    /// Stops the emulation for good: the machine can't go on (see `EmuThread`).
+    fn fail(&mut self, error: Error) {
+        self.failure = Some(error);
+        self.quit = true;
+    }
+
+    /// Keeps the high scores made so far across a reset or power cycle, which the
+    /// debugger can't step back past.
+    fn before_restart(&mut self) {
+        if let Some(high_scores) = &mut self.high_scores {
+            high_scores.restart(&self.gb);
+        }
+        if let Some(debugger) = &mut self.debugger {
+            debugger.cut();
+        }
+    }
+
+    /// Loads a state file, keeping the machine as it was if the file isn't a state.
+    fn load_state(&mut self, path: &Path) -> Result<(), String> {
+        let state = std::fs::read(path).map_err(|e| e.to_string())?;
+        let resume = self.gb.save_state();
+        self.gb.load_state(&state).map_err(|e| {
+            let _ = self.gb.load_state(&resume);
+            e.to_string()
+        })
+    }
+
+    /// Applies a command that isn't handled by `Input`.
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::SetSpeed(speed) => self.limiter.set_speed(speed),
+            Command::SetPaused(paused) => self.paused = paused,
+            Command::SetPalette(palette) => self.gb.set_palette(palette),
+            Command::SetSpriteOutlines(on) => self.gb.set_sprite_outlines(on),
+            Command::SetOverclock(factor) => self.gb.set_overclock(factor),
+            Command::Rewind(rewinding) => self.rewinding = rewinding,
+            Command::ShowStats(show) => self.video.show_stats = show,
+            Command::ShowInput(show) => self.video.show_input = show,
+            Command::ShowGhost(show) => {
+                if let Some(ghost) = &mut self.ghost {
+                    ghost.set_shown(show);
+                }
+            }
+            Command::Reset => {
+                self.before_restart();
+                self.gb.reset();
+            }
+            Command::PowerCycle(init) => {
+                self.before_restart();
+                self.gb.power_cycle(init);
+            }
+            Command::SetAutoplay(enabled) => {
+                if let Some(bot) = &mut self.bot {
+                    bot.set_enabled(enabled);
+                }
+                self.input.autoplay = 0;
+            }
+            Command::RetryPiece => {
+                if let Some(state) = self.practice.as_ref().and_then(Practice::last_spawn) {
+                    if let Err(e) = self.gb.load_state(state) {
+                        self.fail(snapshot_error("practice", e));
+                        return;
+                    }
+                    if let Some(debugger) = &mut self.debugger {
+                        debugger.cut();
+                    }
+                }
+            }
+            Command::SetHardDrop(enabled) => {
+                if let Some(hard_drop) = &mut self.hard_drop {
+                    hard_drop.set_enabled(enabled);
+                }
+            }
+            Command::PlayMacro(key) => {
+                if let Some(macros) = &mut self.macros {
+                    macros.play(&key);
+                }
+            }
+            Command::RecordMacro(key) => {
+                if let Some(macros) = &mut self.macros {
+                    macros.toggle_recording(&key);
+                }
+            }
+            Command::RecordDemo => {
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.toggle(&self.gb);
+                }
+            }
+            Command::Debug(DebugCommand::StepBack(count)) => {
+                if let Some(debugger) = &mut self.debugger {
+                    let (framebuffer, pitch) = self.video.buffer();
+                    if let Err(e) = debugger.step_back(&mut self.gb, &mut self.rewind, count, framebuffer, pitch) {
+                        self.fail(snapshot_error("rewind", e));
+                        return;
+                    }
+                    // Wherever it landed, the frame there has started
+                    self.mid_frame = true;
+                    debugger.publish(&mut self.gb);
+                }
+            }
+            Command::Debug(command) => {
+                if let Some(debugger) = &mut self.debugger {
+                    debugger.apply(command, &mut self.gb);
+                }
+            }
+            Command::DumpTrace => match trace::dump(&self.gb) {
+                Ok(path) => println!("Trace written to {}", path.display()),
+                Err(e) => eprintln!("Error writing the trace: {}", e),
+            },
+            Command::SaveState(path) => match std::fs::write(&path, self.gb.save_state()) {
+                Ok(()) => println!("State saved to {}", path.display()),
+                Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+            },
+            Command::LoadState(path) => {
+                if let Err(e) = self.load_state(&path) {
+                    eprintln!("Can't load the state in {}: {}", path.display(), e);
+                } else if let Some(debugger) = &mut self.debugger {
+                    debugger.cut();
+                }
+            }
+            Command::Quit => self.quit = true,
+            Command::Button(..) | Command::Turbo(..) | Command::ReleaseAll | Command::SetTurboRate(_) | Command::SetDas(_) => {}
+        }
+    }
+}
+
+/// A snapshot of the machine's own making that doesn't load back: the core is broken.
+fn snapshot_error(kind: &str, e: EmulatorError) -> Error {
+    Error::Emulator(EmulatorError::SaveState(format!("Corrupt {} snapshot: {}", kind, e)))
+}
+
+/// Joypad state as the core sees it: regular keys (shaped by `Das`), turbo, script,
+/// autoplayer and remote buttons combined, or a macro's.
+/// Each poll drains the command channel first, so presses that arrive mid-frame are
+/// seen by the game; other commands wait in `deferred` until the frame ends.
+struct Input {
+    commands: Receiver<Command>,
+    held: u8, // Buttons held through their regular key mapping
+    turbo: Turbo,
+    das: Das,
+    script: Option<ScriptOutput>,
+    autoplay: u8, // Buttons the autoplayer holds this frame
+    remote: u8,   // Buttons remote clients hold this frame
+    playing: Option<u8>, // Buttons a macro holds this frame, replacing the above
+    latched: Option<u8>, // Buttons replacing all the above (demos)
+    scheduled: Vec<(u32, u8)>, // Demo playback: changes to `latched` still due this frame, by frame dot
+    recording: Option<Vec<(u32, u8)>>, // Demo recording: `latched` follows the keys where polled, changes logged here
+    deferred: Vec<Command>, // Non-input commands received while polling mid-frame
+    disconnected: bool,
+}
+
+impl Input {
+    /// Applies an input command; hands any other command back.
+    fn accept(&mut self, command: Command) -> Option<Command> {
+        match command {
+            Command::Button(mask, true) => self.held |= mask,
+            Command::Button(mask, false) => self.held &= !mask,
+            Command::Turbo(mask, true) => self.turbo.press(mask),
+            Command::Turbo(mask, false) => self.turbo.release(mask),
+            Command::ReleaseAll => {
+                self.held = 0;
+                self.turbo.clear();
+            }
+            Command::SetTurboRate(hz) => self.turbo.set_rate(hz),
+            Command::SetDas(timing) => self.das.set_timing(timing),
+            other => return Some(other),
+        }
+        None
+    }
+
+    /// Everything held: keys, auto-fire, the script's, the autoplayer's and the remote
+    /// clients' buttons, unless a macro is playing or the frame's buttons are latched.
+    fn buttons(&self) -> u8 {
+        self.latched.or(self.playing).unwrap_or_else(|| {
+            self.das.shape(self.held) | self.turbo.buttons() | self.script.as_ref().map_or(0, ScriptOutput::buttons) | self.autoplay | self.remote
+        })
+    }
+}
+
+impl InputSource for Input {
+    /// Also moves the latched buttons on: to the demo's changes due by `dot`, or, while
+    /// recording, to the buttons held now, logging the change.
+    fn poll_at(&mut self, dot: u32) -> u8 {
+        self.poll();
+        let due = self.scheduled.partition_point(|&(at, _)| at <= dot);
+        if let Some(&(_, buttons)) = self.scheduled[..due].last() {
+            self.latched = Some(buttons);
+        }
+        self.scheduled.drain(..due);
+        if self.recording.is_some() {
+            let held = self.latched.take();
+            let buttons = self.buttons();
+            if let Some(changes) = &mut self.recording
+                && held != Some(buttons)
+            {
+                changes.push((dot, buttons));
+            }
+            self.latched = Some(buttons);
+        }
+        self.buttons()
+    }
+
+    fn next_change(&self) -> Option<u32> {
+        self.scheduled.first().map(|&(dot, _)| dot)
+    }
+
+    fn poll(&mut self) -> u8 {
+        loop {
+            match self.commands.try_recv() {
+                Ok(command) => {
+                    if let Some(command) = self.accept(command) {
+                        self.deferred.push(command);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    break;
+                }
+            }
+        }
+        self.buttons()
+    }
+}
+
+/// Video sink feeding the UI thread: frames are rendered straight into a recycled
+/// buffer, which is queued on `present` (or kept as a spare if the UI is behind).
+struct FrameQueue {
+    frames: SyncSender<Result<Vec<u8>, Error>>,
+    recycle: Receiver<Vec<u8>>,
+    spare: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    script: Option<ScriptOutput>, // OSD text is drawn over each frame before it's queued
+    stats: FrameStats,
+    show_stats: bool,             // Draw the frame-time overlay over each frame
+    show_input: bool,             // Draw the held buttons over each frame
+    buttons: u8,                  // Buttons held as the frame started
+    speedrun: Option<Speedrun>,   // Its clock is drawn over each frame
+    ghost: Option<GhostCells>,    // Ghost piece drawn over each frame (see `Ghost`)
+    stream: Option<FrameFeed>,    // Gets each frame as it's queued, overlays and all
+    disconnected: bool,
+}
+
+impl VideoSink for FrameQueue {
+    fn buffer(&mut self) -> (&mut [u8], usize) {
+        (&mut self.current, PITCH)
+    }
+
+    fn present(&mut self) {
+        if let Some(ghost) = &self.ghost {
+            ghost.draw(&mut self.current, PITCH);
+        }
+        if let Some(script) = &self.script {
+            script.draw(&mut self.current, PITCH);
+        }
+        if self.show_stats {
+            self.stats.draw(&mut self.current, PITCH);
+        }
+        if let Some(speedrun) = &self.speedrun {
+            speedrun.draw(&mut self.current, PITCH);
+        }
+        if self.show_input {
+            let y = SCREEN_HEIGHT as i32 - osd::BUTTONS_HEIGHT - 2;
+            osd::draw_buttons(&mut self.current, PITCH, 2, y, self.buttons, [0xFF, 0xFF, 0x00]);
+        }
+        if let Some(stream) = &self.stream {
+            stream.send(&self.current, PITCH);
+        }
+
+        self.spare.extend(self.recycle.try_iter());
+        let next = self.spare.pop().unwrap_or_else(|| vec![0u8; FRAME_LEN]);
+        match self.frames.try_send(Ok(std::mem::replace(&mut self.current, next))) {
+            Ok(()) => {}
+            Err(mpsc::TrySendError::Full(frame)) => self.spare.extend(frame.ok()),
+            Err(mpsc::TrySendError::Disconnected(_)) => self.disconnected = true,
+        }
+    }
+}
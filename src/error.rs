@@ -0,0 +1,40 @@
+use rustris_core::EmulatorError;
+
+/// Front-end failures, with messages ready to show to the user.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The core refused the ROM, a save state or the machine's state.
+    #[error(transparent)]
+    Emulator(#[from] EmulatorError),
+
+    /// SDL couldn't set up or drive the window, renderer or textures.
+    #[error("SDL error: {0}")]
+    Sdl(String),
+
+    /// A netplay session couldn't be set up (handshake, socket).
+    #[error("{0}")]
+    Netplay(String),
+
+    /// The `--shader` couldn't be read, compiled or run in the window.
+    #[error("{0}")]
+    Shader(String),
+
+    /// The OS wouldn't start the emulation thread.
+    #[error("cannot start the emulation thread: {0}")]
+    Thread(#[source] std::io::Error),
+
+    /// The core panicked while emulating (a broken invariant: a bug); see `crashdump::guard`.
+    #[error("Emulation panicked: {0}")]
+    Panic(String),
+
+    /// The terminal couldn't be taken over for `--debug`.
+    #[error("cannot open the debugger in the terminal: {0}")]
+    Terminal(#[source] std::io::Error),
+}
+
+impl Error {
+    /// Wraps an SDL error (SDL reports plain strings; its builders have their own types).
+    pub fn sdl(e: impl ToString) -> Self {
+        Error::Sdl(e.to_string())
+    }
+}
@@ -0,0 +1,303 @@
+use crate::error::Error;
+use crate::headless::{self, PITCH};
+use crate::options::Options;
+use crate::osd;
+use crate::pacing::Limiter;
+use crate::stats::FrameTimes;
+use crate::video::{self, Rotation};
+
+use rustris_core::{gb, Cartridge, Profile, SCREEN_HEIGHT, SCREEN_WIDTH, GB};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::{Color, PixelFormatEnum};
+
+/// Size of the `.gbs` header; the rip's code and data follow it.
+const HEADER_LEN: usize = 0x70;
+
+/// Lowest load address: the stub's vectors, cartridge header and startup code sit below.
+const MIN_LOAD: u16 = 0x0400;
+
+/// Where the stub's startup code goes, right after the cartridge header.
+const STARTUP: usize = 0x0150;
+
+/// Largest image an MBC5 maps (512 banks of 16 KiB).
+const MAX_ROM: usize = 0x80_0000;
+
+/// Sound registers the channel meters follow, NR10–NR52.
+const SOUND_REGISTERS: (u16, u16) = (0xFF10, 0xFF26);
+
+/// Per channel: the register with its volume (NRx2; NR32 for the wave channel) and the one
+/// whose bit 7 triggers a note (NRx4).
+const CHANNELS: [(u16, u16); 4] = [(0xFF12, 0xFF14), (0xFF17, 0xFF19), (0xFF1C, 0xFF1E), (0xFF21, 0xFF23)];
+
+const BACKGROUND: [u8; 3] = [0x20, 0x24, 0x2C];
+const TEXT: [u8; 3] = [0xE0, 0xE0, 0xE0];
+const DIM: [u8; 3] = [0x80, 0x86, 0x90];
+const METER: [u8; 3] = [0x60, 0xC0, 0x70];
+
+/// A Game Boy Sound System rip (`.gbs`): a game's sound driver and music data cut out of
+/// its ROM, with the addresses to drive it by. Version 1 header, little-endian:
+/// - `00` "GBS", `03` version, `04` number of songs, `05` first song (from 1).
+/// - `06` load, `08` init and `0A` play addresses, `0C` initial stack pointer.
+/// - `0E` timer modulo (TMA) and `0F` timer control (TAC): with TAC bit 2 set, play is
+///   called on the timer interrupt at that rate, otherwise once per VBlank.
+/// - `10`, `30`, `50`: title, author and copyright, 32 bytes each, zero-padded.
+pub struct Gbs {
+    pub songs: u8,
+    pub first_song: u8, // From 1, as players number the songs
+    load: u16,
+    init: u16,
+    play: u16,
+    sp: u16,
+    timer_modulo: u8,
+    timer_control: u8,
+    pub title: String,
+    pub author: String,
+    pub copyright: String,
+    data: Vec<u8>, // Code and data, loaded at `load`
+}
+
+impl Gbs {
+    /// Reads a `.gbs` file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::parse(&bytes)
+    }
+
+    /// Parses a `.gbs` image, refusing what the stub can't map: code loaded over its own
+    /// below `0400`, or addresses outside the ROM.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() <= HEADER_LEN || &bytes[..3] != b"GBS" {
+            return Err(String::from("not a GBS file"));
+        }
+        if bytes[3] != 1 {
+            return Err(format!("unsupported GBS version {}", bytes[3]));
+        }
+        let word = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        let text = |offset: usize| {
+            let field = &bytes[offset..offset + 32];
+            let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+            String::from_utf8_lossy(&field[..end]).trim().to_string()
+        };
+        let gbs = Gbs {
+            songs: bytes[4],
+            first_song: bytes[5],
+            load: word(0x06),
+            init: word(0x08),
+            play: word(0x0A),
+            sp: word(0x0C),
+            timer_modulo: bytes[0x0E],
+            timer_control: bytes[0x0F],
+            title: text(0x10),
+            author: text(0x30),
+            copyright: text(0x50),
+            data: bytes[HEADER_LEN..].to_vec(),
+        };
+        if gbs.songs == 0 || !(1..=gbs.songs).contains(&gbs.first_song) {
+            return Err(format!("first song {} of {} doesn't exist", gbs.first_song, gbs.songs));
+        }
+        for (name, addr) in [("load", gbs.load), ("init", gbs.init), ("play", gbs.play)] {
+            if !(MIN_LOAD..0x8000).contains(&addr) {
+                return Err(format!("{} address {:04X} is outside 0400–7FFF", name, addr));
+            }
+        }
+        if gbs.load as usize + gbs.data.len() > MAX_ROM {
+            return Err(String::from("too large for an MBC5 cartridge"));
+        }
+        Ok(gbs)
+    }
+
+    /// A cartridge that plays `song` (from 1): the rip at its load address on an MBC5 with
+    /// 8 KiB of RAM (bank writes to `2000` switch its banks as they did in the game), and
+    /// in front of it a stub, which:
+    /// - sends the `RST` vectors to the rip's own, `load` plus the vector;
+    /// - enables cartridge RAM, sets the stack and the timer, and calls init with the song
+    ///   number (from 0) in A;
+    /// - then halts forever, calling play from the VBlank or timer interrupt.
+    fn rom(&self, song: u8) -> Vec<u8> {
+        let end = self.load as usize + self.data.len();
+        let size = end.div_ceil(0x4000).max(2).next_power_of_two() * 0x4000;
+        let mut rom = vec![0u8; size];
+        rom[self.load as usize..end].copy_from_slice(&self.data);
+
+        let [play_lo, play_hi] = self.play.to_le_bytes();
+        for vector in (0x00..0x40).step_by(8) {
+            let [lo, hi] = (self.load + vector as u16).to_le_bytes();
+            rom[vector..vector + 3].copy_from_slice(&[0xC3, lo, hi]); // JP load+vector
+        }
+        for vector in (0x40..=0x60).step_by(8) {
+            rom[vector] = 0xD9; // RETI
+        }
+        let (vector, enable) = if self.timer_control & 0x04 != 0 { (0x50, 0x04) } else { (0x40, 0x01) };
+        rom[vector..vector + 4].copy_from_slice(&[0xCD, play_lo, play_hi, 0xD9]); // CALL play; RETI
+
+        rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, STARTUP as u8, (STARTUP >> 8) as u8]); // NOP; JP startup
+        rom[0x0134..0x013E].copy_from_slice(b"GBS VIEWER");
+        rom[0x0147] = 0x1A; // MBC5+RAM
+        rom[0x0148] = (size / 0x8000).trailing_zeros() as u8;
+        rom[0x0149] = 0x02; // 8 KiB
+        rom[0x014D] = rom[0x0134..0x014D].iter().fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
+
+        let [sp_lo, sp_hi] = self.sp.to_le_bytes();
+        let [init_lo, init_hi] = self.init.to_le_bytes();
+        let startup = [
+            0xF3,                      // DI
+            0x31, sp_lo, sp_hi,        // LD SP,sp
+            0x3E, 0x0A,                // LD A,0A
+            0xEA, 0x00, 0x00,          // LD (0000),A: cartridge RAM on
+            0x3E, self.timer_modulo,   // LD A,tma
+            0xE0, 0x06,                // LDH (TMA),A
+            0x3E, self.timer_control & 0x07, // LD A,tac (without the CGB double speed bit)
+            0xE0, 0x07,                // LDH (TAC),A
+            0x3E, song - 1,            // LD A,song
+            0xCD, init_lo, init_hi,    // CALL init
+            0x3E, enable,              // LD A,ie
+            0xE0, 0xFF,                // LDH (IE),A
+            0xAF,                      // XOR A
+            0xE0, 0x0F,                // LDH (IF),A
+            0xFB,                      // EI
+            0x76,                      // HALT
+            0x18, 0xFD,                // JR -3 (back to the HALT)
+        ];
+        rom[STARTUP..STARTUP + startup.len()].copy_from_slice(&startup);
+        rom
+    }
+
+    /// A machine playing `song` (from 1) from its start.
+    fn machine(&self, song: u8) -> Result<GB, Error> {
+        let mut gb = GB::new(Cartridge::from_bytes(self.rom(song)))?;
+        gb.set_profile(Profile::Compatible);
+        gb.watch_writes(SOUND_REGISTERS.0, SOUND_REGISTERS.1);
+        Ok(gb)
+    }
+}
+
+/// GBS driver viewer (`--gbs-viewer`): runs a `.gbs` rip's sound driver on the core, a song
+/// at a time, in a window of its own, and shows what it does. It makes no sound: there's no
+/// APU, so the driver's writes to the sound registers only reach the meters.
+/// - Left/Right (the keys bound to them) pick the previous/next song, A or Start runs the
+///   current one again from the start, and Escape or closing the window quits.
+/// - The screen shows the rip's title, author and copyright, the song number and a meter
+///   per sound channel, lit by the notes the driver starts and their volume.
+///
+/// Fails only on an SDL error, or if the viewer's cartridge doesn't load.
+pub fn run(gbs: &Gbs, song: u8, opts: &Options) -> Result<(), Error> {
+    sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+    let sdl_context = sdl2::init().map_err(Error::sdl)?;
+    let video_subsystem = sdl_context.video().map_err(Error::sdl)?;
+    let (layout_w, layout_h) = video::layout_size(Rotation::R0, 1);
+    let title = if gbs.title.is_empty() { String::from("RUSTЯIS - GBS viewer") } else { format!("RUSTЯIS - {} (GBS viewer, silent)", gbs.title) };
+    let window = video_subsystem
+        .window(&title, layout_w * opts.zoom, layout_h * opts.zoom)
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(Error::sdl)?;
+    let mut canvas = window.into_canvas().build().map_err(Error::sdl)?;
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+        .map_err(Error::sdl)?;
+    let mut event_pump = sdl_context.event_pump().map_err(Error::sdl)?;
+
+    let mut screen = vec![0u8; PITCH * SCREEN_HEIGHT as usize];
+    let mut song = song;
+    let mut gb = gbs.machine(song)?;
+    let mut meters = Meters::default();
+    let mut limiter = Limiter::new(opts.pacing, opts.pacing_tuning, opts.speed, None);
+
+    'running: loop {
+        let mut start = None;
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } |
+                Event::KeyDown { scancode: Some(Scancode::Escape), .. } => break 'running,
+                Event::KeyDown { scancode: Some(sc), .. } => {
+                    match opts.keys.iter().find(|(s, _)| *s == sc).map(|&(_, mask)| mask) {
+                        Some(gb::BTN_LEFT) => start = Some(if song == 1 { gbs.songs } else { song - 1 }),
+                        Some(gb::BTN_RIGHT) => start = Some(if song == gbs.songs { 1 } else { song + 1 }),
+                        Some(gb::BTN_A | gb::BTN_START) => start = Some(song),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(next) = start {
+            song = next;
+            gb = gbs.machine(song)?;
+            meters = Meters::default();
+        }
+
+        while !gb.step(&mut screen, PITCH) {}
+        headless::exit_on_lockup(&gb);
+        meters.after_frame(&mut gb);
+        draw(&mut screen, PITCH, gbs, song, &meters);
+        texture.update(None, &screen, PITCH).map_err(Error::sdl)?;
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let output = canvas.output_size().map_err(Error::sdl)?;
+        for rect in video::dest_rects(output, Rotation::R0, 1) {
+            canvas.copy(&texture, None, rect).map_err(Error::sdl)?;
+        }
+        canvas.present();
+
+        limiter.wait(&mut FrameTimes::default());
+    }
+    Ok(())
+}
+
+/// What each sound channel is doing, from the driver's writes to the sound registers: the
+/// volume its last note started at, fading while nothing new starts.
+#[derive(Default)]
+struct Meters {
+    volume: [u8; 4], // Last volume written (0–15)
+    level: [u8; 4],  // Meter length, set by a note and falling a step a frame
+}
+
+impl Meters {
+    /// Reads the frame's sound register writes.
+    fn after_frame(&mut self, gb: &mut GB) {
+        for level in &mut self.level {
+            *level = level.saturating_sub(1);
+        }
+        for (addr, value) in gb.take_watched_writes() {
+            for (channel, &(volume, trigger)) in CHANNELS.iter().enumerate() {
+                if addr == volume {
+                    // The wave channel has an output level (mute, 100%, 50%, 25%) instead
+                    self.volume[channel] = if channel == 2 { [0, 15, 8, 4][(value as usize >> 5) & 3] } else { value >> 4 };
+                }
+                if addr == trigger && value & 0x80 != 0 {
+                    self.level[channel] = self.volume[channel] * 4;
+                }
+            }
+        }
+    }
+}
+
+/// Draws the viewer's screen over a frame (RGB24, `pitch` bytes per row).
+fn draw(framebuffer: &mut [u8], pitch: usize, gbs: &Gbs, song: u8, meters: &Meters) {
+    let (width, height) = (SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
+    osd::fill_rect(framebuffer, pitch, 0, 0, width, height, BACKGROUND);
+    let centered = |framebuffer: &mut [u8], y: i32, text: &str, color: [u8; 3]| {
+        let x = (width - text.chars().count() as i32 * osd::CHAR_WIDTH) / 2;
+        osd::draw_text(framebuffer, pitch, x, y, text, color);
+    };
+
+    centered(framebuffer, 12, &gbs.title, TEXT);
+    centered(framebuffer, 22, &gbs.author, DIM);
+    centered(framebuffer, 30, &gbs.copyright, DIM);
+    centered(framebuffer, 46, &format!("SONG {} OF {}", song, gbs.songs), TEXT);
+
+    for (channel, &level) in meters.level.iter().enumerate() {
+        let y = 64 + channel as i32 * 10;
+        osd::draw_text(framebuffer, pitch, 20, y, &format!("CH{}", channel + 1), DIM);
+        osd::fill_rect(framebuffer, pitch, 36, y, 104, osd::CHAR_HEIGHT - 1, [0x30, 0x36, 0x40]);
+        osd::fill_rect(framebuffer, pitch, 36, y, level as i32 * 104 / 60, osd::CHAR_HEIGHT - 1, METER);
+    }
+
+    centered(framebuffer, 114, "LEFT/RIGHT: SONG   A/START: AGAIN", DIM);
+    centered(framebuffer, 126, "DRIVER VIEWER ONLY: NO SOUND, NO APU", DIM);
+}
@@ -0,0 +1,81 @@
+use crate::tetris::{self, TetrisState, WELL_COLUMN};
+
+use rustris_core::{GB, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use std::sync::{Arc, Mutex};
+
+/// Color the ghost is tinted with: half of it is mixed into the cells, the outline is solid.
+const GHOST_COLOR: [u8; 3] = [0xA0, 0xC8, 0xFF];
+
+/// Where the ghost goes on the current frame, in screen pixels: the top-left corner of
+/// each cell. Shared between the `Ghost` hook and the video sink that draws it.
+#[derive(Clone, Default)]
+pub struct GhostCells(Arc<Mutex<Vec<(i32, i32)>>>);
+
+impl GhostCells {
+    /// Draws the ghost over the frame: each cell's inside half-tinted, its border solid.
+    pub fn draw(&self, framebuffer: &mut [u8], pitch: usize) {
+        for &(x, y) in self.0.lock().unwrap().iter() {
+            for py in y.max(0)..(y + 8).min(SCREEN_HEIGHT as i32) {
+                for px in x.max(0)..(x + 8).min(SCREEN_WIDTH as i32) {
+                    let border = px == x || px == x + 7 || py == y || py == y + 7;
+                    let i = py as usize * pitch + px as usize * 3;
+                    for (channel, tint) in framebuffer[i..i + 3].iter_mut().zip(GHOST_COLOR) {
+                        *channel = if border { tint } else { ((*channel as u16 + tint as u16) / 2) as u8 };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Ghost piece (`--ghost`, F9): an outline of where the falling Tetris piece would land
+/// if dropped straight down, which the 1989 game doesn't show:
+/// - Placed from the game's state (see `TetrisState`) as each frame is finished, so it
+///   follows the piece on the very frame it moves, and drawn over the frame by the video
+///   sink through `GhostCells`.
+/// - Cells the piece itself covers are left out, so it disappears once the piece is down.
+pub struct Ghost {
+    shown: bool,
+    cells: GhostCells,
+    memory: Vec<u8>, // Copy of the address space to decode
+}
+
+impl Ghost {
+    /// The ghost for `gb`'s game, hidden until `set_shown`; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self { shown: false, cells: GhostCells::default(), memory: vec![0; 0x10000] })
+    }
+
+    /// Handle for the video sink to draw the ghost with.
+    pub fn cells(&self) -> GhostCells {
+        self.cells.clone()
+    }
+
+    pub fn set_shown(&mut self, shown: bool) {
+        self.shown = shown;
+        if !shown {
+            self.cells.0.lock().unwrap().clear();
+        }
+    }
+
+    /// Call once the frame is finished, before it's presented: places the ghost under
+    /// the falling piece, or removes it if there's none.
+    pub fn place(&mut self, gb: &GB) {
+        if !self.shown {
+            return;
+        }
+        gb.read_memory(&mut self.memory);
+        let state = TetrisState::decode(&self.memory);
+        let (scy, scx) = (self.memory[0xFF42] as i32, self.memory[0xFF43] as i32);
+
+        let mut cells = self.cells.0.lock().unwrap();
+        cells.clear();
+        let Some(active) = state.active else { return };
+        for (c, r) in state.board.landing(&active.cells) {
+            if r >= 0 && !active.cells.contains(&(c, r)) {
+                cells.push(((WELL_COLUMN + c) * 8 - scx, r * 8 - scy));
+            }
+        }
+    }
+}
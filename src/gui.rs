@@ -0,0 +1,670 @@
+use crate::debug_window::{DebugWindow, TileViewer};
+use crate::das::DasTiming;
+use crate::debugger::{DebugCommand, Snapshot};
+use crate::error::Error;
+use crate::memsearch::MemorySearch;
+use crate::recent;
+use crate::serial::SerialConsole;
+use crate::watch::WatchList;
+use crate::options::{SPEED_MIN, SPEED_MAX};
+use crate::video::{HostPalette, Rotation};
+
+use rustris_core::GB;
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use egui::epaint::textures::TexturesDelta;
+use egui::epaint::{ImageData, Primitive, TextureId};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{FPoint, Rect};
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator, Vertex};
+use sdl2::video::{Window, WindowContext};
+use sdl2::VideoSubsystem;
+
+/// Runtime settings edited through the panel. The front-end owns them and applies
+/// any change after each panel update.
+#[derive(Clone, PartialEq)]
+pub struct Settings {
+    pub speed: u32,
+    pub overclock: u32,
+    pub paused: bool,
+    pub pause_on_focus_loss: bool,
+    pub palette: HostPalette,
+    pub sprite_outlines: bool,
+    pub rotation: Rotation,
+    pub fullscreen: bool,
+    pub background: [u8; 3],
+    pub hud: bool,
+    pub turbo_hz: u32,
+    pub das: bool,
+    pub das_timing: DasTiming,
+    pub rumble: u32,
+    pub frame_stats: bool,
+    pub input_display: bool,
+    pub ghost: bool,
+    pub autoplay: bool,
+    pub hard_drop: bool,
+}
+
+/// Key that shows/hides the settings panel.
+pub const TOGGLE_KEY: Scancode = Scancode::F1;
+
+/// Key that shows/hides the memory search window.
+pub const SEARCH_KEY: Scancode = Scancode::F2;
+
+/// Key that shows/hides the RAM watch window.
+pub const WATCH_KEY: Scancode = Scancode::F4;
+
+/// Key that shows/hides the debugger window, and with Shift the tile viewer.
+pub const DEBUG_KEY: Scancode = Scancode::F5;
+
+/// Size the debugger and tile viewer windows open at, in points.
+pub const DEBUGGER_SIZE: (u32, u32) = (640, 760);
+pub const TILES_SIZE: (u32, u32) = (300, 560);
+
+/// Restart asked for from the settings panel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    Reset,
+    PowerCycle,
+}
+
+/// The egui front-end, over one SDL event pump:
+/// - An overlay drawn on top of the game image in the main window, hosting the settings
+///   panel and the memory search and RAM watch windows; it takes the mouse while any of
+///   them is open.
+/// - The debugger and the tile viewer, each in a window of its own (see `ToolWindow`) that
+///   can be moved and resized apart from the game's.
+pub struct Gui<'a> {
+    overlay: Egui<'a>,
+    settings_open: bool,
+    search_open: bool,
+    search: MemorySearch,
+    watch_open: bool,
+    watch: WatchList,
+    debugger: DebugWindow,
+    debugger_window: ToolWindow<'a>,
+    tiles: TileViewer,
+    tiles_window: ToolWindow<'a>,
+    game: Vec<String>, // Lines of the "Game" section of the settings panel
+    recent: Vec<String>, // Other recently played ROMs, most recent first
+    open: Option<String>, // ROM picked in the panel, until the front-end takes it
+    restart: Option<Restart>, // Likewise for the reset buttons
+}
+
+impl<'a> Gui<'a> {
+    /// `creator` is the main window's, `debugger_window` and `tiles_window` the windows
+    /// the debugger and tile viewer open in, and `serial` the output shown by the
+    /// debugger's console.
+    pub fn new(
+        creator: &'a TextureCreator<WindowContext>,
+        pixels_per_point: f32,
+        debugger_window: ToolWindow<'a>,
+        tiles_window: ToolWindow<'a>,
+        serial: SerialConsole,
+    ) -> Self {
+        Self {
+            overlay: Egui::new(creator, pixels_per_point),
+            settings_open: false,
+            search_open: false,
+            search: MemorySearch::default(),
+            watch_open: false,
+            watch: WatchList::new(),
+            debugger: DebugWindow::new(serial),
+            debugger_window,
+            tiles: TileViewer::default(),
+            tiles_window,
+            game: Vec::new(),
+            recent: Vec::new(),
+            open: None,
+            restart: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.settings_open = !self.settings_open;
+    }
+
+    pub fn toggle_search(&mut self) {
+        self.search_open = !self.search_open;
+    }
+
+    pub fn toggle_watch(&mut self) {
+        self.watch_open = !self.watch_open;
+    }
+
+    pub fn toggle_debugger(&mut self) {
+        self.debugger_window.toggle();
+    }
+
+    pub fn toggle_tiles(&mut self) {
+        self.tiles_window.toggle();
+    }
+
+    /// Replaces the watch list (e.g. with the one saved for the loaded game).
+    pub fn set_watch_list(&mut self, watch: WatchList) {
+        self.watch = watch;
+    }
+
+    /// Describes the loaded game in the settings panel, from the ROM database when it's
+    /// listed there.
+    pub fn set_game(&mut self, gb: &GB) {
+        self.game = match gb.rom_info() {
+            Some(info) => {
+                let mut lines = vec![info.title.to_string(), format!("Region: {}", info.region)];
+                if !info.quirks.is_empty() {
+                    lines.push(format!("Note: {}", info.quirks));
+                }
+                lines
+            }
+            None => vec![gb.game_title().to_string(), String::from("Not in the ROM database")],
+        };
+        self.game.push(format!("Profile: {:?}", gb.profile()));
+    }
+
+    /// Sets the recent games offered in the panel (without the one being played).
+    pub fn set_recent(&mut self, recent: Vec<String>) {
+        self.recent = recent;
+    }
+
+    /// The `n`th recent game (from 1), for the Ctrl+number hotkeys.
+    pub fn recent(&self, n: usize) -> Option<&str> {
+        self.recent.get(n.checked_sub(1)?).map(String::as_str)
+    }
+
+    /// The ROM the user asked to switch to in the panel, if any.
+    pub fn take_open_request(&mut self) -> Option<String> {
+        self.open.take()
+    }
+
+    /// The reset or power cycle the user asked for in the panel, if any.
+    pub fn take_restart_request(&mut self) -> Option<Restart> {
+        self.restart.take()
+    }
+
+    /// The commands clicked in the debugger window since the last call.
+    pub fn take_debug_commands(&mut self) -> Vec<DebugCommand> {
+        self.debugger.take_commands()
+    }
+
+    /// Restores the debugger window's sections as the game's session left them (see
+    /// `DebugWindow::set_layout`).
+    pub fn set_debug_layout(&mut self, sections: BTreeMap<String, bool>) {
+        self.debugger.set_layout(sections);
+    }
+
+    /// The debugger window's sections, if they were folded or unfolded since the last call.
+    pub fn take_debug_layout(&mut self) -> Option<BTreeMap<String, bool>> {
+        self.debugger.take_layout()
+    }
+
+    /// Whether a memory tool is open (and so needs memory snapshots).
+    pub fn wants_memory(&self) -> bool {
+        self.search_open || self.watch_open
+    }
+
+    /// Whether the debugger window is open.
+    pub fn wants_debugger(&self) -> bool {
+        self.debugger_window.open
+    }
+
+    /// Whether the debugger or the tile viewer is open (and so needs debugger snapshots).
+    pub fn wants_snapshots(&self) -> bool {
+        self.debugger_window.open || self.tiles_window.open
+    }
+
+    fn visible(&self) -> bool {
+        self.settings_open || self.wants_memory()
+    }
+
+    /// Feeds an SDL event to egui: the debugger's or tile viewer's if it happened in one of
+    /// their windows, else the overlay's while it's visible. Returns `true` if egui
+    /// consumed it, in which case the front-end must not forward it to the emulator. Keys
+    /// egui doesn't want go through from any window, so the hotkeys work everywhere.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let id = event.get_window_id();
+        for tool in [&mut self.debugger_window, &mut self.tiles_window] {
+            if id == Some(tool.id()) {
+                return tool.handle_event(event);
+            }
+        }
+        self.visible() && self.overlay.handle_event(event)
+    }
+
+    /// Runs the open windows for one frame: paints the overlay's over whatever is already on
+    /// `canvas`, and draws and presents the debugger's and tile viewer's own. `memory` is
+    /// the latest copy of the address space, for the memory tools, and `debug` the latest
+    /// debugger snapshot.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        settings: &mut Settings,
+        memory: Option<&[u8]>,
+        debug: Option<&Snapshot>,
+    ) -> Result<(), Error> {
+        self.debugger_window.draw(|ctx| self.debugger.show(ctx, debug))?;
+        self.tiles_window.draw(|ctx| self.tiles.show(ctx, debug))?;
+
+        if !self.visible() {
+            self.overlay.events.clear();
+            return Ok(());
+        }
+        let (mut open, mut restart, mut watched) = (None, None, None);
+        self.overlay.run(canvas, |ctx| {
+            if self.settings_open {
+                (open, restart) = settings_window(ctx, settings, &self.game, &self.recent);
+            }
+            if self.search_open {
+                watched = self.search.window(ctx, &mut self.search_open, memory);
+            }
+            if self.watch_open {
+                self.watch.window(ctx, &mut self.watch_open, memory);
+            }
+        })?;
+        self.open = open.or(self.open.take());
+        self.restart = restart.or(self.restart.take());
+        if let Some(addr) = watched {
+            self.watch.add(addr);
+            self.watch_open = true;
+        }
+        Ok(())
+    }
+}
+
+/// egui running in one SDL window.
+/// - Translates SDL events into egui input.
+/// - Paints egui's tessellated meshes straight onto the SDL canvas with
+///   `render_geometry`, so no separate GL context is needed.
+/// - Works in egui points; `pixels_per_point` is the display scale (HiDPI) and mouse
+///   coordinates (SDL window units) are converted through the drawable/window ratio.
+struct Egui<'a> {
+    ctx: egui::Context,
+    painter: Painter<'a>,
+    events: Vec<egui::Event>,
+    modifiers: egui::Modifiers,
+    start: Instant,
+    pixels_per_point: f32,
+    window_to_pixels: f32, // Drawable pixels per SDL window unit
+}
+
+impl<'a> Egui<'a> {
+    fn new(creator: &'a TextureCreator<WindowContext>, pixels_per_point: f32) -> Self {
+        Self {
+            ctx: egui::Context::default(),
+            painter: Painter { creator, textures: HashMap::new() },
+            events: Vec::new(),
+            modifiers: egui::Modifiers::default(),
+            start: Instant::now(),
+            pixels_per_point,
+            window_to_pixels: 1.0,
+        }
+    }
+
+    /// Converts SDL window coordinates into egui points.
+    fn pos(&self, x: i32, y: i32) -> egui::Pos2 {
+        let k = self.window_to_pixels / self.pixels_per_point;
+        egui::pos2(x as f32 * k, y as f32 * k)
+    }
+
+    /// Feeds an SDL event to egui. Returns `true` if egui consumed it.
+    fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                self.events.push(egui::Event::PointerMoved(self.pos(*x, *y)));
+                true
+            }
+
+            Event::MouseButtonDown { x, y, mouse_btn, .. } |
+            Event::MouseButtonUp { x, y, mouse_btn, .. } => {
+                let button = match mouse_btn {
+                    MouseButton::Left   => egui::PointerButton::Primary,
+                    MouseButton::Right  => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    _ => return true,
+                };
+                self.events.push(egui::Event::PointerButton {
+                    pos: self.pos(*x, *y),
+                    button,
+                    pressed: matches!(event, Event::MouseButtonDown { .. }),
+                    modifiers: self.modifiers,
+                });
+                true
+            }
+
+            Event::MouseWheel { x, y, .. } => {
+                self.events.push(egui::Event::MouseWheel {
+                    unit: egui::MouseWheelUnit::Line,
+                    delta: egui::vec2(*x as f32, *y as f32),
+                    modifiers: self.modifiers,
+                });
+                true
+            }
+
+            Event::TextInput { text, .. } => {
+                self.events.push(egui::Event::Text(text.clone()));
+                self.ctx.wants_keyboard_input()
+            }
+
+            Event::KeyDown { keycode: Some(kc), keymod, repeat, .. } |
+            Event::KeyUp { keycode: Some(kc), keymod, repeat, .. } => {
+                self.modifiers = modifiers(*keymod);
+                if let Some(key) = egui::Key::from_name(&kc.name()) {
+                    self.events.push(egui::Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed: matches!(event, Event::KeyDown { .. }),
+                        repeat: *repeat,
+                        modifiers: self.modifiers,
+                    });
+                }
+                // Game keys keep working unless a text field has focus; releases
+                // always go through so no button gets stuck
+                matches!(event, Event::KeyDown { .. })
+                    && *kc != Keycode::Escape
+                    && self.ctx.wants_keyboard_input()
+            }
+
+            _ => false,
+        }
+    }
+
+    /// Runs `content` for one frame and paints what it shows over whatever is already on
+    /// `canvas`.
+    fn run(&mut self, canvas: &mut Canvas<Window>, content: impl FnMut(&egui::Context)) -> Result<(), Error> {
+        let (w, h) = canvas.output_size().map_err(Error::sdl)?;
+        let (window_w, _) = canvas.window().size();
+        self.window_to_pixels = w as f32 / window_w as f32;
+
+        let ppp = self.pixels_per_point;
+        self.ctx.set_pixels_per_point(ppp);
+        let raw_input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(w as f32 / ppp, h as f32 / ppp),
+            )),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            focused: true,
+            ..Default::default()
+        };
+
+        let output = self.ctx.run(raw_input, content);
+        let primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+
+        self.painter.update_textures(&output.textures_delta)?;
+        self.painter.paint(canvas, &primitives, output.pixels_per_point)?;
+        for id in &output.textures_delta.free {
+            self.painter.textures.remove(id);
+        }
+        Ok(())
+    }
+}
+
+/// A tool in an SDL window of its own, made up front and hidden until opened.
+/// - Resizable; the tool fills it.
+/// - Closed with the window's close button or Escape (unless a text field has the
+///   keyboard), which only hides it again.
+/// - Its focus changes are its own: they don't pause the game or release its buttons.
+pub struct ToolWindow<'a> {
+    canvas: Canvas<Window>,
+    egui: Egui<'a>,
+    open: bool,
+}
+
+impl<'a> ToolWindow<'a> {
+    /// `canvas` is the window's (see `tool_canvas`), `creator` its texture creator.
+    pub fn new(canvas: Canvas<Window>, creator: &'a TextureCreator<WindowContext>, pixels_per_point: f32) -> Self {
+        Self { canvas, egui: Egui::new(creator, pixels_per_point), open: false }
+    }
+
+    fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    fn toggle(&mut self) {
+        self.open = !self.open;
+        let window = self.canvas.window_mut();
+        if self.open {
+            window.show();
+            window.raise();
+        } else {
+            window.hide();
+        }
+    }
+
+    /// Takes an event that happened in this window; returns whether egui consumed it.
+    fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Window { win_event: WindowEvent::Close, .. } => {
+                self.toggle();
+                true
+            }
+            Event::KeyDown { scancode: Some(Scancode::Escape), repeat, .. } => {
+                if self.egui.ctx.wants_keyboard_input() {
+                    self.egui.handle_event(event);
+                } else if !repeat {
+                    self.toggle();
+                }
+                true
+            }
+            Event::Window { .. } => true,
+            _ => self.egui.handle_event(event),
+        }
+    }
+
+    /// Runs the tool for one frame and presents the window, if it's open.
+    fn draw(&mut self, content: impl FnMut(&egui::Context)) -> Result<(), Error> {
+        if !self.open {
+            self.egui.events.clear();
+            return Ok(());
+        }
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.egui.run(&mut self.canvas, content)?;
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+/// A hidden, resizable window for a `ToolWindow`, `size` points big on a display scaled by
+/// `pixels_per_point`.
+pub fn tool_canvas(video: &VideoSubsystem, title: &str, size: (u32, u32), pixels_per_point: f32) -> Result<Canvas<Window>, Error> {
+    let (w, h) = size;
+    let mut window = video.window(title, w, h).hidden().resizable().allow_highdpi().build().map_err(Error::sdl)?;
+    // Where the OS doesn't scale windows itself, size them in pixels
+    if window.drawable_size().0 <= w {
+        let scale = |points: u32| (points as f32 * pixels_per_point).round() as u32;
+        window.set_size(scale(w), scale(h)).map_err(Error::sdl)?;
+    }
+    window.into_canvas().build().map_err(Error::sdl)
+}
+
+/// The settings window itself. Returns the recent game and the restart button clicked,
+/// if any.
+fn settings_window(
+    ctx: &egui::Context,
+    settings: &mut Settings,
+    game: &[String],
+    recent: &[String],
+) -> (Option<String>, Option<Restart>) {
+    let (mut open, mut restart) = (None, None);
+    egui::Window::new("Settings")
+        .resizable(false)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            if let Some((title, details)) = game.split_first() {
+                ui.heading(title);
+                for line in details {
+                    ui.label(line);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").on_hover_text("Restart the game, keeping RAM (Ctrl+R)").clicked() {
+                        restart = Some(Restart::Reset);
+                    }
+                    if ui.button("Power cycle").on_hover_text("Switch off and on (Ctrl+Shift+R)").clicked() {
+                        restart = Some(Restart::PowerCycle);
+                    }
+                });
+                ui.separator();
+            }
+            if !recent.is_empty() {
+                ui.heading("Recent games");
+                for (i, path) in recent.iter().enumerate() {
+                    let shortcut = if i < 9 { format!("Ctrl+{}", i + 1) } else { String::new() };
+                    ui.horizontal(|ui| {
+                        if ui.button(recent::display_name(path)).on_hover_text(path).clicked() {
+                            open = Some(path.clone());
+                        }
+                        ui.weak(shortcut);
+                    });
+                }
+                ui.separator();
+            }
+            ui.heading("Video");
+            ui.horizontal(|ui| {
+                ui.label("Palette (P):");
+                for palette in HostPalette::ALL {
+                    ui.radio_value(&mut settings.palette, palette, palette.label());
+                }
+            });
+            ui.checkbox(&mut settings.sprite_outlines, "Outline sprites")
+                .on_hover_text("Darkens the pixels around sprites so they stand out from the background");
+            ui.horizontal(|ui| {
+                ui.label("Rotation:");
+                for rotation in [Rotation::R0, Rotation::R90, Rotation::R180, Rotation::R270] {
+                    ui.radio_value(&mut settings.rotation, rotation, format!("{}°", rotation.degrees()));
+                }
+            });
+            ui.checkbox(&mut settings.fullscreen, "Fullscreen");
+            ui.checkbox(&mut settings.hud, "Tetris score readout (F7)");
+            ui.checkbox(&mut settings.input_display, "Show held buttons (F8)");
+            ui.checkbox(&mut settings.ghost, "Tetris ghost piece (F9)");
+            ui.horizontal(|ui| {
+                ui.label("Background:");
+                ui.color_edit_button_srgb(&mut settings.background);
+            });
+
+            ui.separator();
+            ui.heading("Emulation");
+            ui.add(
+                egui::Slider::new(&mut settings.speed, SPEED_MIN..=SPEED_MAX)
+                    .step_by(25.0)
+                    .suffix("%")
+                    .text("Speed"),
+            );
+            ui.horizontal(|ui| {
+                ui.label("CPU clock:");
+                for factor in [1, 2, 4] {
+                    ui.radio_value(&mut settings.overclock, factor, format!("{}×", factor));
+                }
+            })
+            .response
+            .on_hover_text("Overclocking gives games more time per frame, so they slow down less when busy");
+            ui.checkbox(&mut settings.paused, "Paused");
+            ui.checkbox(&mut settings.pause_on_focus_loss, "Pause when the window loses focus");
+            ui.checkbox(&mut settings.frame_stats, "Frame-time statistics (F3)");
+            ui.checkbox(&mut settings.autoplay, "Autoplay Tetris (F6)");
+            ui.checkbox(&mut settings.hard_drop, "Tetris hard drop on Up (game patch)");
+
+            ui.separator();
+            ui.heading("Input");
+            ui.add(egui::Slider::new(&mut settings.turbo_hz, 1..=30).suffix(" Hz").text("Turbo rate"));
+            ui.checkbox(&mut settings.das, "Left/Right auto-repeat")
+                .on_hover_text("Held Left and Right repeat at the rate below instead of the game's own");
+            ui.add_enabled_ui(settings.das, |ui| {
+                ui.add(egui::Slider::new(&mut settings.das_timing.delay, 2..=60).suffix(" frames").text("Repeat delay"));
+                ui.add(egui::Slider::new(&mut settings.das_timing.repeat, 2..=30).suffix(" frames").text("Repeat rate"));
+            });
+            ui.add(egui::Slider::new(&mut settings.rumble, 0..=100).suffix("%").text("Rumble strength"))
+                .on_hover_text("How hard a game controller shakes for carts with a rumble motor");
+
+            ui.separator();
+            ui.small("F1 hides this panel.");
+        });
+    (open, restart)
+}
+
+fn modifiers(keymod: Mod) -> egui::Modifiers {
+    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+    egui::Modifiers {
+        alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        ctrl,
+        shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+/// Minimal egui painter on top of the SDL renderer.
+struct Painter<'a> {
+    creator: &'a TextureCreator<WindowContext>,
+    textures: HashMap<TextureId, Texture<'a>>,
+}
+
+impl Painter<'_> {
+    /// Creates or patches the textures egui asked for (mostly the font atlas).
+    fn update_textures(&mut self, delta: &TexturesDelta) -> Result<(), Error> {
+        for (id, image_delta) in &delta.set {
+            let ImageData::Color(image) = &image_delta.image;
+            let [w, h] = image.size;
+            // SDL blends straight alpha; egui hands out premultiplied colors
+            let pixels: Vec<u8> = image.pixels.iter().flat_map(|c| c.to_srgba_unmultiplied()).collect();
+
+            match image_delta.pos {
+                None => {
+                    let mut texture = self.creator
+                        .create_texture_static(PixelFormatEnum::RGBA32, w as u32, h as u32)
+                        .map_err(Error::sdl)?;
+                    texture.set_blend_mode(BlendMode::Blend);
+                    texture.update(None, &pixels, w * 4).map_err(Error::sdl)?;
+                    self.textures.insert(*id, texture);
+                }
+                Some([x, y]) => {
+                    if let Some(texture) = self.textures.get_mut(id) {
+                        let rect = Rect::new(x as i32, y as i32, w as u32, h as u32);
+                        texture.update(rect, &pixels, w * 4).map_err(Error::sdl)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn paint(&self, canvas: &mut Canvas<Window>, primitives: &[egui::ClippedPrimitive], ppp: f32) -> Result<(), Error> {
+        canvas.set_blend_mode(BlendMode::Blend);
+
+        for primitive in primitives {
+            let Primitive::Mesh(mesh) = &primitive.primitive else { continue };
+
+            let clip = primitive.clip_rect;
+            canvas.set_clip_rect(Rect::new(
+                (clip.min.x * ppp) as i32,
+                (clip.min.y * ppp) as i32,
+                (clip.width() * ppp).max(0.0) as u32,
+                (clip.height() * ppp).max(0.0) as u32,
+            ));
+
+            let vertices: Vec<Vertex> = mesh.vertices.iter().map(|v| {
+                let [r, g, b, a] = v.color.to_srgba_unmultiplied();
+                Vertex {
+                    position: FPoint::new(v.pos.x * ppp, v.pos.y * ppp),
+                    color: Color::RGBA(r, g, b, a),
+                    // Anti-aliased image edges reach just outside the texture, which SDL refuses
+                    tex_coord: FPoint::new(v.uv.x.clamp(0.0, 1.0), v.uv.y.clamp(0.0, 1.0)),
+                }
+            }).collect();
+
+            let texture = self.textures.get(&mesh.texture_id);
+            canvas.render_geometry(&vertices, texture, mesh.indices.as_slice()).map_err(Error::sdl)?;
+        }
+
+        canvas.set_clip_rect(None);
+        canvas.set_blend_mode(BlendMode::None);
+        Ok(())
+    }
+}
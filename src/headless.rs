@@ -0,0 +1,286 @@
+use rustris_core::{Event, InputSource, Registers, VideoSink, GB, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::crashdump;
+use crate::error::Error;
+use crate::emu_thread::Hooks;
+use crate::script::{Script, ScriptOutput};
+use crate::stream::FrameFeed;
+
+use std::io::Write;
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// Bytes per row of the headless RGB24 framebuffer.
+pub const PITCH: usize = SCREEN_WIDTH as usize * 3;
+
+/// Headless front-end: runs the core with no window, audio device or frame limiter.
+/// - Frames are rendered into an in-memory RGB24 buffer instead of an SDL texture.
+/// - Runs for `frames` frames (or forever when `None`) as fast as the host allows.
+/// - Prints a short throughput summary when done, which doubles as a quick benchmark.
+/// - With `hash_frames`, prints every frame's hash as it's finished (see `frame_hash`).
+/// - Ctrl+C stops the run cleanly so the caller can still flush state.
+/// - Runs `hooks`: the user script's, which makes scripted bots usable at full speed, the
+///   autoplayer (a sandbox for trying its weights quickly), the piece sequence, the
+///   high-score keeper, the session statistics, the speedrun timer (its splits; the
+///   clock isn't drawn), the telemetry, which goes out as fast as the clients take it,
+///   the piece colors, so screenshots show them, the serial output, the stack check
+///   warnings, the remote input clients' buttons (best stamped with frames, see
+///   `remote::serve`, at this speed) and the frame stream, which skips the frames its
+///   viewers can't keep up with. A broken assertion or the script's `pause` ends
+///   the run after that frame, with the reason on stderr, since there's no debugger to
+///   stop in.
+///   Practice mode (there is no retry key here), the ghost piece, demos and the hard-drop
+///   patch are left out.
+/// - A machine that can't go on (the CPU locked up, or the core panicked) ends the run with
+///   a crash dump (see `crashdump::report`).
+///
+/// Returns the machine so the caller can persist its state, plus the last frame rendered
+/// (RGB24, `PITCH` bytes per row) for screenshots and regression checks, and the error that
+/// stopped the machine, if one did.
+pub fn run(mut gb: GB, frames: Option<u64>, hash_frames: bool, hooks: Hooks) -> (GB, Vec<u8>, Result<(), Error>) {
+    let Hooks { mut script, mut high_scores, mut bot, mut pieces, mut session, mut speedrun, mut telemetry, mut colorizer, mut serial, mut stack, mut assertions, mut remote, stream, .. } = hooks;
+    let output = script.as_ref().map(Script::output);
+    let mut screen = Screen {
+        framebuffer: vec![0u8; PITCH * SCREEN_HEIGHT as usize],
+        script: output.clone(),
+        stream,
+    };
+    let mut pad = Pad { script: output, autoplay: 0, remote: 0 };
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed)) {
+        eprintln!("Warning: cannot install Ctrl+C handler: {}", e);
+    }
+
+    let start = Instant::now();
+    let mut count: u64 = 0;
+    let mut result = Ok(());
+    while frames.is_none_or(|n| count < n) && !interrupted.load(Ordering::Relaxed) {
+        if let Some(script) = &mut script {
+            script.frame_start(&mut gb);
+        }
+        if let Some(bot) = &mut bot {
+            pad.autoplay = bot.buttons(&gb);
+        }
+        if let Some(remote) = &mut remote {
+            pad.remote = remote.before_frame();
+        }
+        let mut stop = None;
+        let ran = crashdump::guard(&mut gb, |gb| {
+            gb.run_frame_with(&mut screen, &mut pad, |gb, frame_done| {
+                if let Some(script) = &mut script {
+                    script.after_step(gb, frame_done);
+                    stop = stop.take().or(script.take_pause());
+                }
+                if let Some(assertions) = &mut assertions {
+                    stop = stop.take().or(assertions.after_step(gb));
+                }
+            })
+        });
+        if let Err(e) = ran.and_then(|()| gb.fault().map_err(Error::from)) {
+            crashdump::report(&gb, &e.to_string());
+            result = Err(e);
+            break;
+        }
+        if let Some(high_scores) = &mut high_scores {
+            high_scores.after_frame(&mut gb);
+        }
+        if let Some(pieces) = &mut pieces {
+            pieces.after_frame(&mut gb);
+        }
+        if let Some(session) = &mut session {
+            session.after_frame(&gb);
+        }
+        if let Some(speedrun) = &mut speedrun {
+            speedrun.after_frame(&gb);
+        }
+        if let Some(telemetry) = &mut telemetry {
+            telemetry.after_frame(&gb);
+        }
+        if let Some(colorizer) = &mut colorizer {
+            colorizer.after_frame(&mut gb);
+        }
+        let sent = gb.take_serial_output();
+        if let Some(serial) = &mut serial {
+            serial.write(&sent);
+        }
+        if let Some(stack) = &mut stack {
+            stack.after_frame(&mut gb);
+        }
+        if hash_frames {
+            println!("Frame {}: {:016x}", count, frame_hash(&screen.framebuffer));
+        }
+        count += 1;
+        if let Some(reason) = stop {
+            eprintln!("Stopped: {}", reason);
+            break;
+        }
+    }
+    if let Some(high_scores) = &mut high_scores {
+        high_scores.save(&gb);
+    }
+    if let Some(session) = &mut session {
+        session.finish();
+    }
+    if let Some(speedrun) = &mut speedrun {
+        speedrun.finish();
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{} frames in {:.3} s ({:.1} FPS, {:.1}x real time)",
+        count,
+        elapsed,
+        count as f64 / elapsed,
+        count as f64 / elapsed / 59.7275,
+    );
+    (gb, screen.framebuffer, result)
+}
+
+/// FNV-1a hash of an RGB24 frame (`--hash`): equal on every host for a run that plays out
+/// the same, so comparing hashes checks a build without comparing images. That takes the
+/// same seed (`--hash` defaults it to 0, see `GB::seed`), and the picture is hashed as
+/// shown, so the palette (and script text) must match too.
+pub fn frame_hash(frame: &[u8]) -> u64 {
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    for byte in frame {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01B3);
+    }
+    hash
+}
+
+/// Frames a test ROM may run (two emulated minutes) before `run_test` gives up.
+pub const TEST_TIMEOUT_FRAMES: u64 = 120 * 60;
+
+/// Test ROM runner (`--test-rom`). Understands both common reporting conventions:
+/// - Blargg: results are printed over the serial port. Output is echoed to stdout as it
+///   arrives and the run ends once it contains "Passed" or "Failed".
+/// - mooneye-gb: the ROM executes `LD B,B` with B/C/D/E/H/L = 3/5/8/13/21/34 on success
+///   or all 0x42 on failure.
+///
+/// Gives up after `frames` frames (`TEST_TIMEOUT_FRAMES` by default).
+///
+/// Returns the process exit status: 0 passed, 1 failed, 2 timed out.
+pub fn run_test(mut gb: GB, frames: Option<u64>) -> i32 {
+    let mut screen = Screen {
+        framebuffer: vec![0u8; PITCH * SCREEN_HEIGHT as usize],
+        script: None,
+        stream: None,
+    };
+    let mut output = String::new();
+    let limit = frames.unwrap_or(TEST_TIMEOUT_FRAMES);
+    let (serial_tx, serial) = mpsc::channel();
+    gb.subscribe(move |event| {
+        if let Event::SerialByte(byte) = *event {
+            let _ = serial_tx.send(byte);
+        }
+    });
+
+    for _ in 0..limit {
+        let mut breakpoint = None;
+        gb.run_frame_with(&mut screen, &mut Pad { script: None, autoplay: 0, remote: 0 }, |gb, _| {
+            if gb.take_breakpoint() && let Some(passed) = mooneye_result(gb.registers()) {
+                breakpoint.get_or_insert((passed, gb.registers()));
+            }
+        });
+        if let Err(fault) = gb.fault() {
+            let message = fault.to_string();
+            println!("Failed: {}", message);
+            match crashdump::write(&gb, &message) {
+                Ok(path) => println!("Crash dump written to {}", path.display()),
+                Err(e) => eprintln!("Error writing crash dump: {}", e),
+            }
+            return 1;
+        }
+        if let Some((passed, r)) = breakpoint {
+            println!(
+                "{} (B={:02X} C={:02X} D={:02X} E={:02X} H={:02X} L={:02X})",
+                if passed { "Passed" } else { "Failed" },
+                r.b, r.c, r.d, r.e, r.h, r.l,
+            );
+            return if passed { 0 } else { 1 };
+        }
+
+        let serial: Vec<u8> = serial.try_iter().collect();
+        if serial.is_empty() {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&serial);
+        print!("{}", text);
+        let _ = std::io::stdout().flush();
+        output.push_str(&text);
+
+        let status = if output.contains("Passed") {
+            0
+        } else if output.contains("Failed") {
+            1
+        } else {
+            continue;
+        };
+        if !output.ends_with('\n') {
+            println!();
+        }
+        return status;
+    }
+
+    if !output.is_empty() && !output.ends_with('\n') {
+        println!();
+    }
+    println!("Timed out after {} frames", limit);
+    2
+}
+
+/// Ends the process (status 1, with a crash dump) if the CPU locked up on an opcode the
+/// core doesn't implement: the game can't make progress from there.
+pub fn exit_on_lockup(gb: &GB) {
+    if let Err(fault) = gb.fault() {
+        crashdump::fatal(gb, &fault.to_string());
+    }
+}
+
+/// Interprets the registers at an `LD B,B` breakpoint following the mooneye-gb
+/// convention: `Some(true)` passed, `Some(false)` failed, `None` for any other breakpoint.
+fn mooneye_result(r: Registers) -> Option<bool> {
+    match [r.b, r.c, r.d, r.e, r.h, r.l] {
+        [3, 5, 8, 13, 21, 34] => Some(true),
+        [0x42, 0x42, 0x42, 0x42, 0x42, 0x42] => Some(false),
+        _ => None,
+    }
+}
+
+/// In-memory video sink; only script OSD text is drawn on top.
+struct Screen {
+    framebuffer: Vec<u8>,
+    script: Option<ScriptOutput>,
+    stream: Option<FrameFeed>, // Gets each finished frame
+}
+
+impl VideoSink for Screen {
+    fn buffer(&mut self) -> (&mut [u8], usize) {
+        (&mut self.framebuffer, PITCH)
+    }
+
+    fn present(&mut self) {
+        if let Some(script) = &self.script {
+            script.draw(&mut self.framebuffer, PITCH);
+        }
+        if let Some(stream) = &self.stream {
+            stream.send(&self.framebuffer, PITCH);
+        }
+    }
+}
+
+/// Headless input: only a script, the autoplayer or remote clients can press buttons.
+struct Pad {
+    script: Option<ScriptOutput>,
+    autoplay: u8, // Buttons the autoplayer holds this frame
+    remote: u8,   // Buttons remote clients hold this frame
+}
+
+impl InputSource for Pad {
+    fn poll(&mut self) -> u8 {
+        self.script.as_ref().map_or(0, ScriptOutput::buttons) | self.autoplay | self.remote
+    }
+}
@@ -0,0 +1,77 @@
+use rustris_core::GB;
+
+use std::path::{Path, PathBuf};
+
+/// Frames the game runs after starting before its table is put back: Tetris clears WRAM
+/// while it initializes, and shows no scores until well after its copyright screen.
+const RESTORE_FRAME: u32 = 60;
+
+/// High-score table of a game that forgets it at power-off (the ROM database says where
+/// it lives, see `RomInfo::high_scores`), kept in `<rom>.hiscore` next to the ROM:
+/// - The saved table is written into WRAM `RESTORE_FRAME` frames after the game starts
+///   (boot ROM not counted), once the game has initialized its RAM.
+/// - The game's table is read back and saved when the machine stops, and kept across
+///   resets, which clear it again.
+pub struct HighScores {
+    path: PathBuf,
+    region: (u16, u16),
+    table: Option<Vec<u8>>, // Last known table: the file's, then the game's
+    frames: u32,            // Frames since the game started, up to `RESTORE_FRAME`
+}
+
+impl HighScores {
+    /// The keeper for `gb` (running the ROM at `rom_path`), if its game has a known table.
+    pub fn for_game(gb: &GB, rom_path: &str) -> Option<Self> {
+        let region = gb.rom_info()?.high_scores?;
+        let path = Path::new(rom_path).with_extension("hiscore");
+        let table = match std::fs::read(&path) {
+            Ok(data) if data.len() == (region.1 - region.0) as usize + 1 => Some(data),
+            Ok(_) => {
+                eprintln!("Ignoring {}: not a high-score table for this game", path.display());
+                None
+            }
+            Err(_) => None,
+        };
+        Some(Self { path, region, table, frames: 0 })
+    }
+
+    fn restored(&self) -> bool {
+        self.frames >= RESTORE_FRAME
+    }
+
+    /// Call after every emulated frame: puts the saved table back when it's time.
+    pub fn after_frame(&mut self, gb: &mut GB) {
+        if self.restored() || gb.boot_rom_running() {
+            return;
+        }
+        self.frames += 1;
+        if self.restored() && let Some(table) = &self.table {
+            for (addr, &byte) in (self.region.0..=self.region.1).zip(table) {
+                gb.write_byte(addr, byte);
+            }
+        }
+    }
+
+    /// Call before resetting the machine: keeps this session's scores and puts them back
+    /// once the game has started over.
+    pub fn restart(&mut self, gb: &GB) {
+        self.capture(gb);
+        self.frames = 0;
+    }
+
+    /// Call when the machine stops: writes the table to disk.
+    pub fn save(&mut self, gb: &GB) {
+        self.capture(gb);
+        let Some(table) = &self.table else { return };
+        if let Err(e) = std::fs::write(&self.path, table) {
+            eprintln!("Error writing {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Takes the table from the game, unless the saved one hasn't been put back yet.
+    fn capture(&mut self, gb: &GB) {
+        if self.restored() {
+            self.table = Some((self.region.0..=self.region.1).map(|addr| gb.read_byte(addr)).collect());
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::error::Error;
+use crate::osd::{self, CHAR_HEIGHT, CHAR_WIDTH};
+use crate::tetris::{self, Piece, Spawns, TetrisState};
+
+use rustris_core::GB;
+
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+/// Size of the readout in Game Boy pixels: four lines of up to 12 characters.
+const HUD_WIDTH: u32 = 12 * CHAR_WIDTH as u32;
+const HUD_HEIGHT: u32 = 4 * CHAR_HEIGHT as u32;
+
+/// Gap between the picture and the readout beside it, in Game Boy pixels.
+const MARGIN: u32 = 4;
+
+const TEXT_COLOR: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+/// Tetris readout (`--hud`, F7): score, level, lines and the drought (pieces dealt since
+/// the last long bar), decoded from player 1's memory (see `TetrisState`):
+/// - Drawn at the picture's scale beside it when the window leaves room (wider than
+///   10:9), otherwise in the window's top-right corner, over the game's own panel.
+/// - The drought is counted from the spawns seen in the copies the UI gets, and starts
+///   over with each game.
+/// - Shown only while the game is the original Tetris.
+pub struct Hud<'a> {
+    texture: Texture<'a>,
+    pixels: Vec<u8>, // RGB24, `HUD_WIDTH` × `HUD_HEIGHT`
+    tetris: bool,    // The game is one `TetrisState` understands
+    spawns: Spawns,
+    drought: u32,
+}
+
+impl<'a> Hud<'a> {
+    pub fn new(creator: &'a TextureCreator<WindowContext>) -> Result<Self, Error> {
+        let texture = creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, HUD_WIDTH, HUD_HEIGHT)
+            .map_err(Error::sdl)?;
+        Ok(Self {
+            texture,
+            pixels: vec![0; (HUD_WIDTH * HUD_HEIGHT * 3) as usize],
+            tetris: false,
+            spawns: Spawns::new(),
+            drought: 0,
+        })
+    }
+
+    /// Follows the loaded game (player 1's).
+    pub fn set_game(&mut self, gb: &GB) {
+        self.tetris = tetris::is_tetris(gb);
+        self.spawns = Spawns::new();
+        self.drought = 0;
+    }
+
+    /// Whether the readout needs memory copies.
+    pub fn wants_memory(&self, enabled: bool) -> bool {
+        enabled && self.tetris
+    }
+
+    /// Decodes the latest memory copy and draws the readout next to `picture` (where player
+    /// 1's picture shows, rotation included), within the part of the window that machine
+    /// gets, which ends at `column_end`.
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, memory: &[u8], picture: Rect, column_end: i32) -> Result<(), Error> {
+        if !self.tetris {
+            return Ok(());
+        }
+        let state = TetrisState::decode(memory);
+        if self.spawns.observe(&state) {
+            let long_bar = state.active.is_some_and(|active| active.piece == Piece::I);
+            self.drought = if state.fresh_game() || long_bar { 0 } else { self.drought + 1 };
+        }
+
+        let text = format!(
+            "SCORE {}\nLEVEL {}\nLINES {}\nDROUGHT {}",
+            state.score, state.level, state.lines, self.drought,
+        );
+        self.pixels.fill(0);
+        osd::draw_text(&mut self.pixels, HUD_WIDTH as usize * 3, 0, 0, &text, TEXT_COLOR);
+        self.texture.update(None, &self.pixels, HUD_WIDTH as usize * 3).map_err(Error::sdl)?;
+
+        let scale = (picture.width().min(picture.height()) / 144).max(1);
+        let (w, h) = (HUD_WIDTH * scale, HUD_HEIGHT * scale);
+        let beside = picture.right() + (MARGIN * scale) as i32;
+        let dest = if beside + w as i32 <= column_end {
+            Rect::new(beside, picture.top(), w, h)
+        } else {
+            let (out_w, _) = canvas.output_size().map_err(Error::sdl)?;
+            Rect::new(out_w as i32 - (w + MARGIN * scale) as i32, (MARGIN * scale) as i32, w, h)
+        };
+        canvas.copy(&self.texture, None, dest).map_err(Error::sdl)
+    }
+}
@@ -0,0 +1,243 @@
+/// An I/O register: its name, address and how its value reads.
+pub struct IoRegister {
+    pub name: &'static str,
+    pub addr: u16,
+    pub decode: fn(u8) -> String, // What the bits say, or the value in decimal for plain numbers
+}
+
+const fn reg(name: &'static str, addr: u16, decode: fn(u8) -> String) -> IoRegister {
+    IoRegister { name, addr, decode }
+}
+
+/// The I/O registers the debugger window lists, by area. There's no APU, so the sound
+/// registers hold what the game last wrote.
+pub const GROUPS: [(&str, &[IoRegister]); 8] = [
+    ("Joypad and serial", &[
+        reg("P1", 0xFF00, joypad),
+        reg("SB", 0xFF01, serial_byte),
+        reg("SC", 0xFF02, serial_control),
+    ]),
+    ("Timer", &[
+        reg("DIV", 0xFF04, number),
+        reg("TIMA", 0xFF05, number),
+        reg("TMA", 0xFF06, number),
+        reg("TAC", 0xFF07, timer_control),
+    ]),
+    ("Interrupts", &[
+        reg("IF", 0xFF0F, interrupts),
+        reg("IE", 0xFFFF, interrupts),
+    ]),
+    ("LCD", &[
+        reg("LCDC", 0xFF40, lcd_control),
+        reg("STAT", 0xFF41, lcd_status),
+        reg("SCY", 0xFF42, number),
+        reg("SCX", 0xFF43, number),
+        reg("LY", 0xFF44, number),
+        reg("LYC", 0xFF45, number),
+        reg("DMA", 0xFF46, dma),
+        reg("BGP", 0xFF47, palette),
+        reg("OBP0", 0xFF48, palette),
+        reg("OBP1", 0xFF49, palette),
+        reg("WY", 0xFF4A, number),
+        reg("WX", 0xFF4B, number),
+    ]),
+    ("Sound channels 1 and 2", &[
+        reg("NR10", 0xFF10, sweep),
+        reg("NR11", 0xFF11, duty_length),
+        reg("NR12", 0xFF12, envelope),
+        reg("NR13", 0xFF13, number),
+        reg("NR14", 0xFF14, period_control),
+        reg("NR21", 0xFF16, duty_length),
+        reg("NR22", 0xFF17, envelope),
+        reg("NR23", 0xFF18, number),
+        reg("NR24", 0xFF19, period_control),
+    ]),
+    ("Sound channels 3 and 4", &[
+        reg("NR30", 0xFF1A, wave_dac),
+        reg("NR31", 0xFF1B, number),
+        reg("NR32", 0xFF1C, wave_level),
+        reg("NR33", 0xFF1D, number),
+        reg("NR34", 0xFF1E, period_control),
+        reg("NR41", 0xFF20, noise_length),
+        reg("NR42", 0xFF21, envelope),
+        reg("NR43", 0xFF22, noise),
+        reg("NR44", 0xFF23, period_control),
+    ]),
+    ("Sound control", &[
+        reg("NR50", 0xFF24, master_volume),
+        reg("NR51", 0xFF25, panning),
+        reg("NR52", 0xFF26, sound_on),
+    ]),
+    ("CGB", &[
+        reg("KEY1", 0xFF4D, speed),
+        reg("VBK", 0xFF4F, vram_bank),
+        reg("SVBK", 0xFF70, wram_bank),
+    ]),
+];
+
+/// The register named `name` in `GROUPS`, in any case.
+pub fn named(name: &str) -> Option<&'static IoRegister> {
+    GROUPS.iter().flat_map(|(_, registers)| registers.iter()).find(|register| register.name.eq_ignore_ascii_case(name))
+}
+
+/// The register at `addr` in `GROUPS`, if it's there.
+pub fn at(addr: u16) -> Option<&'static IoRegister> {
+    GROUPS.iter().flat_map(|(_, registers)| registers.iter()).find(|register| register.addr == addr)
+}
+
+const INTERRUPT_NAMES: [&str; 5] = ["VBlank", "STAT", "Timer", "Serial", "Joypad"];
+
+/// Names of the set bits of `value`, `names[0]` being bit 0, or "none".
+fn set_bits(value: u8, names: &[&str]) -> String {
+    let set: Vec<&str> = names.iter().enumerate().filter(|&(bit, _)| value & 1 << bit != 0).map(|(_, &name)| name).collect();
+    if set.is_empty() { String::from("none") } else { set.join(" ") }
+}
+
+fn on(value: u8, bit: u8) -> bool {
+    value & 1 << bit != 0
+}
+
+fn number(value: u8) -> String {
+    value.to_string()
+}
+
+/// A group is selected by writing 0 to its bit, and its pressed buttons then read 0.
+fn joypad(value: u8) -> String {
+    let pressed = !value & 0x0F;
+    match (on(value, 5), on(value, 4)) {
+        (false, false) => format!("buttons and d-pad selected; low: {}", set_bits(pressed, &["0", "1", "2", "3"])),
+        (false, true) => format!("buttons selected; pressed: {}", set_bits(pressed, &["A", "B", "Select", "Start"])),
+        (true, false) => format!("d-pad selected; pressed: {}", set_bits(pressed, &["Right", "Left", "Up", "Down"])),
+        (true, true) => String::from("nothing selected"),
+    }
+}
+
+fn serial_byte(value: u8) -> String {
+    if value.is_ascii_graphic() { format!("'{}'", value as char) } else { String::new() }
+}
+
+fn serial_control(value: u8) -> String {
+    let clock = if on(value, 0) { "internal clock" } else { "external clock" };
+    if on(value, 7) { format!("transferring, {}", clock) } else { String::from(clock) }
+}
+
+fn timer_control(value: u8) -> String {
+    let hz = [4096, 262144, 65536, 16384][(value & 3) as usize];
+    format!("{}, {} Hz", if on(value, 2) { "on" } else { "off" }, hz)
+}
+
+fn interrupts(value: u8) -> String {
+    set_bits(value, &INTERRUPT_NAMES)
+}
+
+fn lcd_control(value: u8) -> String {
+    let map = |bit| if on(value, bit) { "9C00" } else { "9800" };
+    let mut parts = vec![if on(value, 7) { "LCD on" } else { "LCD off" }];
+    if on(value, 0) {
+        parts.push("BG");
+    }
+    if on(value, 5) {
+        parts.push("window");
+    }
+    if on(value, 1) {
+        parts.push(if on(value, 2) { "sprites 8×16" } else { "sprites 8×8" });
+    }
+    format!(
+        "{}; BG map {}, window map {}, tiles {}",
+        parts.join(", "), map(3), map(6), if on(value, 4) { "8000" } else { "8800" }
+    )
+}
+
+fn lcd_status(value: u8) -> String {
+    let mode = ["HBlank", "VBlank", "OAM scan", "drawing"][(value & 3) as usize];
+    let coincidence = if on(value, 2) { ", LY = LYC" } else { "" };
+    let sources = set_bits(value >> 3 & 0x0F, &["HBlank", "VBlank", "OAM", "LYC"]);
+    format!("mode {} ({}){}; interrupt on: {}", value & 3, mode, coincidence, sources)
+}
+
+fn dma(value: u8) -> String {
+    format!("from {:02X}00", value)
+}
+
+/// The shade (0 lightest, 3 darkest) each color 0–3 gets.
+fn palette(value: u8) -> String {
+    let shades: Vec<String> = (0..4).map(|color| (value >> (color * 2) & 3).to_string()).collect();
+    format!("shades {}", shades.join(" "))
+}
+
+fn sweep(value: u8) -> String {
+    if value >> 4 & 7 == 0 {
+        return String::from("no sweep");
+    }
+    let direction = if on(value, 3) { "down" } else { "up" };
+    format!("pace {}, {}, step {}", value >> 4 & 7, direction, value & 7)
+}
+
+fn duty_length(value: u8) -> String {
+    let duty = ["12.5%", "25%", "50%", "75%"][(value >> 6) as usize];
+    format!("duty {}, length {}", duty, value & 0x3F)
+}
+
+fn envelope(value: u8) -> String {
+    let direction = if on(value, 3) { "up" } else { "down" };
+    let dac = if value & 0xF8 == 0 { ", DAC off" } else { "" };
+    format!("volume {}, {} every {}{}", value >> 4, direction, value & 7, dac)
+}
+
+/// NRx4: trigger, length enable and, for channels 1–3, the period's high bits.
+fn period_control(value: u8) -> String {
+    let mut parts = Vec::new();
+    if on(value, 7) {
+        parts.push(String::from("trigger"));
+    }
+    if on(value, 6) {
+        parts.push(String::from("length on"));
+    }
+    parts.push(format!("period high {}", value & 7));
+    parts.join(", ")
+}
+
+fn wave_dac(value: u8) -> String {
+    String::from(if on(value, 7) { "DAC on" } else { "DAC off" })
+}
+
+fn wave_level(value: u8) -> String {
+    String::from(["mute", "100%", "50%", "25%"][(value >> 5 & 3) as usize])
+}
+
+fn noise_length(value: u8) -> String {
+    format!("length {}", value & 0x3F)
+}
+
+fn noise(value: u8) -> String {
+    let width = if on(value, 3) { "7-bit" } else { "15-bit" };
+    format!("shift {}, {}, divider {}", value >> 4, width, value & 7)
+}
+
+fn master_volume(value: u8) -> String {
+    format!("left {}, right {}", value >> 4 & 7, value & 7)
+}
+
+fn panning(value: u8) -> String {
+    format!("left: {}; right: {}", set_bits(value >> 4, &["1", "2", "3", "4"]), set_bits(value & 0x0F, &["1", "2", "3", "4"]))
+}
+
+fn sound_on(value: u8) -> String {
+    if !on(value, 7) {
+        return String::from("off");
+    }
+    format!("on, playing: {}", set_bits(value & 0x0F, &["1", "2", "3", "4"]))
+}
+
+fn speed(value: u8) -> String {
+    let speed = if on(value, 7) { "double speed" } else { "normal speed" };
+    if on(value, 0) { format!("{}, switch armed", speed) } else { String::from(speed) }
+}
+
+fn vram_bank(value: u8) -> String {
+    format!("bank {}", value & 1)
+}
+
+fn wram_bank(value: u8) -> String {
+    format!("bank {}", (value & 7).max(1))
+}
@@ -0,0 +1,152 @@
+use crate::options::{parse_button, BUTTON_NAMES};
+
+use std::path::{Path, PathBuf};
+
+/// Longest macro, in frames (about a minute): recording stops there.
+const MAX_FRAMES: usize = 3600;
+
+/// Input macros (`--macro`): short button sequences bound to keys, kept per game in
+/// `<rom>.macros` next to the ROM:
+/// - Shift+key starts recording the buttons held at the start of each frame, from the
+///   next one; Shift+key again stops (or `MAX_FRAMES` does) and saves the file. Idle
+///   frames before the first press and after the last release are left out.
+/// - The key on its own plays the macro from the start of the next frame, one recorded
+///   frame per emulated frame, its buttons replacing everything else held (demos aside),
+///   so a combo lands the same way every time. Pressing it again starts over.
+/// - The file is text, a line per key, each frame's buttons joined with `+`, `-` for none
+///   and `*N` repeating a frame: `Q = down+start - right*3 a`.
+pub struct Macros {
+    path: PathBuf,
+    macros: Vec<(String, Vec<u8>)>,        // Key name and the buttons of each frame
+    playing: Option<(usize, usize)>,       // Macro being played and its next frame
+    recording: Option<(String, Vec<u8>)>,  // Key being recorded and the frames so far
+}
+
+impl Macros {
+    /// The macros of the ROM at `rom_path`; none if its file is missing or unreadable.
+    pub fn for_rom(rom_path: &str) -> Self {
+        let path = Path::new(rom_path).with_extension("macros");
+        let macros = match std::fs::read_to_string(&path) {
+            Ok(text) => parse(&text).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { path, macros, playing: None, recording: None }
+    }
+
+    /// Plays the macro on `key` from the next frame, if it has one.
+    pub fn play(&mut self, key: &str) {
+        match self.macros.iter().position(|(name, _)| name == key) {
+            Some(index) => self.playing = Some((index, 0)),
+            None => println!("Macro {}: nothing recorded (Shift+{} to record)", key, key),
+        }
+    }
+
+    /// Starts recording the macro on `key`, or stops and saves the recording in progress.
+    pub fn toggle_recording(&mut self, key: &str) {
+        if self.recording.is_some() {
+            self.finish();
+        } else {
+            self.playing = None;
+            self.recording = Some((key.to_string(), Vec::new()));
+            println!("Macro {}: recording (Shift+{} to stop)", key, key);
+        }
+    }
+
+    /// Call at the start of each frame: the buttons the macro playing holds during it.
+    pub fn next_frame(&mut self) -> Option<u8> {
+        let (index, frame) = self.playing?;
+        let buttons = self.macros[index].1.get(frame).copied();
+        self.playing = buttons.is_some().then_some((index, frame + 1));
+        buttons
+    }
+
+    /// Call at the start of each frame, with the buttons it runs with: records them while
+    /// recording.
+    pub fn record(&mut self, buttons: u8) {
+        let Some((_, frames)) = &mut self.recording else { return };
+        if buttons != 0 || !frames.is_empty() {
+            frames.push(buttons);
+        }
+        if frames.len() >= MAX_FRAMES {
+            self.finish();
+        }
+    }
+
+    /// Saves the recording in progress, if any.
+    pub fn finish(&mut self) {
+        let Some((key, mut frames)) = self.recording.take() else { return };
+        let held = frames.iter().rposition(|&buttons| buttons != 0).map_or(0, |last| last + 1);
+        frames.truncate(held);
+        if frames.is_empty() {
+            println!("Macro {}: no buttons recorded, left as it was", key);
+            return;
+        }
+        let len = frames.len();
+        match self.macros.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, old)) => *old = frames,
+            None => self.macros.push((key.clone(), frames)),
+        }
+        match std::fs::write(&self.path, format(&self.macros)) {
+            Ok(()) => println!("Macro {}: recorded {} frames to {}", key, len, self.path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// Reads a macro file (see `Macros`); blank lines and `#` comments are skipped.
+fn parse(text: &str) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut macros = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |e: String| format!("line {}: {}", number + 1, e);
+        // Key names may contain spaces (and `=` is one), the frames never have `=`
+        let (key, sequence) = line.rsplit_once('=').ok_or_else(|| error(String::from("expected KEY = FRAMES")))?;
+        let mut frames = Vec::new();
+        for token in sequence.split_whitespace() {
+            let (buttons, count) = match token.split_once('*') {
+                Some((buttons, count)) => {
+                    let count = count.parse().ok().filter(|n| (1..=MAX_FRAMES).contains(n)).ok_or_else(|| error(format!("invalid repeat count: {}", token)))?;
+                    (buttons, count)
+                }
+                None => (token, 1),
+            };
+            let mut mask = 0;
+            if buttons != "-" {
+                for name in buttons.split('+') {
+                    mask |= parse_button(name).ok_or_else(|| error(format!("unknown button: {}", name)))?;
+                }
+            }
+            frames.extend(std::iter::repeat_n(mask, count));
+            if frames.len() > MAX_FRAMES {
+                return Err(error(format!("longer than {} frames", MAX_FRAMES)));
+            }
+        }
+        macros.push((key.trim().to_string(), frames));
+    }
+    Ok(macros)
+}
+
+/// Writes macros as `parse` reads them, runs of the same buttons as one `*N` frame.
+fn format(macros: &[(String, Vec<u8>)]) -> String {
+    let mut text = String::new();
+    for (key, frames) in macros {
+        text.push_str(key);
+        text.push_str(" =");
+        for run in frames.chunk_by(|a, b| a == b) {
+            let names: Vec<&str> = BUTTON_NAMES.iter().enumerate().filter(|&(bit, _)| run[0] & (1 << bit) != 0).map(|(_, name)| *name).collect();
+            text.push(' ');
+            text.push_str(&if names.is_empty() { String::from("-") } else { names.join("+") });
+            if run.len() > 1 {
+                text.push_str(&format!("*{}", run.len()));
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
@@ -1,147 +1,1439 @@
-mod cartridge;
-mod ppu;
-mod mmu;
-mod cpu;
-mod gb;
+mod assertions;
+mod bot;
+mod colorize;
+mod config;
+mod crashdump;
+mod das;
+mod debug_session;
+mod debug_window;
+mod debugger;
+mod demo;
+mod emu_thread;
+mod error;
+mod gbs;
+mod ghost;
+mod gui;
+mod headless;
+mod hiscores;
+mod hud;
+mod ioregs;
+mod macros;
+mod memsearch;
+mod menu;
+mod netplay;
+mod nocart;
+mod options;
+mod pacing;
+mod practice;
+mod recent;
+mod reload;
+mod remote;
+mod replay;
+mod osd;
+mod rewind;
+mod rumble;
+mod screenshot;
+mod script;
+mod selftest;
+mod serial;
+mod session;
+mod shader;
+mod speedrun;
+mod stack;
+mod stats;
+mod stream;
+mod telemetry;
+mod tetris;
+mod trace;
+mod turbo;
+mod versus;
+mod video;
+mod watch;
+mod websocket;
 
-use gb::GB;
-use cartridge::Cartridge;
+use rustris_core::{Cartridge, EmulatorError, Model, RamInit, GB};
+use rustris_core::gb::{COVERAGE_CODE, COVERAGE_DATA};
+use assertions::Assertions;
+use bot::Bot;
+use colorize::Colorizer;
+use config::{Config, Value};
+use debug_session::DebugSession;
+use debugger::{DebugCommand, DebugView, Debugger, Tui};
+use demo::{Attract, Demo, Recorder};
+use error::Error;
+use emu_thread::{Command, EmuThread, Hooks};
+use ghost::Ghost;
+use gui::{Gui, Restart, Settings, ToolWindow};
+use hiscores::HighScores;
+use hud::Hud;
+use macros::Macros;
+use memsearch::MemoryView;
+use menu::{Action, Menu};
+use recent::RecentRoms;
+use reload::FileWatcher;
+use remote::{Orders, RemoteInput};
+use rumble::{Motor, Rumble};
+use watch::WatchList;
+use options::{FrameHash, Options, SPEED_MIN, SPEED_MAX};
+use pacing::{Limiter, Pacing, Vblank};
+use practice::Practice;
+use script::Script;
+use serial::{SerialConsole, SerialOutput};
+use session::Session;
+use shader::Shader;
+use speedrun::Speedrun;
+use stack::StackWarnings;
+use stats::FrameStats;
+use stream::FrameFeed;
+use telemetry::{Feed, Telemetry};
+use tetris::{HardDrop, PieceQueue};
 
-use sdl2::pixels::PixelFormatEnum;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::{FullscreenType, Window};
 use sdl2::event::Event;
-use sdl2::keyboard::Scancode;
+use sdl2::keyboard::{Mod, Scancode};
 
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::time::{Duration, Instant};
-use std::hint::spin_loop as cpu_relax;
-
-/// Maps SDL scancodes to Game Boy input bitmasks.
-const INPUT_MASKS: [(Scancode, u8); 8] = [
-    (Scancode::Right,  gb::BTN_RIGHT),
-    (Scancode::Left,   gb::BTN_LEFT),
-    (Scancode::Up,     gb::BTN_UP),
-    (Scancode::Down,   gb::BTN_DOWN),
-    (Scancode::X,      gb::BTN_A),
-    (Scancode::Z,      gb::BTN_B),
-    (Scancode::Space,  gb::BTN_SELECT),
-    (Scancode::Return, gb::BTN_START),
-];
-
-/// Frame period:
-/// - Real DMG cadence: 59.7275 FPS → 16_742_706 ns per frame.
-const GB_FRAME_NS: u64 = 16_742_706;    // ~59.7275 FPS (Game Boy)
-
-fn main() {
-    let rom_path = "tetris.gb";
-    let cartridge = match Cartridge::from_file(rom_path) {
-        Ok(cart) => cart,
+
+/// Longest the UI thread waits for a frame before pumping SDL events again. Kept short
+/// so key presses reach the emulation thread within ~1 ms rather than once per frame.
+const INPUT_POLL: Duration = Duration::from_millis(1);
+
+/// Redraw interval when no new frame arrives (paused), keeping the panel responsive.
+const IDLE_REDRAW: Duration = Duration::from_millis(16);
+
+/// Display DPI that corresponds to a scale factor of 1.0 on Windows/X11.
+const BASE_DPI: f32 = 96.0;
+
+/// Mouse inactivity after which the cursor is hidden in fullscreen.
+const CURSOR_HIDE_DELAY: Duration = Duration::from_secs(2);
+
+/// Runs the front-end chosen by the options. Setup failures are printed and end the
+/// process with a failure status, except a ROM that doesn't load in the window, which
+/// opens on the no-cartridge screen instead; the self-test, test, trace, demo verification
+/// and compare modes return their own statuses.
+fn main() -> ExitCode {
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let opts = match Options::parse(std::env::args().skip(1), &config) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if opts.selftest {
+        return status(selftest::run());
+    }
+    if let Some(path) = &opts.gbs {
+        return view_gbs(path, &opts);
+    }
+
+    // In the window, a game can still be opened once it's up (see `nocart`)
+    let (mut gb, no_cart) = match load_machine(&opts.rom_path, &opts) {
+        Ok(gb) => (gb, false),
+        Err(e) if opts.windowed() => {
+            eprintln!("Error loading ROM: {}", e);
+            (nocart::machine(), true)
+        }
         Err(e) => {
             eprintln!("Error loading ROM: {}", e);
-            return;
+            return ExitCode::FAILURE;
         }
     };
 
-    emulate(GB::new(cartridge));
+    let script = match opts.script.as_deref().map(Script::load).transpose() {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("Error loading script: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if opts.test_rom {
+        return status(headless::run_test(gb, opts.frames));
+    }
+    if let Some(trace) = &opts.trace {
+        return status(trace::run(gb, opts.frames, trace));
+    }
+    if let Some(path) = &opts.verify_demo {
+        return status(replay::verify(gb, Path::new(path)));
+    }
+
+    if let Some(role) = &opts.netplay {
+        // Both peers must start from identical machines, so battery saves stay out of it
+        let read = |path: &str| std::fs::read(path).unwrap_or_default();
+        let rom = read(&opts.rom_path);
+        let boot_roms = [&opts.boot_rom, &opts.cgb_boot_rom].map(|path| path.as_deref().map(read).unwrap_or_default());
+        let settings = [gb.model() as u8, gb.profile() as u8, gb.overclock() as u8, opts.ram_init as u8];
+        let config = netplay::config_hash(&[&rom, &settings, &boot_roms[0], &boot_roms[1]]);
+        let result = load_machine(&opts.rom_path, &opts).and_then(|second| netplay::run([gb, second], role, config, &opts));
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Netplay: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if opts.versus {
+        let second = opts.dual.as_deref().unwrap_or(&opts.rom_path);
+        return match load_machine(second, &opts).and_then(|second| versus::run([gb, second], &opts)) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let telemetry = match opts.telemetry.as_deref().map(telemetry::serve).transpose() {
+        Ok(feed) => feed,
+        Err(e) => {
+            eprintln!("Telemetry: can't listen on {}: {}", opts.telemetry.as_deref().unwrap_or_default(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let remote = match opts.remote_input.as_deref().map(remote::serve).transpose() {
+        Ok(orders) => orders,
+        Err(e) => {
+            eprintln!("Remote input: can't listen on {}: {}", opts.remote_input.as_deref().unwrap_or_default(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let stream = match opts.stream.as_deref().map(stream::serve).transpose() {
+        Ok(feed) => feed,
+        Err(e) => {
+            eprintln!("Streaming: can't listen on {}: {}", opts.stream.as_deref().unwrap_or_default(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let servers = Servers { telemetry, remote, stream };
+
+    let save_path = Cartridge::save_path(&opts.rom_path);
+    load_battery(&mut gb, &save_path);
+
+    if !opts.headless {
+        let mut machines = vec![(gb, opts.rom_path.clone())];
+        if let Some(path) = &opts.dual {
+            match load_machine(path, &opts) {
+                Ok(mut second) => {
+                    load_battery(&mut second, &Cartridge::save_path(path));
+                    machines.push((second, path.clone()));
+                }
+                Err(e) => {
+                    eprintln!("Error loading ROM: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        return match emulate(machines, no_cart, &opts, script, servers, &mut config) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let hooks = hooks(&gb, &opts.rom_path, &opts, script, opts.autoplay, servers);
+    let (gb, frame, result) = headless::run(gb, opts.frames, opts.hash == Some(FrameHash::Every), hooks);
+    shutdown(&gb, &save_path);
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if opts.hash == Some(FrameHash::Last) {
+        println!("Frame hash: {:016x}", headless::frame_hash(&frame));
+    }
+
+    if let Some(path) = &opts.screenshot && let Err(e) = screenshot::save_png(Path::new(path), &frame, headless::PITCH) {
+        eprintln!("Error writing {}: {}", path, e);
+    }
+    if let Some(path) = &opts.compare {
+        return status(compare_frame(&frame, Path::new(path)));
+    }
+    ExitCode::SUCCESS
+}
+
+/// Runs the `--gbs-viewer` on the rip at `path`, from `--song` or the rip's first song.
+fn view_gbs(path: &str, opts: &Options) -> ExitCode {
+    let rip = match gbs::Gbs::load(path) {
+        Ok(rip) => rip,
+        Err(e) => {
+            eprintln!("Error loading {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let song = opts.song.unwrap_or(rip.first_song);
+    if song > rip.songs {
+        eprintln!("{} has {} songs, there's no song {}", path, rip.songs, song);
+        return ExitCode::FAILURE;
+    }
+    match gbs::run(&rip, song, opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Exit status of the self-test, test, trace, demo verification and compare modes (0–2).
+fn status(code: i32) -> ExitCode {
+    ExitCode::from(code as u8)
+}
+
+/// Creates a machine for the ROM at `path`, honoring `--model`, `--profile`, `--overclock`,
+/// `--seed`, `--palette`, the boot ROMs, `--boot-animation` (in the window only: tests, traces
+/// and linked machines start straight away) and `--ram-init`. With `--coverage`, tracking picks
+/// up from the ROM's `.cdl` file; `--stack-check` and `--stack-break` turn on the stack check.
+fn load_machine(path: &str, opts: &Options) -> Result<GB, Error> {
+    let cartridge = Cartridge::from_file(path)?;
+    let mut gb = match opts.model {
+        Some(model) => GB::with_model(cartridge, model)?,
+        None => GB::new(cartridge)?,
+    };
+    if let Some(profile) = opts.profile {
+        gb.set_profile(profile);
+    }
+    if let Some(seed) = opts.seed {
+        gb.seed(seed);
+    }
+    gb.set_palette(opts.palette.palette());
+    gb.set_sprite_outlines(opts.sprite_outlines);
+    gb.set_overclock(opts.overclock);
+    let boot_animation = opts.boot_animation && opts.windowed();
+    gb.set_boot_animation(boot_animation);
+    let boot_rom = match gb.model() {
+        Model::Dmg => &opts.boot_rom,
+        Model::Cgb => &opts.cgb_boot_rom,
+    };
+    if let Some(boot_rom) = boot_rom {
+        let data = std::fs::read(boot_rom).map_err(|source| EmulatorError::RomLoad { path: boot_rom.into(), source })?;
+        gb.set_boot_rom(data)?;
+    }
+    if boot_rom.is_some() || boot_animation || opts.ram_init != RamInit::Zero {
+        gb.power_cycle(opts.ram_init);
+    }
+    if opts.stack_check || opts.stack_break {
+        gb.check_stack(true);
+    }
+    if opts.coverage {
+        gb.track_coverage(&std::fs::read(Path::new(path).with_extension("cdl")).unwrap_or_default());
+    }
+    Ok(gb)
+}
+
+/// Restores battery-backed cartridge RAM from the `.sav` file, if there is one.
+fn load_battery(gb: &mut GB, save_path: &Path) {
+    if gb.battery_ram().is_some() && let Ok(data) = std::fs::read(save_path) {
+        gb.load_battery_ram(&data);
+    }
+}
+
+/// Screenshot regression check (`--compare`): reports whether `frame` matches the
+/// reference PNG and returns the exit status (0 match, 1 mismatch, 2 unreadable reference).
+fn compare_frame(frame: &[u8], reference: &Path) -> i32 {
+    match screenshot::load_png(reference) {
+        Ok(expected) => match screenshot::diff_pixels(frame, headless::PITCH, &expected) {
+            0 => {
+                println!("Frame matches {}", reference.display());
+                0
+            }
+            n => {
+                println!("Frame differs from {} in {} pixels", reference.display(), n);
+                1
+            }
+        },
+        Err(e) => {
+            eprintln!("Error reading {}: {}", reference.display(), e);
+            2
+        }
+    }
+}
+
+/// Flushes everything that must survive the process once emulation has stopped.
+/// Both front-ends end here, whether the user quit via Escape, closed the window
+/// or pressed Ctrl+C.
+/// - Battery-backed cartridge RAM is written to the `.sav` file next to the ROM.
+/// - With `--coverage`, the coverage map is written to the `.cdl` file next to it.
+fn shutdown(gb: &GB, save_path: &Path) {
+    if let Some(ram) = gb.battery_ram() && let Err(e) = std::fs::write(save_path, ram) {
+        eprintln!("Error writing {}: {}", save_path.display(), e);
+    }
+    if let Some(map) = gb.coverage() {
+        let cdl_path = save_path.with_extension("cdl");
+        match std::fs::write(&cdl_path, &map) {
+            Ok(()) => {
+                let share = |flag: u8| map.iter().filter(|&&f| f & flag != 0).count() as f64 * 100.0 / map.len() as f64;
+                println!(
+                    "Coverage written to {}: {:.1}% of the ROM executed, {:.1}% read as data",
+                    cdl_path.display(), share(COVERAGE_CODE), share(COVERAGE_DATA)
+                );
+            }
+            Err(e) => eprintln!("Error writing {}: {}", cdl_path.display(), e),
+        }
+    }
+}
+
+/// Saves player 1's game as it was left, for `--auto-resume` to offer continuing from
+/// next time (see `menu::exit_state_path`).
+fn save_exit_state(gb: &GB, rom_path: &str) {
+    let path = menu::exit_state_path(rom_path);
+    if let Err(e) = std::fs::write(&path, gb.save_state()) {
+        eprintln!("Error writing {}: {}", path.display(), e);
+    }
 }
 
-/// SDL front-end:
+/// Window title, showing the game, the current speed when it differs from real time
+/// and whether emulation is paused.
+fn window_title(game: &str, speed: u32, paused: bool) -> String {
+    let mut title = String::from("RUSTЯIS");
+    if !game.is_empty() {
+        title.push_str(&format!(" - {}", game));
+    }
+    if speed != 100 {
+        title.push_str(&format!(" ({}%)", speed));
+    }
+    if paused {
+        title.push_str(" - paused");
+    }
+    title
+}
+
+/// SDL front-end (UI thread):
 /// - Creates a window and a streaming RGB24 texture.
-/// - Locks the texture each frame and lets the PPU render directly into it (no extra copy).
-/// - Handles keyboard input and palette toggle.
-/// - Presents frames and enforces a precise frame rate using a high-resolution limiter
-///   (sleep for the coarse part, busy-wait for the last ~0.5 ms).
-fn emulate(mut gb: GB) {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-
-    let window = video_subsystem
-        .window(
-            "RUSTЯIS",
-            (ppu::SCREEN_WIDTH as u32) * 4,
-            (ppu::SCREEN_HEIGHT as u32) * 4,
-        )
-        .position_centered()
-        .build()
-        .unwrap();
-
-    // IMPORTANT: no present_vsync(); the manual limiter below drives cadence.
-    let mut canvas = window.into_canvas().build().unwrap();
+/// - Runs the emulator on its own thread (see `EmuThread`), so window dragging or slow
+///   present calls can't stall emulation timing; frame pacing lives on that thread.
+/// - Uploads each completed frame into the texture and presents it. Between frames the
+///   UI keeps pumping events every millisecond, so input isn't quantized to presents.
+///   Emulation renders into its own buffers (see `FrameQueue`), so the next frame is
+///   emulated while this one is uploaded and presented; if frames pile up, only the
+///   newest is uploaded.
+/// - Handles keyboard input, the palette hotkey (P cycles the presets) and speed hotkeys
+///   (`-`/`=` step, `0` resets to 100%), forwarding them to the emulation thread as commands.
+/// - Optionally pauses emulation while the window is unfocused (`--pause-on-focus-loss`).
+/// - Turbo keys auto-fire their buttons; the cycle advances once per emulated frame.
+/// - With auto-repeat on (`--das`, see `Das`), held Left/Right reach the game as pulses.
+/// - F1 shows an egui settings panel over the game; changes made there are applied
+///   after each frame.
+/// - M (or a game controller's Back or Guide button) opens the menu over the first
+///   machine's picture, pausing the games (see `Menu`).
+/// - F2 opens the memory search window and F4 the RAM watch list, which work on snapshots
+///   of the first machine's memory (see `MemorySearch` and `WatchList`).
+/// - F5 opens the debugger and Shift+F5 the tile viewer, each in a window of its own (see
+///   `ToolWindow`); events from every window come through the one event pump. The game's
+///   window is resizable too, and closing it quits.
+/// - HiDPI aware: on platforms that report a drawable larger than the window (macOS,
+///   Wayland) the OS already scales; elsewhere the window is enlarged by the display
+///   DPI. The image is always an integer multiple of 160×144 so pixels stay sharp.
+/// - The picture can be rotated in 90° steps (`--rotate`, R key); the window turns
+///   portrait as needed and the arrow keys are remapped to follow the picture.
+/// - Borderless fullscreen-desktop (`--fullscreen`, F11) centers the integer-scaled picture
+///   over a configurable background color and hides the cursor after a short idle time.
+/// - Holding Backspace rewinds through the recent history.
+/// - F3 toggles a frame-time overlay (emulation, present, sleep/spin, limiter overshoot and
+///   missed deadlines); with `--frame-stats` it starts visible and a summary is printed on exit.
+/// - F9 shows where the falling Tetris piece would land (see `Ghost`, `--ghost`).
+/// - F8 draws the buttons each machine holds in its picture's corner (`--input-display`).
+/// - F7 shows Tetris's score, level, lines and drought beside the picture (see `Hud`).
+/// - F6 lets the Tetris autoplayer play (see `Bot`); `--autoplay` turns it on from the start.
+/// - With `--record-demo`, F10 starts and stops recording the first machine's input (see
+///   `Recorder`); with `--attract`, it plays a demo when left idle (see `Attract`).
+/// - With `--macro`, each macro key plays a short recorded input sequence on the first
+///   machine, and Shift+key records it (see `Macros`).
+/// - With `--practice`, Tab goes back to when the falling Tetris piece appeared (see `Practice`).
+/// - With `--dual`, each machine gets its own emulation thread, texture and statistics, and
+///   the pictures sit side by side; the first takes the regular keys and the script, the
+///   second player 2's keys. Speed, pause, palette and rewind apply to both.
+/// - Opened games go to the recent list (see `RecentRoms`); the settings panel lists the
+///   others and Ctrl+1–9 switch the first machine to one of them, saving the battery RAM of
+///   the game being left. Dropping a ROM file on a window switches to it the same way.
+/// - With `no_cart` (player 1's ROM didn't load), the first machine shows the no-cartridge
+///   screen (see `nocart`) until a game is opened.
+/// - With `--hot-reload`, the first machine's ROM is reloaded whenever its file changes (see
+///   `FileWatcher`), like switching to it again; with `--keep-state` the new ROM picks up
+///   where the old one was. A ROM that fails to load leaves the old one running.
+/// - The first machine is wired to the `servers` started: its game goes to the telemetry
+///   clients (see `Telemetry`), takes buttons from the remote input clients too (see
+///   `RemoteInput`) and its frames go to the streaming viewers (see `stream::serve`).
+/// - Escape, closing the window or Ctrl+C (delivered by SDL as a quit event) stop the
+///   emulation threads and flush each machine (see `shutdown`). An SDL failure while
+///   running does the same before it's returned, and so does a machine that can't go on,
+///   after a crash dump of it (see `crashdump::report`); an SDL failure during setup is
+///   returned right away.
+/// - Settings changed during the session (hotkeys or panel) are written to `config`.
+fn emulate(
+    gbs: Vec<(GB, String)>,
+    no_cart: bool,
+    opts: &Options,
+    mut script: Option<Script>,
+    servers: Servers,
+    config: &mut Config,
+) -> Result<(), Error> {
+    let mut game = gbs[0].0.game_title().to_string();
+    let mut settings = Settings {
+        speed: opts.speed,
+        overclock: opts.overclock,
+        paused: false,
+        pause_on_focus_loss: opts.pause_on_focus_loss,
+        palette: opts.palette,
+        sprite_outlines: opts.sprite_outlines,
+        rotation: opts.rotation,
+        fullscreen: opts.fullscreen,
+        background: opts.background,
+        hud: opts.hud,
+        turbo_hz: opts.turbo_hz,
+        das: opts.das,
+        das_timing: opts.das_timing,
+        rumble: opts.rumble,
+        frame_stats: opts.frame_stats,
+        input_display: opts.input_display,
+        ghost: opts.ghost,
+        autoplay: opts.autoplay,
+        hard_drop: opts.hard_drop,
+    };
+    let initial = settings.clone();
+
+    // Opt into per-monitor DPI on Windows (otherwise the window gets bitmap-stretched)
+    // and keep texture scaling nearest-neighbor
+    sdl2::hint::set("SDL_WINDOWS_DPI_AWARENESS", "permonitorv2");
+    sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+
+    let sdl_context = sdl2::init().map_err(Error::sdl)?;
+    let video_subsystem = sdl_context.video().map_err(Error::sdl)?;
+
+    let screens = gbs.len() as u32;
+    let (screen_w, screen_h) = video::layout_size(settings.rotation, screens);
+    let mut builder = video_subsystem.window(
+        &window_title(&game, settings.speed, settings.paused),
+        screen_w * opts.zoom,
+        screen_h * opts.zoom,
+    );
+    builder.position_centered().resizable().allow_highdpi();
+    if opts.shader.is_some() {
+        builder.opengl();
+    }
+    let mut window = builder.build().map_err(Error::sdl)?;
+
+    // Scale factor between UI points and physical pixels
+    let (drawable_w, _) = window.drawable_size();
+    let (window_w, _) = window.size();
+    let (ui_scale, zoom) = if drawable_w > window_w {
+        (drawable_w as f32 / window_w as f32, opts.zoom)
+    } else {
+        let dpi = video_subsystem.display_dpi(0).map(|(ddpi, _, _)| ddpi).unwrap_or(BASE_DPI);
+        let scale = (dpi / BASE_DPI).max(1.0);
+        let zoom = (opts.zoom as f32 * scale).round() as u32;
+        window.set_size(screen_w * zoom, screen_h * zoom).map_err(Error::sdl)?;
+        window.set_position(sdl2::video::WindowPos::Centered, sdl2::video::WindowPos::Centered);
+        (scale, zoom)
+    };
+
+    // No present_vsync() unless pacing by it: otherwise the emulation thread's limiter
+    // drives cadence, and with it the window presents every refresh for the limiter to follow
+    let vblank = (opts.pacing == Pacing::Vsync && vsync_display(&window)).then(Vblank::default);
+    let mut canvas = window.into_canvas();
+    if opts.shader.is_some() && let Some(opengl) = sdl2::render::drivers().position(|driver| driver.name == "opengl") {
+        canvas = canvas.index(opengl as u32);
+    }
+    let mut canvas = if vblank.is_some() { canvas.present_vsync() } else { canvas }.build().map_err(Error::sdl)?;
+    let mut shader = opts.shader.as_deref().map(|path| Shader::new(&video_subsystem, &canvas, path)).transpose()?;
 
     let texture_creator = canvas.texture_creator();
-    let mut texture = texture_creator
-        .create_texture_streaming(
-            PixelFormatEnum::RGB24,
-            ppu::SCREEN_WIDTH as u32,
-            ppu::SCREEN_HEIGHT as u32,
-        )
-        .unwrap();
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
+    if settings.fullscreen {
+        canvas.window_mut().set_fullscreen(FullscreenType::Desktop).map_err(Error::sdl)?;
+    }
 
-    // Precise frame limiter state
-    let frame_period = Duration::from_nanos(GB_FRAME_NS);
-    let mut next_deadline = Instant::now() + frame_period;
+    let mouse = sdl_context.mouse();
+    let mut last_mouse_motion = Instant::now();
+    // Set when losing focus paused the game, so regaining it only lifts that pause
+    let mut paused_by_focus = false;
+
+    // The debugger and the tile viewer get windows of their own, hidden until opened
+    let debugger_canvas = gui::tool_canvas(&video_subsystem, "RUSTЯIS - Debugger", gui::DEBUGGER_SIZE, ui_scale)?;
+    let debugger_creator = debugger_canvas.texture_creator();
+    let tiles_canvas = gui::tool_canvas(&video_subsystem, "RUSTЯIS - Tiles", gui::TILES_SIZE, ui_scale)?;
+    let tiles_creator = tiles_canvas.texture_creator();
+
+    // The debugger window's console shows the first machine's serial output
+    let serial_console = SerialConsole::default();
+    let mut gui = Gui::new(
+        &texture_creator,
+        ui_scale,
+        ToolWindow::new(debugger_canvas, &debugger_creator, ui_scale),
+        ToolWindow::new(tiles_canvas, &tiles_creator, ui_scale),
+        serial_console.clone(),
+    );
+    gui.set_watch_list(WatchList::load(&gbs[0].1));
+    gui.set_game(&gbs[0].0);
+    // The breakpoints come back once the game is debugged: now with --debug, else on F5
+    let mut session = DebugSession::load(&gbs[0].1);
+    gui.set_debug_layout(session.sections().clone());
+    let breakpoints = if opts.debug { session.restore_breakpoints() } else { BTreeSet::new() };
+    let mut hud = Hud::new(&texture_creator)?;
+    hud.set_game(&gbs[0].0);
+    let mut recent = RecentRoms::load();
+    for (_, rom_path) in gbs.iter().skip(usize::from(no_cart)).rev() {
+        recent.add(rom_path);
+    }
+    gui.set_recent(recent.paths()[usize::from(!no_cart)..].to_vec());
+    let mut event_pump = sdl_context.event_pump().map_err(Error::sdl)?;
+    let mut rumble = Rumble::new(&sdl_context).map_err(|e| eprintln!("Warning: no game controller support: {}", e)).ok();
+
+    // The memory tools and the debugger look at the first machine
+    let memory = MemoryView::default();
+    let debug_view = DebugView::default();
+    let mut machines: Vec<Machine> = gbs
+        .into_iter()
+        .enumerate()
+        .map(|(i, (gb, rom_path))| {
+            let texture = texture_creator
+                .create_texture_streaming(
+                    PixelFormatEnum::RGB24,
+                    rustris_core::SCREEN_WIDTH as u32,
+                    rustris_core::SCREEN_HEIGHT as u32,
+                )
+                .map_err(Error::sdl)?;
+            let stats = FrameStats::default();
+            let view = if i == 0 { memory.clone() } else { MemoryView::default() };
+            let wired = if i == 0 { servers.clone() } else { Servers::default() };
+            let mut hooks = hooks(&gb, &rom_path, opts, script.take(), settings.autoplay, wired);
+            if i > 0 {
+                // Demos, macros, the serial output and the assertions follow player 1's machine
+                hooks.recorder = None;
+                hooks.attract = None;
+                hooks.macros = None;
+                hooks.serial = None;
+                hooks.assertions = None;
+            }
+            if i == 0 {
+                hooks.debugger = Some(Debugger::new(debug_view.clone(), breakpoints.clone(), opts.debug, opts.stack_break));
+                hooks.serial.get_or_insert_with(SerialOutput::default).console = Some(serial_console.clone());
+            }
+            let motor = hooks.motor.clone();
+            let emu = spawn_emu(gb, &settings, opts, vblank.clone(), hooks, stats.clone(), view)?;
+            Ok(Machine { emu, texture, stats, rom_path, motor, no_cart: i == 0 && no_cart, frame: None, crashed: false })
+        })
+        .collect::<Result<_, Error>>()?;
+    let mut last_present = Instant::now();
+    let mut failure = None;
+    let mut menu = Menu::default();
+    let mut menu_shown = false; // Whether the games were last told the menu is open
+    if opts.auto_resume && !machines[0].no_cart && menu::exit_state_path(&machines[0].rom_path).is_file() {
+        menu.offer_resume(&machines[0].rom_path, &recent.paths()[1..], settings.palette);
+    }
+    let mut rom_watcher = opts.hot_reload.then(|| FileWatcher::new(&machines[0].rom_path));
+
+    // The debugger's terminal sends its commands through here, so they follow game switches
+    let (debug_commands, debug_rx) = mpsc::channel();
+    let tui = if opts.debug {
+        Tui::open(debug_view.clone(), debug_commands).map_err(|e| failure = Some(Error::Terminal(e))).ok()
+    } else {
+        None
+    };
+
+    'running: while failure.is_none() {
+        let before = settings.clone();
+        let mut switch_to = None;
 
-    'running: loop {
         // --- Event handling ---
         for event in event_pump.poll_iter() {
+            if let Event::MouseMotion { .. } = event {
+                last_mouse_motion = Instant::now();
+                mouse.show_cursor(true);
+            }
+
+            if let Some(rumble) = &mut rumble {
+                rumble.handle_event(&event);
+            }
+            if gui.handle_event(&event) {
+                continue;
+            }
+
+            let button_key = matches!(event, Event::KeyDown { scancode: Some(sc), .. } if is_button(opts, sc, machines.len() > 1));
+            if menu::is_toggle(&event) && !button_key {
+                if menu.is_open() {
+                    menu.close();
+                } else {
+                    let machine = &machines[0];
+                    let others = &recent.paths()[usize::from(!machine.no_cart)..];
+                    menu.open(&machine.rom_path, !machine.no_cart, others, settings.palette);
+                }
+                continue;
+            }
+            // The open menu takes the presses; releases still reach the games
+            if menu.is_open() && matches!(event, Event::KeyDown { .. } | Event::ControllerButtonDown { .. }) {
+                match menu::menu_key(&event, &opts.keys).and_then(|key| menu.press(key)) {
+                    Some(Action::Open(path)) => switch_to = Some(path),
+                    Some(Action::SaveState(path)) => machines[0].emu.send(Command::SaveState(path)),
+                    Some(Action::LoadState(path)) => machines[0].emu.send(Command::LoadState(path)),
+                    Some(Action::Palette(palette)) => settings.palette = palette,
+                    Some(Action::Reset) => send_all(&machines, Command::Reset),
+                    Some(Action::Quit) => break 'running,
+                    None => {}
+                }
+                continue;
+            }
+
             match event {
+                // With the tool windows around, closing the game's window doesn't quit by itself
                 Event::KeyDown { scancode: Some(Scancode::Escape), repeat: false, .. } |
+                Event::Window { win_event: sdl2::event::WindowEvent::Close, .. } |
                 Event::Quit { .. } => break 'running,
 
-                Event::KeyDown { scancode: Some(Scancode::P), repeat: false, .. } => {
-                    gb.toggle_palette();
+                // Taken before the hotkeys, so a macro can sit on any key but Escape
+                Event::KeyDown { scancode: Some(sc), keymod, repeat: false, .. } if opts.macros.contains(&sc) => {
+                    let key = sc.name().to_string();
+                    let record = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    machines[0].emu.send(if record { Command::RecordMacro(key) } else { Command::PlayMacro(key) });
                 }
+                Event::KeyUp { scancode: Some(sc), .. } if opts.macros.contains(&sc) => {}
 
-                Event::KeyDown { scancode: Some(sc), repeat: false, .. } => {
-                    if let Some(mask) = INPUT_MASKS.iter().find(|(s, _)| *s == sc).map(|(_, m)| *m) {
-                        gb.input_press(mask);
+                // Then the game's buttons, so a key bound to one is never taken by a hotkey
+                Event::KeyDown { scancode: Some(sc), repeat, .. } |
+                Event::KeyUp { scancode: Some(sc), repeat, .. } if is_button(opts, sc, machines.len() > 1) => {
+                    if repeat {
+                        continue;
                     }
+                    let pressed = matches!(event, Event::KeyDown { .. });
+                    let player1 = &machines[0].emu;
+                    if let Some(mask) = opts.turbo.iter().find(|(s, _)| *s == sc).map(|(_, m)| *m) {
+                        player1.send(Command::Turbo(settings.rotation.remap_dpad(mask), pressed));
+                    } else if let Some(mask) = opts.keys.iter().find(|(s, _)| *s == sc).map(|(_, m)| *m) {
+                        player1.send(Command::Button(settings.rotation.remap_dpad(mask), pressed));
+                    } else if let Some(player2) = machines.get(1)
+                        && let Some(mask) = opts.player2_keys.iter().find(|(s, _)| *s == sc).map(|(_, m)| *m)
+                    {
+                        player2.emu.send(Command::Button(settings.rotation.remap_dpad(mask), pressed));
+                    }
+                }
+
+                Event::KeyDown { scancode: Some(gui::TOGGLE_KEY), repeat: false, .. } => {
+                    gui.toggle();
+                }
+
+                Event::KeyDown { scancode: Some(gui::SEARCH_KEY), repeat: false, .. } => {
+                    gui.toggle_search();
+                }
+
+                Event::KeyDown { scancode: Some(gui::WATCH_KEY), repeat: false, .. } => {
+                    gui.toggle_watch();
+                }
+
+                Event::KeyDown { scancode: Some(gui::DEBUG_KEY), keymod, repeat: false, .. }
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) =>
+                {
+                    gui.toggle_tiles();
+                }
+
+                Event::KeyDown { scancode: Some(gui::DEBUG_KEY), repeat: false, .. } => {
+                    gui.toggle_debugger();
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F12), repeat: false, .. } => {
+                    machines[0].emu.send(Command::DumpTrace);
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::P), repeat: false, .. } => {
+                    settings.palette = settings.palette.next();
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F11), repeat: false, .. } => {
+                    settings.fullscreen = !settings.fullscreen;
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F3), repeat: false, .. } => {
+                    settings.frame_stats = !settings.frame_stats;
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F9), repeat: false, .. } => {
+                    settings.ghost = !settings.ghost;
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F10), repeat: false, .. } if opts.record_demo.is_some() => {
+                    machines[0].emu.send(Command::RecordDemo);
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F8), repeat: false, .. } => {
+                    settings.input_display = !settings.input_display;
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F7), repeat: false, .. } => {
+                    settings.hud = !settings.hud;
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::F6), repeat: false, .. } => {
+                    settings.autoplay = !settings.autoplay;
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::Tab), repeat: false, .. } => {
+                    send_all(&machines, Command::RetryPiece);
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::Backspace), repeat: false, .. } |
+                Event::KeyUp { scancode: Some(Scancode::Backspace), .. } => {
+                    send_all(&machines, Command::Rewind(matches!(event, Event::KeyDown { .. })));
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::R), keymod, repeat: false, .. }
+                    if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) =>
+                {
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    send_all(&machines, if shift { Command::PowerCycle(opts.ram_init) } else { Command::Reset });
+                }
+
+                Event::KeyDown { scancode: Some(Scancode::R), repeat: false, .. } => {
+                    settings.rotation = settings.rotation.next();
                 }
 
-                Event::KeyUp { scancode: Some(sc), .. } => {
-                    if let Some(mask) = INPUT_MASKS.iter().find(|(s, _)| *s == sc).map(|(_, m)| *m) {
-                        gb.input_release(mask);
+                Event::DropFile { filename, .. } => {
+                    switch_to = Some(filename);
+                }
+
+                Event::KeyDown { scancode: Some(sc), keymod, repeat: false, .. }
+                    if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && recent_index(sc).is_some() =>
+                {
+                    if let Some(path) = recent_index(sc).and_then(|n| gui.recent(n)) {
+                        switch_to = Some(path.to_string());
                     }
                 }
 
+                Event::KeyDown { scancode: Some(sc @ (Scancode::Minus | Scancode::Equals | Scancode::Num0)), .. } => {
+                    settings.speed = match sc {
+                        Scancode::Minus  => settings.speed.saturating_sub(opts.speed_step).max(SPEED_MIN),
+                        Scancode::Equals => (settings.speed + opts.speed_step).min(SPEED_MAX),
+                        _                => 100,
+                    };
+                }
+
                 Event::Window { win_event: sdl2::event::WindowEvent::FocusLost, .. } => {
-                    gb.input_release(
-                        gb::BTN_RIGHT | gb::BTN_LEFT | gb::BTN_UP | gb::BTN_DOWN |
-                        gb::BTN_A | gb::BTN_B | gb::BTN_SELECT | gb::BTN_START
-                    );
+                    send_all(&machines, Command::ReleaseAll);
+                    if settings.pause_on_focus_loss && !settings.paused {
+                        settings.paused = true;
+                        paused_by_focus = true;
+                    }
+                }
+
+                Event::Window { win_event: sdl2::event::WindowEvent::FocusGained, .. } if paused_by_focus => {
+                    settings.paused = false;
+                    paused_by_focus = false;
                 }
 
                 _ => {}
             }
         }
 
-        // Lock the streaming texture and let the emulator render directly into its buffer
-        texture.with_lock(None, |buf: &mut [u8], pitch: usize| {
-            // Run until a full frame is produced
-            while !gb.step(buf, pitch) {}
-        }).unwrap();
+        // --- Commands from the debugger's terminal and window, kept in the game's session ---
+        if gui.wants_debugger() {
+            for addr in session.restore_breakpoints() {
+                machines[0].emu.send(Command::Debug(DebugCommand::ToggleBreakpoint(addr)));
+            }
+        }
+        for command in debug_rx.try_iter() {
+            match command {
+                Command::Quit => break 'running,
+                Command::Debug(command) => {
+                    session.apply(&command);
+                    machines[0].emu.send(Command::Debug(command));
+                }
+                command => machines[0].emu.send(command),
+            }
+        }
+        for command in gui.take_debug_commands() {
+            session.apply(&command);
+            machines[0].emu.send(Command::Debug(command));
+        }
+        if let Some(sections) = gui.take_debug_layout() {
+            session.set_sections(sections);
+        }
+        // A stop the user didn't ask for (a broken assertion, a stack fault) says why in
+        // the debugger, which the terminal already shows
+        if debug_view.take_stop().is_some() && tui.is_none() && !gui.wants_debugger() {
+            gui.toggle_debugger();
+        }
 
-        canvas.copy(&texture, None, None).unwrap();
-        canvas.present();
+        // --- The games wait while the menu is open, which is drawn over the last frame ---
+        if menu.is_open() != menu_shown {
+            menu_shown = menu.is_open();
+            send_all(&machines, Command::ReleaseAll);
+            send_all(&machines, Command::SetPaused(settings.paused || menu.is_open()));
+        }
+        let menu_changed = menu.take_changed();
+        if menu_changed && let Err(e) = upload(&mut machines[0], Some(&menu)) {
+            failure = Some(e);
+            break 'running;
+        }
 
-        // --- Precise frame limiter (sleep + spin to reach exact deadline) ---
-        let now = Instant::now();
-        if next_deadline > now {
-            // Sleep the coarse chunk, leaving a small margin (~0.5 ms) to fine-tune with spinning
-            let remain = next_deadline - now;
-            if remain > Duration::from_micros(500) {
-                std::thread::sleep(remain - Duration::from_micros(500));
+        // --- Wait briefly for the next frame, then go back to pumping events ---
+        // (only on the first machine; the others are just checked)
+        let mut redraw = vblank.is_some() || menu_changed || settings != before || last_present.elapsed() >= IDLE_REDRAW;
+        let mut new_frames = vec![None; machines.len()];
+        for (i, machine) in machines.iter_mut().enumerate() {
+            let wait = if i == 0 { INPUT_POLL } else { Duration::ZERO };
+            match machine.emu.frames.recv_timeout(wait) {
+                Ok(Ok(mut frame)) => {
+                    // Behind: only the newest frame is worth uploading
+                    for newer in machine.emu.frames.try_iter() {
+                        match newer {
+                            Ok(newer) => machine.emu.recycle(std::mem::replace(&mut frame, newer)),
+                            Err(e) => {
+                                machine.crashed = true;
+                                failure = Some(e);
+                                break 'running;
+                            }
+                        }
+                    }
+                    new_frames[i] = Some(Instant::now());
+                    if machine.no_cart {
+                        nocart::draw(&mut frame, headless::PITCH);
+                    }
+                    if let Some(shown) = machine.frame.replace(frame) {
+                        machine.emu.recycle(shown);
+                    }
+                    if let Err(e) = upload(machine, (i == 0).then_some(&menu)) {
+                        failure = Some(e);
+                        break 'running;
+                    }
+                    redraw = true;
+                }
+                Ok(Err(e)) => {
+                    machine.crashed = true;
+                    failure = Some(e);
+                    break 'running;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break 'running,
+            }
+        }
+        if let Some(rumble) = &mut rumble {
+            rumble.update(machines.iter().map(|machine| machine.motor.as_ref()), if settings.paused { 0 } else { settings.rumble });
+        }
+        if !redraw {
+            continue;
+        }
+
+        let drawn = draw_pictures(&mut canvas, &machines, &settings, shader.as_mut())
+            .and_then(|dests| render(&mut canvas, &dests, &mut settings, &mut gui, &mut hud, &memory, &debug_view));
+        if let Err(e) = drawn {
+            failure = Some(e);
+            break 'running;
+        }
+        if let Some(shader) = &mut shader {
+            shader.presented();
+        }
+        last_present = Instant::now();
+        if let Some(vblank) = &vblank {
+            vblank.presented();
+        }
+        for (machine, received) in machines.iter().zip(new_frames) {
+            if let Some(received) = received {
+                machine.stats.record_present(last_present - received);
+            }
+        }
+
+        if settings.fullscreen && last_mouse_motion.elapsed() > CURSOR_HIDE_DELAY {
+            mouse.show_cursor(false);
+        }
+
+        // --- Forward settings changed by hotkeys or the panel ---
+        if settings != before {
+            if settings.speed != before.speed {
+                send_all(&machines, Command::SetSpeed(settings.speed));
+            }
+            if settings.palette != before.palette {
+                send_all(&machines, Command::SetPalette(settings.palette.palette()));
+            }
+            if settings.sprite_outlines != before.sprite_outlines {
+                send_all(&machines, Command::SetSpriteOutlines(settings.sprite_outlines));
+            }
+            if settings.overclock != before.overclock {
+                send_all(&machines, Command::SetOverclock(settings.overclock));
+            }
+            if settings.turbo_hz != before.turbo_hz {
+                send_all(&machines, Command::SetTurboRate(settings.turbo_hz));
+            }
+            if (settings.das, settings.das_timing) != (before.das, before.das_timing) {
+                send_all(&machines, Command::SetDas(settings.das.then_some(settings.das_timing)));
+            }
+            if settings.paused != before.paused {
+                send_all(&machines, Command::SetPaused(settings.paused || menu.is_open()));
+            }
+            if settings.frame_stats != before.frame_stats {
+                send_all(&machines, Command::ShowStats(settings.frame_stats));
+            }
+            if settings.input_display != before.input_display {
+                send_all(&machines, Command::ShowInput(settings.input_display));
+            }
+            if settings.ghost != before.ghost {
+                send_all(&machines, Command::ShowGhost(settings.ghost));
+            }
+            if settings.autoplay != before.autoplay {
+                send_all(&machines, Command::SetAutoplay(settings.autoplay));
+            }
+            if settings.hard_drop != before.hard_drop {
+                send_all(&machines, Command::SetHardDrop(settings.hard_drop));
+            }
+            if settings.rotation != before.rotation {
+                // Held directions were remapped with the old rotation
+                send_all(&machines, Command::ReleaseAll);
+            }
+            if before.fullscreen && !settings.fullscreen {
+                mouse.show_cursor(true);
+            }
+            if let Err(e) = update_window(&mut canvas, &before, &settings, zoom, screens, &game) {
+                failure = Some(e);
+                break 'running;
+            }
+        }
+
+        // --- Reset / power cycle from the panel ---
+        match gui.take_restart_request() {
+            Some(Restart::Reset) => send_all(&machines, Command::Reset),
+            Some(Restart::PowerCycle) => send_all(&machines, Command::PowerCycle(opts.ram_init)),
+            None => {}
+        }
+
+        // --- Switch games, or reload the one played when it's rebuilt ---
+        let switch_to = switch_to.or_else(|| gui.take_open_request());
+        let reload = switch_to.is_none() && rom_watcher.as_mut().is_some_and(FileWatcher::poll);
+        if let Some(rom_path) = switch_to.or_else(|| reload.then(|| machines[0].rom_path.clone())) {
+            match load_machine(&rom_path, opts) {
+                Ok(mut gb) => {
+                    menu.close();
+                    let Machine { emu, texture, stats, rom_path: old_path, no_cart, .. } = machines.remove(0);
+                    let old = emu.stop();
+                    shutdown(&old, &Cartridge::save_path(&old_path));
+                    if opts.auto_resume && !reload && !no_cart {
+                        save_exit_state(&old, &old_path);
+                    }
+                    load_battery(&mut gb, &Cartridge::save_path(&rom_path));
+                    if reload {
+                        if opts.keep_state && !no_cart && let Err(e) = gb.load_state(&old.save_state()) {
+                            eprintln!("Can't keep the state over the reload, starting afresh: {}", e);
+                        }
+                        println!("Reloaded {}", rom_path);
+                    } else if opts.hot_reload {
+                        rom_watcher = Some(FileWatcher::new(&rom_path));
+                    }
+
+                    game = gb.game_title().to_string();
+                    gui.set_game(&gb);
+                    hud.set_game(&gb);
+                    gui.set_watch_list(WatchList::load(&rom_path));
+                    session = DebugSession::load(&rom_path);
+                    gui.set_debug_layout(session.sections().clone());
+                    recent.add(&rom_path);
+                    gui.set_recent(recent.paths()[1..].to_vec());
+                    if let Err(e) = update_window(&mut canvas, &settings, &settings, zoom, screens, &game) {
+                        failure = Some(e);
+                        break 'running;
+                    }
+
+                    let mut hooks = hooks(&gb, &rom_path, opts, None, settings.autoplay, servers.clone());
+                    let breakpoints =
+                        if opts.debug || gui.wants_debugger() { session.restore_breakpoints() } else { BTreeSet::new() };
+                    hooks.debugger = Some(Debugger::new(debug_view.clone(), breakpoints, opts.debug, opts.stack_break));
+                    hooks.serial.get_or_insert_with(SerialOutput::default).console = Some(serial_console.clone());
+                    let motor = hooks.motor.clone();
+                    if opts.auto_resume && !reload && menu::exit_state_path(&rom_path).is_file() {
+                        menu.offer_resume(&rom_path, &recent.paths()[1..], settings.palette);
+                    }
+                    match spawn_emu(gb, &settings, opts, vblank.clone(), hooks, stats.clone(), memory.clone()) {
+                        Ok(emu) => machines.insert(0, Machine { emu, texture, stats, rom_path, motor, no_cart: false, frame: None, crashed: false }),
+                        Err(e) => {
+                            failure = Some(e);
+                            break 'running;
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Error loading {}: {}", rom_path, e),
             }
-            // Busy-wait until the precise deadline
-            while Instant::now() < next_deadline {
-                cpu_relax();
+        }
+    }
+
+    if let Some(tui) = tui {
+        tui.close();
+    }
+    save_settings(config, &initial, &settings);
+    for (i, machine) in machines.into_iter().enumerate() {
+        let gb = machine.emu.stop();
+        if machine.crashed && let Some(e) = &failure {
+            crashdump::report(&gb, &e.to_string());
+        }
+        shutdown(&gb, &Cartridge::save_path(&machine.rom_path));
+        if opts.auto_resume && i == 0 && !machine.no_cart && !machine.crashed {
+            save_exit_state(&gb, &machine.rom_path);
+        }
+        if opts.frame_stats {
+            if screens > 1 {
+                println!("Player {}:", i + 1);
             }
+            print!("{}", machine.stats.summary());
+        }
+    }
+    failure.map_or(Ok(()), Err)
+}
+
+/// Clears the window and draws every machine's picture, through the `--shader` if there's
+/// one; returns where each went (see `video::dest_rects`).
+fn draw_pictures(canvas: &mut Canvas<Window>, machines: &[Machine], settings: &Settings, mut shader: Option<&mut Shader>) -> Result<Vec<Rect>, Error> {
+    let output = canvas.output_size().map_err(Error::sdl)?;
+    let dests = video::dest_rects(output, settings.rotation, machines.len() as u32);
+    let [r, g, b] = settings.background;
+    canvas.set_draw_color(Color::RGB(r, g, b));
+    canvas.clear();
+    for (machine, &dest) in machines.iter().zip(&dests) {
+        match shader.as_deref_mut() {
+            Some(shader) => shader.draw(canvas, &machine.texture, dest, settings.rotation)?,
+            None => canvas
+                .copy_ex(&machine.texture, None, dest, settings.rotation.degrees() as f64, None, false, false)
+                .map_err(Error::sdl)?,
+        }
+    }
+    Ok(dests)
+}
+
+/// Draws the Tetris readout and the egui overlay over the pictures at `dests` (see
+/// `draw_pictures`), and presents the result.
+fn render(
+    canvas: &mut Canvas<Window>,
+    dests: &[Rect],
+    settings: &mut Settings,
+    gui: &mut Gui,
+    hud: &mut Hud,
+    memory: &MemoryView,
+    debug: &DebugView,
+) -> Result<(), Error> {
+    let output = canvas.output_size().map_err(Error::sdl)?;
+    let wants_memory = gui.wants_memory() || hud.wants_memory(settings.hud);
+    let snapshot = if wants_memory { memory.snapshot() } else { None };
+    if settings.hud && let Some(snapshot) = &snapshot {
+        // Where player 1's picture shows once rotated
+        let picture = if settings.rotation.is_portrait() {
+            Rect::from_center(dests[0].center(), dests[0].height(), dests[0].width())
         } else {
-            // We're late; resync to avoid drift accumulation
-            next_deadline = Instant::now();
+            dests[0]
+        };
+        hud.draw(canvas, snapshot, picture, (output.0 / dests.len() as u32) as i32)?;
+    }
+    let debug_snapshot = if gui.wants_snapshots() { debug.snapshot() } else { None };
+    gui.draw(canvas, settings, snapshot.as_deref(), debug_snapshot.as_ref())?;
+    memory.set_wanted(wants_memory);
+    debug.set_wanted(gui.wants_snapshots());
+    canvas.present();
+    Ok(())
+}
+
+/// Whether the window's display refreshes at 60 Hz or twice that, as `--pacing vsync` needs
+/// (59 and 119 count: that's how 59.94 Hz modes report); if not, says so, for the clock to
+/// pace instead.
+fn vsync_display(window: &Window) -> bool {
+    let hz = window.display_mode().map_or(0, |mode| mode.refresh_rate);
+    if matches!(hz, 59 | 60 | 119 | 120) {
+        return true;
+    }
+    let rate = if hz > 0 { format!("{} Hz", hz) } else { "an unknown rate".to_string() };
+    eprintln!("Warning: vsync pacing needs a 60 or 120 Hz display, this one refreshes at {}; pacing by the clock", rate);
+    false
+}
+
+/// Brings the window in line with settings that changed from `before`: size after a turn
+/// to or from portrait, fullscreen, and the title.
+fn update_window(
+    canvas: &mut Canvas<Window>,
+    before: &Settings,
+    settings: &Settings,
+    zoom: u32,
+    screens: u32,
+    game: &str,
+) -> Result<(), Error> {
+    let window = canvas.window_mut();
+    if settings.fullscreen != before.fullscreen {
+        let mode = if settings.fullscreen { FullscreenType::Desktop } else { FullscreenType::Off };
+        window.set_fullscreen(mode).map_err(Error::sdl)?;
+    }
+    let turned = settings.rotation.is_portrait() != before.rotation.is_portrait();
+    if !settings.fullscreen && (turned || before.fullscreen) {
+        let (w, h) = video::layout_size(settings.rotation, screens);
+        window.set_size(w * zoom, h * zoom).map_err(Error::sdl)?;
+    }
+    window.set_title(&window_title(game, settings.speed, settings.paused)).map_err(Error::sdl)
+}
+
+/// One machine shown in the window: its emulation thread, texture, frame statistics and
+/// the ROM it's playing (or failed to).
+struct Machine<'a> {
+    emu: EmuThread,
+    texture: Texture<'a>,
+    stats: FrameStats,
+    rom_path: String,
+    motor: Option<Motor>,   // The cart's rumble motor, if it has one
+    no_cart: bool,          // Showing the no-cartridge screen instead of a game
+    frame: Option<Vec<u8>>, // The last frame received, kept to draw the menu over
+    crashed: bool,          // Its emulation thread stopped on an error (see `EmuThread`)
+}
+
+/// Uploads a machine's last frame to its texture, with `menu` over it while that's open
+/// (over a blank screen if the menu opened before the first frame came).
+fn upload(machine: &mut Machine, menu: Option<&Menu>) -> Result<(), Error> {
+    let menu = menu.filter(|menu| menu.is_open());
+    let Some(frame) = &machine.frame else {
+        if let Some(menu) = menu {
+            let mut shown = vec![0; headless::PITCH * rustris_core::SCREEN_HEIGHT as usize];
+            menu.draw(&mut shown, headless::PITCH);
+            machine.texture.update(None, &shown, headless::PITCH).map_err(Error::sdl)?;
         }
-        // Schedule the next frame deadline
-        next_deadline += frame_period;
-        // -------------------------------------------------------------------
+        return Ok(());
+    };
+    match menu {
+        Some(menu) => {
+            let mut shown = frame.clone();
+            menu.draw(&mut shown, headless::PITCH);
+            machine.texture.update(None, &shown, headless::PITCH)
+        }
+        None => machine.texture.update(None, frame, headless::PITCH),
+    }
+    .map_err(Error::sdl)
+}
+
+/// Starts the emulation thread for a machine, running `hooks` (see `hooks`) and paced as
+/// `--pacing` says (by the window's presents with `vblank`), and brings it in line with the
+/// current settings (palette, overclock, pause, overlays, hard drop, auto-repeat).
+fn spawn_emu(
+    gb: GB,
+    settings: &Settings,
+    opts: &Options,
+    vblank: Option<Vblank>,
+    hooks: Hooks,
+    stats: FrameStats,
+    memory: MemoryView,
+) -> Result<EmuThread, Error> {
+    let limiter = Limiter::new(opts.pacing, opts.pacing_tuning, settings.speed, vblank);
+    let emu = EmuThread::spawn(gb, limiter, settings.turbo_hz, opts.rewind_seconds, hooks, stats, memory)
+        .map_err(Error::Thread)?;
+    emu.send(Command::SetPalette(settings.palette.palette()));
+    emu.send(Command::SetSpriteOutlines(settings.sprite_outlines));
+    emu.send(Command::SetOverclock(settings.overclock));
+    emu.send(Command::SetDas(settings.das.then_some(settings.das_timing)));
+    emu.send(Command::SetPaused(settings.paused));
+    emu.send(Command::ShowStats(settings.frame_stats));
+    emu.send(Command::ShowInput(settings.input_display));
+    emu.send(Command::ShowGhost(settings.ghost));
+    emu.send(Command::SetHardDrop(settings.hard_drop));
+    Ok(emu)
+}
+
+/// The servers started from the command line, for the machine they're wired to (player 1's).
+#[derive(Clone, Default)]
+struct Servers {
+    telemetry: Option<Feed>,   // With `--telemetry`
+    remote: Option<Orders>,    // With `--remote-input`
+    stream: Option<FrameFeed>, // With `--stream`
+}
+
+/// Game-specific code for a machine: `script`, the high-score keeper (with
+/// `--save-high-scores` and a game that needs one), the Tetris autoplayer, playing from the
+/// start if `autoplay`, Tetris practice mode (with `--practice`), the forced piece
+/// sequence (with `--pieces`), the session statistics (with `--session-stats`), the
+/// speedrun timer (with `--timer`), the game telemetry, the remote buttons and the frame
+/// stream for the `servers` given, the Tetris ghost piece (hidden until shown with
+/// `Command::ShowGhost`), the piece colors (with `--colorize`), the demo recorder (with `--record-demo`), attract mode (with `--attract`),
+/// the input macros (with `--macro`),
+/// the Tetris hard-drop patch (off until enabled with `Command::SetHardDrop`), the
+/// serial output (with `--serial-stdout` or `--serial-log`), the stack check warnings
+/// (with `--stack-check` or `--stack-break`) and the assertions (with `--assert` or
+/// `debug.assert`).
+fn hooks(
+    gb: &GB,
+    rom_path: &str,
+    opts: &Options,
+    script: Option<Script>,
+    autoplay: bool,
+    servers: Servers,
+) -> Hooks {
+    let high_scores = opts.save_high_scores.then(|| HighScores::for_game(gb, rom_path)).flatten();
+    let bot = Bot::for_game(gb, autoplay);
+    if autoplay && bot.is_none() {
+        eprintln!("Warning: the autoplayer only plays Tetris, not {}", gb.game_title());
+    }
+    let practice = opts.practice.then(|| Practice::for_game(gb)).flatten();
+    if opts.practice && practice.is_none() {
+        eprintln!("Warning: practice mode only works in Tetris, not {}", gb.game_title());
+    }
+    let pieces = opts.pieces.clone().and_then(|pieces| PieceQueue::for_game(gb, pieces));
+    if opts.pieces.is_some() && pieces.is_none() {
+        eprintln!("Warning: --pieces only works in Tetris, not {}", gb.game_title());
+    }
+    let session = opts.session_stats.then(|| Session::for_game(gb)).flatten();
+    if opts.session_stats && session.is_none() {
+        eprintln!("Warning: session statistics only work in Tetris, not {}", gb.game_title());
+    }
+    let speedrun = opts.timer.and_then(|split_on| Speedrun::for_game(gb, split_on));
+    if opts.timer.is_some() && speedrun.is_none() {
+        eprintln!("Warning: the speedrun timer only works in Tetris, not {}", gb.game_title());
+    }
+    let Servers { telemetry, remote, stream } = servers;
+    let telemetry_given = telemetry.is_some();
+    let telemetry = telemetry.and_then(|feed| Telemetry::for_game(gb, feed));
+    if telemetry_given && telemetry.is_none() {
+        eprintln!("Warning: telemetry only covers Tetris, not {}", gb.game_title());
+    }
+    let ghost = Ghost::for_game(gb);
+    if opts.ghost && ghost.is_none() {
+        eprintln!("Warning: the ghost piece only works in Tetris, not {}", gb.game_title());
+    }
+    let colorizer = opts.colorize.then(|| Colorizer::for_game(gb)).flatten();
+    if opts.colorize && colorizer.is_none() {
+        eprintln!("Warning: piece colors only work in Tetris, not {}", gb.game_title());
+    }
+    let recorder = opts.record_demo.as_deref().map(Recorder::new);
+    let attract = opts.attract.as_deref().and_then(|path| match Demo::load(Path::new(path)) {
+        Ok(demo) => Some(Attract::new(demo, opts.attract_idle)),
+        Err(e) => {
+            eprintln!("Warning: can't load the demo {}: {}", path, e);
+            None
+        }
+    });
+    let macros = (!opts.macros.is_empty()).then(|| Macros::for_rom(rom_path));
+    let hard_drop = HardDrop::for_game(gb);
+    if opts.hard_drop && hard_drop.is_none() {
+        eprintln!("Warning: the hard-drop patch only works in Tetris, not {}", gb.game_title());
+    }
+    let serial_log = opts.serial_log.as_deref().and_then(|path| {
+        match std::fs::OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Warning: can't open the serial log {}: {}", path, e);
+                None
+            }
+        }
+    });
+    let serial = (opts.serial_stdout || serial_log.is_some()).then(|| SerialOutput::new(opts.serial_stdout, serial_log));
+    Hooks {
+        script,
+        high_scores,
+        bot,
+        practice,
+        pieces,
+        session,
+        speedrun,
+        telemetry,
+        ghost,
+        colorizer,
+        recorder,
+        attract,
+        macros,
+        hard_drop,
+        debugger: None,
+        serial,
+        stack: (opts.stack_check || opts.stack_break).then(StackWarnings::default),
+        assertions: (!opts.assertions.is_empty()).then(|| Assertions::new(&opts.assertions)),
+        motor: gb.has_rumble().then(Motor::default),
+        remote: remote.map(RemoteInput::new),
+        stream,
+    }
+}
+
+/// Writes the settings changed during the session to the config file. The others are
+/// left alone, so values that only came from flags don't end up in the file.
+fn save_settings(config: &mut Config, start: &Settings, end: &Settings) {
+    if end == start {
+        return;
+    }
+    if end.speed != start.speed {
+        config.set("emulation", "speed", Value::Int(end.speed as i64));
+    }
+    if end.overclock != start.overclock {
+        config.set("emulation", "overclock", Value::Int(end.overclock as i64));
+    }
+    if end.pause_on_focus_loss != start.pause_on_focus_loss {
+        config.set("emulation", "pause_on_focus_loss", Value::Bool(end.pause_on_focus_loss));
+    }
+    if end.frame_stats != start.frame_stats {
+        config.set("emulation", "frame_stats", Value::Bool(end.frame_stats));
+    }
+    if end.input_display != start.input_display {
+        config.set("video", "input_display", Value::Bool(end.input_display));
+    }
+    if end.ghost != start.ghost {
+        config.set("video", "ghost", Value::Bool(end.ghost));
+    }
+    if end.hard_drop != start.hard_drop {
+        config.set("emulation", "hard_drop", Value::Bool(end.hard_drop));
+    }
+    if end.palette != start.palette {
+        config.set("video", "palette", Value::Str(String::from(end.palette.name())));
+    }
+    if end.sprite_outlines != start.sprite_outlines {
+        config.set("video", "sprite_outlines", Value::Bool(end.sprite_outlines));
+    }
+    if end.rotation != start.rotation {
+        config.set("video", "rotation", Value::Int(end.rotation.degrees() as i64));
+    }
+    if end.fullscreen != start.fullscreen {
+        config.set("video", "fullscreen", Value::Bool(end.fullscreen));
+    }
+    if end.hud != start.hud {
+        config.set("video", "hud", Value::Bool(end.hud));
+    }
+    if end.background != start.background {
+        let [r, g, b] = end.background;
+        config.set("video", "background", Value::Str(format!("{:02X}{:02X}{:02X}", r, g, b)));
+    }
+    if end.turbo_hz != start.turbo_hz {
+        config.set("input", "turbo_hz", Value::Int(end.turbo_hz as i64));
+    }
+    if end.das != start.das {
+        config.set("input", "das", Value::Bool(end.das));
+    }
+    if end.das_timing.delay != start.das_timing.delay {
+        config.set("input", "das_delay", Value::Int(end.das_timing.delay as i64));
+    }
+    if end.das_timing.repeat != start.das_timing.repeat {
+        config.set("input", "das_repeat", Value::Int(end.das_timing.repeat as i64));
+    }
+    if end.rumble != start.rumble {
+        config.set("input", "rumble", Value::Int(end.rumble as i64));
+    }
+    config.save();
+}
+
+/// Whether `scancode` is bound to a button or turbo button, player 2's counting only with a
+/// second machine running.
+fn is_button(opts: &Options, scancode: Scancode, two_players: bool) -> bool {
+    let bound = |keys: &[(Scancode, u8)]| keys.iter().any(|&(sc, _)| sc == scancode);
+    bound(&opts.keys) || bound(&opts.turbo) || (two_players && bound(&opts.player2_keys))
+}
+
+/// Recent-game number (1–9) of a digit key.
+fn recent_index(scancode: Scancode) -> Option<usize> {
+    let digits = [
+        Scancode::Num1, Scancode::Num2, Scancode::Num3, Scancode::Num4, Scancode::Num5,
+        Scancode::Num6, Scancode::Num7, Scancode::Num8, Scancode::Num9,
+    ];
+    digits.iter().position(|&d| d == scancode).map(|i| i + 1)
+}
+
+/// Sends `command` to every machine.
+fn send_all(machines: &[Machine], command: Command) {
+    for machine in machines {
+        machine.emu.send(command.clone());
     }
 }
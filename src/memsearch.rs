@@ -0,0 +1,207 @@
+use rustris_core::GB;
+
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Address ranges the search covers: work RAM and high RAM.
+const REGIONS: [RangeInclusive<u16>; 2] = [0xC000..=0xDFFF, 0xFF80..=0xFFFE];
+
+/// Results listed in the window; narrow the search further to see the rest.
+const MAX_SHOWN: usize = 200;
+
+/// Copy of the address space that the emulation thread publishes after each frame, but
+/// only while a memory tool (search, watch list) asks for it.
+/// - Cloning gives another handle to the same copy.
+#[derive(Clone, Default)]
+pub struct MemoryView(Arc<Mutex<View>>);
+
+#[derive(Default)]
+struct View {
+    wanted: bool,
+    memory: Vec<u8>, // Empty until the first publish
+}
+
+impl MemoryView {
+    /// Starts or stops publishing.
+    pub fn set_wanted(&self, wanted: bool) {
+        self.lock().wanted = wanted;
+    }
+
+    /// Called by the emulation thread: copies the machine's memory if anyone is looking.
+    pub fn publish(&self, gb: &GB) {
+        let mut view = self.lock();
+        if view.wanted {
+            view.memory.resize(0x10000, 0);
+            gb.read_memory(&mut view.memory);
+        }
+    }
+
+    /// The last published copy, if there's one.
+    pub fn snapshot(&self) -> Option<Vec<u8>> {
+        let view = self.lock();
+        (!view.memory.is_empty()).then(|| view.memory.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, View> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// How a candidate's current value must relate to the target.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+}
+
+/// What the current value is compared with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Previous, // The value at the previous search (or at "New search")
+    Value,    // A fixed value typed by the user
+}
+
+/// Classic RAM search for finding where a game keeps a number (lines, score, level):
+/// - "New search" snapshots memory and makes every address in `REGIONS` a candidate.
+/// - Each "Search" keeps the candidates whose current value compares as asked with
+///   their previous value (=: unchanged, ≠: changed, >, <) or with a given value, then
+///   takes a new snapshot for the next round.
+/// - Results can be sent to the watch list (see `WatchList`).
+pub struct MemorySearch {
+    candidates: Vec<u16>,
+    previous: Vec<u8>, // Memory at the last search
+    comparison: Comparison,
+    target: Target,
+    value: String,     // Text of the value field (decimal, or hex with a `$`/`0x` prefix)
+    error: Option<String>,
+}
+
+impl Default for MemorySearch {
+    fn default() -> Self {
+        Self {
+            candidates: Vec::new(),
+            previous: Vec::new(),
+            comparison: Comparison::Equal,
+            target: Target::Previous,
+            value: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl MemorySearch {
+    fn reset(&mut self, memory: &[u8]) {
+        self.candidates = REGIONS.iter().flat_map(|r| r.clone()).collect();
+        self.previous = memory.to_vec();
+        self.error = None;
+    }
+
+    fn search(&mut self, memory: &[u8]) {
+        if self.previous.is_empty() {
+            self.reset(memory);
+        }
+        let fixed = match self.target {
+            Target::Previous => None,
+            Target::Value => match parse_value(&self.value) {
+                Some(v) => Some(v),
+                None => {
+                    self.error = Some(format!("Not a byte value: {}", self.value));
+                    return;
+                }
+            },
+        };
+
+        let (comparison, previous) = (self.comparison, &self.previous);
+        self.candidates.retain(|&addr| {
+            let current = memory[addr as usize];
+            let target = fixed.unwrap_or(previous[addr as usize]);
+            match comparison {
+                Comparison::Equal => current == target,
+                Comparison::NotEqual => current != target,
+                Comparison::Greater => current > target,
+                Comparison::Less => current < target,
+            }
+        });
+        self.previous = memory.to_vec();
+        self.error = None;
+    }
+
+    /// The search window. `memory` is the latest copy from the emulation thread. Returns
+    /// the address whose "Watch" button was clicked, if any.
+    pub fn window(&mut self, ctx: &egui::Context, open: &mut bool, memory: Option<&[u8]>) -> Option<u16> {
+        let mut watch = None;
+        egui::Window::new("Memory search")
+            .open(open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(memory) = memory else {
+                    ui.label("Waiting for the emulator...");
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label("Current value");
+                    ui.radio_value(&mut self.comparison, Comparison::Equal, "=");
+                    ui.radio_value(&mut self.comparison, Comparison::NotEqual, "≠");
+                    ui.radio_value(&mut self.comparison, Comparison::Greater, ">");
+                    ui.radio_value(&mut self.comparison, Comparison::Less, "<");
+                });
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.target, Target::Previous, "previous value");
+                    ui.radio_value(&mut self.target, Target::Value, "value:");
+                    ui.add(egui::TextEdit::singleline(&mut self.value).desired_width(48.0));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Search").clicked() {
+                        self.search(memory);
+                    }
+                    if ui.button("New search").clicked() {
+                        self.reset(memory);
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                ui.separator();
+                if self.previous.is_empty() {
+                    ui.label("Press New search, change the number in the game, then search for changed values.");
+                    return;
+                }
+                ui.label(format!("{} candidates", self.candidates.len()));
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    egui::Grid::new("search_results").striped(true).show(ui, |ui| {
+                        ui.strong("Address");
+                        ui.strong("Previous");
+                        ui.strong("Current");
+                        ui.label("");
+                        ui.end_row();
+                        for &addr in self.candidates.iter().take(MAX_SHOWN) {
+                            let (previous, current) = (self.previous[addr as usize], memory[addr as usize]);
+                            if ui.button(format!("{:04X}", addr)).on_hover_text("Copy address").clicked() {
+                                ui.ctx().copy_text(format!("{:04X}", addr));
+                            }
+                            ui.monospace(format!("{:02X} ({})", previous, previous));
+                            ui.monospace(format!("{:02X} ({})", current, current));
+                            if ui.small_button("Watch").clicked() {
+                                watch = Some(addr);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        watch
+    }
+}
+
+/// Parses a byte typed as decimal or as hex with a `$` or `0x` prefix.
+fn parse_value(text: &str) -> Option<u8> {
+    let text = text.trim();
+    match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,402 @@
+use crate::osd;
+use crate::recent;
+use crate::video::HostPalette;
+
+use rustris_core::gb::{BTN_A, BTN_B, BTN_DOWN, BTN_LEFT, BTN_RIGHT, BTN_START, BTN_UP};
+use rustris_core::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+
+use std::path::{Path, PathBuf};
+
+/// Opens and closes the menu; so do a game controller's Back and Guide buttons.
+pub const MENU_KEY: Scancode = Scancode::M;
+
+/// Save state slots, numbered from 1.
+const STATE_SLOTS: u8 = 4;
+
+/// Where the rows start, how far apart they are, and how many fit above the hints.
+const TOP: i32 = 16;
+const ROW_HEIGHT: i32 = 8;
+const ROWS: usize = 15;
+
+/// Characters that fit on a row after the cursor.
+const ROW_CHARS: usize = 36;
+
+const TEXT: [u8; 3] = [0xE0, 0xE0, 0xE0];
+const TITLE: [u8; 3] = [0xFF, 0xD0, 0x60];
+const GRAYED: [u8; 3] = [0x80, 0x80, 0x80];
+const BAR: [u8; 3] = [0x30, 0x50, 0x90];
+
+/// A press the menu understands, from the keyboard or a game controller.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MenuKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+/// What picking an item asks of the front-end.
+pub enum Action {
+    Open(String),
+    SaveState(PathBuf),
+    LoadState(PathBuf),
+    Palette(HostPalette),
+    Reset,
+    Quit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum Page {
+    #[default]
+    Main,
+    Browse,
+    Recent,
+    Save,
+    Load,
+    Resume, // The offer to resume from the exit state
+}
+
+/// What a row does when picked.
+#[derive(Clone)]
+enum Item {
+    Resume,
+    Page(Page),
+    Folder(PathBuf),
+    Game(String),
+    SaveSlot(PathBuf),
+    LoadSlot(PathBuf),
+    Palette,
+    Reset,
+    Quit,
+    Nothing, // An empty slot, a folder that can't be read
+}
+
+/// In-game menu (M, or a controller's Back or Guide button): opening a game, saving and
+/// loading states, the palette and quitting, drawn over the first machine's picture
+/// while its game waits paused.
+/// - Up/Down move, A or Start picks, B goes back a page (closing it from the first),
+///   Left/Right change the palette. The keys are player 1's as well as the arrows,
+///   Return and Escape; on a controller, the D-pad, A and B.
+/// - Games are opened from a browser of `.gb` and `.gbc` files starting in the folder of
+///   the one playing, or from the recent list.
+/// - States go to `STATE_SLOTS` files next to the ROM (see `state_path`).
+/// - With `--auto-resume` it opens by itself on a game that was left mid-play, offering
+///   to continue from its exit state (see `offer_resume`).
+#[derive(Default)]
+pub struct Menu {
+    open: bool,
+    changed: bool, // Since the last `take_changed`
+    page: Page,
+    items: Vec<(String, Item)>,
+    cursor: usize,
+    top: usize,         // First row shown, when the page doesn't fit
+    main_cursor: usize, // Row of the first page to come back to
+    folder: PathBuf,    // Shown by the browser
+    rom_path: String,   // The game playing, if `has_game`
+    has_game: bool,
+    recent: Vec<String>,
+    palette: HostPalette,
+}
+
+impl Menu {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens the first page for the game at `rom_path` (`has_game` false: the
+    /// no-cartridge screen, without states), offering the `recent` games.
+    pub fn open(&mut self, rom_path: &str, has_game: bool, recent: &[String], palette: HostPalette) {
+        self.rom_path = rom_path.to_string();
+        self.has_game = has_game;
+        self.recent = recent.to_vec();
+        self.palette = palette;
+        self.folder = std::fs::canonicalize(rom_path)
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        self.open = true;
+        self.changed = true;
+        self.main_cursor = 0;
+        self.show(Page::Main);
+    }
+
+    /// Opens on the offer to continue the game at `rom_path` from its exit state (see
+    /// `exit_state_path`); turning it down leaves the game going from power-on.
+    pub fn offer_resume(&mut self, rom_path: &str, recent: &[String], palette: HostPalette) {
+        self.open(rom_path, true, recent, palette);
+        self.show(Page::Resume);
+    }
+
+    pub fn close(&mut self) {
+        self.changed |= self.open;
+        self.open = false;
+    }
+
+    /// Whether the menu opened, closed or moved since the last call, so its picture
+    /// needs drawing again.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Handles a press; returns what the item picked asks for, if anything. The menu
+    /// closes itself after any action but a palette change.
+    pub fn press(&mut self, key: MenuKey) -> Option<Action> {
+        self.changed = true;
+        match key {
+            MenuKey::Up => self.move_cursor(self.cursor.checked_sub(1).unwrap_or(self.items.len() - 1)),
+            MenuKey::Down => self.move_cursor((self.cursor + 1) % self.items.len()),
+            MenuKey::Left | MenuKey::Right if matches!(self.items[self.cursor].1, Item::Palette) => {
+                let step = if key == MenuKey::Left { HostPalette::ALL.len() - 1 } else { 1 };
+                return Some(self.set_palette(step));
+            }
+            MenuKey::Left | MenuKey::Right => {}
+            MenuKey::Back if matches!(self.page, Page::Main | Page::Resume) => self.close(),
+            MenuKey::Back => {
+                let back = self.main_cursor;
+                self.show(Page::Main);
+                self.move_cursor(back);
+            }
+            MenuKey::Select => {
+                let action = match self.items[self.cursor].1.clone() {
+                    Item::Resume => None,
+                    Item::Page(page) => {
+                        self.main_cursor = self.cursor;
+                        self.show(page);
+                        return None;
+                    }
+                    Item::Folder(folder) => {
+                        // Coming back up, the cursor stays on the folder just left
+                        let left = std::mem::replace(&mut self.folder, folder);
+                        self.show(Page::Browse);
+                        if let Some(row) = self.items.iter().position(|(_, item)| matches!(item, Item::Folder(f) if *f == left)) {
+                            self.move_cursor(row);
+                        }
+                        return None;
+                    }
+                    Item::Palette => return Some(self.set_palette(1)),
+                    Item::Nothing => return None,
+                    Item::Game(path) => Some(Action::Open(path)),
+                    Item::SaveSlot(path) => Some(Action::SaveState(path)),
+                    Item::LoadSlot(path) => Some(Action::LoadState(path)),
+                    Item::Reset => Some(Action::Reset),
+                    Item::Quit => Some(Action::Quit),
+                };
+                self.close();
+                return action;
+            }
+        }
+        None
+    }
+
+    /// Draws the menu over a frame (RGB24, `pitch` bytes per row), the game dimmed
+    /// behind it.
+    pub fn draw(&self, framebuffer: &mut [u8], pitch: usize) {
+        for byte in framebuffer.iter_mut() {
+            *byte /= 3;
+        }
+        let width = SCREEN_WIDTH as i32;
+        let title = fit(&self.title(), ROW_CHARS + 2);
+        osd::draw_text(framebuffer, pitch, (width - title.chars().count() as i32 * osd::CHAR_WIDTH) / 2, 4, &title, TITLE);
+
+        for (row, (label, item)) in self.items.iter().enumerate().skip(self.top).take(ROWS) {
+            let y = TOP + (row - self.top) as i32 * ROW_HEIGHT;
+            if row == self.cursor {
+                osd::fill_rect(framebuffer, pitch, 4, y - 1, width - 8, ROW_HEIGHT - 1, BAR);
+                osd::draw_text(framebuffer, pitch, 6, y, ">", TEXT);
+            }
+            let color = if matches!(item, Item::Nothing) { GRAYED } else { TEXT };
+            osd::draw_text(framebuffer, pitch, 12, y, &fit(label, ROW_CHARS), color);
+        }
+
+        // Where the rows shown sit in a page that doesn't fit
+        if self.items.len() > ROWS {
+            let track = ROWS as i32 * ROW_HEIGHT;
+            let (len, top) = (self.items.len() as i32, self.top as i32);
+            osd::fill_rect(framebuffer, pitch, width - 3, TOP - 1 + top * track / len, 2, ROWS as i32 * track / len, GRAYED);
+        }
+
+        let hints = match self.page {
+            Page::Main => "A: PICK   B: RESUME",
+            Page::Resume => "A: PICK   B: START OVER",
+            _ => "A: PICK   B: BACK",
+        };
+        let x = (width - hints.len() as i32 * osd::CHAR_WIDTH) / 2;
+        osd::draw_text(framebuffer, pitch, x, SCREEN_HEIGHT as i32 - 8, hints, GRAYED);
+    }
+
+    fn title(&self) -> String {
+        match self.page {
+            Page::Main | Page::Resume if self.has_game => recent::display_name(&self.rom_path),
+            Page::Main | Page::Resume => "RUSTRIS".to_string(),
+            Page::Browse => self.folder.file_name().map_or_else(|| self.folder.display().to_string(), |name| name.to_string_lossy().into_owned()),
+            Page::Recent => "RECENT GAMES".to_string(),
+            Page::Save => "SAVE STATE".to_string(),
+            Page::Load => "LOAD STATE".to_string(),
+        }
+    }
+
+    fn show(&mut self, page: Page) {
+        self.page = page;
+        self.items = match page {
+            Page::Main => self.main_items(),
+            Page::Browse => browse(&self.folder),
+            Page::Recent => self.recent.iter().map(|path| (recent::display_name(path), Item::Game(path.clone()))).collect(),
+            Page::Save | Page::Load => (1..=STATE_SLOTS)
+                .map(|slot| {
+                    let path = state_path(&self.rom_path, slot);
+                    let used = path.is_file();
+                    let label = format!("SLOT {}  {}", slot, if used { "SAVED" } else { "EMPTY" });
+                    let item = match page {
+                        Page::Save => Item::SaveSlot(path),
+                        _ if used => Item::LoadSlot(path),
+                        _ => Item::Nothing,
+                    };
+                    (label, item)
+                })
+                .collect(),
+            Page::Resume => vec![
+                ("CONTINUE WHERE YOU LEFT OFF".to_string(), Item::LoadSlot(exit_state_path(&self.rom_path))),
+                ("START OVER".to_string(), Item::Resume),
+            ],
+        };
+        self.cursor = 0;
+        self.top = 0;
+    }
+
+    fn main_items(&self) -> Vec<(String, Item)> {
+        let mut items = vec![("RESUME".to_string(), Item::Resume), ("OPEN GAME".to_string(), Item::Page(Page::Browse))];
+        if !self.recent.is_empty() {
+            items.push(("RECENT GAMES".to_string(), Item::Page(Page::Recent)));
+        }
+        if self.has_game {
+            items.push(("SAVE STATE".to_string(), Item::Page(Page::Save)));
+            items.push(("LOAD STATE".to_string(), Item::Page(Page::Load)));
+        }
+        items.push((format!("PALETTE: < {} >", self.palette.name()), Item::Palette));
+        if self.has_game {
+            items.push(("RESET".to_string(), Item::Reset));
+        }
+        items.push(("QUIT".to_string(), Item::Quit));
+        items
+    }
+
+    fn move_cursor(&mut self, row: usize) {
+        self.cursor = row.min(self.items.len() - 1);
+        self.top = self.top.clamp((self.cursor + 1).saturating_sub(ROWS), self.cursor);
+    }
+
+    /// Moves `step` presets along, relabelling the palette row.
+    fn set_palette(&mut self, step: usize) -> Action {
+        let i = HostPalette::ALL.iter().position(|&palette| palette == self.palette).unwrap_or(0);
+        self.palette = HostPalette::ALL[(i + step) % HostPalette::ALL.len()];
+        let cursor = self.cursor;
+        self.items = self.main_items();
+        self.cursor = cursor;
+        Action::Palette(self.palette)
+    }
+}
+
+/// The press an event is for the menu, if any: player 1's keys (`keys`), the arrows,
+/// Return and Escape, or a controller's D-pad, A and B. Held keys repeat.
+pub fn menu_key(event: &Event, keys: &[(Scancode, u8)]) -> Option<MenuKey> {
+    match *event {
+        Event::KeyDown { scancode: Some(scancode), .. } => match scancode {
+            Scancode::Up => Some(MenuKey::Up),
+            Scancode::Down => Some(MenuKey::Down),
+            Scancode::Left => Some(MenuKey::Left),
+            Scancode::Right => Some(MenuKey::Right),
+            Scancode::Return => Some(MenuKey::Select),
+            Scancode::Escape | Scancode::Backspace => Some(MenuKey::Back),
+            _ => match keys.iter().find(|(s, _)| *s == scancode)?.1 {
+                BTN_UP => Some(MenuKey::Up),
+                BTN_DOWN => Some(MenuKey::Down),
+                BTN_LEFT => Some(MenuKey::Left),
+                BTN_RIGHT => Some(MenuKey::Right),
+                BTN_A | BTN_START => Some(MenuKey::Select),
+                BTN_B => Some(MenuKey::Back),
+                _ => None,
+            },
+        },
+        Event::ControllerButtonDown { button, .. } => match button {
+            Button::DPadUp => Some(MenuKey::Up),
+            Button::DPadDown => Some(MenuKey::Down),
+            Button::DPadLeft => Some(MenuKey::Left),
+            Button::DPadRight => Some(MenuKey::Right),
+            Button::A | Button::Start => Some(MenuKey::Select),
+            Button::B => Some(MenuKey::Back),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether an event opens or closes the menu.
+pub fn is_toggle(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::KeyDown { scancode: Some(MENU_KEY), repeat: false, .. } |
+        Event::ControllerButtonDown { button: Button::Back | Button::Guide, .. }
+    )
+}
+
+/// File a ROM's state goes to in a save slot: same name, `.ss1` to `.ss4`.
+pub fn state_path(rom_path: &str, slot: u8) -> PathBuf {
+    Path::new(rom_path).with_extension(format!("ss{}", slot))
+}
+
+/// File a ROM's exit state goes to with `--auto-resume`: same name, `.ss0`, clear of the
+/// save slots.
+pub fn exit_state_path(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension("ss0")
+}
+
+/// The browser's rows for `folder`: its parent, then the folders and the Game Boy ROMs
+/// in it, hidden ones left out, each group by name.
+fn browse(folder: &Path) -> Vec<(String, Item)> {
+    let mut items = Vec::new();
+    if let Some(parent) = folder.parent() {
+        items.push(("..".to_string(), Item::Folder(parent.to_path_buf())));
+    }
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        items.push(("CAN'T READ THIS FOLDER".to_string(), Item::Nothing));
+        return items;
+    };
+    let (mut folders, mut games) = (Vec::new(), Vec::new());
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            folders.push((format!("{}/", name), Item::Folder(path)));
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gb") || ext.eq_ignore_ascii_case("gbc")) {
+            games.push((name, Item::Game(path.display().to_string())));
+        }
+    }
+    folders.sort_by_key(|(name, _)| name.to_lowercase());
+    games.sort_by_key(|(name, _)| name.to_lowercase());
+    items.extend(folders);
+    items.extend(games);
+    if items.len() <= 1 {
+        items.push(("NO GAMES HERE".to_string(), Item::Nothing));
+    }
+    items
+}
+
+/// `text` cut to `chars`, ending in `..` if it didn't fit.
+fn fit(text: &str, chars: usize) -> String {
+    if text.chars().count() <= chars {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(chars - 2).collect();
+    cut.push_str("..");
+    cut
+}
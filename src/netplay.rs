@@ -0,0 +1,503 @@
+use crate::crashdump;
+use crate::demo::Checkpoint;
+use crate::error::Error;
+use crate::headless::{self, PITCH};
+use crate::options::{NetRole, Options};
+use crate::pacing::Limiter;
+use crate::stats::FrameTimes;
+use crate::video::{self, Rotation};
+
+use rustris_core::{link, GB, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::{Color, PixelFormatEnum};
+
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Frames the local buttons are held back before they take effect. Hides that much
+/// latency without any rollback at all.
+const INPUT_DELAY: u32 = 2;
+
+/// Furthest the simulation may run past the last confirmed remote input; beyond that
+/// it waits for the peer instead of predicting further.
+const MAX_ROLLBACK: u32 = 8;
+
+/// Local inputs carried by one packet at most. Every packet repeats all the inputs the
+/// peer hasn't acknowledged yet, so a lost packet is covered by the next one.
+const MAX_PACKET_INPUTS: usize = 32;
+
+/// Silence after which the peer is considered gone.
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the guest repeats its hello, and how long both sides wait for each other.
+const HANDSHAKE_RETRY: Duration = Duration::from_millis(250);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Frames between two checks that both sides' machines still match (see `Rollback::sums`).
+const CHECK_INTERVAL: u32 = 60;
+
+/// Checksums kept for comparing with the peer's, which may arrive a few checks late.
+const KEPT_SUMS: usize = 8;
+
+/// First bytes of every packet, so stray datagrams (and older versions) are ignored.
+const MAGIC: [u8; 4] = *b"RNP2";
+
+/// Datagrams exchanged by the two peers.
+/// - `Hello`: guest → host, with a hash of the ROM and settings (see `config_hash`).
+/// - `Welcome`: host → guest, with the seed both sides give their machines.
+/// - `Reject`: host → guest, when the hashes don't match.
+/// - `Input`: the sender's buttons for frames `start..`, its current frame and frame
+///   advantage (for time sync), `ack`, the number of the receiver's inputs it has, and
+///   its latest checksum of the machines (see `Rollback::sums`; frame 0 before the first).
+enum Message {
+    Hello { config: u32 },
+    Welcome { seed: u64 },
+    Reject,
+    Input { frame: u32, advantage: i8, ack: u32, check: (u32, u32), start: u32, inputs: Vec<u8> },
+}
+
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        match self {
+            Message::Hello { config } => {
+                out.push(b'H');
+                out.extend_from_slice(&config.to_le_bytes());
+            }
+            Message::Welcome { seed } => {
+                out.push(b'W');
+                out.extend_from_slice(&seed.to_le_bytes());
+            }
+            Message::Reject => out.push(b'R'),
+            Message::Input { frame, advantage, ack, check, start, inputs } => {
+                out.push(b'I');
+                out.extend_from_slice(&frame.to_le_bytes());
+                out.push(*advantage as u8);
+                out.extend_from_slice(&ack.to_le_bytes());
+                out.extend_from_slice(&check.0.to_le_bytes());
+                out.extend_from_slice(&check.1.to_le_bytes());
+                out.extend_from_slice(&start.to_le_bytes());
+                out.extend_from_slice(inputs);
+            }
+        }
+        out
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        let body = data.strip_prefix(&MAGIC)?;
+        let (&kind, body) = body.split_first()?;
+        let u32_at = |at: usize| Some(u32::from_le_bytes(body.get(at..at + 4)?.try_into().ok()?));
+        match kind {
+            b'H' => Some(Message::Hello { config: u32_at(0)? }),
+            b'W' => Some(Message::Welcome { seed: u64::from_le_bytes(body.get(..8)?.try_into().ok()?) }),
+            b'R' => Some(Message::Reject),
+            b'I' => Some(Message::Input {
+                frame: u32_at(0)?,
+                advantage: *body.get(4)? as i8,
+                ack: u32_at(5)?,
+                check: (u32_at(9)?, u32_at(13)?),
+                start: u32_at(17)?,
+                inputs: body[21..].to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// FNV-1a hash of everything both peers must agree on (ROM image, model, profile, overclock,
+/// RAM contents at power-on, boot ROMs).
+pub fn config_hash(parts: &[&[u8]]) -> u32 {
+    let mut hash: u32 = 0x811C_9DC5;
+    for byte in parts.iter().flat_map(|p| p.iter()) {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+/// The other side of the session.
+struct Peer {
+    socket: UdpSocket,
+    addr: SocketAddr,
+}
+
+impl Peer {
+    fn send(&self, message: &Message) {
+        // Losses are covered by redundancy and retries, so errors aren't fatal
+        let _ = self.socket.send_to(&message.encode(), self.addr);
+    }
+
+    /// Next datagram from the peer, if one is waiting (the socket is non-blocking).
+    fn recv(&self) -> Option<Message> {
+        let mut buf = [0u8; 512];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) if from == self.addr => return Message::decode(&buf[..len]),
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Connects the two peers. Returns the peer, the shared seed and which machine this
+/// side controls (the host is player 1).
+fn handshake(role: &NetRole, config: u32) -> Result<(Peer, u64, usize), String> {
+    let mut buf = [0u8; 512];
+    match role {
+        NetRole::Host(port) => {
+            let socket = UdpSocket::bind(("0.0.0.0", *port)).map_err(|e| format!("can't bind port {}: {}", port, e))?;
+            socket.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+            println!("Waiting for a player on port {}...", port);
+            loop {
+                let (len, addr) = socket.recv_from(&mut buf).map_err(|_| String::from("no player joined"))?;
+                match Message::decode(&buf[..len]) {
+                    Some(Message::Hello { config: theirs }) if theirs == config => {
+                        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+                        let peer = Peer { socket, addr };
+                        peer.send(&Message::Welcome { seed });
+                        println!("Player 2 joined from {}", addr);
+                        return Ok((peer, seed, 0));
+                    }
+                    Some(Message::Hello { .. }) => {
+                        let _ = socket.send_to(&Message::Reject.encode(), addr);
+                        eprintln!("Netplay: {} runs a different ROM or settings", addr);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        NetRole::Join(host) => {
+            let addr = host
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or_else(|| format!("can't resolve {}", host))?;
+            let local = if addr.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+            let socket = UdpSocket::bind(local).map_err(|e| e.to_string())?;
+            socket.set_read_timeout(Some(HANDSHAKE_RETRY)).map_err(|e| e.to_string())?;
+            println!("Connecting to {}...", addr);
+            let start = Instant::now();
+            while start.elapsed() < HANDSHAKE_TIMEOUT {
+                let _ = socket.send_to(&Message::Hello { config }.encode(), addr);
+                match socket.recv_from(&mut buf) {
+                    Ok((len, from)) if from == addr => match Message::decode(&buf[..len]) {
+                        Some(Message::Welcome { seed }) => return Ok((Peer { socket, addr }, seed, 1)),
+                        Some(Message::Reject) => return Err(String::from("the host runs a different ROM or settings")),
+                        _ => {}
+                    },
+                    Ok(_) => {}
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                    Err(e) => return Err(e.to_string()),
+                }
+            }
+            Err(format!("no answer from {}", addr))
+        }
+    }
+}
+
+/// Rollback state for a pair of linked machines that both peers simulate identically.
+/// - Each frame runs with this side's delayed buttons and the peer's buttons, either
+///   confirmed or predicted (the last confirmed ones repeated).
+/// - A snapshot of both machines is kept for each of the last `MAX_ROLLBACK + 1`
+///   frames. When a confirmed input differs from the prediction a frame ran with, the
+///   machines go back to that frame's snapshot and everything since is resimulated.
+/// - Every `CHECK_INTERVAL` frames the machines are summed up (registers and RAM, see
+///   `Checkpoint`) as the frame starts; once every input before it is confirmed, the sum
+///   can't change any more and goes to the peer, whose sum for the frame must match.
+struct Rollback {
+    machines: [GB; 2],
+    screens: [Vec<u8>; 2],
+    local: usize,                   // Index of the machine this side controls
+    frame: u32,                     // Next frame to simulate
+    local_inputs: Vec<u8>,          // This side's buttons by frame (the first INPUT_DELAY are empty)
+    remote_inputs: Vec<u8>,         // The peer's confirmed buttons by frame
+    used: Vec<u8>,                  // The peer's buttons each simulated frame ran with
+    checked: u32,                   // Frames below this ran with confirmed input
+    states: VecDeque<[Vec<u8>; 2]>, // Snapshots taken before frames `frame - len..frame`
+    pending: Option<(u32, u32)>,    // Frame and checksum not confirmed yet
+    sums: VecDeque<(u32, u32)>,     // Confirmed checksums by frame, the latest `KEPT_SUMS`
+    rollbacks: u64,
+    resimulated: u64,
+}
+
+impl Rollback {
+    fn new(machines: [GB; 2], local: usize) -> Self {
+        Self {
+            machines,
+            screens: [vec![0u8; PITCH * SCREEN_HEIGHT as usize], vec![0u8; PITCH * SCREEN_HEIGHT as usize]],
+            local,
+            frame: 0,
+            local_inputs: vec![0; INPUT_DELAY as usize],
+            remote_inputs: Vec::new(),
+            used: Vec::new(),
+            checked: 0,
+            states: VecDeque::new(),
+            pending: None,
+            sums: VecDeque::new(),
+            rollbacks: 0,
+            resimulated: 0,
+        }
+    }
+
+    /// Whether the next frame is within `MAX_ROLLBACK` of the confirmed input.
+    fn can_advance(&self) -> bool {
+        self.frame < self.remote_inputs.len() as u32 + MAX_ROLLBACK
+    }
+
+    /// Records the peer's buttons for frames `start..`. Only the next missing frame
+    /// extends the confirmed run; anything past a gap arrives again in a later packet.
+    fn receive(&mut self, start: u32, inputs: &[u8]) {
+        for (frame, &buttons) in (start as usize..).zip(inputs) {
+            if frame == self.remote_inputs.len() {
+                self.remote_inputs.push(buttons);
+            }
+        }
+    }
+
+    /// Rolls back to the first frame whose prediction turned out wrong, if any, and
+    /// resimulates up to the current frame.
+    fn correct(&mut self) {
+        let known = (self.remote_inputs.len() as u32).min(self.frame);
+        let wrong = (self.checked..known).find(|&f| self.used[f as usize] != self.remote_inputs[f as usize]);
+        self.checked = known;
+
+        if let Some(first) = wrong {
+            let back = (self.frame - first) as usize;
+            let at = self.states.len() - back;
+            let state = self.states[at].clone();
+            self.states.truncate(at);
+            for (gb, data) in self.machines.iter_mut().zip(&state) {
+                if let Err(e) = gb.load_state(data) {
+                    crashdump::fatal(gb, &format!("Corrupt rollback snapshot: {}", e));
+                }
+            }
+
+            let target = self.frame;
+            self.frame = first;
+            while self.frame < target {
+                self.simulate();
+            }
+            self.rollbacks += 1;
+            self.resimulated += back as u64;
+        }
+
+        if let Some((frame, sum)) = self.pending.take_if(|&mut (frame, _)| frame <= self.checked) {
+            self.sums.push_back((frame, sum));
+            if self.sums.len() > KEPT_SUMS {
+                self.sums.pop_front();
+            }
+        }
+    }
+
+    /// The latest confirmed checksum, `(0, 0)` before the first.
+    fn latest_sum(&self) -> (u32, u32) {
+        self.sums.back().copied().unwrap_or((0, 0))
+    }
+
+    /// Whether the peer's checksum for a frame disagrees with this side's: `None` while
+    /// this side hasn't confirmed that frame yet (or has forgotten it).
+    fn mismatch(&self, (frame, sum): (u32, u32)) -> Option<bool> {
+        self.sums.iter().find(|&&(f, _)| f == frame).map(|&(_, ours)| ours != sum)
+    }
+
+    /// Takes this side's buttons and runs the next frame.
+    fn advance(&mut self, buttons: u8) {
+        self.local_inputs.push(buttons);
+        self.simulate();
+    }
+
+    fn simulate(&mut self) {
+        let f = self.frame as usize;
+        let remote = self.remote_inputs.get(f).or(self.remote_inputs.last()).copied().unwrap_or(0);
+
+        self.states.push_back([self.machines[0].save_state(), self.machines[1].save_state()]);
+        if self.states.len() > MAX_ROLLBACK as usize + 1 {
+            self.states.pop_front();
+        }
+        if f > 0 && (f as u32).is_multiple_of(CHECK_INTERVAL) {
+            self.pending = Some((f as u32, checksum(&self.machines)));
+        }
+
+        self.machines[self.local].set_buttons(self.local_inputs[f]);
+        self.machines[1 - self.local].set_buttons(remote);
+        let [a, b] = &mut self.screens;
+        link::run_linked_frame(&mut self.machines, [a, b], PITCH);
+        for gb in &self.machines {
+            headless::exit_on_lockup(gb);
+        }
+
+        if f < self.used.len() {
+            self.used[f] = remote;
+        } else {
+            self.used.push(remote);
+        }
+        self.frame += 1;
+    }
+
+    /// This side's inputs from frame `start` on, as many as fit in a packet.
+    fn local_inputs_from(&self, start: u32) -> Vec<u8> {
+        let from = (start as usize).min(self.local_inputs.len());
+        self.local_inputs[from..].iter().take(MAX_PACKET_INPUTS).copied().collect()
+    }
+}
+
+/// Sums up both machines' registers and RAM.
+fn checksum(machines: &[GB; 2]) -> u32 {
+    let mut data = Vec::new();
+    for gb in machines {
+        let Checkpoint { registers: r, ram } = Checkpoint::of(gb);
+        data.extend_from_slice(&[r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l]);
+        data.extend_from_slice(&r.sp.to_le_bytes());
+        data.extend_from_slice(&r.pc.to_le_bytes());
+        data.extend_from_slice(&ram.to_le_bytes());
+    }
+    config_hash(&[&data])
+}
+
+/// Online versus over UDP with rollback (`--netplay-host` / `--netplay-join`):
+/// - After the handshake both peers run the same two linked machines (see
+///   `link::run_linked_frame`) from the host's seed; the host plays machine 1, the guest
+///   machine 2, each with the regular keys. Battery saves are neither loaded nor written,
+///   so both sides start from the same state.
+/// - Every frame sends this side's recent inputs, predicts the peer's missing ones and
+///   corrects mispredictions by rolling back (see `Rollback`).
+/// - Time sync: a side that runs ahead of the other (after halving out the latency both
+///   see) skips a frame so neither keeps stalling on the other.
+/// - The peers compare checksums of the machines every `CHECK_INTERVAL` frames; if they
+///   differ the games have gone out of sync (a bug, or a setting the handshake doesn't
+///   cover), and the session ends saying so rather than play on with different games.
+/// - Escape or closing the window ends the session; the peer notices after
+///   `PEER_TIMEOUT`. A summary of rollbacks and stalls is printed on exit.
+///
+/// Fails if no session could be set up, or on an SDL error.
+pub fn run(mut machines: [GB; 2], role: &NetRole, config: u32, opts: &Options) -> Result<(), Error> {
+    let (peer, seed, local) = handshake(role, config).map_err(Error::Netplay)?;
+    peer.socket.set_nonblocking(true).map_err(|e| Error::Netplay(e.to_string()))?;
+    for (i, gb) in machines.iter_mut().enumerate() {
+        gb.seed(seed ^ i as u64);
+        gb.set_linked(true);
+        // Start over from the shared seed, so random RAM matches on both sides
+        gb.power_cycle(opts.ram_init);
+    }
+
+    sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+    let sdl_context = sdl2::init().map_err(Error::sdl)?;
+    let video_subsystem = sdl_context.video().map_err(Error::sdl)?;
+    let (layout_w, layout_h) = video::layout_size(Rotation::R0, 2);
+    let title = format!("RUSTЯIS - netplay (player {})", local + 1);
+    let window = video_subsystem
+        .window(&title, layout_w * opts.zoom, layout_h * opts.zoom)
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(Error::sdl)?;
+    let mut canvas = window.into_canvas().build().map_err(Error::sdl)?;
+    let texture_creator = canvas.texture_creator();
+    let texture = || {
+        texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .map_err(Error::sdl)
+    };
+    let mut textures = [texture()?, texture()?];
+    let mut event_pump = sdl_context.event_pump().map_err(Error::sdl)?;
+
+    let mut game = Rollback::new(machines, local);
+    let mut buttons = 0u8;
+    let mut remote_frame = 0u32;
+    let mut remote_advantage = 0i32;
+    let mut peer_ack = 0u32;
+    let mut peer_sum = (0u32, 0u32);
+    let mut last_heard = Instant::now();
+    let mut stalls = 0u64;
+    let mut limiter = Limiter::new(opts.pacing, opts.pacing_tuning, 100, None);
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } |
+                Event::KeyDown { scancode: Some(Scancode::Escape), .. } => break 'running,
+                Event::KeyDown { scancode: Some(sc), repeat: false, .. } => {
+                    if let Some((_, mask)) = opts.keys.iter().find(|(s, _)| *s == sc) {
+                        buttons |= mask;
+                    }
+                }
+                Event::KeyUp { scancode: Some(sc), .. } => {
+                    if let Some((_, mask)) = opts.keys.iter().find(|(s, _)| *s == sc) {
+                        buttons &= !mask;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // --- Network ---
+        while let Some(message) = peer.recv() {
+            last_heard = Instant::now();
+            match message {
+                // Our welcome got lost and the guest is still knocking
+                Message::Hello { .. } if local == 0 => peer.send(&Message::Welcome { seed }),
+                Message::Input { frame, advantage, ack, check, start, inputs } => {
+                    remote_frame = remote_frame.max(frame);
+                    remote_advantage = advantage as i32;
+                    peer_ack = peer_ack.max(ack);
+                    if check.0 > peer_sum.0 {
+                        peer_sum = check;
+                    }
+                    game.receive(start, &inputs);
+                }
+                _ => {}
+            }
+        }
+        if last_heard.elapsed() > PEER_TIMEOUT {
+            eprintln!("Netplay: connection to {} lost", peer.addr);
+            break;
+        }
+
+        // --- Simulation ---
+        game.correct();
+        if game.mismatch(peer_sum) == Some(true) {
+            eprintln!("Netplay: the games went out of sync with {} by frame {}", peer.addr, peer_sum.0);
+            break;
+        }
+        let advantage = game.frame as i32 - remote_frame as i32;
+        let ahead = (advantage - remote_advantage) / 2 >= 1;
+        if game.can_advance() && !ahead {
+            game.advance(buttons);
+            for (texture, screen) in textures.iter_mut().zip(&game.screens) {
+                texture.update(None, screen, PITCH).map_err(Error::sdl)?;
+            }
+        } else {
+            stalls += 1;
+        }
+        peer.send(&Message::Input {
+            frame: game.frame,
+            advantage: advantage.clamp(i8::MIN as i32, i8::MAX as i32) as i8,
+            ack: game.remote_inputs.len() as u32,
+            check: game.latest_sum(),
+            start: peer_ack,
+            inputs: game.local_inputs_from(peer_ack),
+        });
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let output = canvas.output_size().map_err(Error::sdl)?;
+        for (texture, rect) in textures.iter().zip(video::dest_rects(output, Rotation::R0, 2)) {
+            canvas.copy(texture, None, rect).map_err(Error::sdl)?;
+        }
+        canvas.present();
+
+        // Paced as the emulation thread is, but by the clock even with vsync pacing
+        limiter.wait(&mut FrameTimes::default());
+    }
+
+    println!(
+        "Netplay: {} frames, {} rollbacks ({} frames resimulated), {} frames waited",
+        game.frame, game.rollbacks, game.resimulated, stalls,
+    );
+    Ok(())
+}
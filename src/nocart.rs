@@ -0,0 +1,52 @@
+use crate::osd;
+
+use rustris_core::{Cartridge, SCREEN_HEIGHT, SCREEN_WIDTH, GB};
+
+/// What the screen says, line by line, centered.
+const MESSAGE: [&str; 7] = [
+    "NO CARTRIDGE",
+    "",
+    "DROP A .GB OR .GBC FILE",
+    "ON THIS WINDOW TO PLAY IT",
+    "",
+    "OR PRESS F1 TO PICK A",
+    "RECENT GAME (CTRL+1-9)",
+];
+
+const BACKGROUND: [u8; 3] = [0x20, 0x24, 0x2C];
+const TEXT: [u8; 3] = [0xE0, 0xE0, 0xE0];
+const SLOT: [u8; 3] = [0x60, 0x66, 0x70];
+
+/// A machine with nothing to play, for when the ROM asked for doesn't load and the window
+/// opens anyway: a 32 KiB cart without a mapper whose program just spins, so the usual
+/// frame loop keeps running (and the window keeps answering) until a game is opened.
+/// Its picture is replaced by `draw`'s.
+pub fn machine() -> GB {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]); // NOP; JP $0150
+    rom[0x0134..0x0140].copy_from_slice(b"NO CARTRIDGE");
+    rom[0x014D] = rom[0x0134..0x014D].iter().fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
+    rom[0x0150..0x0153].copy_from_slice(&[0xF3, 0x18, 0xFE]); // DI; JR $0151
+    GB::new(Cartridge::from_bytes(rom)).expect("the no-cartridge ROM has no mapper")
+}
+
+/// Draws the no-cartridge screen over a frame (RGB24, `pitch` bytes per row): an empty
+/// cartridge slot and how to open a game.
+pub fn draw(framebuffer: &mut [u8], pitch: usize) {
+    let (width, height) = (SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
+    osd::fill_rect(framebuffer, pitch, 0, 0, width, height, BACKGROUND);
+
+    // The slot: an outline with the connector's pins along its floor
+    let (slot_x, slot_y, slot_w, slot_h) = (56, 16, 48, 28);
+    osd::fill_rect(framebuffer, pitch, slot_x, slot_y, slot_w, slot_h, SLOT);
+    osd::fill_rect(framebuffer, pitch, slot_x + 2, slot_y, slot_w - 4, slot_h - 2, BACKGROUND);
+    for pin in (slot_x + 6..slot_x + slot_w - 6).step_by(4) {
+        osd::fill_rect(framebuffer, pitch, pin, slot_y + slot_h - 6, 2, 4, SLOT);
+    }
+
+    let top = slot_y + slot_h + 14;
+    for (row, line) in MESSAGE.iter().enumerate() {
+        let x = (width - line.len() as i32 * osd::CHAR_WIDTH) / 2;
+        osd::draw_text(framebuffer, pitch, x, top + row as i32 * (osd::CHAR_HEIGHT + 2), line, TEXT);
+    }
+}
@@ -0,0 +1,946 @@
+use rustris_core::{gb, Model, Profile, RamInit};
+use crate::assertions::Assertion;
+use crate::config::{Config, Value};
+use crate::das::DasTiming;
+use crate::pacing::{Pacing, PacingTuning};
+use crate::speedrun::SplitOn;
+use crate::tetris::Piece;
+use crate::video::{HostPalette, Rotation};
+
+use sdl2::keyboard::Scancode;
+
+/// Command-line options for the SDL front-end, on top of the settings in `config.toml`
+/// (see `Config`); flags win over the file.
+///
+/// Usage: `rustris [OPTIONS] [ROM]`
+/// - `ROM`: path to the cartridge image (defaults to `paths.rom`, or `tetris.gb`).
+/// - `--speed <PERCENT>`: initial emulation speed (25–400, default 100); it paces the frames
+///   only, there's no sound to resample.
+/// - `--pause-on-focus-loss`: pause emulation while the window is unfocused.
+/// - `--pacing <hybrid|sleep|spin|vsync>`: how each frame waits for its turn (see
+///   `Limiter`; default hybrid).
+/// - `--spin-margin <US>`, `--sleep-slack <US>`: its tuning (0–10000 µs spun before each
+///   deadline with hybrid, default 500; woken early with sleep, default 0).
+/// - `--turbo <KEY>=<BUTTON>`: bind a turbo key (repeatable; replaces the default S=A, A=B).
+/// - `--turbo-hz <HZ>`: turbo auto-fire rate (default 10).
+/// - `--macro <KEY>`: bind an input macro to KEY, which plays it; Shift+KEY records it
+///   (repeatable; see `Macros`).
+/// - `--das`: turn held Left/Right into auto-repeat pulses timed by the emulator (see `Das`).
+/// - `--das-delay <FRAMES>`, `--das-repeat <FRAMES>`: their timing (2–60 frames before the
+///   second pulse, default 16; 2–30 between later ones, default 6).
+/// - `--rumble <PERCENT>`: how hard a game controller shakes for rumble carts (default 100;
+///   0 turns it off, see `Rumble`).
+/// - `--rotate <DEGREES>`: rotate the picture clockwise by 0, 90, 180 or 270 degrees.
+/// - `--fullscreen`: start in borderless fullscreen-desktop mode.
+/// - `--background <RRGGBB>`: color around the picture when it doesn't fill the window.
+/// - `--input-display`: draw the held buttons in the picture's corner.
+/// - `--ghost`: show where the falling Tetris piece would land (see `Ghost`).
+/// - `--colorize`: draw the Tetris pieces in their modern colors (see `Colorizer`).
+/// - `--hud`: show Tetris's score, level, lines and drought beside the picture (see `Hud`).
+/// - `--palette <NAME>`: host palette for DMG games: color (default), green, colorblind or
+///   high-contrast (see `HostPalette`).
+/// - `--sprite-outlines`: darken the pixels around sprites so they stand out (see
+///   `GB::set_sprite_outlines`).
+/// - `--zoom <N>`: window size in multiples of the screen at a display scale of 1.0 (default 4).
+/// - `--shader <FILE>`: draw the picture through a GLSL fragment shader, reloaded as it's
+///   edited (see `Shader`).
+/// - `--rewind-seconds <N>`: rewind history length (default 30, 0 disables rewind).
+/// - `--script <FILE>`: run a rhai script with emulator hooks (see `Script`).
+/// - `--headless`: run without a window or audio device, as fast as possible.
+/// - `--frames <N>`: stop after N frames (headless runs forever otherwise).
+/// - `--screenshot <FILE>`: save the last frame as a PNG (implies `--headless`).
+/// - `--compare <FILE>`: compare the last frame with a reference PNG and exit with the
+///   result (implies `--headless`).
+/// - `--hash`: print a hash of the last frame, to check a run plays the same elsewhere
+///   (implies `--headless`, and `--seed 0` unless a seed is given, so runs repeat; see
+///   `headless::frame_hash`).
+/// - `--hash-frames`: print every frame's hash instead (implies `--headless`).
+/// - `--test-rom`: run ROM as a Blargg/mooneye test ROM and exit with its result.
+/// - `--selftest`: check the emulator itself with built-in tests and exit with the result
+///   (see `selftest::run`); no ROM needed.
+/// - `--model <auto|dmg|cgb>`: hardware to emulate (default auto, from the ROM header).
+/// - `--profile <auto|tetris|compatible|accurate>`: accuracy profile (default auto, from the ROM header).
+/// - `--overclock <1|2|4>`: run the CPU this many times faster than the rest of the machine,
+///   so games that drop frames lag less (default 1, see `GB::set_overclock`).
+/// - `--frame-stats`: show the frame-time overlay and print a pacing summary on exit.
+/// - `--dual <ROM>`: run a second Game Boy with ROM next to the first (player 2).
+/// - `--versus`: link two Game Boys running ROM (player 2's runs the `--dual` ROM, if any)
+///   with the cable, side by side (see `versus::run`).
+/// - `--gbs-viewer <FILE>`: run a `.gbs` music rip's sound driver and show what it does,
+///   silently, instead of a game (see `gbs::run`).
+/// - `--song <N>`: song the `--gbs-viewer` starts with (default the file's first).
+/// - `--seed <N>`: seed the machines' random source so runs are reproducible.
+/// - `--boot-rom <FILE>` / `--cgb-boot-rom <FILE>`: boot ROM dump run at power-on by DMG /
+///   CGB machines (default: none, start from the post-boot state).
+/// - `--boot-animation`: without a DMG boot ROM, play the logo animation at power-on anyway,
+///   in the window only (see `GB::set_boot_animation`).
+/// - `--ram-init <zero|random>`: RAM contents at power-on (default zero).
+/// - `--save-high-scores`: keep the high-score table of games that forget it (Tetris) in a
+///   `.hiscore` file next to the ROM.
+/// - `--auto-resume`: save player 1's game to an exit state when it's closed, and offer to
+///   continue from it the next time the same ROM is opened (see `Menu::offer_resume`).
+/// - `--session-stats`: track the Tetris games played, printed on exit and added to
+///   `sessions.csv` in the config directory (see `Session`).
+/// - `--timer <level|LINES>`: Tetris speedrun timer splitting at each level or every LINES
+///   lines (see `Speedrun`).
+/// - `--autoplay`: let the built-in bot play Tetris (see `Bot`).
+/// - `--hard-drop`: patch Tetris so Up drops the falling piece (see `HardDrop`).
+/// - `--practice`: Tetris practice mode, Tab retries the falling piece (see `Practice`).
+/// - `--pieces <LETTERS>`: deal this Tetris piece sequence (e.g. `IOTLJSZ`) over and over
+///   instead of random pieces (see `PieceQueue`).
+/// - `--telemetry <[HOST:]PORT>`: serve Tetris's state over WebSocket (see `Telemetry`);
+///   HOST defaults to 127.0.0.1.
+/// - `--remote-input <[HOST:]PORT>`: take player 1's buttons from TCP or WebSocket clients
+///   too (see `remote::serve`); HOST defaults to 127.0.0.1.
+/// - `--stream <[HOST:]PORT>`: serve player 1's screen over HTTP as MJPEG and WebSocket
+///   frames (see `stream::serve`); HOST defaults to 127.0.0.1.
+/// - `--record-demo <FILE>`: F10 records an input movie to FILE (see `Recorder`).
+/// - `--attract <FILE>`: play the movie in FILE whenever the game is left idle (see `Attract`).
+/// - `--attract-idle <SECONDS>`: how long the game must be idle first (default 30).
+/// - `--verify-demo <FILE>`: replay the movie in FILE and check every frame against the
+///   state it recorded, stopping at the first that differs (implies `--headless`; see
+///   `replay::verify`).
+/// - `--netplay-host <PORT>` / `--netplay-join <HOST:PORT>`: two-player link play over UDP.
+/// - `--debug`: start stopped, with the debugger in the terminal (see `Tui`).
+/// - `--hot-reload`: reload player 1's ROM whenever the file changes, e.g. rebuilt by the
+///   assembler (see `FileWatcher`); the game restarts from power-on.
+/// - `--keep-state`: with `--hot-reload`, carry the machine's state (CPU, RAM, registers)
+///   over to the new ROM instead, to try a change without replaying up to it.
+/// - `--serial-stdout`: echo what the game sends over the link port to stdout.
+/// - `--serial-log <FILE>`: append what the game sends over the link port to FILE.
+/// - `--stack-check`: warn when the stack runs out of work RAM and high RAM or over the
+///   game's high RAM variables (see `GB::check_stack`).
+/// - `--stack-break`: stop in the debugger at such a stack access instead.
+/// - `--assert <ASSERTION>`: stop in the debugger when a byte of memory breaks an
+///   invariant such as `C0DE <= 14` (repeatable; replaces `debug.assert`, see `Assertion`).
+/// - `--coverage`: record which ROM bytes run as code or are read as data, in a `.cdl`
+///   file next to the ROM that grows across sessions.
+/// - `--trace <FILE>`: log the state before every instruction (implies `--headless`).
+/// - `--diff-trace <FILE>`: compare the run with a reference log and stop at the first
+///   divergent instruction (implies `--headless`).
+pub struct Options {
+    pub rom_path: String,
+    pub speed: u32,
+    pub pause_on_focus_loss: bool,
+    pub pacing: Pacing,
+    pub pacing_tuning: PacingTuning,
+    pub turbo: Vec<(Scancode, u8)>,
+    pub turbo_hz: u32,
+    pub macros: Vec<Scancode>, // Keys with an input macro
+    pub das: bool,
+    pub das_timing: DasTiming,
+    pub rumble: u32, // Controller vibration strength for rumble carts, in percent
+    pub rotation: Rotation,
+    pub fullscreen: bool,
+    pub background: [u8; 3],
+    pub hud: bool,
+    pub input_display: bool,
+    pub ghost: bool,
+    pub colorize: bool,
+    pub palette: HostPalette,
+    pub sprite_outlines: bool,
+    pub zoom: u32,
+    pub shader: Option<String>, // Post-processing fragment shader for the window's pictures
+    pub speed_step: u32, // Change per press of the speed hotkeys, in percent
+    pub keys: [(Scancode, u8); 8],         // Player 1's buttons
+    pub player2_keys: [(Scancode, u8); 8], // Player 2's buttons in `--dual` and `--versus` modes
+    pub rewind_seconds: u32,
+    pub script: Option<String>,
+    pub headless: bool,
+    pub frames: Option<u64>,
+    pub screenshot: Option<String>,
+    pub compare: Option<String>,
+    pub hash: Option<FrameHash>, // Framebuffer hashes to print
+    pub test_rom: bool,
+    pub selftest: bool,
+    pub model: Option<Model>, // None = pick from the cartridge header
+    pub profile: Option<Profile>, // None = pick from the cartridge header
+    pub overclock: u32,           // CPU clock multiplier
+    pub frame_stats: bool,
+    pub dual: Option<String>, // ROM of the second (player 2) Game Boy
+    pub versus: bool,         // Link the two Game Boys (see `versus::run`)
+    pub gbs: Option<String>,  // Music rip whose driver to show (see `gbs::run`)
+    pub song: Option<u8>,     // Its first song to play, from 1
+    pub seed: Option<u64>,
+    pub boot_rom: Option<String>,     // DMG boot ROM
+    pub cgb_boot_rom: Option<String>, // CGB boot ROM
+    pub boot_animation: bool,         // Logo animation without a boot ROM
+    pub ram_init: RamInit,
+    pub save_high_scores: bool,
+    pub auto_resume: bool,
+    pub session_stats: bool,
+    pub timer: Option<SplitOn>, // Speedrun timer and when it splits
+    pub telemetry: Option<String>, // Address of the telemetry server
+    pub remote_input: Option<String>, // Address of the remote input server
+    pub stream: Option<String>, // Address of the frame streaming server
+    pub record_demo: Option<String>, // File F10 records a demo to
+    pub attract: Option<String>,     // Demo played when idle
+    pub attract_idle: u32,           // Seconds without input before it plays
+    pub verify_demo: Option<String>, // Demo to replay against its checkpoints
+    pub autoplay: bool,
+    pub hard_drop: bool,
+    pub practice: bool,
+    pub pieces: Option<Vec<Piece>>, // Forced Tetris piece sequence
+    pub netplay: Option<NetRole>,
+    pub debug: bool,
+    pub hot_reload: bool,
+    pub keep_state: bool, // Carry the state over on hot reloads
+    pub serial_stdout: bool,
+    pub serial_log: Option<String>,
+    pub stack_check: bool,
+    pub stack_break: bool,
+    pub assertions: Vec<Assertion>,
+    pub coverage: bool,
+    pub trace: Option<Trace>,
+}
+
+/// Which side of a netplay session this process is.
+pub enum NetRole {
+    Host(u16),    // Wait for a player on this UDP port; the host is player 1
+    Join(String), // Connect to the host at HOST:PORT as player 2
+}
+
+/// Per-instruction trace mode (see `trace::run`).
+pub enum Trace {
+    Write(String), // Log file to create
+    Diff(String),  // Reference log to compare against
+}
+
+/// Which framebuffer hashes a headless run prints (see `headless::frame_hash`).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FrameHash {
+    Last,  // The last frame's, once the run ends
+    Every, // One line per frame, as it's finished
+}
+
+/// Emulation speed bounds, in percent of the real DMG cadence.
+pub const SPEED_MIN: u32 = 25;
+pub const SPEED_MAX: u32 = 400;
+
+/// Default keys of player 1 and of player 2 (`--dual`).
+const DEFAULT_KEYS: [(Scancode, u8); 8] = [
+    (Scancode::Right,  gb::BTN_RIGHT),
+    (Scancode::Left,   gb::BTN_LEFT),
+    (Scancode::Up,     gb::BTN_UP),
+    (Scancode::Down,   gb::BTN_DOWN),
+    (Scancode::X,      gb::BTN_A),
+    (Scancode::Z,      gb::BTN_B),
+    (Scancode::Space,  gb::BTN_SELECT),
+    (Scancode::Return, gb::BTN_START),
+];
+const DEFAULT_PLAYER2_KEYS: [(Scancode, u8); 8] = [
+    (Scancode::L, gb::BTN_RIGHT),
+    (Scancode::J, gb::BTN_LEFT),
+    (Scancode::I, gb::BTN_UP),
+    (Scancode::K, gb::BTN_DOWN),
+    (Scancode::G, gb::BTN_A),
+    (Scancode::F, gb::BTN_B),
+    (Scancode::T, gb::BTN_SELECT),
+    (Scancode::Y, gb::BTN_START),
+];
+
+/// Settings `config.toml` understands, as `section.key`.
+const CONFIG_KEYS: &[&str] = &[
+    "video.palette", "video.rotation", "video.fullscreen", "video.background", "video.zoom", "video.hud",
+    "video.input_display", "video.ghost", "video.colorize", "video.sprite_outlines", "video.shader",
+    "emulation.speed", "emulation.speed_step", "emulation.pause_on_focus_loss",
+    "emulation.pacing", "emulation.spin_margin", "emulation.sleep_slack",
+    "emulation.rewind_seconds", "emulation.model", "emulation.profile", "emulation.overclock", "emulation.frame_stats",
+    "emulation.boot_animation", "emulation.ram_init", "emulation.save_high_scores", "emulation.auto_resume", "emulation.session_stats", "emulation.hard_drop",
+    "input.right", "input.left", "input.up", "input.down",
+    "input.a", "input.b", "input.select", "input.start", "input.turbo", "input.turbo_hz", "input.macros", "input.rumble",
+    "input.das", "input.das_delay", "input.das_repeat",
+    "input.player2.right", "input.player2.left", "input.player2.up", "input.player2.down",
+    "input.player2.a", "input.player2.b", "input.player2.select", "input.player2.start",
+    "paths.rom", "paths.boot_rom", "paths.cgb_boot_rom",
+    "debug.assert",
+];
+
+pub const USAGE: &str = "usage: rustris [--speed PERCENT] [--pause-on-focus-loss] \
+                         [--pacing hybrid|sleep|spin|vsync] [--spin-margin US] [--sleep-slack US] \
+                         [--turbo KEY=BUTTON]... [--turbo-hz HZ] [--macro KEY]... \
+                         [--das] [--das-delay FRAMES] [--das-repeat FRAMES] [--rumble PERCENT] [--rotate DEGREES] \
+                         [--fullscreen] [--background RRGGBB] [--hud] [--input-display] [--ghost] [--colorize] [--palette NAME] [--sprite-outlines] [--zoom N] [--shader FILE] \
+                         [--rewind-seconds N] [--script FILE] \
+                         [--headless] [--frames N] [--screenshot FILE] [--compare FILE] [--hash] [--hash-frames] \
+                         [--test-rom] [--selftest] [--model auto|dmg|cgb] \
+                         [--profile auto|tetris|compatible|accurate] [--overclock 1|2|4] [--frame-stats] \
+                         [--dual ROM] [--versus] [--gbs-viewer FILE] [--song N] [--seed N] \
+                         [--boot-rom FILE] [--cgb-boot-rom FILE] [--boot-animation] [--ram-init zero|random] [--save-high-scores] [--auto-resume] \
+                         [--session-stats] [--timer level|LINES] [--autoplay] [--hard-drop] [--practice] [--pieces LETTERS] \
+                         [--telemetry [HOST:]PORT] [--remote-input [HOST:]PORT] [--stream [HOST:]PORT] [--record-demo FILE] [--attract FILE] [--attract-idle SECONDS] [--verify-demo FILE] \
+                         [--netplay-host PORT | --netplay-join HOST:PORT] \
+                         [--debug] [--hot-reload] [--keep-state] [--serial-stdout] [--serial-log FILE] [--stack-check] [--stack-break] [--assert ASSERTION]... [--coverage] [--trace FILE | --diff-trace FILE] [ROM]";
+
+/// Button names in the order of the key tables (the joypad bits'), as used in `config.toml`.
+pub const BUTTON_NAMES: [&str; 8] = ["right", "left", "up", "down", "a", "b", "select", "start"];
+
+impl Options {
+    /// Parses the given arguments (without the program name), starting from the settings
+    /// in `config`.
+    pub fn parse<I: Iterator<Item = String>>(mut args: I, config: &Config) -> Result<Self, String> {
+        let mut opts = Options {
+            rom_path: String::from("tetris.gb"),
+            speed: 100,
+            pause_on_focus_loss: false,
+            pacing: Pacing::Hybrid,
+            pacing_tuning: PacingTuning::default(),
+            turbo: vec![(Scancode::S, gb::BTN_A), (Scancode::A, gb::BTN_B)],
+            turbo_hz: 10,
+            macros: Vec::new(),
+            das: false,
+            das_timing: DasTiming::default(),
+            rumble: 100,
+            rotation: Rotation::R0,
+            fullscreen: false,
+            background: [0, 0, 0],
+            hud: false,
+            input_display: false,
+            ghost: false,
+            colorize: false,
+            palette: HostPalette::Color,
+            sprite_outlines: false,
+            zoom: 4,
+            shader: None,
+            speed_step: 25,
+            keys: DEFAULT_KEYS,
+            player2_keys: DEFAULT_PLAYER2_KEYS,
+            rewind_seconds: 30,
+            script: None,
+            headless: false,
+            frames: None,
+            screenshot: None,
+            compare: None,
+            hash: None,
+            test_rom: false,
+            selftest: false,
+            model: None,
+            profile: None,
+            overclock: 1,
+            frame_stats: false,
+            dual: None,
+            versus: false,
+            gbs: None,
+            song: None,
+            seed: None,
+            boot_rom: None,
+            cgb_boot_rom: None,
+            boot_animation: false,
+            ram_init: RamInit::Zero,
+            save_high_scores: false,
+            auto_resume: false,
+            session_stats: false,
+            timer: None,
+            telemetry: None,
+            remote_input: None,
+            stream: None,
+            record_demo: None,
+            attract: None,
+            attract_idle: 30,
+            verify_demo: None,
+            autoplay: false,
+            hard_drop: false,
+            practice: false,
+            pieces: None,
+            netplay: None,
+            debug: false,
+            hot_reload: false,
+            keep_state: false,
+            serial_stdout: false,
+            serial_log: None,
+            stack_check: false,
+            stack_break: false,
+            assertions: Vec::new(),
+            coverage: false,
+            trace: None,
+        };
+        opts.apply_config(config).map_err(|e| match config.path() {
+            Some(path) => format!("{}: {}", path.display(), e),
+            None => e,
+        })?;
+        let mut custom_turbo = false;
+        let mut custom_macros = false;
+        let mut custom_assertions = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--speed" => opts.speed = parse_speed(&args.next().ok_or("--speed requires a value")?)?,
+                "--pause-on-focus-loss" => opts.pause_on_focus_loss = true,
+                "--pacing" => opts.pacing = parse_pacing(&args.next().ok_or("--pacing requires a value")?)?,
+                "--spin-margin" => {
+                    opts.pacing_tuning.spin_margin = parse_pacing_us(&args.next().ok_or("--spin-margin requires a value")?)?
+                }
+                "--sleep-slack" => {
+                    opts.pacing_tuning.sleep_slack = parse_pacing_us(&args.next().ok_or("--sleep-slack requires a value")?)?
+                }
+                "--turbo" => {
+                    let mapping = parse_turbo(&args.next().ok_or("--turbo requires KEY=BUTTON")?)?;
+                    if !custom_turbo {
+                        opts.turbo.clear();
+                        custom_turbo = true;
+                    }
+                    opts.turbo.push(mapping);
+                }
+                "--turbo-hz" => opts.turbo_hz = parse_turbo_hz(&args.next().ok_or("--turbo-hz requires a value")?)?,
+                "--macro" => {
+                    let key = parse_macro_key(&args.next().ok_or("--macro requires a key")?)?;
+                    if !custom_macros {
+                        opts.macros.clear();
+                        custom_macros = true;
+                    }
+                    opts.macros.push(key);
+                }
+                "--das" => opts.das = true,
+                "--das-delay" => opts.das_timing.delay = parse_das_delay(&args.next().ok_or("--das-delay requires a value")?)?,
+                "--das-repeat" => opts.das_timing.repeat = parse_das_repeat(&args.next().ok_or("--das-repeat requires a value")?)?,
+                "--rumble" => opts.rumble = parse_rumble(&args.next().ok_or("--rumble requires a value")?)?,
+                "--rotate" => opts.rotation = parse_rotation(&args.next().ok_or("--rotate requires a value")?)?,
+                "--fullscreen" => opts.fullscreen = true,
+                "--hud" => opts.hud = true,
+                "--input-display" => opts.input_display = true,
+                "--ghost" => opts.ghost = true,
+                "--colorize" => opts.colorize = true,
+                "--background" => {
+                    let value = args.next().ok_or("--background requires a value")?;
+                    opts.background = parse_color(&value).ok_or_else(|| format!("invalid color: {}", value))?;
+                }
+                "--palette" => opts.palette = parse_palette(&args.next().ok_or("--palette requires a value")?)?,
+                "--sprite-outlines" => opts.sprite_outlines = true,
+                "--zoom" => opts.zoom = parse_zoom(&args.next().ok_or("--zoom requires a value")?)?,
+                "--shader" => opts.shader = Some(args.next().ok_or("--shader requires a file")?),
+                "--rewind-seconds" => {
+                    opts.rewind_seconds = parse_rewind(&args.next().ok_or("--rewind-seconds requires a value")?)?;
+                }
+                "--script" => opts.script = Some(args.next().ok_or("--script requires a file")?),
+                "--headless" => opts.headless = true,
+                "--frames" => {
+                    let value = args.next().ok_or("--frames requires a value")?;
+                    opts.frames = Some(value.parse().map_err(|_| format!("invalid frame count: {}", value))?);
+                }
+                "--screenshot" => {
+                    opts.screenshot = Some(args.next().ok_or("--screenshot requires a file")?);
+                    opts.headless = true;
+                }
+                "--compare" => {
+                    opts.compare = Some(args.next().ok_or("--compare requires a file")?);
+                    opts.headless = true;
+                }
+                "--hash" => {
+                    opts.hash = Some(FrameHash::Last);
+                    opts.headless = true;
+                }
+                "--hash-frames" => {
+                    opts.hash = Some(FrameHash::Every);
+                    opts.headless = true;
+                }
+                "--test-rom" => opts.test_rom = true,
+                "--selftest" => opts.selftest = true,
+                "--trace" => {
+                    opts.trace = Some(Trace::Write(args.next().ok_or("--trace requires a file")?));
+                    opts.headless = true;
+                }
+                "--diff-trace" => {
+                    opts.trace = Some(Trace::Diff(args.next().ok_or("--diff-trace requires a file")?));
+                    opts.headless = true;
+                }
+                "--frame-stats" => opts.frame_stats = true,
+                "--debug" => opts.debug = true,
+                "--hot-reload" => opts.hot_reload = true,
+                "--keep-state" => opts.keep_state = true,
+                "--serial-stdout" => opts.serial_stdout = true,
+                "--serial-log" => opts.serial_log = Some(args.next().ok_or("--serial-log requires a file")?),
+                "--stack-check" => opts.stack_check = true,
+                "--stack-break" => opts.stack_break = true,
+                "--assert" => {
+                    let assertion = Assertion::parse(&args.next().ok_or("--assert requires an assertion")?)?;
+                    if !custom_assertions {
+                        opts.assertions.clear();
+                        custom_assertions = true;
+                    }
+                    opts.assertions.push(assertion);
+                }
+                "--coverage" => opts.coverage = true,
+                "--dual" => opts.dual = Some(args.next().ok_or("--dual requires a ROM")?),
+                "--versus" => opts.versus = true,
+                "--gbs-viewer" => opts.gbs = Some(args.next().ok_or("--gbs-viewer requires a file")?),
+                "--song" => {
+                    let value = args.next().ok_or("--song requires a value")?;
+                    opts.song = Some(value.parse().ok().filter(|&n| n > 0).ok_or_else(|| format!("invalid song: {}", value))?);
+                }
+                "--netplay-host" => {
+                    let value = args.next().ok_or("--netplay-host requires a port")?;
+                    let port = value.parse().map_err(|_| format!("invalid port: {}", value))?;
+                    opts.netplay = Some(NetRole::Host(port));
+                }
+                "--netplay-join" => {
+                    opts.netplay = Some(NetRole::Join(args.next().ok_or("--netplay-join requires HOST:PORT")?));
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed requires a value")?;
+                    opts.seed = Some(value.parse().map_err(|_| format!("invalid seed: {}", value))?);
+                }
+                "--boot-rom" => opts.boot_rom = Some(args.next().ok_or("--boot-rom requires a file")?),
+                "--cgb-boot-rom" => opts.cgb_boot_rom = Some(args.next().ok_or("--cgb-boot-rom requires a file")?),
+                "--boot-animation" => opts.boot_animation = true,
+                "--ram-init" => opts.ram_init = parse_ram_init(&args.next().ok_or("--ram-init requires a value")?)?,
+                "--save-high-scores" => opts.save_high_scores = true,
+                "--auto-resume" => opts.auto_resume = true,
+                "--session-stats" => opts.session_stats = true,
+                "--telemetry" => {
+                    let value = args.next().ok_or("--telemetry requires a port")?;
+                    opts.telemetry = Some(if value.contains(':') { value } else { format!("127.0.0.1:{}", value) });
+                }
+                "--remote-input" => {
+                    let value = args.next().ok_or("--remote-input requires a port")?;
+                    opts.remote_input = Some(if value.contains(':') { value } else { format!("127.0.0.1:{}", value) });
+                }
+                "--stream" => {
+                    let value = args.next().ok_or("--stream requires a port")?;
+                    opts.stream = Some(if value.contains(':') { value } else { format!("127.0.0.1:{}", value) });
+                }
+                "--record-demo" => opts.record_demo = Some(args.next().ok_or("--record-demo requires a file")?),
+                "--attract" => opts.attract = Some(args.next().ok_or("--attract requires a file")?),
+                "--attract-idle" => {
+                    opts.attract_idle = parse_attract_idle(&args.next().ok_or("--attract-idle requires a value")?)?
+                }
+                "--verify-demo" => {
+                    opts.verify_demo = Some(args.next().ok_or("--verify-demo requires a file")?);
+                    opts.headless = true;
+                }
+                "--timer" => opts.timer = Some(parse_splits(&args.next().ok_or("--timer requires a value")?)?),
+                "--autoplay" => opts.autoplay = true,
+                "--hard-drop" => opts.hard_drop = true,
+                "--practice" => opts.practice = true,
+                "--pieces" => opts.pieces = Some(parse_pieces(&args.next().ok_or("--pieces requires a sequence")?)?),
+                "--model" => opts.model = parse_model(&args.next().ok_or("--model requires a value")?)?,
+                "--profile" => opts.profile = parse_profile(&args.next().ok_or("--profile requires a value")?)?,
+                "--overclock" => opts.overclock = parse_overclock(&args.next().ok_or("--overclock requires a value")?)?,
+                "-h" | "--help" => return Err(String::from(USAGE)),
+                _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+                _ => opts.rom_path = arg,
+            }
+        }
+
+        if (opts.dual.is_some() || opts.versus) && (opts.headless || opts.test_rom) {
+            return Err(String::from("--dual and --versus need the window (no --headless, --test-rom or screenshots)"));
+        }
+        if opts.versus && (opts.netplay.is_some() || opts.script.is_some() || opts.trace.is_some()) {
+            return Err(String::from("--versus can't be combined with netplay, --script or tracing"));
+        }
+        if opts.netplay.is_some() && (opts.dual.is_some() || opts.headless || opts.test_rom || opts.script.is_some()) {
+            return Err(String::from("netplay can't be combined with --dual, --headless, --test-rom or --script"));
+        }
+        if opts.debug && (opts.headless || opts.test_rom || opts.versus || opts.netplay.is_some()) {
+            return Err(String::from("--debug needs the window (no --headless, --test-rom or screenshots) and can't be combined with --versus or netplay"));
+        }
+        if opts.stack_break && (opts.headless || opts.test_rom || opts.versus || opts.netplay.is_some()) {
+            return Err(String::from("--stack-break stops in the debugger, which needs the window (no --headless, --test-rom or screenshots) and can't be combined with --versus or netplay"));
+        }
+        if opts.hot_reload && (opts.headless || opts.test_rom || opts.versus || opts.netplay.is_some()) {
+            return Err(String::from("--hot-reload needs the window (no --headless, --test-rom or screenshots) and can't be combined with --versus or netplay"));
+        }
+        if opts.gbs.is_some() && (opts.headless || opts.test_rom || opts.trace.is_some() || opts.dual.is_some() || opts.versus || opts.netplay.is_some() || opts.debug || opts.script.is_some()) {
+            return Err(String::from("--gbs-viewer runs in a window of its own (no --headless, --test-rom or screenshots) and can't be combined with --dual, --versus, netplay, --debug or --script"));
+        }
+        if opts.song.is_some() && opts.gbs.is_none() {
+            return Err(String::from("--song only applies to --gbs-viewer"));
+        }
+        if let Some(sc) = opts.macros.iter().find(|sc| opts.keys.iter().chain(&opts.turbo).chain(&opts.player2_keys).any(|(k, _)| k == *sc)) {
+            return Err(format!("{} is bound to a button, so it can't have a macro", sc.name()));
+        }
+        if opts.keep_state && !opts.hot_reload {
+            return Err(String::from("--keep-state only applies to --hot-reload"));
+        }
+        if opts.trace.is_some() && (opts.test_rom || opts.script.is_some() || opts.screenshot.is_some() || opts.compare.is_some() || opts.hash.is_some()) {
+            return Err(String::from("tracing can't be combined with --test-rom, --script, --screenshot, --compare or --hash"));
+        }
+        if opts.verify_demo.is_some() && (opts.test_rom || opts.trace.is_some() || opts.script.is_some() || opts.screenshot.is_some() || opts.compare.is_some() || opts.hash.is_some()) {
+            return Err(String::from("--verify-demo can't be combined with --test-rom, tracing, --script, --screenshot, --compare or --hash"));
+        }
+        if opts.hash.is_some() && opts.test_rom {
+            return Err(String::from("--hash can't be combined with --test-rom"));
+        }
+        if opts.hash.is_some() {
+            // The random source is seeded from the OS otherwise, and hashes would differ per run
+            opts.seed.get_or_insert(0);
+        }
+        Ok(opts)
+    }
+
+    /// `true` if the game plays in the window: not headless (screenshots included), a test
+    /// ROM, a trace, netplay, `--versus` or `--gbs-viewer`.
+    pub fn windowed(&self) -> bool {
+        !self.headless && !self.test_rom && self.trace.is_none() && self.netplay.is_none() && !self.versus && self.gbs.is_none()
+    }
+
+    /// Takes the settings `config` sets, validated like the matching flags. Unknown keys
+    /// only get a warning, so a file from a newer version still loads.
+    fn apply_config(&mut self, config: &Config) -> Result<(), String> {
+        for key in config.keys() {
+            if !CONFIG_KEYS.contains(&key.as_str()) {
+                eprintln!("Warning: unknown setting {} in the config file", key);
+            }
+        }
+
+        // Scalar settings, in the same spelling as their flags
+        let text = |section: &str, key: &str| -> Result<Option<String>, String> {
+            match config.get(section, key) {
+                None => Ok(None),
+                Some(value) => value.as_text().map(Some).ok_or_else(|| format!("{}.{} can't be a list", section, key)),
+            }
+        };
+        let flag = |section: &str, key: &str| -> Result<Option<bool>, String> {
+            match config.get(section, key) {
+                None => Ok(None),
+                Some(Value::Bool(b)) => Ok(Some(*b)),
+                Some(_) => Err(format!("{}.{} must be true or false", section, key)),
+            }
+        };
+        let setting = |section: &str, key: &str, e: String| format!("{}.{}: {}", section, key, e);
+
+        if let Some(v) = text("video", "palette")? {
+            self.palette = parse_palette(&v).map_err(|e| setting("video", "palette", e))?;
+        }
+        if let Some(v) = text("video", "rotation")? {
+            self.rotation = parse_rotation(&v).map_err(|e| setting("video", "rotation", e))?;
+        }
+        if let Some(v) = flag("video", "fullscreen")? {
+            self.fullscreen = v;
+        }
+        if let Some(v) = flag("video", "hud")? {
+            self.hud = v;
+        }
+        if let Some(v) = flag("video", "input_display")? {
+            self.input_display = v;
+        }
+        if let Some(v) = flag("video", "ghost")? {
+            self.ghost = v;
+        }
+        if let Some(v) = flag("video", "colorize")? {
+            self.colorize = v;
+        }
+        if let Some(v) = flag("video", "sprite_outlines")? {
+            self.sprite_outlines = v;
+        }
+        if let Some(v) = text("video", "background")? {
+            self.background = parse_color(&v).ok_or_else(|| setting("video", "background", format!("invalid color: {}", v)))?;
+        }
+        if let Some(v) = text("video", "zoom")? {
+            self.zoom = parse_zoom(&v).map_err(|e| setting("video", "zoom", e))?;
+        }
+        if let Some(v) = text("video", "shader")? {
+            self.shader = Some(v);
+        }
+        if let Some(v) = text("emulation", "speed")? {
+            self.speed = parse_speed(&v).map_err(|e| setting("emulation", "speed", e))?;
+        }
+        if let Some(v) = text("emulation", "speed_step")? {
+            self.speed_step = match v.parse() {
+                Ok(step @ 1..=100) => step,
+                _ => return Err(setting("emulation", "speed_step", format!("must be between 1 and 100: {}", v))),
+            };
+        }
+        if let Some(v) = flag("emulation", "pause_on_focus_loss")? {
+            self.pause_on_focus_loss = v;
+        }
+        if let Some(v) = text("emulation", "pacing")? {
+            self.pacing = parse_pacing(&v).map_err(|e| setting("emulation", "pacing", e))?;
+        }
+        if let Some(v) = text("emulation", "spin_margin")? {
+            self.pacing_tuning.spin_margin = parse_pacing_us(&v).map_err(|e| setting("emulation", "spin_margin", e))?;
+        }
+        if let Some(v) = text("emulation", "sleep_slack")? {
+            self.pacing_tuning.sleep_slack = parse_pacing_us(&v).map_err(|e| setting("emulation", "sleep_slack", e))?;
+        }
+        if let Some(v) = text("emulation", "rewind_seconds")? {
+            self.rewind_seconds = parse_rewind(&v).map_err(|e| setting("emulation", "rewind_seconds", e))?;
+        }
+        if let Some(v) = text("emulation", "model")? {
+            self.model = parse_model(&v).map_err(|e| setting("emulation", "model", e))?;
+        }
+        if let Some(v) = text("emulation", "profile")? {
+            self.profile = parse_profile(&v).map_err(|e| setting("emulation", "profile", e))?;
+        }
+        if let Some(v) = text("emulation", "overclock")? {
+            self.overclock = parse_overclock(&v).map_err(|e| setting("emulation", "overclock", e))?;
+        }
+        if let Some(v) = flag("emulation", "frame_stats")? {
+            self.frame_stats = v;
+        }
+        if let Some(v) = flag("emulation", "boot_animation")? {
+            self.boot_animation = v;
+        }
+        if let Some(v) = text("emulation", "ram_init")? {
+            self.ram_init = parse_ram_init(&v).map_err(|e| setting("emulation", "ram_init", e))?;
+        }
+        if let Some(v) = flag("emulation", "save_high_scores")? {
+            self.save_high_scores = v;
+        }
+        if let Some(v) = flag("emulation", "auto_resume")? {
+            self.auto_resume = v;
+        }
+        if let Some(v) = flag("emulation", "session_stats")? {
+            self.session_stats = v;
+        }
+        if let Some(v) = flag("emulation", "hard_drop")? {
+            self.hard_drop = v;
+        }
+
+        for (section, keys) in [("input", &mut self.keys), ("input.player2", &mut self.player2_keys)] {
+            for (name, (scancode, _)) in BUTTON_NAMES.iter().zip(keys.iter_mut()) {
+                if let Some(v) = text(section, name)? {
+                    *scancode = Scancode::from_name(&v).ok_or_else(|| setting(section, name, format!("unknown key: {}", v)))?;
+                }
+            }
+        }
+        match config.get("input", "turbo") {
+            None => {}
+            Some(Value::Array(items)) => {
+                self.turbo = items
+                    .iter()
+                    .map(|item| parse_turbo(&item.as_text().unwrap_or_default()))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| setting("input", "turbo", e))?;
+            }
+            Some(_) => return Err(String::from("input.turbo must be a list like [\"S=A\", \"A=B\"]")),
+        }
+        if let Some(v) = text("input", "turbo_hz")? {
+            self.turbo_hz = parse_turbo_hz(&v).map_err(|e| setting("input", "turbo_hz", e))?;
+        }
+        match config.get("input", "macros") {
+            None => {}
+            Some(Value::Array(items)) => {
+                self.macros = items
+                    .iter()
+                    .map(|item| parse_macro_key(&item.as_text().unwrap_or_default()))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| setting("input", "macros", e))?;
+            }
+            Some(_) => return Err(String::from("input.macros must be a list like [\"Q\", \"W\"]")),
+        }
+        if let Some(v) = flag("input", "das")? {
+            self.das = v;
+        }
+        if let Some(v) = text("input", "das_delay")? {
+            self.das_timing.delay = parse_das_delay(&v).map_err(|e| setting("input", "das_delay", e))?;
+        }
+        if let Some(v) = text("input", "das_repeat")? {
+            self.das_timing.repeat = parse_das_repeat(&v).map_err(|e| setting("input", "das_repeat", e))?;
+        }
+        if let Some(v) = text("input", "rumble")? {
+            self.rumble = parse_rumble(&v).map_err(|e| setting("input", "rumble", e))?;
+        }
+
+        if let Some(v) = text("paths", "rom")? {
+            self.rom_path = v;
+        }
+        if let Some(v) = text("paths", "boot_rom")? {
+            self.boot_rom = Some(v);
+        }
+        if let Some(v) = text("paths", "cgb_boot_rom")? {
+            self.cgb_boot_rom = Some(v);
+        }
+
+        match config.get("debug", "assert") {
+            None => {}
+            Some(Value::Array(items)) => {
+                self.assertions = items
+                    .iter()
+                    .map(|item| Assertion::parse(&item.as_text().unwrap_or_default()))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| setting("debug", "assert", e))?;
+            }
+            Some(_) => return Err(String::from("debug.assert must be a list like [\"C0DE <= 14\"]")),
+        }
+        Ok(())
+    }
+}
+
+fn parse_speed(value: &str) -> Result<u32, String> {
+    let speed: u32 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid speed: {}", value))?;
+    if !(SPEED_MIN..=SPEED_MAX).contains(&speed) {
+        return Err(format!("speed must be between {}% and {}%", SPEED_MIN, SPEED_MAX));
+    }
+    Ok(speed)
+}
+
+fn parse_pacing(value: &str) -> Result<Pacing, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "audio" => Err("audio pacing needs sound output, which RUSTЯIS doesn't have yet".to_string()),
+        name => Pacing::from_name(name).ok_or_else(|| format!("pacing must be hybrid, sleep, spin or vsync: {}", value)),
+    }
+}
+
+/// Parses a pacing tuning value, in microseconds.
+fn parse_pacing_us(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(us @ 0..=10000) => Ok(us),
+        _ => Err(format!("pacing tuning must be between 0 and 10000 µs: {}", value)),
+    }
+}
+
+/// Parses a `KEY=BUTTON` turbo mapping.
+fn parse_turbo(value: &str) -> Result<(Scancode, u8), String> {
+    let (key, button) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid turbo mapping: {}", value))?;
+    let sc = Scancode::from_name(key).ok_or_else(|| format!("unknown key: {}", key))?;
+    let mask = parse_button(button).ok_or_else(|| format!("unknown button: {}", button))?;
+    Ok((sc, mask))
+}
+
+/// Parses a macro key: one that isn't a button's, Escape or Shift, which records macros.
+fn parse_macro_key(name: &str) -> Result<Scancode, String> {
+    match Scancode::from_name(name) {
+        Some(Scancode::Escape | Scancode::LShift | Scancode::RShift) => Err(format!("{} can't have a macro", name)),
+        Some(sc) => Ok(sc),
+        None => Err(format!("unknown key: {}", name)),
+    }
+}
+
+fn parse_turbo_hz(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(hz @ 1..=30) => Ok(hz),
+        _ => Err(format!("turbo rate must be between 1 and 30 Hz: {}", value)),
+    }
+}
+
+fn parse_das_delay(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(frames @ 2..=60) => Ok(frames),
+        _ => Err(format!("auto-repeat delay must be between 2 and 60 frames: {}", value)),
+    }
+}
+
+fn parse_das_repeat(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(frames @ 2..=30) => Ok(frames),
+        _ => Err(format!("auto-repeat rate must be between 2 and 30 frames: {}", value)),
+    }
+}
+
+fn parse_rumble(value: &str) -> Result<u32, String> {
+    match value.trim_end_matches('%').parse() {
+        Ok(percent @ 0..=100) => Ok(percent),
+        _ => Err(format!("rumble strength must be between 0% and 100%: {}", value)),
+    }
+}
+
+fn parse_attract_idle(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(seconds @ 1..) => Ok(seconds),
+        _ => Err(format!("attract idle time must be at least 1 second: {}", value)),
+    }
+}
+
+fn parse_rotation(value: &str) -> Result<Rotation, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(Rotation::from_degrees)
+        .ok_or_else(|| format!("rotation must be 0, 90, 180 or 270: {}", value))
+}
+
+fn parse_palette(value: &str) -> Result<HostPalette, String> {
+    HostPalette::from_name(&value.to_ascii_lowercase())
+        .ok_or_else(|| format!("palette must be color, green, colorblind or high-contrast: {}", value))
+}
+
+fn parse_overclock(value: &str) -> Result<u32, String> {
+    match value.trim_end_matches(['x', '×']).parse() {
+        Ok(factor @ (1 | 2 | 4)) => Ok(factor),
+        _ => Err(format!("overclock must be 1, 2 or 4: {}", value)),
+    }
+}
+
+fn parse_zoom(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(zoom @ 1..=16) => Ok(zoom),
+        _ => Err(format!("zoom must be between 1 and 16: {}", value)),
+    }
+}
+
+fn parse_rewind(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(n @ 0..=600) => Ok(n),
+        _ => Err(format!("rewind length must be between 0 and 600 seconds: {}", value)),
+    }
+}
+
+fn parse_model(value: &str) -> Result<Option<Model>, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "auto" => Ok(None),
+        "dmg" => Ok(Some(Model::Dmg)),
+        "cgb" => Ok(Some(Model::Cgb)),
+        _ => Err(format!("model must be auto, dmg or cgb: {}", value)),
+    }
+}
+
+fn parse_profile(value: &str) -> Result<Option<Profile>, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "auto" => Ok(None),
+        "tetris" => Ok(Some(Profile::Tetris)),
+        "compatible" => Ok(Some(Profile::Compatible)),
+        "accurate" => Ok(Some(Profile::Accurate)),
+        _ => Err(format!("profile must be auto, tetris, compatible or accurate: {}", value)),
+    }
+}
+
+fn parse_ram_init(value: &str) -> Result<RamInit, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "zero" => Ok(RamInit::Zero),
+        "random" => Ok(RamInit::Random),
+        _ => Err(format!("RAM init must be zero or random: {}", value)),
+    }
+}
+
+/// Parses a Tetris piece sequence: piece letters (I, O, T, S, Z, L, J), any case.
+fn parse_pieces(value: &str) -> Result<Vec<Piece>, String> {
+    let pieces = value
+        .chars()
+        .map(|letter| Piece::from_letter(letter).ok_or_else(|| format!("not a Tetris piece: {}", letter)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if pieces.is_empty() {
+        return Err(String::from("--pieces requires at least one piece"));
+    }
+    Ok(pieces)
+}
+
+/// Parses when the speedrun timer splits: `level`, or a number of lines.
+fn parse_splits(value: &str) -> Result<SplitOn, String> {
+    if value.eq_ignore_ascii_case("level") {
+        return Ok(SplitOn::Level);
+    }
+    match value.parse::<u16>() {
+        Ok(lines) if lines > 0 => Ok(SplitOn::Lines(lines)),
+        _ => Err(format!("invalid timer splits (expected level or a number of lines): {}", value)),
+    }
+}
+
+/// Parses a Game Boy button name (case-insensitive) into its input bitmask.
+pub fn parse_button(name: &str) -> Option<u8> {
+    match name.to_ascii_lowercase().as_str() {
+        "right"  => Some(gb::BTN_RIGHT),
+        "left"   => Some(gb::BTN_LEFT),
+        "up"     => Some(gb::BTN_UP),
+        "down"   => Some(gb::BTN_DOWN),
+        "a"      => Some(gb::BTN_A),
+        "b"      => Some(gb::BTN_B),
+        "select" => Some(gb::BTN_SELECT),
+        "start"  => Some(gb::BTN_START),
+        _ => None,
+    }
+}
+
+/// Parses an `RRGGBB` hex color (an optional leading `#` is accepted).
+pub fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+}
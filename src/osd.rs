@@ -0,0 +1,148 @@
+use rustris_core::gb::{BTN_A, BTN_B, BTN_DOWN, BTN_LEFT, BTN_RIGHT, BTN_SELECT, BTN_START, BTN_UP};
+use rustris_core::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+/// Glyph cell size in pixels; each glyph is 3×5 with one column/row of spacing.
+pub const CHAR_WIDTH: i32 = 4;
+pub const CHAR_HEIGHT: i32 = 6;
+
+/// On-screen display: draws text straight into an RGB24 Game Boy framebuffer.
+/// - Uses a built-in 3×5 bitmap font (digits, uppercase letters, common punctuation);
+///   lowercase is drawn as uppercase and anything else as `?`.
+/// - Text gets a one-pixel dark shadow so it stays readable over any background.
+/// - `\n` starts a new line; pixels outside the 160×144 screen are clipped.
+pub fn draw_text(framebuffer: &mut [u8], pitch: usize, x: i32, y: i32, text: &str, color: [u8; 3]) {
+    let (mut cx, mut cy) = (x, y);
+    for c in text.chars() {
+        if c == '\n' {
+            cx = x;
+            cy += CHAR_HEIGHT;
+            continue;
+        }
+        let rows = glyph(c);
+        draw_glyph(framebuffer, pitch, cx + 1, cy + 1, &rows, [0, 0, 0]);
+        draw_glyph(framebuffer, pitch, cx, cy, &rows, color);
+        cx += CHAR_WIDTH;
+    }
+}
+
+fn draw_glyph(framebuffer: &mut [u8], pitch: usize, x: i32, y: i32, rows: &[u8; 5], color: [u8; 3]) {
+    for (dy, row) in rows.iter().enumerate() {
+        for dx in 0..3 {
+            if row & (0b100 >> dx) == 0 {
+                continue;
+            }
+            let (px, py) = (x + dx, y + dy as i32);
+            if px < 0 || py < 0 || px >= SCREEN_WIDTH as i32 || py >= SCREEN_HEIGHT as i32 {
+                continue;
+            }
+            let i = py as usize * pitch + px as usize * 3;
+            framebuffer[i..i + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+/// Height of the button widget drawn by `draw_buttons` (44 pixels wide).
+pub const BUTTONS_HEIGHT: i32 = 12;
+
+/// Where each button sits in the widget: mask, then x, y, width and height.
+const BUTTON_LAYOUT: [(u8, i32, i32, i32, i32); 8] = [
+    (BTN_UP, 4, 0, 4, 4),
+    (BTN_LEFT, 0, 4, 4, 4),
+    (BTN_RIGHT, 8, 4, 4, 4),
+    (BTN_DOWN, 4, 8, 4, 4),
+    (BTN_SELECT, 16, 6, 6, 2),
+    (BTN_START, 24, 6, 6, 2),
+    (BTN_B, 34, 5, 4, 4),
+    (BTN_A, 40, 3, 4, 4),
+];
+
+/// Draws a Game Boy pad (D-pad, Select, Start, B, A) with its top-left corner at `x`, `y`,
+/// the buttons in `buttons` lit. Like text, each part gets a dark outline.
+pub fn draw_buttons(framebuffer: &mut [u8], pitch: usize, x: i32, y: i32, buttons: u8, color: [u8; 3]) {
+    const RELEASED: [u8; 3] = [0x50, 0x50, 0x50];
+    for (_, bx, by, w, h) in BUTTON_LAYOUT {
+        fill_rect(framebuffer, pitch, x + bx - 1, y + by - 1, w + 2, h + 2, [0, 0, 0]);
+    }
+    // The D-pad's middle, so the arrows read as one cross
+    fill_rect(framebuffer, pitch, x + 4, y + 4, 4, 4, RELEASED);
+    for (mask, bx, by, w, h) in BUTTON_LAYOUT {
+        let fill = if buttons & mask != 0 { color } else { RELEASED };
+        fill_rect(framebuffer, pitch, x + bx, y + by, w, h, fill);
+    }
+}
+
+/// Fills a `w`×`h` rectangle with `color`, clipped to the screen; no outline.
+pub fn fill_rect(framebuffer: &mut [u8], pitch: usize, x: i32, y: i32, w: i32, h: i32, color: [u8; 3]) {
+    for py in y.max(0)..(y + h).min(SCREEN_HEIGHT as i32) {
+        for px in x.max(0)..(x + w).min(SCREEN_WIDTH as i32) {
+            let i = py as usize * pitch + px as usize * 3;
+            framebuffer[i..i + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+/// 3×5 glyph rows, top to bottom; bit 2 is the leftmost column.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0, 0, 0, 0, 0],
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 1, 1, 1],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        ':' => [0, 2, 0, 2, 0],
+        ';' => [0, 2, 0, 2, 4],
+        '!' => [2, 2, 2, 0, 2],
+        '?' => [7, 1, 2, 0, 2],
+        '-' => [0, 0, 7, 0, 0],
+        '+' => [0, 2, 7, 2, 0],
+        '=' => [0, 7, 0, 7, 0],
+        '*' => [0, 5, 2, 5, 0],
+        '/' => [1, 1, 2, 4, 4],
+        '%' => [5, 1, 2, 4, 5],
+        '#' => [5, 7, 5, 7, 5],
+        '_' => [0, 0, 0, 0, 7],
+        '\'' => [2, 2, 0, 0, 0],
+        '"' => [5, 5, 0, 0, 0],
+        '(' => [1, 2, 2, 2, 1],
+        ')' => [4, 2, 2, 2, 4],
+        '[' => [3, 2, 2, 2, 3],
+        ']' => [6, 2, 2, 2, 6],
+        '<' => [1, 2, 4, 2, 1],
+        '>' => [4, 2, 1, 2, 4],
+        _ => [7, 1, 2, 0, 2],
+    }
+}
@@ -0,0 +1,166 @@
+use crate::emu_thread::GB_FRAME_NS;
+use crate::stats::FrameTimes;
+
+use std::hint::spin_loop as cpu_relax;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How a frame loop waits for each frame's deadline (`--pacing`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pacing {
+    Hybrid, // Sleep, then spin the last `spin_margin`: precise for little CPU
+    Sleep,  // Only sleep, waking `sleep_slack` early: least CPU, frames come a little unevenly
+    Spin,   // Busy-wait the whole way: most precise, keeps a core busy
+    Vsync,  // A frame per refresh of a 60 or 120 Hz display, as the window presents them
+}
+
+impl Pacing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hybrid" => Some(Self::Hybrid),
+            "sleep" => Some(Self::Sleep),
+            "spin" => Some(Self::Spin),
+            "vsync" => Some(Self::Vsync),
+            _ => None,
+        }
+    }
+}
+
+/// Tuning of the pacing strategies that have some, in microseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PacingTuning {
+    pub spin_margin: u32, // Hybrid: spun before each deadline instead of slept
+    pub sleep_slack: u32, // Sleep: woken before each deadline, against the OS oversleeping
+}
+
+impl Default for PacingTuning {
+    fn default() -> Self {
+        Self { spin_margin: 500, sleep_slack: 0 }
+    }
+}
+
+/// Presents of the main window, counted for vsync pacing: the UI thread calls `presented`
+/// after each one, which with vsync returns at the display's refresh, and the emulation
+/// threads wait for the count to move on. Cloning gives another handle to the same count.
+#[derive(Clone, Default)]
+pub struct Vblank(Arc<(Mutex<u64>, Condvar)>);
+
+impl Vblank {
+    pub fn presented(&self) {
+        let (count, changed) = &*self.0;
+        *count.lock().unwrap() += 1;
+        changed.notify_all();
+    }
+
+    fn count(&self) -> u64 {
+        *self.0.0.lock().unwrap()
+    }
+
+    /// Waits up to `timeout` for a present after the `seen`th; returns the count then.
+    fn wait_past(&self, seen: u64, timeout: Duration) -> Option<u64> {
+        let (count, changed) = &*self.0;
+        let (count, _) = changed.wait_timeout_while(count.lock().unwrap(), timeout, |count| *count <= seen).unwrap();
+        (*count > seen).then_some(*count)
+    }
+}
+
+/// Frame limiter of the emulation thread (and of the `--versus` and netplay windows):
+/// after each frame, waits until the next one is due, as its `Pacing` says:
+/// - Hybrid, Sleep and Spin keep to the clock: a deadline every frame period (the real
+///   DMG's, scaled by the speed), resynced rather than caught up with when a frame runs
+///   late.
+/// - Vsync lets the display set the pace: the next frame starts at the first present of
+///   the window (see `Vblank`) that comes at least three quarters of a period after the
+///   last one, so a 60 Hz display gets a frame per refresh (0.45% faster than the DMG)
+///   and a 120 Hz one every other refresh. If the window doesn't present in time
+///   (minimized, say) it falls back to the clock. At speeds other than 100%, or without a
+///   window presenting for it, it paces like Hybrid.
+///
+/// Where each wait's time went goes into the frame's `FrameTimes`: sleeping (or waiting
+/// for the display), spinning, and how late it woke.
+pub struct Limiter {
+    pacing: Pacing,
+    tuning: PacingTuning,
+    vblank: Option<Vblank>, // The window's presents, for Vsync pacing
+    seen: u64,              // Presents counted when the last frame started (Vsync)
+    speed: u32,             // Percent of the DMG's speed
+    period: Duration,
+    deadline: Instant, // When the next frame is due
+}
+
+impl Limiter {
+    pub fn new(pacing: Pacing, tuning: PacingTuning, speed: u32, vblank: Option<Vblank>) -> Self {
+        let period = frame_period(speed);
+        let seen = vblank.as_ref().map_or(0, Vblank::count);
+        Self { pacing, tuning, vblank, seen, speed, period, deadline: Instant::now() + period }
+    }
+
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+        self.period = frame_period(speed);
+    }
+
+    /// Starts timing afresh, after a pause: the next frame gets a whole period.
+    pub fn restart(&mut self) {
+        self.deadline = Instant::now() + self.period;
+        if let Some(vblank) = &self.vblank {
+            self.seen = vblank.count();
+        }
+    }
+
+    /// Waits until the next frame is due, recording how into `times`.
+    pub fn wait(&mut self, times: &mut FrameTimes) {
+        let now = Instant::now();
+        if self.pacing == Pacing::Vsync && self.speed == 100 && let Some(vblank) = &self.vblank {
+            // Presents that come too soon are refreshes to skip (120 Hz)
+            let earliest = self.deadline - self.period / 4;
+            let give_up = self.deadline + self.period / 2;
+            let mut presented = None;
+            while let Some(count) = vblank.wait_past(self.seen, give_up.saturating_duration_since(Instant::now())) {
+                self.seen = count;
+                if Instant::now() >= earliest {
+                    presented = Some(Instant::now());
+                    break;
+                }
+            }
+            let woke = Instant::now();
+            times.sleep = woke - now;
+            times.missed = presented.is_none();
+            self.deadline = presented.unwrap_or(woke) + self.period;
+            return;
+        }
+
+        if self.deadline > now {
+            let sleep_until = match self.pacing {
+                Pacing::Sleep => self.deadline - Duration::from_micros(self.tuning.sleep_slack as u64),
+                Pacing::Spin => now,
+                Pacing::Hybrid | Pacing::Vsync => self.deadline - Duration::from_micros(self.tuning.spin_margin as u64),
+            };
+            if sleep_until > now {
+                thread::sleep(sleep_until - now);
+            }
+            let spin_start = Instant::now();
+            if self.pacing != Pacing::Sleep {
+                while Instant::now() < self.deadline {
+                    cpu_relax();
+                }
+            }
+            let woke = Instant::now();
+            times.sleep = spin_start - now;
+            times.spin = woke - spin_start;
+            times.overshoot = woke.saturating_duration_since(self.deadline);
+        } else {
+            // Late: resync rather than run the missed frames back to back
+            times.missed = true;
+            self.deadline = now;
+        }
+        self.deadline += self.period;
+    }
+}
+
+/// Frame period for a given speed: the real DMG period scaled by `100 / speed`. Speed
+/// only moves the deadlines; with no APU there are no samples to resample along with it.
+fn frame_period(speed: u32) -> Duration {
+    Duration::from_nanos(GB_FRAME_NS * 100 / speed as u64)
+}
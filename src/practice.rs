@@ -0,0 +1,31 @@
+use crate::tetris::{self, Spawns};
+
+use rustris_core::GB;
+
+/// Tetris practice mode (`--practice`): a save state taken as each piece appears (see
+/// `Spawns`), which the retry key (Tab) goes back to, to play the same piece in the same
+/// situation again. Only the latest spawn is kept; retrying it again and again keeps going
+/// back to it, since the restored well is the one it was taken with.
+pub struct Practice {
+    spawns: Spawns,
+    spawn: Option<Vec<u8>>, // Save state taken at the last spawn
+}
+
+impl Practice {
+    /// The keeper for `gb`'s game; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self { spawns: Spawns::new(), spawn: None })
+    }
+
+    /// Call after every emulated frame: takes the save state when a piece has spawned.
+    pub fn after_frame(&mut self, gb: &GB) {
+        if self.spawns.check(gb).is_some() {
+            self.spawn = Some(gb.save_state());
+        }
+    }
+
+    /// The save state of the last spawn, if a piece has spawned yet.
+    pub fn last_spawn(&self) -> Option<&[u8]> {
+        self.spawn.as_deref()
+    }
+}
@@ -0,0 +1,65 @@
+use crate::config::config_dir;
+
+use std::path::{Path, PathBuf};
+
+/// Games kept in the list.
+const MAX_RECENT: usize = 10;
+
+/// Recently opened ROMs, most recent first, kept in `recent.txt` in the config directory
+/// (one absolute path per line) and rewritten whenever a game is opened.
+pub struct RecentRoms {
+    paths: Vec<String>,
+    file: Option<PathBuf>, // None: not saved (no config directory)
+}
+
+impl RecentRoms {
+    /// The saved list; empty if there's none yet. Entries whose file is gone are dropped.
+    pub fn load() -> Self {
+        let file = config_dir().map(|dir| dir.join("recent.txt"));
+        let paths = file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|text| {
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && Path::new(line).is_file())
+                    .take(MAX_RECENT)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { paths, file }
+    }
+
+    /// The games, most recent first.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Moves `rom_path` to the top of the list and saves it.
+    pub fn add(&mut self, rom_path: &str) {
+        let path = std::fs::canonicalize(rom_path).map_or_else(|_| rom_path.to_string(), |p| p.display().to_string());
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT);
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(file) = &self.file else { return };
+        let text: String = self.paths.iter().map(|p| format!("{}\n", p)).collect();
+        let written = match file.parent() {
+            Some(dir) => std::fs::create_dir_all(dir).and_then(|_| std::fs::write(file, text)),
+            None => std::fs::write(file, text),
+        };
+        if let Err(e) = written {
+            eprintln!("Error writing {}: {}", file.display(), e);
+        }
+    }
+}
+
+/// Name shown for a ROM in the list: its file name without the extension.
+pub fn display_name(rom_path: &str) -> String {
+    let path = Path::new(rom_path);
+    path.file_stem().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
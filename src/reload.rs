@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file is looked at.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a changed file must stay unchanged before it's reloaded, so a ROM still being
+/// written (`rgblink` then `rgbfix` patching it in place) isn't picked up half done.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Watches a file the front-end reloads when it changes: the ROM for `--hot-reload`, once
+/// it's been rebuilt, and the `--shader` being worked on. The file is polled (modification
+/// time and size), which works the same on every platform and on network drives; a file
+/// that goes missing for a while, as some build tools and editors replace it, counts as
+/// changed once it's back.
+pub struct FileWatcher {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>, // When the file was last modified and its size, as last seen
+    changed: Option<Instant>,         // When it was last seen changing, while it settles
+    checked: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        Self { stamp: stamp(&path), path, changed: None, checked: Instant::now() }
+    }
+
+    /// Call often: returns `true` once the file has changed and then settled.
+    pub fn poll(&mut self) -> bool {
+        if self.checked.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.checked = Instant::now();
+        let now = stamp(&self.path);
+        if now != self.stamp {
+            self.stamp = now;
+            self.changed = Some(self.checked);
+            return false;
+        }
+        match self.changed {
+            Some(at) if self.stamp.is_some() && at.elapsed() >= SETTLE => {
+                self.changed = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
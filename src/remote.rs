@@ -0,0 +1,168 @@
+use crate::options::parse_button;
+use crate::websocket;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Frames a `tap` holds its buttons for: long enough for games that read the joypad
+/// every other frame.
+const TAP_FRAMES: u64 = 2;
+
+/// Receiving end of the remote input server started by `serve`, handed to each
+/// `RemoteInput` in turn as player 1's machine changes games.
+#[derive(Clone)]
+pub struct Orders {
+    queue: Arc<Mutex<Vec<Order>>>, // Received and not yet taken by the machine
+    frame: Arc<AtomicU64>,         // The machine's next frame, for `frame` requests
+}
+
+/// A press or release from a client.
+#[derive(Clone, Copy)]
+struct Order {
+    frame: Option<u64>, // Frame it's stamped with; `None` for the next one
+    delay: u64,         // Frames after that to wait (a tap's release)
+    buttons: u8,
+    pressed: bool,
+}
+
+/// Starts the remote input server on `addr` (`HOST:PORT`), taking button presses from
+/// any number of clients, each on its own thread until it disconnects. Clients connect
+/// either over plain TCP, sending one command per line, or over WebSocket (RFC 6455, any
+/// path), sending one per text message. Commands, case-insensitive:
+/// - `press BUTTON… [@FRAME]`, `release BUTTON… [@FRAME]`: hold or let go of the buttons
+///   (`a`, `b`, `select`, `start`, `up`, `down`, `left`, `right`; `release` alone lets go
+///   of them all).
+/// - `tap BUTTON… [@FRAME]`: press the buttons for `TAP_FRAMES` frames.
+/// - `frame`: answers `frame N`, the next frame the machine will run.
+///
+/// Without `@FRAME` a command acts on the next frame; with it, on frame FRAME (counted
+/// from 0 when the game started; a frame already run means the next one), so a client
+/// can queue a whole sequence ahead of time. Commands for the same frame act in the
+/// order they came in. Invalid commands are answered with `error: …`; valid ones aren't
+/// answered at all.
+///
+/// Fails if the address can't be bound.
+pub fn serve(addr: &str) -> io::Result<Orders> {
+    let listener = TcpListener::bind(addr)?;
+    let orders = Orders { queue: Arc::new(Mutex::new(Vec::new())), frame: Arc::new(AtomicU64::new(0)) };
+    let server = orders.clone();
+    thread::Builder::new().name(String::from("remote-accept")).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let orders = server.clone();
+            let spawned = thread::Builder::new().name(String::from("remote-client")).spawn(move || {
+                if let Err(e) = serve_client(stream, &orders) {
+                    eprintln!("Remote input: client dropped: {}", e);
+                }
+            });
+            if let Err(e) = spawned {
+                eprintln!("Remote input: can't serve a client: {}", e);
+            }
+        }
+    })?;
+    Ok(orders)
+}
+
+/// Takes commands from one client until it disconnects, telling WebSocket clients from
+/// plain ones by their opening `GET`.
+fn serve_client(stream: TcpStream, orders: &Orders) -> io::Result<()> {
+    let mut start = [0u8; 4];
+    let seen = stream.peek(&mut start)?;
+    if &start[..seen] == b"GET " {
+        websocket::handshake(&stream)?;
+        stream.set_read_timeout(None)?;
+        let (mut reader, mut writer) = (&stream, &stream);
+        while let Some(text) = websocket::read_text(&mut reader)? {
+            if let Some(reply) = orders.command(&text) {
+                writer.write_all(&websocket::text_frame(&reply))?;
+            }
+        }
+    } else {
+        let mut writer = &stream;
+        for line in BufReader::new(&stream).lines() {
+            if let Some(reply) = orders.command(&line?) {
+                writeln!(writer, "{}", reply)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Orders {
+    /// Queues the orders in a command; returns the reply, if there is one.
+    fn command(&self, text: &str) -> Option<String> {
+        let text = text.trim().to_ascii_lowercase();
+        if text.is_empty() {
+            return None;
+        }
+        match parse(&text) {
+            Ok(orders) if orders.is_empty() => Some(format!("frame {}", self.frame.load(Ordering::Relaxed))),
+            Ok(orders) => {
+                self.queue.lock().unwrap().extend(orders);
+                None
+            }
+            Err(e) => Some(format!("error: {}", e)),
+        }
+    }
+}
+
+/// The orders a command stands for; none for `frame`.
+fn parse(text: &str) -> Result<Vec<Order>, String> {
+    let mut words = text.split_whitespace();
+    let verb = words.next().unwrap_or_default();
+    let mut frame = None;
+    let mut buttons = 0;
+    for word in words {
+        match word.strip_prefix('@') {
+            Some(number) => frame = Some(number.parse().map_err(|_| format!("invalid frame: {}", number))?),
+            None => buttons |= parse_button(word).ok_or_else(|| format!("unknown button: {}", word))?,
+        }
+    }
+    let order = |delay, pressed| Order { frame, delay, buttons, pressed };
+    match verb {
+        "frame" => Ok(Vec::new()),
+        "release" if buttons == 0 => Ok(vec![Order { buttons: 0xFF, ..order(0, false) }]),
+        "press" | "tap" if buttons == 0 => Err(format!("no buttons given: {}", text)),
+        "press" => Ok(vec![order(0, true)]),
+        "release" => Ok(vec![order(0, false)]),
+        "tap" => Ok(vec![order(0, true), order(TAP_FRAMES, false)]),
+        _ => Err(format!("unknown command (expected press, release, tap or frame): {}", verb)),
+    }
+}
+
+/// Buttons held by remote clients (`--remote-input`, see `serve`), combined with the
+/// keys into player 1's joypad.
+pub struct RemoteInput {
+    orders: Orders,
+    queued: Vec<(u64, Order)>, // Orders waiting for their frame, soonest first
+    held: u8,
+    frame: u64, // The frame about to run
+}
+
+impl RemoteInput {
+    pub fn new(orders: Orders) -> Self {
+        orders.frame.store(0, Ordering::Relaxed);
+        Self { orders, queued: Vec::new(), held: 0, frame: 0 }
+    }
+
+    /// Call as each frame starts: carries out the orders due by then and returns the
+    /// buttons held through the frame.
+    pub fn before_frame(&mut self) -> u8 {
+        let received = std::mem::take(&mut *self.orders.queue.lock().unwrap());
+        self.queued.extend(received.into_iter().map(|order| (order.frame.unwrap_or(self.frame) + order.delay, order)));
+        self.queued.sort_by_key(|&(frame, _)| frame); // Stable: same-frame orders keep their order
+        let due = self.queued.partition_point(|&(frame, _)| frame <= self.frame);
+        for (_, order) in self.queued.drain(..due) {
+            if order.pressed {
+                self.held |= order.buttons;
+            } else {
+                self.held &= !order.buttons;
+            }
+        }
+        self.frame += 1;
+        self.orders.frame.store(self.frame, Ordering::Relaxed);
+        self.held
+    }
+}
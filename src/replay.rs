@@ -0,0 +1,96 @@
+use crate::demo::{Change, Checkpoint, Demo};
+
+use rustris_core::{InputSource, Registers, GB};
+
+use std::path::Path;
+
+/// Replay verification (`--verify-demo`), headless: plays a demo from its starting state
+/// with nothing but its buttons, and checks every frame against the checkpoint recorded
+/// as the same frame ended in the original run (see `Checkpoint`): the registers and the
+/// digest of RAM must match. The first frame that doesn't is reported, with the registers
+/// both runs ended it with. The machine must be set up as it was for the recording
+/// (`--model`, `--profile`, `--overclock`), and the recording made without anything else
+/// touching the machine (scripts, the autoplayer's or remote buttons are fine, being
+/// recorded as buttons; the piece sequence or the hard-drop patch aren't).
+///
+/// Returns the process exit status: 0 every frame matched, 1 diverged (or the CPU locked
+/// up), 2 the demo couldn't be read or has no checkpoints.
+pub fn verify(mut gb: GB, path: &Path) -> i32 {
+    let demo = match Demo::load(path) {
+        Ok(demo) => demo,
+        Err(e) => {
+            eprintln!("Error loading demo {}: {}", path.display(), e);
+            return 2;
+        }
+    };
+    let checkpoints = demo.checkpoints();
+    if checkpoints.is_empty() {
+        eprintln!("{} has no checkpoints to verify against (recorded by an older version?)", path.display());
+        return 2;
+    }
+    if let Err(e) = gb.load_state(demo.state()) {
+        eprintln!("Can't load the demo's starting state: {}", e);
+        return 2;
+    }
+
+    let mut frames = gb.frames_with(Playback { buttons: 0, changes: &[] });
+    for (frame, recorded) in checkpoints.iter().enumerate() {
+        let (buttons, changes) = demo.frame(frame);
+        *frames.input() = Playback { buttons, changes };
+        if frames.next().is_none() {
+            let fault = frames.gb().fault().err().map_or_else(String::new, |e| e.to_string());
+            println!("Diverged at frame {}: {}", frame, fault);
+            return 1;
+        }
+        let replayed = Checkpoint::of(frames.gb());
+        if replayed == *recorded {
+            continue;
+        }
+        let mut differs = Vec::new();
+        if replayed.registers != recorded.registers {
+            differs.push("registers");
+        }
+        if replayed.ram != recorded.ram {
+            differs.push("RAM");
+        }
+        println!("Diverged at frame {} of {} (mismatched: {}):", frame, checkpoints.len(), differs.join(", "));
+        println!("- {}", registers_line(&recorded.registers));
+        println!("+ {}", registers_line(&replayed.registers));
+        return 1;
+    }
+    println!("All {} frames matched the recording", checkpoints.len());
+    0
+}
+
+/// Registers as a trace line shows them (see `trace::line`).
+fn registers_line(r: &Registers) -> String {
+    format!(
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X}",
+        r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, r.pc,
+    )
+}
+
+/// A demo frame's buttons: held from its start, changing within it where recorded.
+struct Playback<'a> {
+    buttons: u8,
+    changes: &'a [Change], // Still due this frame
+}
+
+impl InputSource for Playback<'_> {
+    fn poll(&mut self) -> u8 {
+        self.buttons
+    }
+
+    fn poll_at(&mut self, dot: u32) -> u8 {
+        let due = self.changes.partition_point(|change| change.dot <= dot);
+        if let Some(change) = self.changes[..due].last() {
+            self.buttons = change.buttons;
+        }
+        self.changes = &self.changes[due..];
+        self.buttons
+    }
+
+    fn next_change(&self) -> Option<u32> {
+        self.changes.first().map(|change| change.dot)
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+
+/// Rewind buffer: a ring of periodic save states, newest kept in full and every older
+/// one stored as a compressed delta against its successor.
+///
+/// - `push` records a new snapshot; the previous one is replaced by `prev XOR new`,
+///   run-length encoded (consecutive frames differ in few bytes, so deltas are tiny).
+/// - `pop` hands back the newest snapshot and reconstructs the one before it, so
+///   holding rewind walks backwards one snapshot per call.
+/// - Each snapshot keeps its position on the debugger's clock (see `Debugger::clock`),
+///   for stepping back from there.
+/// - When full, the oldest deltas are dropped.
+pub struct Rewind {
+    newest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>, // deltas[i] turns snapshot i+1 back into snapshot i
+    positions: VecDeque<u64>,  // Where each snapshot was taken, oldest first
+    capacity: usize,
+}
+
+impl Rewind {
+    /// Creates a buffer that holds up to `capacity` snapshots.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            newest: None,
+            deltas: VecDeque::with_capacity(capacity),
+            positions: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, state: Vec<u8>, position: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(prev) = self.newest.take() {
+            if prev.len() == state.len() {
+                self.deltas.push_back(encode_delta(&prev, &state));
+                if self.deltas.len() >= self.capacity {
+                    self.deltas.pop_front();
+                    self.positions.pop_front();
+                }
+            } else {
+                // Snapshot layout changed; older deltas can't be applied anymore
+                self.deltas.clear();
+                self.positions.clear();
+            }
+        }
+        self.newest = Some(state);
+        self.positions.push_back(position);
+    }
+
+    /// Returns the newest snapshot and its position.
+    pub fn pop(&mut self) -> Option<(Vec<u8>, u64)> {
+        let current = self.newest.take()?;
+        let position = self.positions.pop_back().unwrap_or_default();
+        if let Some(delta) = self.deltas.pop_back() {
+            let mut prev = current.clone();
+            apply_delta(&mut prev, &delta);
+            self.newest = Some(prev);
+        }
+        Some((current, position))
+    }
+
+    /// Where the snapshots were taken, oldest first.
+    pub fn positions(&self) -> impl DoubleEndedIterator<Item = u64> + '_ {
+        self.positions.iter().copied()
+    }
+}
+
+/// Encodes `a XOR b` as a sequence of `(zero run, literal length, literal bytes)`
+/// records, with both lengths as LEB128 varints.
+fn encode_delta(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < a.len() {
+        let start = i;
+        while i < a.len() && a[i] == b[i] {
+            i += 1;
+        }
+        let zeros = i - start;
+
+        let lit_start = i;
+        while i < a.len() && a[i] != b[i] {
+            i += 1;
+        }
+
+        put_varint(&mut out, zeros);
+        put_varint(&mut out, i - lit_start);
+        out.extend(a[lit_start..i].iter().zip(&b[lit_start..i]).map(|(x, y)| x ^ y));
+    }
+    out
+}
+
+/// XORs a delta produced by `encode_delta` into `state`.
+fn apply_delta(state: &mut [u8], delta: &[u8]) {
+    let mut pos = 0;
+    let mut d = delta;
+    while !d.is_empty() {
+        pos += get_varint(&mut d);
+        let len = get_varint(&mut d);
+        for (s, x) in state[pos..pos + len].iter_mut().zip(&d[..len]) {
+            *s ^= x;
+        }
+        d = &d[len..];
+        pos += len;
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn get_varint(d: &mut &[u8]) -> usize {
+    let mut v = 0;
+    let mut shift = 0;
+    loop {
+        let b = d[0];
+        *d = &d[1..];
+        v |= ((b & 0x7F) as usize) << shift;
+        if b & 0x80 == 0 {
+            return v;
+        }
+        shift += 7;
+    }
+}
@@ -0,0 +1,82 @@
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::{GameControllerSubsystem, Sdl};
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long each rumble request lasts; renewed well before it runs out while the motor
+/// keeps going, so a pad stops by itself if the UI thread stalls.
+const RUMBLE_LENGTH: Duration = Duration::from_millis(250);
+const RUMBLE_RENEW: Duration = Duration::from_millis(100);
+
+/// How hard a cartridge's rumble motor runs (0–1, see `GB::take_rumble`), set by the
+/// emulation thread after each frame and read by the UI thread for the pads. Cloning gives
+/// another handle to the same level.
+#[derive(Clone, Default)]
+pub struct Motor(Arc<AtomicU32>); // The level's f32 bits
+
+impl Motor {
+    pub fn set(&self, level: f32) {
+        self.0.store(level.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Game controllers shaking along with the rumble carts' motors: the first pad plugged in
+/// for player 1's machine, the second for player 2's.
+/// - Pads are opened as SDL reports them, those plugged in before start included, and
+///   dropped when unplugged.
+/// - A pad gets its motor's level scaled by the strength setting, on both of its motors.
+pub struct Rumble {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>,
+}
+
+struct Pad {
+    controller: GameController,
+    sent: u16,   // Strength last asked for
+    at: Instant, // When
+}
+
+impl Rumble {
+    pub fn new(sdl: &Sdl) -> Result<Self, String> {
+        Ok(Self { subsystem: sdl.game_controller()?, pads: Vec::new() })
+    }
+
+    /// Opens pads as they're plugged in and forgets them as they're unplugged.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => match self.subsystem.open(which) {
+                Ok(controller) => {
+                    if !controller.has_rumble() {
+                        eprintln!("Game controller {} can't rumble", controller.name());
+                    }
+                    self.pads.push(Pad { controller, sent: 0, at: Instant::now() });
+                }
+                Err(e) => eprintln!("Error opening game controller {}: {}", which, e),
+            },
+            Event::ControllerDeviceRemoved { which, .. } => self.pads.retain(|pad| pad.controller.instance_id() != which),
+            _ => {}
+        }
+    }
+
+    /// Passes each machine's motor (`None` for carts without one) on to its pad, at
+    /// `strength` percent.
+    pub fn update<'a>(&mut self, motors: impl Iterator<Item = Option<&'a Motor>>, strength: u32) {
+        for (pad, motor) in self.pads.iter_mut().zip(motors) {
+            let level = motor.map_or(0.0, Motor::level) * strength as f32 / 100.0;
+            let amount = (level.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+            if amount == pad.sent && (amount == 0 || pad.at.elapsed() < RUMBLE_RENEW) {
+                continue;
+            }
+            // Pads without rumble refuse; nothing to do about it
+            let _ = pad.controller.set_rumble(amount, amount, RUMBLE_LENGTH.as_millis() as u32);
+            (pad.sent, pad.at) = (amount, Instant::now());
+        }
+    }
+}
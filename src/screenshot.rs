@@ -0,0 +1,56 @@
+use rustris_core::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Saves an RGB24 Game Boy frame (`pitch` bytes per row) as a 160×144 PNG.
+pub fn save_png(path: &Path, framebuffer: &[u8], pitch: usize) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&packed(framebuffer, pitch)).map_err(|e| e.to_string())
+}
+
+/// Loads a 160×144 PNG (RGB or RGBA, 8-bit) as packed RGB24 pixels.
+pub fn load_png(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+
+    if info.width != SCREEN_WIDTH as u32 || info.height != SCREEN_HEIGHT as u32 {
+        return Err(format!("expected a {}x{} image, got {}x{}", SCREEN_WIDTH, SCREEN_HEIGHT, info.width, info.height));
+    }
+    data.truncate(info.buffer_size());
+    match info.color_type {
+        png::ColorType::Rgb => Ok(data),
+        png::ColorType::Rgba => Ok(data.chunks_exact(4).flat_map(|px| [px[0], px[1], px[2]]).collect()),
+        other => Err(format!("unsupported PNG color type: {:?}", other)),
+    }
+}
+
+/// Number of pixels in which a frame differs from packed RGB24 `reference` pixels.
+pub fn diff_pixels(framebuffer: &[u8], pitch: usize, reference: &[u8]) -> usize {
+    packed(framebuffer, pitch)
+        .chunks_exact(3)
+        .zip(reference.chunks_exact(3))
+        .filter(|(a, b)| a != b)
+        .count()
+}
+
+/// Drops any row padding, leaving `SCREEN_WIDTH * 3` bytes per row.
+pub fn packed(framebuffer: &[u8], pitch: usize) -> Vec<u8> {
+    let row = SCREEN_WIDTH as usize * 3;
+    framebuffer
+        .chunks(pitch)
+        .take(SCREEN_HEIGHT as usize)
+        .flat_map(|line| &line[..row])
+        .copied()
+        .collect()
+}
@@ -0,0 +1,322 @@
+use rustris_core::GB;
+use crate::assertions::{Assertion, Assertions};
+use crate::options::parse_button;
+use crate::osd;
+use crate::tetris::TetrisState;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+
+/// Upper bound on the work a single hook may do, so a runaway script can't hang emulation.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Default OSD text color.
+const TEXT_COLOR: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+/// User script (rhai) driven by the emulation loop.
+///
+/// Hooks (all optional) are plain script functions:
+/// - `on_frame()`: at the start of every emulated frame, before the game runs.
+/// - `on_vblank()`: when the frame is complete and VBlank begins, before it's presented.
+/// - `on_write(addr, value)`: after the game writes to an address registered with `watch`.
+///
+/// Hooks share a persistent object map as `this` (e.g. `this.count += 1`), since rhai
+/// functions can't see the script's top-level variables. API available to the script:
+/// - `read(addr)`, `read16(addr)`, `write(addr, value)`: memory bus access.
+/// - `press(button)`, `release(button)`: hold/let go of a button, named as for `--turbo`
+///   ("a", "start", ...). Script buttons are combined with the player's.
+/// - `text(x, y, msg)`, `text(x, y, msg, 0xRRGGBB)`: OSD text on the current frame.
+/// - `watch(addr)`, `watch(start, end)`: deliver writes in that range to `on_write`.
+/// - `invariant(text)`: an assertion such as `"C0DE <= 14"` (see `Assertion`), checked
+///   after every instruction from then on; breaking it pauses emulation like `pause`.
+/// - `pause(msg)`: stop in the debugger once the hook returns, showing `msg`.
+/// - `frame_count()`: frames emulated since the script was loaded.
+/// - `tetris()`: Tetris's state (see `TetrisState`) as a map: `board` (18 rows of 10
+///   booleans, top row first), `active` (`#{piece: "T", cells: [[column, row], ...]}` or
+///   `()`), `next` (`"S"` or `()`), `score`, `level` and `lines`.
+///
+/// A runtime error is reported once and disables the script; emulation keeps going.
+/// The script's buttons and OSD text reach the front-end through `ScriptOutput`.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    this: Dynamic,
+    host: Arc<Mutex<Host>>,
+    invariants: Assertions,
+    pause: Option<String>, // Why the script wants emulation stopped, until taken
+    on_frame: bool,
+    on_vblank: bool,
+    on_write: bool,
+    failed: bool,
+}
+
+/// Handle to what the script feeds back into the front-end: its held buttons (for the
+/// input source) and its OSD text (for the video sink).
+#[derive(Clone)]
+pub struct ScriptOutput(Arc<Mutex<Host>>);
+
+impl ScriptOutput {
+    /// Buttons the script currently holds.
+    pub fn buttons(&self) -> u8 {
+        lock(&self.0).buttons
+    }
+
+    /// Draws (and clears) the OSD text queued for the current frame.
+    pub fn draw(&self, framebuffer: &mut [u8], pitch: usize) {
+        for (x, y, text, color) in lock(&self.0).text.drain(..) {
+            osd::draw_text(framebuffer, pitch, x, y, &text, color);
+        }
+    }
+}
+
+/// State shared between the script API functions and the emulation loop. Effects are
+/// buffered here during a hook and applied to the machine once it returns.
+struct Host {
+    memory: Vec<u8>,                       // Bus snapshot taken right before the hook
+    writes: Vec<(u16, u8)>,                // Pending bus writes
+    buttons: u8,                           // Buttons held by the script
+    text: Vec<(i32, i32, String, [u8; 3])>, // OSD text for the current frame
+    watches: Vec<(u16, u16)>,              // Ranges not yet handed to the core
+    invariants: Vec<Assertion>,            // Declared by the script, not yet checked
+    pause: Option<String>,                 // Asked for by the running hook
+    frame: u64,
+}
+
+impl Script {
+    /// Compiles the script at `path` and runs its top-level statements.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let host = Arc::new(Mutex::new(Host {
+            memory: vec![0; 0x10000],
+            writes: Vec::new(),
+            buttons: 0,
+            text: Vec::new(),
+            watches: Vec::new(),
+            invariants: Vec::new(),
+            pause: None,
+            frame: 0,
+        }));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine, &host);
+
+        let ast = engine.compile_file(path.into()).map_err(|e| e.to_string())?;
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(|e| e.to_string())?;
+        // What the top-level statements declared
+        let mut invariants = Assertions::default();
+        for invariant in lock(&host).invariants.drain(..) {
+            invariants.add(invariant);
+        }
+        let pause = lock(&host).pause.take();
+
+        let has_hook = |name: &str, arity: usize| {
+            ast.iter_functions().any(|f| f.name == name && f.params.len() == arity)
+        };
+        let (on_frame, on_vblank, on_write) = (has_hook("on_frame", 0), has_hook("on_vblank", 0), has_hook("on_write", 2));
+
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            this: Dynamic::from_map(Map::new()),
+            host,
+            invariants,
+            pause,
+            on_frame,
+            on_vblank,
+            on_write,
+            failed: false,
+        })
+    }
+
+    /// Call at the start of each frame: runs `on_frame`.
+    pub fn frame_start(&mut self, gb: &mut GB) {
+        let mut host = lock(&self.host);
+        host.frame += 1;
+        if self.on_write {
+            for (start, end) in host.watches.drain(..) {
+                gb.watch_writes(start, end);
+            }
+        }
+        drop(host);
+
+        if self.on_frame {
+            self.call(gb, "on_frame", ());
+        }
+    }
+
+    /// Call after every instruction (see `GB::run_frame_with`): delivers watched writes
+    /// to `on_write`, runs `on_vblank` once the frame is done and checks the invariants.
+    pub fn after_step(&mut self, gb: &mut GB, frame_done: bool) {
+        if self.on_write {
+            for (addr, value) in gb.take_watched_writes() {
+                self.call(gb, "on_write", (addr as i64, value as i64));
+            }
+        }
+        if frame_done && self.on_vblank {
+            self.call(gb, "on_vblank", ());
+        }
+        if let Some(failed) = self.invariants.after_step(gb) {
+            self.pause.get_or_insert(failed);
+        }
+    }
+
+    /// Why the script wants emulation stopped (`pause`, a broken invariant), if it does
+    /// since the last call.
+    pub fn take_pause(&mut self) -> Option<String> {
+        self.pause.take()
+    }
+
+    /// Handle the front-end uses to pick up the script's buttons and OSD text.
+    pub fn output(&self) -> ScriptOutput {
+        ScriptOutput(self.host.clone())
+    }
+
+    /// Runs one hook against a fresh memory snapshot, then applies its buffered effects.
+    fn call(&mut self, gb: &mut GB, name: &str, args: impl FuncArgs) {
+        if self.failed {
+            return;
+        }
+        gb.read_memory(&mut lock(&self.host).memory);
+
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.this);
+        if let Err(e) = self.engine.call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, args) {
+            eprintln!("Script error in {}: {} (script disabled)", name, e);
+            self.failed = true;
+        }
+
+        let mut host = lock(&self.host);
+        for (addr, value) in host.writes.drain(..) {
+            gb.write_byte(addr, value);
+        }
+        for invariant in host.invariants.drain(..) {
+            self.invariants.add(invariant);
+        }
+        if let Some(message) = host.pause.take() {
+            self.pause.get_or_insert(message);
+        }
+        drop(host);
+        // The script's own writes must not re-trigger `on_write`
+        if self.on_write {
+            gb.take_watched_writes();
+        }
+    }
+}
+
+fn lock(host: &Mutex<Host>) -> MutexGuard<'_, Host> {
+    host.lock().unwrap()
+}
+
+fn address(addr: i64) -> Result<u16, Box<EvalAltResult>> {
+    u16::try_from(addr).map_err(|_| format!("address out of range: {}", addr).into())
+}
+
+fn button(name: &str) -> Result<u8, Box<EvalAltResult>> {
+    parse_button(name).ok_or_else(|| format!("unknown button: {}", name).into())
+}
+
+/// Registers the script API; every function works on the shared `Host`.
+fn register_api(engine: &mut Engine, host: &Arc<Mutex<Host>>) {
+    let h = host.clone();
+    engine.register_fn("read", move |addr: i64| -> Result<i64, Box<EvalAltResult>> {
+        Ok(lock(&h).memory[address(addr)? as usize] as i64)
+    });
+    let h = host.clone();
+    engine.register_fn("read16", move |addr: i64| -> Result<i64, Box<EvalAltResult>> {
+        let host = lock(&h);
+        let lo = host.memory[address(addr)? as usize];
+        let hi = host.memory[address(addr + 1)? as usize];
+        Ok(u16::from_le_bytes([lo, hi]) as i64)
+    });
+    let h = host.clone();
+    engine.register_fn("write", move |addr: i64, value: i64| -> Result<(), Box<EvalAltResult>> {
+        let addr = address(addr)?;
+        let mut host = lock(&h);
+        host.memory[addr as usize] = value as u8; // Later reads in this hook see the write
+        host.writes.push((addr, value as u8));
+        Ok(())
+    });
+
+    let h = host.clone();
+    engine.register_fn("press", move |name: &str| -> Result<(), Box<EvalAltResult>> {
+        lock(&h).buttons |= button(name)?;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("release", move |name: &str| -> Result<(), Box<EvalAltResult>> {
+        lock(&h).buttons &= !button(name)?;
+        Ok(())
+    });
+
+    let h = host.clone();
+    engine.register_fn("text", move |x: i64, y: i64, msg: &str| {
+        lock(&h).text.push((x as i32, y as i32, msg.to_string(), TEXT_COLOR));
+    });
+    let h = host.clone();
+    engine.register_fn("text", move |x: i64, y: i64, msg: &str, color: i64| {
+        let [_, r, g, b] = (color as u32).to_be_bytes();
+        lock(&h).text.push((x as i32, y as i32, msg.to_string(), [r, g, b]));
+    });
+
+    let h = host.clone();
+    engine.register_fn("watch", move |addr: i64| -> Result<(), Box<EvalAltResult>> {
+        let addr = address(addr)?;
+        lock(&h).watches.push((addr, addr));
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("watch", move |start: i64, end: i64| -> Result<(), Box<EvalAltResult>> {
+        let (start, end) = (address(start)?, address(end)?);
+        lock(&h).watches.push((start, end));
+        Ok(())
+    });
+
+    let h = host.clone();
+    engine.register_fn("invariant", move |text: &str| -> Result<(), Box<EvalAltResult>> {
+        lock(&h).invariants.push(Assertion::parse(text)?);
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("pause", move |msg: &str| {
+        lock(&h).pause.get_or_insert_with(|| msg.to_string());
+    });
+
+    let h = host.clone();
+    engine.register_fn("frame_count", move || lock(&h).frame as i64);
+
+    let h = host.clone();
+    engine.register_fn("tetris", move || tetris_map(&TetrisState::decode(&lock(&h).memory)));
+}
+
+/// `TetrisState` as the script sees it (see `Script`).
+fn tetris_map(state: &TetrisState) -> Map {
+    let board: Array = state
+        .board
+        .0
+        .iter()
+        .map(|row| Dynamic::from_array(row.iter().map(|&filled| Dynamic::from_bool(filled)).collect()))
+        .collect();
+    let active = state.active.map_or(Dynamic::UNIT, |active| {
+        let cells: Array = active
+            .cells
+            .iter()
+            .map(|&(c, r)| Dynamic::from_array(vec![Dynamic::from_int(c as i64), Dynamic::from_int(r as i64)]))
+            .collect();
+        let mut piece = Map::new();
+        piece.insert("piece".into(), active.piece.name().into());
+        piece.insert("cells".into(), cells.into());
+        piece.into()
+    });
+
+    let mut map = Map::new();
+    map.insert("board".into(), board.into());
+    map.insert("active".into(), active);
+    map.insert("next".into(), state.next.map_or(Dynamic::UNIT, |piece| piece.name().into()));
+    map.insert("score".into(), Dynamic::from_int(state.score as i64));
+    map.insert("level".into(), Dynamic::from_int(state.level as i64));
+    map.insert("lines".into(), Dynamic::from_int(state.lines as i64));
+    map
+}
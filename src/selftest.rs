@@ -0,0 +1,333 @@
+use crate::headless::PITCH;
+
+use rustris_core::{Cartridge, Registers, GB, SCREEN_HEIGHT};
+
+/// Registers as the vectors give them: AF, BC, DE, HL, SP.
+type Regs = [u16; 5];
+
+/// Where the vectors' code runs (work RAM), and the stack pointer most start with.
+const CODE: u16 = 0xC000;
+const SP: u16 = 0xDFF0;
+
+/// Instructions a vector may take, so a broken jump can't run away.
+const MAX_STEPS: usize = 8;
+
+/// Frames the test ROM gets to report, and frames shown after it has, for the picture.
+const ROM_FRAMES: u32 = 60;
+const SETTLE_FRAMES: u32 = 3;
+
+/// An instruction test: `code`, run from `CODE` with the registers `before`, must leave
+/// them `after`.
+struct Vector {
+    name: &'static str,
+    code: &'static [u8],
+    before: Regs,
+    after: Regs,
+}
+
+const fn v(name: &'static str, code: &'static [u8], before: Regs, after: Regs) -> Vector {
+    Vector { name, code, before, after }
+}
+
+/// Instruction and flag vectors, the expected results worked out from the documented
+/// behavior rather than taken from this core: the flag corner cases (half carries, borrows,
+/// Z after rotates, DAA, the 16-bit adds, F's low nibble) that a bad build or an
+/// optimizer getting them wrong would show first.
+const VECTORS: &[Vector] = &[
+    v("ADD A,B: half carry", &[0x80], [0x0F00, 0x0100, 0, 0, SP], [0x1020, 0x0100, 0, 0, SP]),
+    v("ADD A,B: carry to zero", &[0x80], [0x3A00, 0xC600, 0, 0, SP], [0x00B0, 0xC600, 0, 0, SP]),
+    v("ADC A,B: carry in", &[0x88], [0x0F10, 0, 0, 0, SP], [0x1020, 0, 0, 0, SP]),
+    v("ADC A,B: carry out", &[0x88], [0xE110, 0x1E00, 0, 0, SP], [0x00B0, 0x1E00, 0, 0, SP]),
+    v("SUB B: zero", &[0x90], [0x3E00, 0x3E00, 0, 0, SP], [0x00C0, 0x3E00, 0, 0, SP]),
+    v("SUB B: half borrow", &[0x90], [0x3E00, 0x0F00, 0, 0, SP], [0x2F60, 0x0F00, 0, 0, SP]),
+    v("SUB B: borrow", &[0x90], [0x3E00, 0x4000, 0, 0, SP], [0xFE50, 0x4000, 0, 0, SP]),
+    v("SBC A,B: borrow in", &[0x98], [0x1010, 0x0F00, 0, 0, SP], [0x00E0, 0x0F00, 0, 0, SP]),
+    v("SBC A,B: borrow out", &[0x98], [0x3B10, 0x4F00, 0, 0, SP], [0xEB70, 0x4F00, 0, 0, SP]),
+    v("AND B", &[0xA0], [0x5A50, 0x3F00, 0, 0, SP], [0x1A20, 0x3F00, 0, 0, SP]),
+    v("AND B: zero", &[0xA0], [0x5A50, 0xA500, 0, 0, SP], [0x00A0, 0xA500, 0, 0, SP]),
+    v("XOR B", &[0xA8], [0x5A50, 0x3F00, 0, 0, SP], [0x6500, 0x3F00, 0, 0, SP]),
+    v("XOR B: zero", &[0xA8], [0x5A50, 0x5A00, 0, 0, SP], [0x0080, 0x5A00, 0, 0, SP]),
+    v("OR B", &[0xB0], [0x5A50, 0x2100, 0, 0, SP], [0x7B00, 0x2100, 0, 0, SP]),
+    v("CP B: equal", &[0xB8], [0x3C00, 0x3C00, 0, 0, SP], [0x3CC0, 0x3C00, 0, 0, SP]),
+    v("CP B: less", &[0xB8], [0x3C00, 0x4000, 0, 0, SP], [0x3C50, 0x4000, 0, 0, SP]),
+    v("INC B: half carry", &[0x04], [0x0010, 0x0F00, 0, 0, SP], [0x0030, 0x1000, 0, 0, SP]),
+    v("INC B: wraps to zero", &[0x04], [0x0010, 0xFF00, 0, 0, SP], [0x00B0, 0, 0, 0, SP]),
+    v("DEC B: half borrow", &[0x05], [0x0010, 0x1000, 0, 0, SP], [0x0070, 0x0F00, 0, 0, SP]),
+    v("DEC B: zero", &[0x05], [0x0010, 0x0100, 0, 0, SP], [0x00D0, 0, 0, 0, SP]),
+    v("DAA after ADD", &[0x80, 0x27], [0x1500, 0x2700, 0, 0, SP], [0x4200, 0x2700, 0, 0, SP]),
+    v("DAA after ADD: carry", &[0x80, 0x27], [0x9900, 0x0100, 0, 0, SP], [0x0090, 0x0100, 0, 0, SP]),
+    v("DAA after SUB", &[0x90, 0x27], [0x4200, 0x1500, 0, 0, SP], [0x2740, 0x1500, 0, 0, SP]),
+    v("CPL", &[0x2F], [0x3590, 0, 0, 0, SP], [0xCAF0, 0, 0, 0, SP]),
+    v("SCF", &[0x37], [0x00E0, 0, 0, 0, SP], [0x0090, 0, 0, 0, SP]),
+    v("CCF", &[0x3F], [0x0070, 0, 0, 0, SP], [0, 0, 0, 0, SP]),
+    v("RLCA", &[0x07], [0x8580, 0, 0, 0, SP], [0x0B10, 0, 0, 0, SP]),
+    v("RRCA", &[0x0F], [0x0180, 0, 0, 0, SP], [0x8010, 0, 0, 0, SP]),
+    v("RLA: Z stays clear", &[0x17], [0x8080, 0, 0, 0, SP], [0x0010, 0, 0, 0, SP]),
+    v("RRA", &[0x1F], [0x0190, 0, 0, 0, SP], [0x8010, 0, 0, 0, SP]),
+    v("RLC B", &[0xCB, 0x00], [0, 0x8500, 0, 0, SP], [0x0010, 0x0B00, 0, 0, SP]),
+    v("RRC B", &[0xCB, 0x08], [0x0010, 0, 0, 0, SP], [0x0080, 0, 0, 0, SP]),
+    v("RL B: zero", &[0xCB, 0x10], [0, 0x8000, 0, 0, SP], [0x0090, 0, 0, 0, SP]),
+    v("RR B", &[0xCB, 0x18], [0x0010, 0x0100, 0, 0, SP], [0x0010, 0x8000, 0, 0, SP]),
+    v("SLA B", &[0xCB, 0x20], [0, 0xC100, 0, 0, SP], [0x0010, 0x8200, 0, 0, SP]),
+    v("SRA B", &[0xCB, 0x28], [0, 0x8A00, 0, 0, SP], [0, 0xC500, 0, 0, SP]),
+    v("SWAP B", &[0xCB, 0x30], [0x0010, 0xF100, 0, 0, SP], [0, 0x1F00, 0, 0, SP]),
+    v("SRL B", &[0xCB, 0x38], [0, 0x0100, 0, 0, SP], [0x0090, 0, 0, 0, SP]),
+    v("BIT 7,B: set", &[0xCB, 0x78], [0x0050, 0x8000, 0, 0, SP], [0x0030, 0x8000, 0, 0, SP]),
+    v("BIT 7,B: clear", &[0xCB, 0x78], [0, 0x7F00, 0, 0, SP], [0x00A0, 0x7F00, 0, 0, SP]),
+    v("RES 0,B", &[0xCB, 0x80], [0, 0xFF00, 0, 0, SP], [0, 0xFE00, 0, 0, SP]),
+    v("SET 7,B", &[0xCB, 0xF8], [0, 0x0100, 0, 0, SP], [0, 0x8100, 0, 0, SP]),
+    v("ADD HL,BC: half carry", &[0x09], [0x00C0, 0x0001, 0, 0x0FFF, SP], [0x00A0, 0x0001, 0, 0x1000, SP]),
+    v("ADD HL,BC: carry", &[0x09], [0, 0x8000, 0, 0x8000, SP], [0x0010, 0x8000, 0, 0, SP]),
+    v("INC BC: no flags", &[0x03], [0, 0xFFFF, 0, 0, SP], [0, 0, 0, 0, SP]),
+    v("DEC DE: no flags", &[0x1B], [0x0080, 0, 0, 0, SP], [0x0080, 0, 0xFFFF, 0, SP]),
+    v("ADD SP,e: carries", &[0xE8, 0x08], [0x00C0, 0, 0, 0, 0xDFF8], [0x0030, 0, 0, 0, 0xE000]),
+    v("ADD SP,e: negative", &[0xE8, 0xFF], [0x00C0, 0, 0, 0, SP], [0x0010, 0, 0, 0, 0xDFEF]),
+    v("LD HL,SP+e", &[0xF8, 0x01], [0x00C0, 0, 0, 0, 0xDFFF], [0x0030, 0, 0, 0xE000, 0xDFFF]),
+    v("POP AF: low nibble", &[0xC5, 0xF1], [0, 0x12FF, 0, 0, SP], [0x12F0, 0x12FF, 0, 0, SP]),
+    v("PUSH/POP: stack order", &[0xD5, 0xC1], [0, 0, 0xBEEF, 0, SP], [0, 0xBEEF, 0xBEEF, 0, SP]),
+    v("RST 38H: calls 0038", &[0xFF], [0, 0, 0, 0, SP], [0, 0x3800, 0, 0, SP]),
+];
+
+/// Restart handler of the test ROM, at 0038, for the RST 38H vector: `LD B,$38; RET`.
+const ROM_RST_38: [u8; 3] = [0x06, 0x38, 0xC9];
+
+/// Timer interrupt handler of the test ROM, at 0050: `LD C,1; RETI`.
+const ROM_TIMER_HANDLER: [u8; 3] = [0x0E, 0x01, 0xD9];
+
+/// The test ROM's program, at 0150 (the header jumps there). It runs six tests, keeping the
+/// number of the one under way in E, then prints `Passed` over the link port, or `Failed`
+/// and that number. The last test leaves the screen showing tile 0 everywhere, whose rows
+/// are colors 0–3 through an identity BGP, which `run` checks in the picture. Assembled by
+/// hand: jumps and calls are to the addresses in the comments.
+const ROM_PROGRAM: &[u8] = &[
+    0xF3,              // DI
+    0x31, 0xFE, 0xFF,  // LD SP,$FFFE
+    // Test 1: CALL and RET
+    0x1E, 0x01,        // LD E,1
+    0x3E, 0x12,        // LD A,$12
+    0xCD, 0xE9, 0x01,  // CALL inc_a
+    0xFE, 0x13,        // CP $13
+    0x20, 0x6E,        // JR NZ,fail
+    // Test 2: loads and stores through HL
+    0x1E, 0x02,        // LD E,2
+    0x21, 0x00, 0xC0,  // LD HL,$C000
+    0x22,              // LD (HL+),A
+    0x36, 0x5A,        // LD (HL),$5A
+    0x2B,              // DEC HL
+    0x2A,              // LD A,(HL+)
+    0x86,              // ADD A,(HL)
+    0xFE, 0x6D,        // CP $6D
+    0x20, 0x5F,        // JR NZ,fail
+    // Test 3: the stack
+    0x1E, 0x03,        // LD E,3
+    0x01, 0x34, 0x12,  // LD BC,$1234
+    0xC5,              // PUSH BC
+    0xE1,              // POP HL
+    0x7C,              // LD A,H
+    0xAD,              // XOR L
+    0xFE, 0x26,        // CP $26
+    0x20, 0x52,        // JR NZ,fail
+    // Test 4: a counted loop adding 10 down to 1
+    0x1E, 0x04,        // LD E,4
+    0x06, 0x0A,        // LD B,10
+    0xAF,              // XOR A
+    // sum (0180):
+    0x80,              // ADD A,B
+    0x05,              // DEC B
+    0x20, 0xFC,        // JR NZ,sum
+    0xFE, 0x37,        // CP 55
+    0x20, 0x45,        // JR NZ,fail
+    // Test 5: the timer interrupt ends HALT, its handler sets C
+    0x1E, 0x05,        // LD E,5
+    0x0E, 0x00,        // LD C,0
+    0x3E, 0xF0,        // LD A,$F0
+    0xE0, 0x05,        // LDH (TIMA),A
+    0x3E, 0x05,        // LD A,$05
+    0xE0, 0x07,        // LDH (TAC),A
+    0x3E, 0x04,        // LD A,$04
+    0xE0, 0xFF,        // LDH (IE),A
+    0xAF,              // XOR A
+    0xE0, 0x0F,        // LDH (IF),A
+    0xFB,              // EI
+    0x76,              // HALT
+    0x00,              // NOP
+    0xF3,              // DI
+    0x79,              // LD A,C
+    0xFE, 0x01,        // CP 1
+    0x20, 0x29,        // JR NZ,fail
+    // Test 6: LY reaches VBlank; the LCD goes off to load tile 0
+    0x1E, 0x06,        // LD E,6
+    // vblank (01A6):
+    0xF0, 0x44,        // LDH A,(LY)
+    0xFE, 0x90,        // CP 144
+    0x20, 0xFA,        // JR NZ,vblank
+    0xAF,              // XOR A
+    0xE0, 0x40,        // LDH (LCDC),A
+    0x21, 0x00, 0x80,  // LD HL,$8000
+    0x01, 0xEB, 0x01,  // LD BC,tile
+    0x16, 0x10,        // LD D,16
+    // copy (01B7):
+    0x0A,              // LD A,(BC)
+    0x22,              // LD (HL+),A
+    0x03,              // INC BC
+    0x15,              // DEC D
+    0x20, 0xFA,        // JR NZ,copy
+    0x3E, 0xE4,        // LD A,$E4
+    0xE0, 0x47,        // LDH (BGP),A
+    0x3E, 0x91,        // LD A,$91
+    0xE0, 0x40,        // LDH (LCDC),A
+    0x21, 0xFB, 0x01,  // LD HL,passed
+    0xCD, 0xDE, 0x01,  // CALL print
+    // done (01CB):
+    0x18, 0xFE,        // JR done
+    // fail (01CD):
+    0x21, 0x03, 0x02,  // LD HL,failed
+    0xCD, 0xDE, 0x01,  // CALL print
+    0x7B,              // LD A,E
+    0xC6, 0x30,        // ADD A,'0'
+    0xE0, 0x01,        // LDH (SB),A
+    0x3E, 0x81,        // LD A,$81
+    0xE0, 0x02,        // LDH (SC),A
+    0x18, 0xED,        // JR done
+    // print (01DE):
+    0x2A,              // LD A,(HL+)
+    0xA7,              // AND A
+    0xC8,              // RET Z
+    0xE0, 0x01,        // LDH (SB),A
+    0x3E, 0x81,        // LD A,$81
+    0xE0, 0x02,        // LDH (SC),A
+    0x18, 0xF5,        // JR print
+    // inc_a (01E9):
+    0x3C,              // INC A
+    0xC9,              // RET
+    // tile (01EB): rows of colors 0, 1, 2, 3, twice
+    0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF,
+    // passed (01FB): "Passed\n", NUL-terminated
+    0x50, 0x61, 0x73, 0x73, 0x65, 0x64, 0x0A, 0x00,
+    // failed (0203): "Failed ", NUL-terminated
+    0x46, 0x61, 0x69, 0x6C, 0x65, 0x64, 0x20, 0x00,
+];
+
+/// Self-test (`--selftest`), to check a build before reporting emulation bugs: runs the
+/// instruction vectors (`VECTORS`), then a tiny test ROM built in (`ROM_PROGRAM`) for
+/// calls, the stack, memory, loops, the timer interrupt and HALT, LY, and the picture
+/// drawn from VRAM. Each failure is printed, then a line per part.
+///
+/// Returns the process exit status: 0 passed, 1 failed.
+pub fn run() -> i32 {
+    let mut passed = true;
+
+    let failures = run_vectors();
+    for failure in &failures {
+        println!("FAIL {}", failure);
+    }
+    println!("CPU vectors: {}/{} passed", VECTORS.len() - failures.len(), VECTORS.len());
+    passed &= failures.is_empty();
+
+    match run_rom() {
+        Ok(()) => println!("Test ROM: passed"),
+        Err(e) => {
+            println!("Test ROM: {}", e);
+            passed = false;
+        }
+    }
+
+    println!("Self-test {}", if passed { "passed" } else { "FAILED" });
+    if passed { 0 } else { 1 }
+}
+
+/// Runs every vector on one machine; describes those that fail.
+fn run_vectors() -> Vec<String> {
+    let mut gb = match GB::new(Cartridge::from_bytes(test_rom())) {
+        Ok(gb) => gb,
+        Err(e) => return vec![format!("can't build the machine: {}", e)],
+    };
+    let mut framebuffer = vec![0u8; PITCH * SCREEN_HEIGHT as usize];
+    let mut failures = Vec::new();
+    for vector in VECTORS {
+        for (addr, &byte) in (CODE..).zip(vector.code) {
+            gb.write_byte(addr, byte);
+        }
+        gb.set_registers(&registers(vector.before));
+        let end = CODE + vector.code.len() as u16;
+        for _ in 0..MAX_STEPS {
+            gb.step(&mut framebuffer, PITCH);
+            if gb.registers().pc == end {
+                break;
+            }
+        }
+        let r = gb.registers();
+        let after = [word(r.a, r.f), word(r.b, r.c), word(r.d, r.e), word(r.h, r.l), r.sp];
+        if r.pc != end {
+            failures.push(format!("{}: stopped at {:04X} instead of {:04X}", vector.name, r.pc, end));
+        } else if after != vector.after {
+            failures.push(format!("{}: expected {}, got {}", vector.name, show(vector.after), show(after)));
+        }
+    }
+    failures
+}
+
+/// Runs the test ROM until it reports, then checks the picture it leaves.
+fn run_rom() -> Result<(), String> {
+    let mut gb = GB::new(Cartridge::from_bytes(test_rom())).map_err(|e| format!("can't build the machine: {}", e))?;
+    let mut framebuffer = vec![0u8; PITCH * SCREEN_HEIGHT as usize];
+    let mut output = String::new();
+    let mut frames = 0;
+    while !output.contains("Passed") {
+        if output.contains("Failed") && output.len() > "Failed ".len() {
+            return Err(format!("failed test {}", &output["Failed ".len()..]));
+        }
+        if frames == ROM_FRAMES {
+            return Err(String::from("timed out"));
+        }
+        while !gb.step(&mut framebuffer, PITCH) {}
+        gb.fault().map_err(|e| e.to_string())?;
+        output.push_str(&String::from_utf8_lossy(&gb.take_serial_output()));
+        frames += 1;
+    }
+
+    for _ in 0..SETTLE_FRAMES {
+        while !gb.step(&mut framebuffer, PITCH) {}
+    }
+    let colors = gb.palette().colors;
+    for y in 0..SCREEN_HEIGHT as usize {
+        let expected = colors[y % 4];
+        // The left column of tiles, clear of anything but tile 0
+        for x in 0..8 {
+            let i = y * PITCH + x * 3;
+            if framebuffer[i..i + 3] != expected {
+                return Err(format!("wrong picture: pixel ({}, {}) is {:02X?} instead of {:02X?}", x, y, &framebuffer[i..i + 3], expected));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The test ROM image: a 32 KiB cart without a mapper, with a header, the timer handler
+/// and the program.
+fn test_rom() -> Vec<u8> {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0038..0x003B].copy_from_slice(&ROM_RST_38);
+    rom[0x0050..0x0053].copy_from_slice(&ROM_TIMER_HANDLER);
+    rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]); // NOP; JP $0150
+    rom[0x0134..0x013C].copy_from_slice(b"SELFTEST");
+    rom[0x014D] = rom[0x0134..0x014D].iter().fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
+    rom[0x0150..0x0150 + ROM_PROGRAM.len()].copy_from_slice(ROM_PROGRAM);
+    rom
+}
+
+fn registers([af, bc, de, hl, sp]: Regs) -> Registers {
+    let [a, f] = af.to_be_bytes();
+    let [b, c] = bc.to_be_bytes();
+    let [d, e] = de.to_be_bytes();
+    let [h, l] = hl.to_be_bytes();
+    Registers { a, f, b, c, d, e, h, l, sp, pc: CODE }
+}
+
+fn word(high: u8, low: u8) -> u16 {
+    u16::from_be_bytes([high, low])
+}
+
+fn show([af, bc, de, hl, sp]: Regs) -> String {
+    format!("AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X}", af, bc, de, hl, sp)
+}
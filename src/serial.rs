@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Bytes of serial output the console keeps; older output is dropped.
+const CONSOLE_LEN: usize = 64 * 1024;
+
+/// The game's serial output as the debugger window's console shows it. Cloning gives
+/// another handle to the same text.
+#[derive(Clone, Default)]
+pub struct SerialConsole(Arc<Mutex<Vec<u8>>>);
+
+impl SerialConsole {
+    /// The output so far, as text (bytes that aren't UTF-8 show as �).
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    fn push(&self, bytes: &[u8]) {
+        let mut kept = self.0.lock().unwrap();
+        kept.extend_from_slice(bytes);
+        let excess = kept.len().saturating_sub(CONSOLE_LEN);
+        kept.drain(..excess);
+    }
+}
+
+/// Where the bytes a game sends over the link port go, the usual printf channel of test
+/// ROMs and homebrew:
+/// - The debugger window's console (see `SerialConsole`), for player 1's machine.
+/// - stdout with `--serial-stdout`, as they arrive.
+/// - The file given with `--serial-log`, appended to.
+#[derive(Default)]
+pub struct SerialOutput {
+    pub console: Option<SerialConsole>,
+    stdout: bool,
+    log: Option<File>,
+}
+
+impl SerialOutput {
+    pub fn new(stdout: bool, log: Option<File>) -> Self {
+        Self { console: None, stdout, log }
+    }
+
+    /// Passes on the bytes sent since the last call (see `GB::take_serial_output`). A log
+    /// file that can't be written to is dropped with a warning.
+    pub fn write(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if let Some(console) = &self.console {
+            console.push(bytes);
+        }
+        if self.stdout {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(bytes).and_then(|()| stdout.flush());
+        }
+        if let Some(log) = &mut self.log && let Err(e) = log.write_all(bytes) {
+            eprintln!("Warning: can't write the serial log: {}", e);
+            self.log = None;
+        }
+    }
+}
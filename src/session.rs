@@ -0,0 +1,197 @@
+use crate::config::config_dir;
+use crate::emu_thread::GB_FRAME_NS;
+use crate::tetris::{self, Spawns};
+
+use rustris_core::GB;
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First line of `sessions.csv`.
+const CSV_HEADER: &str = "started,game,seconds,pieces,lines,tetrises,tetris_rate,lines_per_minute,score,level";
+
+/// One game of Tetris, from its first piece to the last piece seen.
+struct Game {
+    started: u64,     // Seconds since the Unix epoch (UTC)
+    start_frame: u64, // `Session::frame` at the first piece
+    frames: u64,      // Frames from the first piece to the latest
+    pieces: u32,      // Pieces locked
+    lines: u32,
+    tetrises: u32,    // Four lines cleared by one piece
+    last_lines: u16,  // The game's line counter at the latest piece
+    score: u32,
+    level: u8,
+}
+
+impl Game {
+    fn seconds(&self) -> f64 {
+        self.frames as f64 * GB_FRAME_NS as f64 / 1e9
+    }
+
+    /// Share of the lines cleared four at a time, in percent.
+    fn tetris_rate(&self) -> f64 {
+        if self.lines == 0 { 0.0 } else { self.tetrises as f64 * 400.0 / self.lines as f64 }
+    }
+
+    fn lines_per_minute(&self) -> f64 {
+        let seconds = self.seconds();
+        if seconds == 0.0 { 0.0 } else { self.lines as f64 * 60.0 / seconds }
+    }
+}
+
+/// Tetris session statistics (`--session-stats`): every game played is tracked from the
+/// game's memory (see `Spawns` and `TetrisState`) and, when the machine stops, summed up on
+/// the console and appended to `sessions.csv` in the config directory, one row per game:
+/// - A game starts at its first piece (empty well, no lines) and its time runs to the
+///   last piece seen, so menus and the game-over screen don't count.
+/// - Lines are the game's own counter; a piece that adds four to it is a Tetris.
+/// - Time is emulated time, so fast-forwarding doesn't inflate lines per minute.
+/// - Going back in time (rewind, practice retries) keeps counting the pieces played again.
+pub struct Session {
+    title: String,
+    spawns: Spawns,
+    frame: u64, // Frames emulated
+    game: Option<Game>,
+    games: Vec<Game>,
+}
+
+impl Session {
+    /// The tracker for `gb`'s game; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self {
+            title: gb.game_title().to_string(),
+            spawns: Spawns::new(),
+            frame: 0,
+            game: None,
+            games: Vec::new(),
+        })
+    }
+
+    /// Call after every emulated frame: counts the piece and lines when a piece has
+    /// spawned (the previous one locked).
+    pub fn after_frame(&mut self, gb: &GB) {
+        self.frame += 1;
+        let Some(state) = self.spawns.check(gb) else { return };
+        if state.fresh_game() {
+            self.games.extend(self.game.take());
+            let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            self.game = Some(Game {
+                started,
+                start_frame: self.frame,
+                frames: 0,
+                pieces: 0,
+                lines: 0,
+                tetrises: 0,
+                last_lines: 0,
+                score: 0,
+                level: state.level,
+            });
+            return;
+        }
+        let Some(game) = &mut self.game else { return };
+        game.pieces += 1;
+        // The counter goes down when going back in time; the lines were counted already
+        let cleared = state.lines.saturating_sub(game.last_lines) as u32;
+        game.lines += cleared;
+        if cleared == 4 {
+            game.tetrises += 1;
+        }
+        game.last_lines = state.lines;
+        game.frames = self.frame - game.start_frame;
+        game.score = state.score;
+        game.level = state.level;
+    }
+
+    /// Call when the machine stops: prints the summary and appends the games to the CSV
+    /// file. Does nothing if no game was played.
+    pub fn finish(&mut self) {
+        self.games.extend(self.game.take());
+        if self.games.is_empty() {
+            return;
+        }
+        print!("{}", self.summary());
+        self.export();
+    }
+
+    fn summary(&self) -> String {
+        let mut out = format!("Session stats: {} game(s) of {}\n", self.games.len(), self.title);
+        for (i, game) in self.games.iter().enumerate() {
+            let seconds = game.seconds() as u64;
+            let _ = writeln!(
+                out,
+                "  #{:<3} {:>2}:{:02}  {:>4} pieces  {:>4} lines  {:>3} Tetrises ({:5.1}%)  {:5.1} lines/min  score {}",
+                i + 1,
+                seconds / 60,
+                seconds % 60,
+                game.pieces,
+                game.lines,
+                game.tetrises,
+                game.tetris_rate(),
+                game.lines_per_minute(),
+                game.score,
+            );
+        }
+        out
+    }
+
+    /// Appends a row per game to `sessions.csv`, writing the header first if the file is new.
+    fn export(&self) {
+        let Some(path) = config_dir().map(|dir| dir.join("sessions.csv")) else { return };
+        let mut rows = String::new();
+        if !path.exists() {
+            rows.push_str(CSV_HEADER);
+            rows.push('\n');
+        }
+        for game in &self.games {
+            let _ = writeln!(
+                rows,
+                "{},{},{:.1},{},{},{},{:.1},{:.1},{},{}",
+                utc_time(game.started),
+                csv_field(&self.title),
+                game.seconds(),
+                game.pieces,
+                game.lines,
+                game.tetrises,
+                game.tetris_rate(),
+                game.lines_per_minute(),
+                game.score,
+                game.level,
+            );
+        }
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| file.write_all(rows.as_bytes()));
+        match written {
+            Ok(()) => println!("Session stats appended to {}", path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// `text` as a CSV field, quoted if it needs to be.
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS` (UTC) of a Unix time, which spreadsheets read as a date.
+pub fn utc_time(seconds: u64) -> String {
+    // Days to a civil date, from Howard Hinnant's `civil_from_days`
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time = seconds % 86_400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
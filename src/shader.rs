@@ -0,0 +1,201 @@
+use crate::error::Error;
+use crate::reload::FileWatcher;
+use crate::video::Rotation;
+
+use glow::HasContext;
+use sdl2::VideoSubsystem;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+/// Vertex stage every shader is linked with: a quad over the picture, `TexCoord` running
+/// over the Game Boy screen (0,0 top left, 1,1 bottom right) whichever way it's rotated.
+const VERTEX_SHADER: &str = "#version 110
+attribute vec2 Position;
+attribute vec2 SourceCoord;
+varying vec2 TexCoord;
+
+void main() {
+    TexCoord = SourceCoord;
+    gl_Position = vec4(Position, 0.0, 1.0);
+}
+";
+
+/// Corners of the picture clockwise from the top left, in clip space, with the corner of
+/// the screen each shows unrotated.
+const CORNERS: [[f32; 2]; 4] = [[-1.0, 1.0], [1.0, 1.0], [1.0, -1.0], [-1.0, -1.0]];
+const SOURCE_CORNERS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+/// Attribute locations, bound before linking.
+const POSITION: u32 = 1;
+const SOURCE_COORD: u32 = 2;
+
+/// A user post-processing shader (`--shader`) the pictures are drawn through:
+/// - The file is a GLSL 1.10/1.20 fragment shader, so it runs on any OpenGL 2.1 driver.
+///   It can use the uniforms `Source` (the screen, a `sampler2D` sampled nearest),
+///   `SourceSize` (160×144), `OutputSize` (the picture on the window, in pixels) and
+///   `FrameCount` (an `int` counting presents), and the varying `TexCoord`.
+/// - The window's renderer must be SDL's OpenGL one, whose context the shader runs in: the
+///   rest of the window (HUD, menus, egui) is drawn by SDL as without a shader.
+/// - The file is watched (see `FileWatcher`) and recompiled when it changes. A version
+///   that doesn't compile is reported and the last one that did stays in use.
+pub struct Shader {
+    gl: glow::Context,
+    path: String,
+    watcher: FileWatcher,
+    program: glow::Program,
+    vertices: glow::Buffer,
+    frame: i32, // Presents drawn through it, for `FrameCount`
+}
+
+impl Shader {
+    /// Loads and compiles the shader at `path` for `canvas`'s renderer. Call before any
+    /// other window gets a renderer, while `canvas`'s context is still the current one.
+    pub fn new(video: &VideoSubsystem, canvas: &Canvas<Window>, path: &str) -> Result<Self, Error> {
+        if canvas.info().name != "opengl" {
+            return Err(Error::Shader(format!("--shader needs SDL's OpenGL renderer, the window got \"{}\"", canvas.info().name)));
+        }
+        // SAFETY: SDL's entry points, only called while the renderer's context is current
+        // (here, and in `draw` once SDL has made it so). Its objects go with the context
+        let gl = unsafe { glow::Context::from_loader_function(|name| video.gl_get_proc_address(name) as *const _) };
+        let program = compile(&gl, path)?;
+        let vertices = unsafe { gl.create_buffer() }.map_err(Error::Shader)?;
+        Ok(Self { gl, path: path.to_string(), watcher: FileWatcher::new(path), program, vertices, frame: 0 })
+    }
+
+    /// Counts a present, for `FrameCount`.
+    pub fn presented(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Draws `texture` through the shader where `Canvas::copy_ex` would put it (see
+    /// `video::dest_rect`), rotated by `rotation`, between what `canvas` has drawn so far
+    /// and what it draws next. Recompiles the shader first if its file has changed.
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, texture: &Texture, dest: Rect, rotation: Rotation) -> Result<(), Error> {
+        let (_, out_h) = canvas.output_size().map_err(Error::sdl)?;
+        let picture = if rotation.is_portrait() { Rect::from_center(dest.center(), dest.height(), dest.width()) } else { dest };
+        let query = texture.query();
+
+        // SAFETY: SDL has run everything queued before the shader draws, and binding the
+        // texture makes the renderer's context current; all the state touched that SDL
+        // keeps track of is put back before it draws again
+        unsafe {
+            let raw = texture.raw();
+            if sdl2::sys::SDL_RenderFlush(canvas.raw()) != 0 {
+                return Err(Error::sdl(sdl2::get_error()));
+            }
+            let (mut scale_x, mut scale_y) = (0.0, 0.0);
+            if sdl2::sys::SDL_GL_BindTexture(raw, &mut scale_x, &mut scale_y) != 0 {
+                return Err(Error::sdl(sdl2::get_error()));
+            }
+            if self.watcher.poll() {
+                self.reload();
+            }
+            let gl = &self.gl;
+            let program = gl.get_parameter_i32(glow::CURRENT_PROGRAM);
+            let buffer = gl.get_parameter_i32(glow::ARRAY_BUFFER_BINDING);
+            let mut viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+            let blend = gl.is_enabled(glow::BLEND);
+            let scissor = gl.is_enabled(glow::SCISSOR_TEST);
+
+            gl.disable(glow::BLEND);
+            gl.disable(glow::SCISSOR_TEST);
+            gl.viewport(picture.x(), out_h as i32 - picture.bottom(), picture.width() as i32, picture.height() as i32);
+            gl.use_program(Some(self.program));
+            let uniform = |name| gl.get_uniform_location(self.program, name);
+            gl.uniform_1_i32(uniform("Source").as_ref(), 0);
+            gl.uniform_2_f32(uniform("SourceSize").as_ref(), query.width as f32, query.height as f32);
+            gl.uniform_2_f32(uniform("OutputSize").as_ref(), picture.width() as f32, picture.height() as f32);
+            gl.uniform_1_i32(uniform("FrameCount").as_ref(), self.frame);
+
+            // Screen corner i shows the source corner a quarter turn back per 90°; drawn
+            // as a strip: top left, top right, bottom left, bottom right
+            let turns = rotation.degrees() as usize / 90;
+            let mut data = Vec::with_capacity(16);
+            for corner in [0, 1, 3, 2] {
+                let [s, t] = SOURCE_CORNERS[(corner + 4 - turns) % 4];
+                data.extend(CORNERS[corner]);
+                data.extend([s * scale_x, t * scale_y]);
+            }
+            let bytes: Vec<u8> = data.iter().flat_map(|v: &f32| v.to_ne_bytes()).collect();
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vertices));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &bytes, glow::STREAM_DRAW);
+            gl.vertex_attrib_pointer_f32(POSITION, 2, glow::FLOAT, false, 16, 0);
+            gl.vertex_attrib_pointer_f32(SOURCE_COORD, 2, glow::FLOAT, false, 16, 8);
+            gl.enable_vertex_attrib_array(POSITION);
+            gl.enable_vertex_attrib_array(SOURCE_COORD);
+            gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+            gl.disable_vertex_attrib_array(POSITION);
+            gl.disable_vertex_attrib_array(SOURCE_COORD);
+
+            gl.bind_buffer(glow::ARRAY_BUFFER, native(buffer).map(glow::NativeBuffer));
+            gl.use_program(native(program).map(glow::NativeProgram));
+            gl.viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            if blend {
+                gl.enable(glow::BLEND);
+            }
+            if scissor {
+                gl.enable(glow::SCISSOR_TEST);
+            }
+            sdl2::sys::SDL_GL_UnbindTexture(raw);
+        }
+        Ok(())
+    }
+
+    /// Swaps in the shader as its file now is, if it compiles. The context must be current.
+    fn reload(&mut self) {
+        match compile(&self.gl, &self.path) {
+            Ok(program) => {
+                unsafe { self.gl.delete_program(self.program) };
+                self.program = program;
+                println!("Shader: reloaded {}", self.path);
+            }
+            Err(e) => eprintln!("{} (keeping the previous version)", e),
+        }
+    }
+}
+
+/// An OpenGL object name as `glGetIntegerv` gives it, `None` for 0.
+fn native(name: i32) -> Option<std::num::NonZeroU32> {
+    std::num::NonZeroU32::new(name as u32)
+}
+
+/// Reads the fragment shader at `path` and links it with `VERTEX_SHADER`.
+fn compile(gl: &glow::Context, path: &str) -> Result<glow::Program, Error> {
+    let source = std::fs::read_to_string(path).map_err(|e| Error::Shader(format!("cannot read shader {}: {}", path, e)))?;
+    unsafe {
+        let program = gl.create_program().map_err(Error::Shader)?;
+        let mut shaders = Vec::new();
+        let mut failure = None;
+        for (kind, stage, source) in [(glow::VERTEX_SHADER, "vertex", VERTEX_SHADER), (glow::FRAGMENT_SHADER, "fragment", source.as_str())] {
+            let shader = gl.create_shader(kind).map_err(Error::Shader)?;
+            gl.shader_source(shader, source);
+            gl.compile_shader(shader);
+            if !gl.get_shader_compile_status(shader) && failure.is_none() {
+                failure = Some(format!("{} {} shader doesn't compile:\n{}", path, stage, gl.get_shader_info_log(shader).trim_end()));
+            }
+            gl.attach_shader(program, shader);
+            shaders.push(shader);
+        }
+        if failure.is_none() {
+            gl.bind_attrib_location(program, POSITION, "Position");
+            gl.bind_attrib_location(program, SOURCE_COORD, "SourceCoord");
+            gl.link_program(program);
+            if !gl.get_program_link_status(program) {
+                failure = Some(format!("{} doesn't link:\n{}", path, gl.get_program_info_log(program).trim_end()));
+            }
+        }
+        for shader in shaders {
+            gl.detach_shader(program, shader);
+            gl.delete_shader(shader);
+        }
+        match failure {
+            Some(e) => {
+                gl.delete_program(program);
+                Err(Error::Shader(e))
+            }
+            None => Ok(program),
+        }
+    }
+}
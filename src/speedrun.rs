@@ -0,0 +1,195 @@
+use crate::config::config_dir;
+use crate::emu_thread::GB_FRAME_NS;
+use crate::osd::{self, CHAR_HEIGHT, CHAR_WIDTH};
+use crate::session::{csv_field, utc_time};
+use crate::tetris::{self, Spawns, TetrisState};
+
+use rustris_core::{GB, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Frames without a new piece or a change of lines after which the run is over: longer
+/// than any line-clear animation, shorter than the game-over and congratulation screens.
+const STALL_FRAMES: u64 = 180;
+
+/// First line of `splits.csv`.
+const CSV_HEADER: &str = "started,game,split,time,segment";
+
+const TIMER_COLOR: [u8; 3] = [0x9B, 0xFF, 0x5A];
+
+/// When the timer splits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitOn {
+    Level,      // Each time the level goes up
+    Lines(u16), // Each time the line count passes a multiple of this
+}
+
+/// A run: one game from its first piece.
+struct Run {
+    started: u64,                // Seconds since the Unix epoch (UTC)
+    start_frame: u64,            // `Speedrun::frame` at the first piece
+    last_event: u64,             // Frame of the latest spawn or line change
+    splits: Vec<(String, u64)>,  // Label and frames since the start
+    level: u8,
+    lines: u16,
+    over: bool,
+}
+
+impl Run {
+    /// Frames from the start to the end of the run (its last event once it's over).
+    fn time(&self, frame: u64) -> u64 {
+        if self.over { self.last_event - self.start_frame } else { frame - self.start_frame }
+    }
+}
+
+/// Tetris speedrun timer (`--timer`), an LCD-style clock drawn in the picture's
+/// bottom-right corner, over the game's own panel:
+/// - It starts with the first piece of a game (empty well, no lines) and counts emulated
+///   time, so pauses, slowdowns and fast-forward don't change the result.
+/// - It splits when the level goes up or the line count reaches the next multiple of a
+///   goal (see `SplitOn`), read from RAM (see `TetrisState`); the latest split shows
+///   under the clock.
+/// - The run is over once no piece has come for `STALL_FRAMES` (a top-out or the game's
+///   end screen); the final time, when the last piece appeared or the last lines were
+///   made, stays on screen and the splits are printed and appended to `splits.csv` in the
+///   config directory. Quitting mid-run records the run so far.
+pub struct Speedrun {
+    title: String,
+    split_on: SplitOn,
+    spawns: Spawns,
+    memory: Vec<u8>, // Copy of the address space to decode
+    frame: u64,      // Frames emulated
+    run: Option<Run>,
+}
+
+impl Speedrun {
+    /// The timer for `gb`'s game; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB, split_on: SplitOn) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self {
+            title: gb.game_title().to_string(),
+            split_on,
+            spawns: Spawns::new(),
+            memory: vec![0; 0x10000],
+            frame: 0,
+            run: None,
+        })
+    }
+
+    /// Call after every emulated frame: starts, splits and ends runs.
+    pub fn after_frame(&mut self, gb: &GB) {
+        self.frame += 1;
+        gb.read_memory(&mut self.memory);
+        let state = TetrisState::decode(&self.memory);
+        let spawned = self.spawns.observe(&state);
+        if spawned && state.fresh_game() {
+            self.end_run();
+            let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            self.run = Some(Run {
+                started,
+                start_frame: self.frame,
+                last_event: self.frame,
+                splits: Vec::new(),
+                level: state.level,
+                lines: 0,
+                over: false,
+            });
+            return;
+        }
+
+        let frame = self.frame;
+        let Some(run) = self.run.as_mut().filter(|run| !run.over) else { return };
+        if spawned || state.lines != run.lines {
+            run.last_event = frame;
+        }
+        let split = match self.split_on {
+            SplitOn::Level if state.level > run.level => Some(format!("LEVEL {}", state.level)),
+            SplitOn::Lines(goal) if state.lines / goal > run.lines / goal => {
+                Some(format!("{} LINES", state.lines / goal * goal))
+            }
+            _ => None,
+        };
+        if let Some(label) = split {
+            run.splits.push((label, frame - run.start_frame));
+        }
+        run.level = state.level;
+        run.lines = state.lines;
+        if frame - run.last_event > STALL_FRAMES {
+            self.end_run();
+        }
+    }
+
+    /// Draws the clock and the latest split in the bottom-right corner of `framebuffer`.
+    pub fn draw(&self, framebuffer: &mut [u8], pitch: usize) {
+        let Some(run) = &self.run else { return };
+        let time = clock(run.time(self.frame));
+        let mut text = match run.splits.last() {
+            Some((label, frames)) => format!("{}\n{}\n", label, clock(*frames)),
+            None => String::new(),
+        };
+        text.push_str(if run.over { "FINAL\n" } else { "" });
+        text.push_str(&time);
+        let lines = text.lines().count() as i32;
+        let width = text.lines().map(str::len).max().unwrap_or(0) as i32 * CHAR_WIDTH;
+        let x = SCREEN_WIDTH as i32 - width - 2;
+        let y = SCREEN_HEIGHT as i32 - lines * CHAR_HEIGHT - 2;
+        osd::draw_text(framebuffer, pitch, x, y, &text, TIMER_COLOR);
+    }
+
+    /// Call when the machine stops: records the run in progress, if any.
+    pub fn finish(&mut self) {
+        self.end_run();
+    }
+
+    /// Ends the current run (if it isn't over already): prints its splits and appends
+    /// them to the CSV file.
+    fn end_run(&mut self) {
+        let Some(run) = self.run.as_mut().filter(|run| !run.over) else { return };
+        run.over = true;
+        let run = &*run;
+        let final_time = run.time(self.frame);
+
+        let mut report = format!("Speedrun: {} in {}\n", self.title, clock(final_time));
+        let mut rows = String::new();
+        let mut previous = 0;
+        let splits = run.splits.iter().map(|(label, frames)| (label.as_str(), *frames));
+        for (label, frames) in splits.chain([("FINAL", final_time)]) {
+            let _ = writeln!(report, "  {:<10} {:>9}  (+{})", label, clock(frames), clock(frames - previous));
+            let _ = writeln!(
+                rows,
+                "{},{},{},{:.2},{:.2}",
+                utc_time(run.started),
+                csv_field(&self.title),
+                label.to_ascii_lowercase(),
+                seconds(frames),
+                seconds(frames - previous),
+            );
+            previous = frames;
+        }
+        print!("{}", report);
+
+        let Some(path) = config_dir().map(|dir| dir.join("splits.csv")) else { return };
+        if !path.exists() {
+            rows.insert_str(0, &format!("{}\n", CSV_HEADER));
+        }
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| file.write_all(rows.as_bytes()));
+        if let Err(e) = written {
+            eprintln!("Error writing {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn seconds(frames: u64) -> f64 {
+    frames as f64 * GB_FRAME_NS as f64 / 1e9
+}
+
+/// `M:SS.CC` of a number of frames.
+fn clock(frames: u64) -> String {
+    let centis = (seconds(frames) * 100.0) as u64;
+    format!("{}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
@@ -0,0 +1,19 @@
+use rustris_core::{StackFaultKind, GB};
+
+use std::collections::HashSet;
+
+/// Stack check warnings (`--stack-check`): prints the stack faults the machine reports
+/// (see `GB::check_stack`) on stderr, each kind once per instruction, so a game that does
+/// it every frame doesn't flood the terminal.
+#[derive(Default)]
+pub struct StackWarnings {
+    reported: HashSet<(StackFaultKind, u16)>, // Kind and PC
+}
+
+impl StackWarnings {
+    pub fn after_frame(&mut self, gb: &mut GB) {
+        if let Some(fault) = gb.take_stack_fault() && self.reported.insert((fault.kind, fault.pc)) {
+            eprintln!("Warning: {}", fault);
+        }
+    }
+}
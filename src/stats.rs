@@ -0,0 +1,217 @@
+use crate::osd;
+
+use rustris_core::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Frames averaged by the live overlay before it refreshes (about one second).
+const OVERLAY_WINDOW: u64 = 60;
+
+/// Frames in the overlay's graph, one pixel column each, newest on the right.
+const GRAPH_FRAMES: usize = 100;
+/// Tallest bar of the graph in pixels, and the time one pixel stands for.
+const GRAPH_HEIGHT: i32 = 40;
+const GRAPH_SCALE: Duration = Duration::from_micros(500);
+
+/// The parts of each bar of the graph, bottom up: name and color.
+const GRAPH_PARTS: [(&str, [u8; 3]); 4] =
+    [("CPU", [255, 90, 90]), ("PPU", [90, 220, 90]), ("HOOK", [90, 150, 255]), ("IDLE", [110, 110, 110])];
+
+/// Where the time of one frame went on the emulation thread.
+#[derive(Clone, Copy, Default)]
+pub struct FrameTimes {
+    pub emulate: Duration,   // Running the core (plus script hooks and rewind)
+    pub core: Duration,      // Of which inside the core, per-instruction hooks included
+    pub ppu: Duration,       // Of which in the PPU, while the overlay shows (see `GB::time_ppu`)
+    pub sleep: Duration,     // Coarse `thread::sleep` in the limiter
+    pub spin: Duration,      // Busy-wait up to the deadline
+    pub overshoot: Duration, // How far past the deadline the limiter actually woke
+    pub missed: bool,        // The frame finished after its deadline
+}
+
+/// Frame-time statistics shared by the emulation thread (emulation, sleep, spin,
+/// deadlines) and the UI thread (present time).
+/// - Keeps totals since start for the exit summary, plus a rolling window of
+///   `OVERLAY_WINDOW` frames for the live overlay.
+/// - Cloning gives another handle to the same counters.
+#[derive(Clone, Default)]
+pub struct FrameStats(Arc<Mutex<Counters>>);
+
+#[derive(Default)]
+struct Counters {
+    total: Totals,
+    window: Totals, // Frames since the overlay last refreshed
+    shown: Totals,  // The last complete window, drawn by the overlay
+    recent: VecDeque<FrameTimes>, // The last `GRAPH_FRAMES` frames, drawn by the overlay's graph
+}
+
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    frames: u64,
+    missed: u64,
+    emulate: Stat,
+    cpu: Stat,
+    ppu: Stat,
+    hooks: Stat,
+    present: Stat,
+    sleep: Stat,
+    spin: Stat,
+    overshoot: Stat,
+}
+
+/// Running mean and maximum of one duration.
+#[derive(Clone, Copy, Default)]
+struct Stat {
+    count: u32,
+    sum: Duration,
+    max: Duration,
+}
+
+impl Stat {
+    fn add(&mut self, d: Duration) {
+        self.count += 1;
+        self.sum += d;
+        self.max = self.max.max(d);
+    }
+
+    fn mean_ms(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { ms(self.sum) / self.count as f64 }
+    }
+
+    fn max_ms(&self) -> f64 {
+        ms(self.max)
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl FrameTimes {
+    /// Time in the core outside the PPU: the CPU, timers, DMA and per-instruction hooks.
+    fn cpu(&self) -> Duration {
+        self.core.saturating_sub(self.ppu)
+    }
+
+    /// Time running outside the core: per-frame hooks, rewind snapshots and the like.
+    fn hooks(&self) -> Duration {
+        self.emulate.saturating_sub(self.core)
+    }
+
+    /// Time the limiter waited for the deadline.
+    fn idle(&self) -> Duration {
+        self.sleep + self.spin
+    }
+}
+
+impl Totals {
+    fn add_frame(&mut self, times: &FrameTimes) {
+        self.frames += 1;
+        self.emulate.add(times.emulate);
+        self.cpu.add(times.cpu());
+        self.ppu.add(times.ppu);
+        self.hooks.add(times.hooks());
+        if times.missed {
+            self.missed += 1;
+        } else {
+            self.sleep.add(times.sleep);
+            self.spin.add(times.spin);
+            self.overshoot.add(times.overshoot);
+        }
+    }
+}
+
+impl FrameStats {
+    /// Records one frame of the emulation thread.
+    pub fn record_frame(&self, times: FrameTimes) {
+        let mut counters = self.lock();
+        counters.total.add_frame(&times);
+        counters.window.add_frame(&times);
+        if counters.window.frames == OVERLAY_WINDOW {
+            counters.shown = std::mem::take(&mut counters.window);
+        }
+        if counters.recent.len() == GRAPH_FRAMES {
+            counters.recent.pop_front();
+        }
+        counters.recent.push_back(times);
+    }
+
+    /// Records how long the UI took to upload and present one frame.
+    pub fn record_present(&self, present: Duration) {
+        let mut counters = self.lock();
+        counters.total.present.add(present);
+        counters.window.present.add(present);
+    }
+
+    /// Draws the overlay: the last window's averages and maxima in the top-left corner, and
+    /// a graph of the last frames in the bottom-right one (see `draw_graph`).
+    pub fn draw(&self, framebuffer: &mut [u8], pitch: usize) {
+        let counters = self.lock();
+        let t = counters.shown;
+        let text = format!(
+            "EMU  {:5.2} MAX {:5.2}\nCPU {:5.2} PPU {:4.2} HOOK {:4.2}\nPRES {:5.2} MAX {:5.2}\nSLEEP {:5.2} SPIN {:4.2}\nLATE {:5.3} MAX {:5.3}\nMISSED {}/{}",
+            t.emulate.mean_ms(), t.emulate.max_ms(),
+            t.cpu.mean_ms(), t.ppu.mean_ms(), t.hooks.mean_ms(),
+            t.present.mean_ms(), t.present.max_ms(),
+            t.sleep.mean_ms(), t.spin.mean_ms(),
+            t.overshoot.mean_ms(), t.overshoot.max_ms(),
+            t.missed, t.frames,
+        );
+        osd::draw_text(framebuffer, pitch, 2, 2, &text, [255, 255, 0]);
+        draw_graph(framebuffer, pitch, &counters.recent);
+    }
+
+    /// Multi-line report of everything recorded since start, in milliseconds.
+    pub fn summary(&self) -> String {
+        let t = self.lock().total;
+        let mut out = format!(
+            "Frame stats: {} frames, {} missed deadlines ({:.2}%)\n",
+            t.frames,
+            t.missed,
+            if t.frames == 0 { 0.0 } else { t.missed as f64 * 100.0 / t.frames as f64 },
+        );
+        for (name, stat) in [
+            ("emulation", t.emulate),
+            ("  cpu", t.cpu),
+            ("  ppu", t.ppu),
+            ("  hooks", t.hooks),
+            ("present", t.present),
+            ("sleep", t.sleep),
+            ("spin", t.spin),
+            ("overshoot", t.overshoot),
+        ] {
+            out.push_str(&format!("  {:<10} avg {:7.3} ms  max {:7.3} ms\n", name, stat.mean_ms(), stat.max_ms()));
+        }
+        out
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Counters> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// Draws one stacked bar per frame, `GRAPH_SCALE` per pixel: CPU, PPU and hook time, then the
+/// limiter's idle time on top, so a frame that met its deadline reaches the height of the
+/// frame period and a late one shows no idle part. The legend sits above the bars, each
+/// name in its part's color.
+fn draw_graph(framebuffer: &mut [u8], pitch: usize, recent: &VecDeque<FrameTimes>) {
+    let right = SCREEN_WIDTH as i32 - 2;
+    let bottom = SCREEN_HEIGHT as i32 - 2;
+    let left = right - GRAPH_FRAMES as i32;
+    osd::fill_rect(framebuffer, pitch, left - 1, bottom - GRAPH_HEIGHT - 1, GRAPH_FRAMES as i32 + 2, GRAPH_HEIGHT + 2, [0, 0, 0]);
+    for (x, times) in (right - recent.len() as i32..).zip(recent) {
+        let mut y = bottom;
+        for (part, (_, color)) in [times.cpu(), times.ppu, times.hooks(), times.idle()].into_iter().zip(GRAPH_PARTS) {
+            let top = (y - (part.as_secs_f64() / GRAPH_SCALE.as_secs_f64()).round() as i32).max(bottom - GRAPH_HEIGHT);
+            osd::fill_rect(framebuffer, pitch, x, top, 1, y - top, color);
+            y = top;
+        }
+    }
+    let mut x = left;
+    for (name, color) in GRAPH_PARTS {
+        osd::draw_text(framebuffer, pitch, x, bottom - GRAPH_HEIGHT - 1 - osd::CHAR_HEIGHT, name, color);
+        x += (name.len() as i32 + 1) * osd::CHAR_WIDTH;
+    }
+}
@@ -0,0 +1,194 @@
+use crate::screenshot;
+use crate::websocket;
+
+use rustris_core::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use jpeg_encoder::{ColorType, Encoder, EncodingError, SamplingFactor};
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Frames waiting to be sent; newer ones are dropped while the queue is full, so slow
+/// viewers never hold up emulation.
+const QUEUE: usize = 2;
+
+/// Longest a viewer may take to send its request or accept a frame before it's dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// MJPEG frames are scaled up by this much, pixel for pixel, so JPEG's blocks don't blur
+/// the edges of the Game Boy's pixels.
+const SCALE: usize = 4;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Served at `/`: the MJPEG stream filling the page.
+const PAGE: &str = "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>RUSTЯIS</title>\
+                    <style>body{margin:0;height:100vh;display:flex;align-items:center;justify-content:center;background:#000}\
+                    img{height:100%;image-rendering:pixelated}</style></head>\
+                    <body><img src=\"/stream.mjpg\" alt=\"Game Boy screen\"></body></html>\n";
+
+/// Sending end of the frame streaming server started by `serve`, shared by the video
+/// sinks of player 1's machine.
+#[derive(Clone)]
+pub struct FrameFeed {
+    frames: SyncSender<Vec<u8>>,
+    viewers: Arc<AtomicUsize>, // Connected viewers; frames are only copied while there are some
+}
+
+impl FrameFeed {
+    /// Queues a finished frame (RGB24, `pitch` bytes per row) for the viewers, if any.
+    pub fn send(&self, framebuffer: &[u8], pitch: usize) {
+        if self.viewers.load(Ordering::Relaxed) > 0 {
+            // A full queue means the viewers are behind; they get a later frame instead
+            let _ = self.frames.try_send(screenshot::packed(framebuffer, pitch));
+        }
+    }
+}
+
+/// A connected viewer and how it takes frames.
+enum Viewer {
+    Mjpeg(TcpStream),     // An endless multipart HTTP response, one JPEG per frame
+    WebSocket(TcpStream), // One binary message per frame: 160×144 RGB24 pixels, top row first
+}
+
+/// Starts the frame streaming server on `addr` (`HOST:PORT`), an HTTP server that sends
+/// the frames shown in the window (overlays included) to any number of viewers:
+/// - `/stream.mjpg`: an MJPEG stream (`multipart/x-mixed-replace`), which browsers show
+///   in an `<img>` as it plays; frames are scaled up `SCALE` times.
+/// - `/`: a page showing that stream.
+/// - A WebSocket upgrade on any path (RFC 6455): each frame as a binary message of raw
+///   RGB24 pixels, for dashboards that draw it on a canvas.
+///
+/// One thread answers requests, another encodes and sends the frames; both run until the
+/// process ends. A viewer that stops reading is dropped. Fails if the address can't be
+/// bound.
+pub fn serve(addr: &str) -> io::Result<FrameFeed> {
+    let listener = TcpListener::bind(addr)?;
+    let (frames, queue) = mpsc::sync_channel(QUEUE);
+    let viewers = Arc::new(Mutex::new(Vec::new()));
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let (accepted, connected) = (viewers.clone(), count.clone());
+    thread::Builder::new().name(String::from("stream-accept")).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            match answer(stream) {
+                Ok(Some(viewer)) => {
+                    let mut viewers = accepted.lock().unwrap();
+                    viewers.push(viewer);
+                    connected.store(viewers.len(), Ordering::Relaxed);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Streaming: rejected a viewer: {}", e),
+            }
+        }
+    })?;
+    let connected = count.clone();
+    thread::Builder::new()
+        .name(String::from("stream-send"))
+        .spawn(move || broadcast(queue, viewers, connected))?;
+
+    Ok(FrameFeed { frames, viewers: count })
+}
+
+/// Reads an HTTP request and answers it; returns the viewer if it asked for frames.
+fn answer(stream: TcpStream) -> io::Result<Option<Viewer>> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') && name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+            key = Some(value.trim().to_string());
+        }
+    }
+
+    let mut writer = &stream;
+    if let Some(key) = key {
+        websocket::accept(&stream, &key)?;
+        return Ok(Some(Viewer::WebSocket(stream)));
+    }
+    match path.as_str() {
+        "/stream.mjpg" => {
+            writer.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\n\
+                  Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+            )?;
+            Ok(Some(Viewer::Mjpeg(stream)))
+        }
+        "/" | "/index.html" => {
+            write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                PAGE.len(),
+                PAGE,
+            )?;
+            Ok(None)
+        }
+        _ => {
+            writer.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+            Ok(None)
+        }
+    }
+}
+
+/// Sends each frame to every viewer, encoding it only for the kinds of viewer connected,
+/// and drops the viewers that fail to take it.
+fn broadcast(queue: Receiver<Vec<u8>>, viewers: Arc<Mutex<Vec<Viewer>>>, connected: Arc<AtomicUsize>) {
+    for frame in queue {
+        let mut viewers = viewers.lock().unwrap();
+        let mut part = None;
+        let mut message = None;
+        viewers.retain_mut(|viewer| match viewer {
+            Viewer::Mjpeg(stream) => {
+                let part = part.get_or_insert_with(|| match jpeg(&frame) {
+                    Ok(jpeg) => {
+                        let mut part = format!("--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n", jpeg.len()).into_bytes();
+                        part.extend_from_slice(&jpeg);
+                        part.extend_from_slice(b"\r\n");
+                        part
+                    }
+                    Err(e) => {
+                        eprintln!("Streaming: can't encode a frame: {}", e);
+                        Vec::new()
+                    }
+                });
+                stream.write_all(part).is_ok()
+            }
+            Viewer::WebSocket(stream) => stream.write_all(message.get_or_insert_with(|| websocket::binary_frame(&frame))).is_ok(),
+        });
+        connected.store(viewers.len(), Ordering::Relaxed);
+    }
+}
+
+/// A packed RGB24 frame as a JPEG, scaled up `SCALE` times.
+fn jpeg(frame: &[u8]) -> Result<Vec<u8>, EncodingError> {
+    let (width, height) = (SCREEN_WIDTH as usize * SCALE, SCREEN_HEIGHT as usize * SCALE);
+    let mut scaled = Vec::with_capacity(width * height * 3);
+    for row in frame.chunks_exact(SCREEN_WIDTH as usize * 3) {
+        let wide: Vec<u8> = row.chunks_exact(3).flat_map(|px| px.repeat(SCALE)).collect();
+        for _ in 0..SCALE {
+            scaled.extend_from_slice(&wide);
+        }
+    }
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, JPEG_QUALITY);
+    encoder.set_sampling_factor(SamplingFactor::R_4_4_4);
+    encoder.encode(&scaled, width as u16, height as u16, ColorType::Rgb)?;
+    Ok(out)
+}
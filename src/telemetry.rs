@@ -0,0 +1,134 @@
+use crate::tetris::{self, TetrisState};
+use crate::websocket;
+
+use rustris_core::GB;
+
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Snapshots waiting to be sent; newer ones are dropped while the queue is full, so a
+/// slow client never holds up emulation.
+const QUEUE: usize = 8;
+
+/// Sending end of the telemetry server started by `serve`, shared by the machines'
+/// `Telemetry` hooks.
+#[derive(Clone)]
+pub struct Feed {
+    snapshots: SyncSender<String>,
+    clients: Arc<AtomicUsize>, // Connected clients; snapshots are only made while there are some
+}
+
+/// Starts the telemetry server on `addr` (`HOST:PORT`): a WebSocket server (RFC 6455,
+/// any path) that sends every connected client each snapshot as a text message.
+/// - One thread accepts clients and answers their handshakes, another sends them the
+///   snapshots; both run until the process ends.
+/// - Messages from clients are ignored; a client that stops reading (for the handshake's
+///   timeout, see `websocket::handshake`) is dropped.
+///
+/// Fails if the address can't be bound.
+pub fn serve(addr: &str) -> io::Result<Feed> {
+    let listener = TcpListener::bind(addr)?;
+    let (snapshots, queue) = mpsc::sync_channel(QUEUE);
+    let clients = Arc::new(Mutex::new(Vec::new()));
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let (accepted, connected) = (clients.clone(), count.clone());
+    thread::Builder::new().name(String::from("telemetry-accept")).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            match websocket::handshake(&stream) {
+                Ok(()) => {
+                    let mut clients = accepted.lock().unwrap();
+                    clients.push(stream);
+                    connected.store(clients.len(), Ordering::Relaxed);
+                }
+                Err(e) => eprintln!("Telemetry: rejected a client: {}", e),
+            }
+        }
+    })?;
+    let connected = count.clone();
+    thread::Builder::new()
+        .name(String::from("telemetry-send"))
+        .spawn(move || broadcast(queue, clients, connected))?;
+
+    Ok(Feed { snapshots, clients: count })
+}
+
+/// Sends each snapshot to every client as a WebSocket text message, dropping the clients
+/// that fail to take it.
+fn broadcast(queue: Receiver<String>, clients: Arc<Mutex<Vec<TcpStream>>>, connected: Arc<AtomicUsize>) {
+    for snapshot in queue {
+        let message = websocket::text_frame(&snapshot);
+        let mut clients = clients.lock().unwrap();
+        clients.retain_mut(|client| client.write_all(&message).is_ok());
+        connected.store(clients.len(), Ordering::Relaxed);
+    }
+}
+
+/// Game telemetry (`--telemetry`): after every frame, while a client is connected, the
+/// game's state (see `TetrisState`) goes to the server started by `serve` as one JSON
+/// object:
+/// `{"frame":…,"score":…,"level":…,"lines":…,"next":"T","active":{"piece":"L","cells":[[c,r],…]},"board":[…]}`
+/// - `frame` counts the machine's frames since it started.
+/// - `next` and `active` are `null` when the game shows no such piece.
+/// - `board` is the well from the top row down, one string of 10 characters per row:
+///   `#` for a filled cell, `.` for an empty one. Cells are `[column, row]` in the well.
+///
+/// Only the original Tetris is understood (games with the Tetris profile).
+pub struct Telemetry {
+    feed: Feed,
+    frame: u64,
+    memory: Vec<u8>, // Copy of the address space to decode
+}
+
+impl Telemetry {
+    /// The reporter for `gb`'s game; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB, feed: Feed) -> Option<Self> {
+        tetris::is_tetris(gb).then(|| Self { feed, frame: 0, memory: vec![0; 0x10000] })
+    }
+
+    /// Call after every emulated frame: queues a snapshot if anyone is listening.
+    pub fn after_frame(&mut self, gb: &GB) {
+        self.frame += 1;
+        if self.feed.clients.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        gb.read_memory(&mut self.memory);
+        let snapshot = json(self.frame, &TetrisState::decode(&self.memory));
+        // A full queue means the clients are behind; they get a later frame instead
+        let _ = self.feed.snapshots.try_send(snapshot);
+    }
+}
+
+fn json(frame: u64, state: &TetrisState) -> String {
+    let mut out = format!(
+        "{{\"frame\":{},\"score\":{},\"level\":{},\"lines\":{},\"next\":",
+        frame, state.score, state.level, state.lines,
+    );
+    match state.next {
+        Some(piece) => write!(out, "\"{}\"", piece.name()),
+        None => write!(out, "null"),
+    }
+    .unwrap();
+    out.push_str(",\"active\":");
+    match &state.active {
+        Some(active) => {
+            let cells: Vec<String> = active.cells.iter().map(|(c, r)| format!("[{},{}]", c, r)).collect();
+            write!(out, "{{\"piece\":\"{}\",\"cells\":[{}]}}", active.piece.name(), cells.join(","))
+        }
+        None => write!(out, "null"),
+    }
+    .unwrap();
+    let rows: Vec<String> = state
+        .board
+        .0
+        .iter()
+        .map(|row| format!("\"{}\"", row.iter().map(|&filled| if filled { '#' } else { '.' }).collect::<String>()))
+        .collect();
+    write!(out, ",\"board\":[{}]}}", rows.join(",")).unwrap();
+    out
+}
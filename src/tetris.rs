@@ -0,0 +1,371 @@
+use rustris_core::gb::BTN_UP;
+use rustris_core::{Profile, GB};
+
+/// Size of the well, in cells.
+pub const WELL_WIDTH: usize = 10;
+pub const WELL_HEIGHT: usize = 18;
+
+/// Background map column of the well's leftmost cell (row 0 is the top of the map).
+pub const WELL_COLUMN: i32 = 2;
+
+/// Background tile the game draws empty cells with.
+const EMPTY_TILE: u8 = 0x2F;
+
+/// Score: 3 bytes of packed BCD, least significant first.
+const SCORE: usize = 0xC0A0;
+
+/// Lines cleared: 2 bytes of packed BCD, least significant first.
+const LINES: usize = 0xFF9E;
+
+/// Current level, in binary.
+const LEVEL: usize = 0xFFA9;
+
+/// The falling piece's and the preview's numbers (see `Piece::id`), in the game's sprite
+/// records for them.
+const ACTIVE_PIECE: u16 = 0xC203;
+const NEXT_PIECE: u16 = 0xC213;
+
+/// The falling piece's vertical position in pixels, in its sprite record.
+const ACTIVE_Y: u16 = 0xC201;
+
+/// Four cells as (column, row).
+pub type Cells = [(i32, i32); 4];
+
+/// The seven tetrominoes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Piece {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    L,
+    J,
+}
+
+impl Piece {
+    pub const ALL: [Piece; 7] = [Piece::I, Piece::O, Piece::T, Piece::S, Piece::Z, Piece::L, Piece::J];
+
+    /// The piece's cells in one orientation, normalized (see `normalize`).
+    fn cells(self) -> Cells {
+        match self {
+            Piece::I => [(0, 0), (1, 0), (2, 0), (3, 0)],
+            Piece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Piece::T => [(0, 0), (1, 0), (2, 0), (1, 1)],
+            Piece::S => [(1, 0), (2, 0), (0, 1), (1, 1)],
+            Piece::Z => [(0, 0), (1, 0), (1, 1), (2, 1)],
+            Piece::L => [(0, 0), (1, 0), (2, 0), (0, 1)],
+            Piece::J => [(0, 0), (1, 0), (2, 0), (2, 1)],
+        }
+    }
+
+    /// The distinct orientations of the piece, normalized (one for O, two for I, S and Z).
+    pub fn orientations(self) -> Vec<Cells> {
+        let mut shapes = Vec::new();
+        let mut shape = normalize(self.cells());
+        for _ in 0..4 {
+            if !shapes.contains(&shape) {
+                shapes.push(shape);
+            }
+            shape = normalize(shape.map(|(c, r)| (r, -c)));
+        }
+        shapes
+    }
+
+    /// Which piece the cells form, in any orientation.
+    fn identify(cells: Cells) -> Option<Piece> {
+        let shape = normalize(cells);
+        Piece::ALL.into_iter().find(|piece| piece.orientations().contains(&shape))
+    }
+
+    /// The piece with this letter (either case).
+    pub fn from_letter(letter: char) -> Option<Piece> {
+        Piece::ALL.into_iter().find(|piece| piece.name().eq_ignore_ascii_case(&letter.to_string()))
+    }
+
+    /// The game's number for the piece in its first orientation.
+    fn id(self) -> u8 {
+        match self {
+            Piece::L => 0x00,
+            Piece::J => 0x04,
+            Piece::I => 0x08,
+            Piece::O => 0x0C,
+            Piece::Z => 0x10,
+            Piece::S => 0x14,
+            Piece::T => 0x18,
+        }
+    }
+
+    /// The piece's letter.
+    pub fn name(self) -> &'static str {
+        match self {
+            Piece::I => "I",
+            Piece::O => "O",
+            Piece::T => "T",
+            Piece::S => "S",
+            Piece::Z => "Z",
+            Piece::L => "L",
+            Piece::J => "J",
+        }
+    }
+}
+
+/// Moves `cells` so the smallest column and row are 0, and sorts them, so equal shapes
+/// compare equal wherever they are.
+pub fn normalize(cells: Cells) -> Cells {
+    let left = cells.iter().map(|&(c, _)| c).min().unwrap_or(0);
+    let top = cells.iter().map(|&(_, r)| r).min().unwrap_or(0);
+    let mut shape = cells.map(|(c, r)| (c - left, r - top));
+    shape.sort_unstable();
+    shape
+}
+
+/// The cells of the well, `[row][column]` with row 0 at the top; `true` if filled.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Board(pub [[bool; WELL_WIDTH]; WELL_HEIGHT]);
+
+impl Board {
+    /// Whether a cell is taken. The walls and floor are; the space above the well isn't.
+    pub fn filled(&self, column: i32, row: i32) -> bool {
+        if !(0..WELL_WIDTH as i32).contains(&column) || row >= WELL_HEIGHT as i32 {
+            return true;
+        }
+        row >= 0 && self.0[row as usize][column as usize]
+    }
+
+    /// Whether all the cells are free.
+    pub fn fits(&self, cells: &Cells) -> bool {
+        cells.iter().all(|&(c, r)| !self.filled(c, r))
+    }
+
+    /// Where `cells` (which fit) come to rest when dropped straight down.
+    pub fn landing(&self, cells: &Cells) -> Cells {
+        let mut landed = *cells;
+        loop {
+            let below = landed.map(|(c, r)| (c, r + 1));
+            if !self.fits(&below) {
+                return landed;
+            }
+            landed = below;
+        }
+    }
+}
+
+/// The piece falling down the well.
+#[derive(Clone, Copy)]
+pub struct ActivePiece {
+    pub piece: Piece,
+    pub cells: Cells, // In the well, as for `Board::filled`
+}
+
+/// What's going on in a game of Tetris, decoded from its memory so scripts, overlays, the
+/// autoplayer and other tools share one reading of it:
+/// - The well comes from the background map the game displays (the map LCDC selects).
+/// - The falling piece is the tetromino drawn with sprites inside the well, the next piece
+///   the one drawn outside it (the preview). Between pieces (and in menus) they may be
+///   missing.
+/// - Score, level and lines come from the game's variables in RAM.
+///
+/// Only meaningful while the original Tetris is running (see `is_tetris`).
+pub struct TetrisState {
+    pub board: Board,
+    pub active: Option<ActivePiece>,
+    pub next: Option<Piece>,
+    pub score: u32,
+    pub level: u8,
+    pub lines: u16,
+}
+
+impl TetrisState {
+    /// Decodes the state from a copy of the address space (`GB::read_memory`).
+    pub fn decode(memory: &[u8]) -> Self {
+        let map = if memory[0xFF40] & 0x08 != 0 { 0x9C00 } else { 0x9800 };
+        let mut board = Board([[false; WELL_WIDTH]; WELL_HEIGHT]);
+        for (row, cells) in board.0.iter_mut().enumerate() {
+            for (column, cell) in cells.iter_mut().enumerate() {
+                *cell = memory[map + row * 32 + WELL_COLUMN as usize + column] != EMPTY_TILE;
+            }
+        }
+
+        let mut inside = Vec::new();
+        let mut outside = Vec::new();
+        for (cell, _) in sprite_cells(memory) {
+            if in_well(cell) {
+                inside.push(cell);
+            } else {
+                outside.push(cell);
+            }
+        }
+        let tetromino = |cells: Vec<(i32, i32)>| -> Option<(Cells, Piece)> {
+            let cells: Cells = cells.try_into().ok()?;
+            Some((cells, Piece::identify(cells)?))
+        };
+        // Sprites overlapping the stack are a piece that has just locked
+        let active = tetromino(inside)
+            .filter(|(cells, _)| board.fits(cells))
+            .map(|(cells, piece)| ActivePiece { piece, cells });
+        let next = tetromino(outside).map(|(_, piece)| piece);
+
+        Self {
+            board,
+            active,
+            next,
+            score: bcd(&memory[SCORE..SCORE + 3]),
+            level: memory[LEVEL],
+            lines: bcd(&memory[LINES..LINES + 2]) as u16,
+        }
+    }
+
+    /// Whether the game has just started: the well is empty and no line was made yet.
+    pub fn fresh_game(&self) -> bool {
+        self.lines == 0 && self.board.0.iter().flatten().all(|&filled| !filled)
+    }
+}
+
+/// The tiles the falling piece and the preview are drawn with in `memory` (the copy
+/// `state` was decoded from), each with the piece it belongs to.
+pub fn piece_tiles(memory: &[u8], state: &TetrisState) -> Vec<(u8, Piece)> {
+    sprite_cells(memory)
+        .filter_map(|(cell, tile)| {
+            let piece = if in_well(cell) { state.active.map(|active| active.piece) } else { state.next };
+            piece.map(|piece| (tile, piece))
+        })
+        .collect()
+}
+
+/// The visible sprites as (cell, tile): the well cell each covers (columns outside the
+/// well included) and its tile index.
+fn sprite_cells(memory: &[u8]) -> impl Iterator<Item = ((i32, i32), u8)> + '_ {
+    memory[0xFE00..0xFEA0].chunks_exact(4).filter_map(|sprite| {
+        let (y, x) = (sprite[0] as i32, sprite[1] as i32);
+        if y == 0 || y >= 160 || x == 0 || x >= 168 {
+            return None;
+        }
+        Some((((x - 8).div_euclid(8) - WELL_COLUMN, (y - 16).div_euclid(8)), sprite[2]))
+    })
+}
+
+fn in_well((column, _): (i32, i32)) -> bool {
+    (0..WELL_WIDTH as i32).contains(&column)
+}
+
+/// Notices new pieces: a piece has spawned when one is falling and the well isn't the one
+/// seen at the last spawn (the previous piece locked, and any lines it completed were
+/// cleared). Going back to an earlier state with the same well doesn't count.
+pub struct Spawns {
+    board: Option<Board>, // Well at the last spawn
+    memory: Vec<u8>,      // Copy of the address space to decode
+}
+
+impl Spawns {
+    pub fn new() -> Self {
+        Self { board: None, memory: vec![0; 0x10000] }
+    }
+
+    /// Call after every emulated frame: the game's state if a piece spawned during it.
+    pub fn check(&mut self, gb: &GB) -> Option<TetrisState> {
+        gb.read_memory(&mut self.memory);
+        let state = TetrisState::decode(&self.memory);
+        self.observe(&state).then_some(state)
+    }
+
+    /// Like `check`, for a state decoded elsewhere (such as from a `MemoryView` copy):
+    /// whether a piece has spawned since the last state seen.
+    pub fn observe(&mut self, state: &TetrisState) -> bool {
+        if state.active.is_none() || self.board.as_ref() == Some(&state.board) {
+            return false;
+        }
+        self.board = Some(state.board.clone());
+        true
+    }
+}
+
+/// Makes the game deal `pieces` over and over instead of drawing them at random
+/// (`--pieces`), for practicing an opening or a drought:
+/// - At the first spawn of a game (empty well, no lines yet) the sequence starts over: the
+///   falling piece is replaced with its first piece and the preview with the second.
+/// - At every later spawn the game has just taken the preview and drawn a new one, which
+///   is replaced with the sequence's next piece.
+pub struct PieceQueue {
+    pieces: Vec<Piece>,
+    dealt: usize, // Pieces of the sequence dealt so far, the preview included
+    spawns: Spawns,
+}
+
+impl PieceQueue {
+    /// The queue for `gb`'s game; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB, pieces: Vec<Piece>) -> Option<Self> {
+        is_tetris(gb).then(|| Self { pieces, dealt: 0, spawns: Spawns::new() })
+    }
+
+    /// Call after every emulated frame: deals the next piece when one has spawned.
+    pub fn after_frame(&mut self, gb: &mut GB) {
+        let Some(state) = self.spawns.check(gb) else { return };
+        if state.fresh_game() {
+            self.dealt = 0;
+        }
+        if self.dealt == 0 {
+            gb.write_byte(ACTIVE_PIECE, self.deal().id());
+        }
+        gb.write_byte(NEXT_PIECE, self.deal().id());
+    }
+
+    fn deal(&mut self) -> Piece {
+        let piece = self.pieces[self.dealt % self.pieces.len()];
+        self.dealt += 1;
+        piece
+    }
+}
+
+/// Hard drop (`--hard-drop`, off by default): a patch to the game that sends the falling
+/// piece straight down to where it would land when Up is pressed, as modern Tetris games
+/// do; the 1989 game ignores Up.
+/// - The emulator has no cheat or ROM-patch engine, so like `PieceQueue` it changes the
+///   game's RAM between frames: the piece's position is moved down by the rows it can fall
+///   (see `Board::landing`), and the game locks it on its next gravity step.
+/// - Only a fresh press of Up drops a piece; holding it doesn't drop the next one.
+pub struct HardDrop {
+    enabled: bool,
+    up: bool,        // Up was held on the previous frame
+    memory: Vec<u8>, // Copy of the address space to decode
+}
+
+impl HardDrop {
+    /// The patch for `gb`'s game, off until `set_enabled`; `None` if it isn't Tetris.
+    pub fn for_game(gb: &GB) -> Option<Self> {
+        is_tetris(gb).then(|| Self { enabled: false, up: false, memory: vec![0; 0x10000] })
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Call after every emulated frame with the buttons held during it: drops the falling
+    /// piece if Up was just pressed.
+    pub fn after_frame(&mut self, gb: &mut GB, buttons: u8) {
+        let pressed = buttons & BTN_UP != 0 && !self.up;
+        self.up = buttons & BTN_UP != 0;
+        if !self.enabled || !pressed {
+            return;
+        }
+        gb.read_memory(&mut self.memory);
+        let state = TetrisState::decode(&self.memory);
+        let Some(active) = state.active else { return };
+        let rows = state.board.landing(&active.cells)[0].1 - active.cells[0].1;
+        if rows > 0 {
+            let y = self.memory[ACTIVE_Y as usize];
+            gb.write_byte(ACTIVE_Y, y.wrapping_add(rows as u8 * 8));
+        }
+    }
+}
+
+/// Value of packed BCD bytes, least significant first.
+fn bcd(bytes: &[u8]) -> u32 {
+    bytes.iter().rev().fold(0, |value, &byte| value * 100 + (byte >> 4) as u32 * 10 + (byte & 0x0F) as u32)
+}
+
+/// Whether `gb` runs the original Tetris (the game with the Tetris profile), whose memory
+/// `TetrisState` understands.
+pub fn is_tetris(gb: &GB) -> bool {
+    gb.rom_info().is_some_and(|info| info.profile == Profile::Tetris)
+}
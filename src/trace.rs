@@ -0,0 +1,194 @@
+use crate::headless::{self, PITCH};
+use crate::options::Trace;
+
+use rustris_core::{disasm, TraceEntry, GB, SCREEN_HEIGHT};
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Matching lines shown before the first divergence.
+const CONTEXT_LINES: usize = 5;
+
+/// Canonical state before an instruction, in the Gameboy Doctor format, which SameBoy,
+/// BGB and other emulators can be made to log:
+/// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`
+pub fn line(gb: &GB) -> String {
+    let r = gb.registers();
+    let mem = |i: u16| gb.read_byte(r.pc.wrapping_add(i));
+    format!(
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+        r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, r.pc, mem(0), mem(1), mem(2), mem(3),
+    )
+}
+
+/// An instruction from the CPU's history (see `GB::trace`) in the same format, with its
+/// opcode instead of PCMEM and, if the same opcode is still mapped at its address, its
+/// disassembly (operands as memory holds them now):
+/// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 OP:00 NOP`
+pub fn history_line(gb: &GB, entry: &TraceEntry) -> String {
+    let r = &entry.registers;
+    let mut line = format!(
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} OP:{:02X}",
+        r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, r.pc, entry.opcode,
+    );
+    if gb.read_byte(r.pc) == entry.opcode {
+        let bytes = [entry.opcode, gb.read_byte(r.pc.wrapping_add(1)), gb.read_byte(r.pc.wrapping_add(2))];
+        line.push(' ');
+        line.push_str(&disasm::disassemble(r.pc, &bytes).0);
+    }
+    line
+}
+
+/// Writes the CPU's history, oldest first (see `history_line`), to
+/// `rustris-trace-<unix time>.txt` in the current directory and returns where it went.
+pub fn dump(gb: &GB) -> std::io::Result<PathBuf> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = PathBuf::from(format!("rustris-trace-{}.txt", time));
+    let mut out = BufWriter::new(File::create(&path)?);
+    for entry in gb.trace() {
+        writeln!(out, "{}", history_line(gb, &entry))?;
+    }
+    out.flush()?;
+    Ok(path)
+}
+
+/// Splits a log line into `KEY:VALUE` fields. Keys are case-insensitive and values are
+/// hex numbers (optionally `0x`-prefixed, comma-separated for PCMEM), so logs from other
+/// emulators compare by value rather than by spelling.
+fn fields(line: &str) -> Vec<(String, Vec<u32>)> {
+    line.split_whitespace()
+        .filter_map(|token| {
+            let (key, value) = token.split_once(':')?;
+            let values = value
+                .split(',')
+                .map(|v| {
+                    let v = v.trim_start_matches("0x").trim_start_matches("0X");
+                    u32::from_str_radix(v, 16).ok()
+                })
+                .collect::<Option<Vec<u32>>>()?;
+            Some((key.to_ascii_uppercase(), values))
+        })
+        .collect()
+}
+
+/// Names of the fields in `reference` that `actual` disagrees on. Fields only one side
+/// logs are ignored.
+fn differences(reference: &str, actual: &str) -> Vec<String> {
+    let actual = fields(actual);
+    fields(reference)
+        .into_iter()
+        .filter(|(key, value)| actual.iter().any(|(k, v)| k == key && v != value))
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// Trace runner (`--trace` / `--diff-trace`), headless with no input:
+/// - Before every instruction (not interrupt dispatch or HALT idling) the machine's state
+///   is formatted as a canonical line (see `line`).
+/// - `Trace::Write` appends each line to the file.
+/// - `Trace::Diff` checks each line against the next non-empty line of a reference log,
+///   such as one from SameBoy or BGB starting at 0100 with the post-boot registers. The
+///   first divergent instruction is reported with a few lines of context and the fields
+///   that differ.
+/// - Runs until `frames` frames, the end of the reference log or Ctrl+C.
+///
+/// Returns the process exit status: 0 done (no divergence), 1 diverged, 2 I/O error.
+pub fn run(mut gb: GB, frames: Option<u64>, trace: &Trace) -> i32 {
+    let (mut writer, mut reference) = match trace {
+        Trace::Write(path) => match File::create(path) {
+            Ok(file) => (Some(BufWriter::new(file)), None),
+            Err(e) => {
+                eprintln!("Error creating {}: {}", path, e);
+                return 2;
+            }
+        },
+        Trace::Diff(path) => match File::open(path) {
+            Ok(file) => (None, Some(BufReader::new(file).lines())),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                return 2;
+            }
+        },
+    };
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed)) {
+        eprintln!("Warning: cannot install Ctrl+C handler: {}", e);
+    }
+
+    let mut framebuffer = vec![0u8; PITCH * SCREEN_HEIGHT as usize];
+    let mut context: VecDeque<String> = VecDeque::new();
+    let mut matched: u64 = 0;
+    let mut count: u64 = 0;
+
+    while frames.is_none_or(|n| count < n) && !interrupted.load(Ordering::Relaxed) {
+        let state = line(&gb);
+        let executed = gb.instruction_count();
+        if gb.step(&mut framebuffer, PITCH) {
+            count += 1;
+        }
+        if gb.fault().is_err() {
+            if let Some(writer) = &mut writer {
+                let _ = writer.flush();
+            }
+            headless::exit_on_lockup(&gb);
+        }
+        if gb.instruction_count() == executed {
+            continue;
+        }
+
+        if let Some(writer) = &mut writer {
+            if let Err(e) = writeln!(writer, "{}", state) {
+                eprintln!("Error writing trace: {}", e);
+                return 2;
+            }
+            continue;
+        }
+
+        let Some(lines) = &mut reference else { continue };
+        let expected = match lines.find(|l| l.as_ref().map_or(true, |l| !l.trim().is_empty())) {
+            Some(Ok(expected)) => expected,
+            Some(Err(e)) => {
+                eprintln!("Error reading reference log: {}", e);
+                return 2;
+            }
+            None => {
+                println!("Reference log ends; all {} instructions matched", matched);
+                return 0;
+            }
+        };
+
+        let diff = differences(&expected, &state);
+        if !diff.is_empty() {
+            println!("Divergence at instruction {} ({} differs):", matched + 1, diff.join(", "));
+            for earlier in &context {
+                println!("  {}", earlier);
+            }
+            println!("- {}", expected.trim());
+            println!("+ {}", state);
+            return 1;
+        }
+        matched += 1;
+        context.push_back(state);
+        if context.len() > CONTEXT_LINES {
+            context.pop_front();
+        }
+    }
+
+    if let Some(writer) = &mut writer
+        && let Err(e) = writer.flush()
+    {
+        eprintln!("Error writing trace: {}", e);
+        return 2;
+    }
+    if reference.is_some() {
+        println!("Stopped after {} frames; {} instructions matched", count, matched);
+    }
+    0
+}
@@ -0,0 +1,65 @@
+/// Approximate DMG frame rate, used to convert a turbo rate in Hz into frames.
+const GB_FPS: f64 = 59.7275;
+
+/// Turbo (auto-fire) input layer, combined with the regular keys into the joypad state
+/// the core polls.
+///
+/// - The front-end maps turbo keys to button masks and reports them via `press`/`release`.
+/// - While held, the buttons alternate between pressed and released, switching state
+///   on frame boundaries so every press lasts whole frames.
+/// - The result is OR-ed with the regular mapping, so buttons also held through their
+///   regular key are never released by the turbo cycle.
+pub struct Turbo {
+    half_period: u32, // Frames spent in each of the pressed/released phases
+    held: u8,         // Buttons whose turbo key is currently down
+    frame: u32,       // Frames elapsed since the first turbo key went down
+}
+
+impl Turbo {
+    /// Creates the turbo layer, auto-firing at `hz` presses per second.
+    pub fn new(hz: u32) -> Self {
+        let mut turbo = Self {
+            half_period: 1,
+            held: 0,
+            frame: 0,
+        };
+        turbo.set_rate(hz);
+        turbo
+    }
+
+    /// Changes the auto-fire rate, in presses per second.
+    pub fn set_rate(&mut self, hz: u32) {
+        let frames_per_press = (GB_FPS / hz.max(1) as f64).round() as u32;
+        self.half_period = (frames_per_press / 2).max(1);
+    }
+
+    /// A turbo key for `mask` went down.
+    pub fn press(&mut self, mask: u8) {
+        if self.held == 0 {
+            self.frame = 0; // Start with a press as soon as the key goes down
+        }
+        self.held |= mask;
+    }
+
+    /// A turbo key for `mask` went up.
+    pub fn release(&mut self, mask: u8) {
+        self.held &= !mask;
+    }
+
+    /// Buttons the turbo cycle holds down during the current frame.
+    pub fn buttons(&self) -> u8 {
+        if (self.frame / self.half_period).is_multiple_of(2) { self.held } else { 0 }
+    }
+
+    /// Advances the turbo cycle by one frame.
+    pub fn tick(&mut self) {
+        if self.held != 0 {
+            self.frame = self.frame.wrapping_add(1);
+        }
+    }
+
+    /// Releases everything, e.g. when the window loses focus.
+    pub fn clear(&mut self) {
+        self.held = 0;
+    }
+}
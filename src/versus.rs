@@ -0,0 +1,103 @@
+use crate::error::Error;
+use crate::headless::{self, PITCH};
+use crate::options::Options;
+use crate::pacing::Limiter;
+use crate::stats::FrameTimes;
+use crate::video::{self, Rotation};
+
+use rustris_core::{link, GB, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::{Color, PixelFormatEnum};
+
+/// Local link-cable versus (`--versus`): two machines joined by the in-process cable,
+/// played from one keyboard, like two Game Boys and a cable on the same desk:
+/// - Both machines run in lockstep on this thread (see `link::run_linked_frame`), so
+///   every transfer lands the same frame it would on hardware.
+/// - The pictures sit side by side; player 1 uses the regular keys, player 2 theirs
+///   (`[input.player2]`).
+/// - Battery saves are neither loaded nor written: both machines usually run the same
+///   ROM and would share one save file.
+/// - Escape or closing the window ends the session.
+///
+/// Fails only on an SDL error.
+pub fn run(mut machines: [GB; 2], opts: &Options) -> Result<(), Error> {
+    for gb in &mut machines {
+        gb.set_linked(true);
+    }
+
+    sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+    let sdl_context = sdl2::init().map_err(Error::sdl)?;
+    let video_subsystem = sdl_context.video().map_err(Error::sdl)?;
+    let (layout_w, layout_h) = video::layout_size(Rotation::R0, 2);
+    let title = format!("RUSTЯIS - {} versus", machines[0].game_title());
+    let window = video_subsystem
+        .window(&title, layout_w * opts.zoom, layout_h * opts.zoom)
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(Error::sdl)?;
+    let mut canvas = window.into_canvas().build().map_err(Error::sdl)?;
+    let texture_creator = canvas.texture_creator();
+    let texture = || {
+        texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .map_err(Error::sdl)
+    };
+    let mut textures = [texture()?, texture()?];
+    let mut event_pump = sdl_context.event_pump().map_err(Error::sdl)?;
+
+    let mut screens = [vec![0u8; PITCH * SCREEN_HEIGHT as usize], vec![0u8; PITCH * SCREEN_HEIGHT as usize]];
+    let key_sets = [&opts.keys, &opts.player2_keys];
+    let mut buttons = [0u8; 2];
+    let mut limiter = Limiter::new(opts.pacing, opts.pacing_tuning, 100, None);
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } |
+                Event::KeyDown { scancode: Some(Scancode::Escape), .. } => break 'running,
+                Event::KeyDown { scancode: Some(sc), repeat: false, .. } => {
+                    for (held, keys) in buttons.iter_mut().zip(key_sets) {
+                        if let Some((_, mask)) = keys.iter().find(|(s, _)| *s == sc) {
+                            *held |= mask;
+                        }
+                    }
+                }
+                Event::KeyUp { scancode: Some(sc), .. } => {
+                    for (held, keys) in buttons.iter_mut().zip(key_sets) {
+                        if let Some((_, mask)) = keys.iter().find(|(s, _)| *s == sc) {
+                            *held &= !mask;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (gb, &held) in machines.iter_mut().zip(&buttons) {
+            gb.set_buttons(held);
+        }
+        let [a, b] = &mut screens;
+        link::run_linked_frame(&mut machines, [a, b], PITCH);
+        for gb in &machines {
+            headless::exit_on_lockup(gb);
+        }
+        for (texture, screen) in textures.iter_mut().zip(&screens) {
+            texture.update(None, screen, PITCH).map_err(Error::sdl)?;
+        }
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let output = canvas.output_size().map_err(Error::sdl)?;
+        for (texture, rect) in textures.iter().zip(video::dest_rects(output, Rotation::R0, 2)) {
+            canvas.copy(texture, None, rect).map_err(Error::sdl)?;
+        }
+        canvas.present();
+
+        // Paced as the emulation thread is, but by the clock even with vsync pacing
+        limiter.wait(&mut FrameTimes::default());
+    }
+    Ok(())
+}
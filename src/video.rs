@@ -0,0 +1,165 @@
+use rustris_core::{gb, Palette, SCREEN_WIDTH, SCREEN_HEIGHT};
+use rustris_core::{COLORBLIND_PALETTE, COLOR_PALETTE, GREEN_PALETTE, HIGH_CONTRAST_PALETTE};
+
+use sdl2::rect::Rect;
+
+/// Output rotation applied when presenting frames (clockwise).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees {
+            0   => Some(Rotation::R0),
+            90  => Some(Rotation::R90),
+            180 => Some(Rotation::R180),
+            270 => Some(Rotation::R270),
+            _ => None,
+        }
+    }
+
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::R0   => 0,
+            Rotation::R90  => 90,
+            Rotation::R180 => 180,
+            Rotation::R270 => 270,
+        }
+    }
+
+    /// Next rotation, 90° further clockwise.
+    pub fn next(self) -> Self {
+        Self::from_degrees((self.degrees() + 90) % 360).unwrap()
+    }
+
+    /// `true` when the image is taller than wide (90° or 270°).
+    pub fn is_portrait(self) -> bool {
+        matches!(self, Rotation::R90 | Rotation::R270)
+    }
+
+    /// Size of the rotated Game Boy screen, in Game Boy pixels.
+    pub fn screen_size(self) -> (u32, u32) {
+        if self.is_portrait() {
+            (SCREEN_HEIGHT as u32, SCREEN_WIDTH as u32)
+        } else {
+            (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+        }
+    }
+
+    /// Remaps D-pad buttons so that arrow keys follow the rotated picture
+    /// (e.g. at 90° the game's "left" points up on screen, so Up presses Left).
+    /// Non-directional buttons pass through unchanged.
+    pub fn remap_dpad(self, mask: u8) -> u8 {
+        // Directions in clockwise screen order: up, right, down, left
+        const CW: [u8; 4] = [gb::BTN_UP, gb::BTN_RIGHT, gb::BTN_DOWN, gb::BTN_LEFT];
+        let steps = (self.degrees() / 90) as usize;
+
+        let mut out = mask & !(gb::BTN_UP | gb::BTN_RIGHT | gb::BTN_DOWN | gb::BTN_LEFT);
+        for (i, dir) in CW.iter().enumerate() {
+            if mask & dir != 0 {
+                out |= CW[(i + 4 - steps) % 4];
+            }
+        }
+        out
+    }
+}
+
+/// Palette presets for DMG games, picked with `--palette`, the settings panel or P.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HostPalette {
+    #[default]
+    Color,
+    Green,
+    Colorblind,   // Orange and blue for the middle shades, for red-green color blindness
+    HighContrast, // Evenly spaced grays
+}
+
+impl HostPalette {
+    pub const ALL: [HostPalette; 4] = [HostPalette::Color, HostPalette::Green, HostPalette::Colorblind, HostPalette::HighContrast];
+
+    /// The preset named `name` as in the config file and on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HostPalette::Color        => "color",
+            HostPalette::Green        => "green",
+            HostPalette::Colorblind   => "colorblind",
+            HostPalette::HighContrast => "high-contrast",
+        }
+    }
+
+    /// Name shown in the settings panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            HostPalette::Color        => "Color",
+            HostPalette::Green        => "DMG green",
+            HostPalette::Colorblind   => "Colorblind",
+            HostPalette::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn palette(self) -> Palette {
+        match self {
+            HostPalette::Color        => COLOR_PALETTE,
+            HostPalette::Green        => GREEN_PALETTE,
+            HostPalette::Colorblind   => COLORBLIND_PALETTE,
+            HostPalette::HighContrast => HIGH_CONTRAST_PALETTE,
+        }
+    }
+
+    /// The preset after this one, wrapping around; for the P key.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&palette| palette == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// Size of `screens` rotated Game Boy screens side by side, in Game Boy pixels.
+pub fn layout_size(rotation: Rotation, screens: u32) -> (u32, u32) {
+    let (w, h) = rotation.screen_size();
+    (w * screens, h)
+}
+
+/// Destination rectangles for `screens` pictures side by side: the output is split into
+/// equal columns and each picture placed in its own with `dest_rect`.
+pub fn dest_rects(output: (u32, u32), rotation: Rotation, screens: u32) -> Vec<Rect> {
+    let (out_w, out_h) = output;
+    let column = out_w / screens;
+    (0..screens)
+        .map(|i| {
+            let mut rect = dest_rect((column, out_h), rotation);
+            rect.offset((i * column) as i32, 0);
+            rect
+        })
+        .collect()
+}
+
+/// Destination rectangle for `Canvas::copy_ex` on an output of `output` pixels.
+///
+/// - Picks the largest integer scale at which the rotated screen fits the output and
+///   centers it.
+/// - `copy_ex` rotates around the rectangle's center, so for 90°/270° the rectangle is
+///   given in unrotated orientation (width and height swapped).
+pub fn dest_rect(output: (u32, u32), rotation: Rotation) -> Rect {
+    let (out_w, out_h) = output;
+    let (screen_w, screen_h) = rotation.screen_size();
+    let scale = (out_w / screen_w).min(out_h / screen_h).max(1);
+
+    let (w, h) = (screen_w * scale, screen_h * scale);
+    let cx = out_w as i32 / 2;
+    let cy = out_h as i32 / 2;
+
+    if rotation.is_portrait() {
+        Rect::from_center((cx, cy), h, w)
+    } else {
+        Rect::from_center((cx, cy), w, h)
+    }
+}
@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+
+/// How a watched address is shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    U8,
+    U16,      // Little-endian
+    Bcd(u8),  // Packed BCD over this many bytes (1–4), least significant byte first
+}
+
+impl Format {
+    const ALL: [Format; 6] = [Format::U8, Format::U16, Format::Bcd(1), Format::Bcd(2), Format::Bcd(3), Format::Bcd(4)];
+
+    /// Name used in the watch file and the format menu.
+    fn name(self) -> String {
+        match self {
+            Format::U8 => String::from("u8"),
+            Format::U16 => String::from("u16"),
+            Format::Bcd(bytes) => format!("bcd{}", bytes),
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Format::ALL.into_iter().find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Formats the value at `addr`.
+    fn show(self, memory: &[u8], addr: u16) -> String {
+        let byte = |i: u16| memory[addr.wrapping_add(i) as usize];
+        match self {
+            Format::U8 => format!("{} (${:02X})", byte(0), byte(0)),
+            Format::U16 => {
+                let v = u16::from_le_bytes([byte(0), byte(1)]);
+                format!("{} (${:04X})", v, v)
+            }
+            Format::Bcd(bytes) => {
+                // Each byte's hex digits are its two decimal digits
+                let digits: String = (0..bytes as u16).rev().map(|i| format!("{:02X}", byte(i))).collect();
+                let trimmed = digits.trim_start_matches('0');
+                String::from(if trimmed.is_empty() { "0" } else { trimmed })
+            }
+        }
+    }
+}
+
+/// One watched address.
+struct Entry {
+    addr: u16,
+    format: Format,
+    name: String,
+}
+
+/// Named addresses shown live in the watch window, saved per game next to the ROM
+/// (`<rom>.watch`) whenever the list changes.
+///
+/// The file has one entry per line: `ADDR FORMAT NAME`, e.g. `C0A0 bcd3 Score`, with the
+/// address in hex and FORMAT one of `u8`, `u16` or `bcd1`–`bcd4`. `#` starts a comment
+/// (comments aren't kept when the list is rewritten).
+pub struct WatchList {
+    entries: Vec<Entry>,
+    path: Option<PathBuf>, // None: not saved (no ROM path)
+    new_addr: String,      // Text of the "add" field
+    error: Option<String>,
+}
+
+impl WatchList {
+    /// An empty list that isn't saved anywhere.
+    pub fn new() -> Self {
+        Self { entries: Vec::new(), path: None, new_addr: String::new(), error: None }
+    }
+
+    /// The list saved for the ROM at `rom_path`, or an empty one that will be saved there.
+    pub fn load(rom_path: &str) -> Self {
+        let path = Path::new(rom_path).with_extension("watch");
+        let mut list = Self::new();
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            for (n, line) in text.lines().enumerate() {
+                let line = line.split('#').next().unwrap_or("").trim();
+                if line.is_empty() {
+                    continue;
+                }
+                match parse_entry(line) {
+                    Some(entry) => list.entries.push(entry),
+                    None => eprintln!("{}:{}: invalid watch entry: {}", path.display(), n + 1, line),
+                }
+            }
+        }
+        list.path = Some(path);
+        list
+    }
+
+    /// Adds `addr` as a byte, unless it's already watched.
+    pub fn add(&mut self, addr: u16) {
+        if self.entries.iter().all(|e| e.addr != addr) {
+            self.entries.push(Entry { addr, format: Format::U8, name: String::new() });
+            self.save();
+        }
+    }
+
+    fn save(&mut self) {
+        let Some(path) = &self.path else { return };
+        let text: String = self
+            .entries
+            .iter()
+            .map(|e| format!("{:04X} {} {}\n", e.addr, e.format.name(), e.name))
+            .collect();
+        self.error = std::fs::write(path, text).err().map(|e| format!("Error writing {}: {}", path.display(), e));
+    }
+
+    /// The watch window. `memory` is the latest copy from the emulation thread.
+    pub fn window(&mut self, ctx: &egui::Context, open: &mut bool, memory: Option<&[u8]>) {
+        let mut changed = false;
+        egui::Window::new("RAM watch")
+            .open(open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let mut remove = None;
+                egui::Grid::new("watch_list").striped(true).show(ui, |ui| {
+                    ui.strong("Name");
+                    ui.strong("Address");
+                    ui.strong("Format");
+                    ui.strong("Value");
+                    ui.end_row();
+                    for (i, entry) in self.entries.iter_mut().enumerate() {
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut entry.name).desired_width(100.0))
+                            .lost_focus();
+                        ui.monospace(format!("{:04X}", entry.addr));
+                        egui::ComboBox::from_id_salt(("watch_format", i))
+                            .selected_text(entry.format.name())
+                            .width(60.0)
+                            .show_ui(ui, |ui| {
+                                for format in Format::ALL {
+                                    changed |= ui.selectable_value(&mut entry.format, format, format.name()).changed();
+                                }
+                            });
+                        match memory {
+                            Some(memory) => ui.monospace(entry.format.show(memory, entry.addr)),
+                            None => ui.monospace("-"),
+                        };
+                        if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = remove {
+                    self.entries.remove(i);
+                    changed = true;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Address:");
+                    ui.add(egui::TextEdit::singleline(&mut self.new_addr).desired_width(48.0).hint_text("C0A0"));
+                    if ui.button("Add").clicked() {
+                        match u16::from_str_radix(self.new_addr.trim().trim_start_matches('$'), 16) {
+                            Ok(addr) => {
+                                self.new_addr.clear();
+                                self.add(addr);
+                            }
+                            Err(_) => self.error = Some(format!("Not a hex address: {}", self.new_addr)),
+                        }
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+        if changed {
+            self.save();
+        }
+    }
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut parts = line.splitn(3, char::is_whitespace);
+    let addr = u16::from_str_radix(parts.next()?.trim_start_matches('$'), 16).ok()?;
+    let format = Format::parse(parts.next()?)?;
+    let name = parts.next().unwrap_or("").trim().to_string();
+    Some(Entry { addr, format, name })
+}
@@ -0,0 +1,330 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Longest a client may take to send its handshake before it's dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Appended to the client's key to prove the server speaks WebSocket (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Longest message a client may send; anything bigger gets the connection dropped.
+const MAX_MESSAGE: u64 = 64 * 1024;
+
+/// Reads the client's HTTP upgrade request (any path) and accepts it. Leaves the stream's
+/// read and write timeouts at `HANDSHAKE_TIMEOUT`, for the caller to change if it likes.
+pub fn handshake(stream: &TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut key = None;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') && name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+            key = Some(value.trim().to_string());
+        }
+    }
+    let Some(key) = key else {
+        let mut stream = stream;
+        stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket request"));
+    };
+    accept(stream, &key)
+}
+
+/// Accepts an upgrade request already read, whose `Sec-WebSocket-Key` header was `key`.
+pub fn accept(mut stream: &TcpStream, key: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key),
+    )
+}
+
+/// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()))
+}
+
+/// `text` as a message from the server (unmasked, in one frame).
+pub fn text_frame(text: &str) -> Vec<u8> {
+    frame(0x1, text.as_bytes())
+}
+
+/// `data` as a binary message from the server (unmasked, in one frame).
+pub fn binary_frame(data: &[u8]) -> Vec<u8> {
+    frame(0x2, data)
+}
+
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut message = vec![0x80 | opcode]; // Final fragment
+    match payload.len() {
+        len @ 0..=125 => message.push(len as u8),
+        len @ 126..=0xFFFF => {
+            message.push(126);
+            message.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            message.push(127);
+            message.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Reads the client's next text message, putting fragmented ones back together; `None`
+/// once the client closes the connection, cleanly or between messages. Pings and binary
+/// messages are skipped.
+pub fn read_text(stream: &mut impl Read) -> io::Result<Option<String>> {
+    let mut message = Vec::new();
+    let mut text = false;
+    loop {
+        let mut head = [0u8; 2];
+        match stream.read_exact(&mut head) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && message.is_empty() => return Ok(None),
+            result => result?,
+        }
+        let (fin, opcode, masked) = (head[0] & 0x80 != 0, head[0] & 0x0F, head[1] & 0x80 != 0);
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                stream.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if message.len() as u64 + len > MAX_MESSAGE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+        }
+        let mut mask = [0u8; 4];
+        if masked {
+            stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; len as usize];
+        stream.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        match opcode {
+            0x8 => return Ok(None),         // Close
+            0x9 | 0xA => continue,          // Ping, pong: control frames between fragments
+            0x1 => text = true,             // First fragment of a text message
+            0x2 => text = false,            // First fragment of a binary one
+            _ => {}                         // Continuation
+        }
+        message.extend_from_slice(&payload);
+        if fin {
+            if text {
+                return String::from_utf8(message).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+            message.clear();
+        }
+    }
+}
+
+/// SHA-1 digest of `data`, for the WebSocket handshake.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+    use std::net::TcpListener;
+
+    /// A frame as a client sends it: masked, with the shortest length encoding unless
+    /// `long` asks for the 8-byte one.
+    fn client_frame(fin: bool, opcode: u8, payload: &[u8], long: bool) -> Vec<u8> {
+        const MASK: [u8; 4] = [0x37, 0xFA, 0x21, 0x3D];
+        let mut frame = vec![if fin { 0x80 } else { 0 } | opcode];
+        match payload.len() {
+            len @ 0..=125 if !long => frame.push(0x80 | len as u8),
+            len @ 126..=0xFFFF if !long => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&MASK);
+        frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ MASK[i % 4]));
+        frame
+    }
+
+    fn read_all(bytes: Vec<u8>) -> Vec<Option<String>> {
+        let mut stream = Cursor::new(bytes);
+        let mut messages = Vec::new();
+        loop {
+            let message = read_text(&mut stream).unwrap();
+            messages.push(message.clone());
+            if message.is_none() {
+                return messages;
+            }
+        }
+    }
+
+    #[test]
+    fn sha1_and_base64_match_known_values() {
+        assert_eq!(base64(&sha1(b"abc")), "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+        assert_eq!(base64(&sha1(b"")), "2jmj7l5rSw0yVb/vlWAYkK/YBwk=");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn accepts_the_rfc_6455_example_key() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn handshake_answers_an_upgrade_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .write_all(b"GET /chat HTTP/1.1\r\nHost: server.example.com\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n")
+            .unwrap();
+        let (server, _) = listener.accept().unwrap();
+        handshake(&server).unwrap();
+        drop(server);
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"), "{}", response);
+        assert!(response.contains("\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn reads_masked_messages_of_every_length_encoding() {
+        let medium = "m".repeat(300);
+        let mut bytes = client_frame(true, 0x1, b"Hello", false);
+        bytes.extend(client_frame(true, 0x1, medium.as_bytes(), false));
+        bytes.extend(client_frame(true, 0x1, b"eight-byte length", true));
+        assert_eq!(read_all(bytes), [Some(String::from("Hello")), Some(medium), Some(String::from("eight-byte length")), None]);
+    }
+
+    #[test]
+    fn refuses_messages_over_the_limit() {
+        let bytes = client_frame(true, 0x1, &vec![b'x'; MAX_MESSAGE as usize + 1], false);
+        assert_eq!(read_text(&mut Cursor::new(bytes)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Fragments count together
+        let half = vec![b'x'; MAX_MESSAGE as usize / 2 + 1];
+        let mut bytes = client_frame(false, 0x1, &half, false);
+        bytes.extend(client_frame(true, 0x0, &half, false));
+        assert_eq!(read_text(&mut Cursor::new(bytes)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn joins_fragments_around_control_frames() {
+        let mut bytes = client_frame(false, 0x1, b"Hel", false);
+        bytes.extend(client_frame(true, 0x9, b"ping", false));
+        bytes.extend(client_frame(false, 0x0, b"lo, ", false));
+        bytes.extend(client_frame(true, 0x0, b"world", false));
+        // A binary message is skipped, fragments and all
+        bytes.extend(client_frame(false, 0x2, &[1, 2], false));
+        bytes.extend(client_frame(true, 0x0, &[3], false));
+        bytes.extend(client_frame(true, 0x1, b"after", false));
+        assert_eq!(read_all(bytes), [Some(String::from("Hello, world")), Some(String::from("after")), None]);
+    }
+
+    #[test]
+    fn stops_at_a_close_frame() {
+        let mut bytes = client_frame(true, 0x1, b"last", false);
+        bytes.extend(client_frame(true, 0x8, &[0x03, 0xE8], false));
+        bytes.extend(client_frame(true, 0x1, b"never read", false));
+        let mut stream = Cursor::new(bytes);
+        assert_eq!(read_text(&mut stream).unwrap(), Some(String::from("last")));
+        assert_eq!(read_text(&mut stream).unwrap(), None);
+    }
+
+    #[test]
+    fn a_connection_cut_mid_message_is_an_error() {
+        let mut bytes = client_frame(false, 0x1, b"Hel", false);
+        assert_eq!(read_text(&mut Cursor::new(bytes.clone())).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        bytes.extend(&client_frame(true, 0x0, b"lo", false)[..4]);
+        assert_eq!(read_text(&mut Cursor::new(bytes)).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn server_frames_use_the_shortest_length() {
+        assert_eq!(text_frame("hi"), [0x81, 2, b'h', b'i']);
+        assert_eq!(binary_frame(&[0; 300])[..4], [0x82, 126, 0x01, 0x2C]);
+        assert_eq!(binary_frame(&[0; 0x10000])[..10], [0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0]);
+    }
+}